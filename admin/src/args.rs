@@ -1,7 +1,7 @@
 use std::{fs, io, path::PathBuf};
 
 use clap::{Error, Parser, Subcommand};
-use shuttle_common::project::ProjectName;
+use shuttle_common::{models::project::ContainerLimitsOverride, project::ProjectName};
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -31,6 +31,52 @@ pub enum Command {
     /// Viewing and managing stats
     #[command(subcommand)]
     Stats(StatsCommand),
+
+    /// Viewing and managing container limits
+    #[command(subcommand)]
+    Limits(LimitsCommand),
+
+    /// View the actions recently taken by all accounts
+    Audit {
+        /// How many actions to display
+        #[arg(long, default_value = "100")]
+        limit: u32,
+    },
+
+    /// Suspend, delete or export accounts
+    #[command(subcommand)]
+    Account(AccountCommand),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AccountCommand {
+    /// Stop an account from logging in and destroy the projects it owns
+    Suspend {
+        /// Account to suspend
+        account_name: String,
+    },
+
+    /// Lift the suspension of an account. Its projects start again on their next request
+    Unsuspend {
+        /// Account to unsuspend
+        account_name: String,
+    },
+
+    /// Delete an account together with its projects, resources, secrets and logs
+    Delete {
+        /// Account to delete
+        account_name: String,
+    },
+
+    /// Export the projects, deployments and secret names of an account as JSON
+    Export {
+        /// Account to export
+        account_name: String,
+
+        /// File to write the export to, instead of stdout
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -99,6 +145,59 @@ pub enum StatsCommand {
     },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum LimitsCommand {
+    /// Override the limits of a project. Applied the next time its container is created
+    Project {
+        /// Project to set the limits for
+        #[arg(long)]
+        project: ProjectName,
+
+        #[command(flatten)]
+        limits: LimitsArgs,
+    },
+
+    /// View or update the limits of an account tier. Limits which are not given stay unchanged
+    Tier {
+        /// Account tier to manage the limits of
+        #[arg(long)]
+        tier: String,
+
+        #[command(flatten)]
+        limits: LimitsArgs,
+    },
+}
+
+#[derive(Parser, Debug)]
+pub struct LimitsArgs {
+    /// Hard memory limit in bytes
+    #[arg(long)]
+    pub memory_bytes: Option<i64>,
+
+    /// Relative CPU weight against other containers
+    #[arg(long)]
+    pub cpu_shares: Option<i64>,
+
+    /// CPU time in microseconds the container can use in every period of 100ms
+    #[arg(long)]
+    pub cpu_quota: Option<i64>,
+
+    /// Maximum number of processes and threads
+    #[arg(long)]
+    pub pids_limit: Option<i64>,
+}
+
+impl From<LimitsArgs> for ContainerLimitsOverride {
+    fn from(args: LimitsArgs) -> Self {
+        Self {
+            memory_bytes: args.memory_bytes,
+            cpu_shares: args.cpu_shares,
+            cpu_quota: args.cpu_quota,
+            pids_limit: args.pids_limit,
+        }
+    }
+}
+
 fn load_credentials(s: &str) -> Result<serde_json::Value, Error> {
     let credentials = fs::read_to_string(PathBuf::from(s))?;
     serde_json::from_str(&credentials).map_err(|err| Error::from(io::Error::from(err)))
@@ -1,7 +1,8 @@
 use anyhow::{Context, Result};
+use reqwest::StatusCode;
 use serde::{de::DeserializeOwned, Serialize};
 use shuttle_common::{
-    models::{project, stats, ToJson},
+    models::{account, audit, error::ApiError, project, stats, user, ToJson},
     project::ProjectName,
 };
 use tracing::trace;
@@ -74,6 +75,101 @@ impl Client {
             .await
     }
 
+    pub async fn set_project_limits(
+        &self,
+        project_name: &ProjectName,
+        overrides: &project::ContainerLimitsOverride,
+    ) -> Result<project::ContainerLimits> {
+        let path = format!("/admin/projects/{project_name}/limits");
+        self.put(&path, Some(overrides)).await
+    }
+
+    pub async fn get_tier_limits(&self, tier: &str) -> Result<project::ContainerLimits> {
+        let path = format!("/admin/tiers/{tier}/limits");
+        self.get(&path).await
+    }
+
+    pub async fn set_tier_limits(
+        &self,
+        tier: &str,
+        limits: &project::ContainerLimits,
+    ) -> Result<project::ContainerLimits> {
+        let path = format!("/admin/tiers/{tier}/limits");
+        self.put(&path, Some(limits)).await
+    }
+
+    pub async fn get_audit_log(&self, limit: u32) -> Result<Vec<audit::Response>> {
+        let path = format!("/admin/audit?limit={limit}");
+        self.get(&path).await
+    }
+
+    pub async fn get_user_audit_log(&self, limit: u32) -> Result<Vec<audit::Response>> {
+        let path = format!("/users/audit?all=true&limit={limit}");
+        self.get(&path).await
+    }
+
+    pub async fn suspend_user(&self, account_name: &str) -> Result<user::Response> {
+        let path = format!("/users/{account_name}/suspension");
+        self.put(&path, Option::<String>::None).await
+    }
+
+    pub async fn unsuspend_user(&self, account_name: &str) -> Result<user::Response> {
+        let path = format!("/users/{account_name}/suspension");
+        self.delete(&path, Option::<String>::None).await
+    }
+
+    pub async fn delete_user(&self, account_name: &str) -> Result<user::Response> {
+        let path = format!("/users/{account_name}");
+        self.delete(&path, Option::<String>::None).await
+    }
+
+    pub async fn suspend_account_projects(&self, account_name: &str) -> Result<Vec<String>> {
+        let path = format!("/admin/accounts/{account_name}/suspend");
+        self.post(&path, Option::<String>::None).await
+    }
+
+    /// Delete the projects of an account. Projects that failed to delete are in the response,
+    /// which the gateway sends with an error status.
+    pub async fn delete_account_projects(
+        &self,
+        account_name: &str,
+    ) -> Result<account::DeleteResponse> {
+        let path = format!("/admin/accounts/{account_name}");
+
+        trace!(self.api_key, "using api key");
+
+        let response = reqwest::Client::new()
+            .delete(format!("{}{}", self.api_url, path))
+            .bearer_auth(&self.api_key)
+            .send()
+            .await
+            .context("failed to make delete request")?;
+
+        if response.status() != StatusCode::INTERNAL_SERVER_ERROR {
+            return response
+                .to_json()
+                .await
+                .context("failed to extract json body from delete response");
+        }
+
+        let body = response
+            .bytes()
+            .await
+            .context("failed to read delete response")?;
+
+        match serde_json::from_slice(&body) {
+            Ok(response) => Ok(response),
+            Err(_) => Err(serde_json::from_slice::<ApiError>(&body)
+                .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into())
+                .into()),
+        }
+    }
+
+    pub async fn export_account(&self, account_name: &str) -> Result<account::ExportBundle> {
+        let path = format!("/admin/accounts/{account_name}/export");
+        self.get(&path).await
+    }
+
     async fn post<T: Serialize, R: DeserializeOwned>(
         &self,
         path: &str,
@@ -98,6 +194,30 @@ impl Client {
             .context("failed to extract json body from post response")
     }
 
+    async fn put<T: Serialize, R: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: Option<T>,
+    ) -> Result<R> {
+        trace!(self.api_key, "using api key");
+
+        let mut builder = reqwest::Client::new()
+            .put(format!("{}{}", self.api_url, path))
+            .bearer_auth(&self.api_key);
+
+        if let Some(body) = body {
+            builder = builder.json(&body);
+        }
+
+        builder
+            .send()
+            .await
+            .context("failed to make put request")?
+            .to_json()
+            .await
+            .context("failed to extract json body from put response")
+    }
+
     async fn delete<T: Serialize, R: DeserializeOwned>(
         &self,
         path: &str,
@@ -1,9 +1,10 @@
 use clap::Parser;
 use shuttle_admin::{
-    args::{AcmeCommand, Args, Command, StatsCommand},
+    args::{AccountCommand, AcmeCommand, Args, Command, LimitsCommand, StatsCommand},
     client::Client,
     config::get_api_key,
 };
+use shuttle_common::models::{account, audit, project::ContainerLimitsOverride};
 use std::{
     collections::{hash_map::RandomState, HashMap},
     fmt::Write,
@@ -161,6 +162,132 @@ async fn main() {
                 resp.builds_count, has_capacity
             )
         }
+        Command::Limits(LimitsCommand::Project { project, limits }) => {
+            let limits = client
+                .set_project_limits(&project, &limits.into())
+                .await
+                .expect("to set project limits");
+
+            format!("Limits of {project} are now {limits}. They will be applied the next time the project is restarted")
+        }
+        Command::Limits(LimitsCommand::Tier { tier, limits }) => {
+            let overrides: ContainerLimitsOverride = limits.into();
+            let current = client
+                .get_tier_limits(&tier)
+                .await
+                .expect("to get tier limits");
+
+            if overrides == ContainerLimitsOverride::default() {
+                format!("Limits of the {tier} tier are {current}")
+            } else {
+                let limits = client
+                    .set_tier_limits(&tier, &current.with_overrides(&overrides))
+                    .await
+                    .expect("to set tier limits");
+
+                format!("Limits of the {tier} tier are now {limits}")
+            }
+        }
+        Command::Audit { limit } => {
+            let mut events = client
+                .get_audit_log(limit)
+                .await
+                .expect("to get the gateway audit log");
+            events.extend(
+                client
+                    .get_user_audit_log(limit)
+                    .await
+                    .expect("to get the auth audit log"),
+            );
+            events.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+            events.truncate(limit as usize);
+
+            audit::get_audit_table(&events)
+        }
+        Command::Account(AccountCommand::Suspend { account_name }) => {
+            // Block new tokens first, so the projects cannot be brought back while being destroyed
+            client
+                .suspend_user(&account_name)
+                .await
+                .expect("to suspend the account");
+            let projects = client
+                .suspend_account_projects(&account_name)
+                .await
+                .expect("to destroy the projects of the account");
+
+            format!(
+                "Suspended {account_name} and destroyed {} of its projects",
+                projects.len()
+            )
+        }
+        Command::Account(AccountCommand::Unsuspend { account_name }) => {
+            client
+                .unsuspend_user(&account_name)
+                .await
+                .expect("to unsuspend the account");
+
+            format!("Lifted the suspension of {account_name}")
+        }
+        Command::Account(AccountCommand::Delete { account_name }) => {
+            // Delete the projects first, so the account is still around to retry with if that fails
+            let account::DeleteResponse { deleted, failed } = client
+                .delete_account_projects(&account_name)
+                .await
+                .expect("to delete the projects of the account");
+
+            if failed.is_empty() {
+                client
+                    .delete_user(&account_name)
+                    .await
+                    .expect("to delete the account");
+            }
+
+            let mut res = if failed.is_empty() {
+                format!("Deleted {account_name}")
+            } else {
+                format!("Deleted {} projects of {account_name}", deleted.len())
+            };
+            for project in deleted {
+                write!(res, "\n\t- {project}").expect("to write name of deleted project");
+            }
+
+            if !failed.is_empty() {
+                write!(
+                    res,
+                    "\nKept {account_name}, since some projects could not be deleted:"
+                )
+                .expect("to write failed projects");
+                for account::ProjectFailure {
+                    project_name,
+                    error,
+                } in failed
+                {
+                    write!(res, "\n\t- {project_name}: {error}")
+                        .expect("to write name of failed project");
+                }
+                write!(res, "\nRun this command again to retry them").expect("to write retry hint");
+            }
+
+            res
+        }
+        Command::Account(AccountCommand::Export {
+            account_name,
+            output,
+        }) => {
+            let bundle = client
+                .export_account(&account_name)
+                .await
+                .expect("to export the account");
+            let json = serde_json::to_string_pretty(&bundle).expect("to serialize the export");
+
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, json).expect("to write the export");
+                    format!("Exported {account_name} to {}", path.display())
+                }
+                None => json,
+            }
+        }
     };
 
     println!("{res}");
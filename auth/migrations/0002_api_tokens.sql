@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS api_tokens (
+  key TEXT PRIMARY KEY,
+  account_name TEXT NOT NULL REFERENCES users (account_name) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  scopes TEXT NOT NULL,
+  project_name TEXT,
+  expires_at DATETIME,
+  created_at DATETIME NOT NULL,
+  UNIQUE (account_name, name)
+);
@@ -0,0 +1,9 @@
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+  token_hash TEXT PRIMARY KEY,
+  account_name TEXT NOT NULL REFERENCES users (account_name) ON DELETE CASCADE,
+  api_token_name TEXT,
+  expires_at DATETIME NOT NULL,
+  revoked BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_account_name ON refresh_tokens (account_name);
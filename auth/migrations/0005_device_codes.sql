@@ -0,0 +1,9 @@
+-- Codes for devices, such as the CLI, waiting for a user to approve their login in the browser.
+-- Only a hash of the device code is stored, while the short user code is shown to the user.
+CREATE TABLE IF NOT EXISTS device_codes (
+  device_code_hash TEXT PRIMARY KEY,
+  user_code TEXT NOT NULL UNIQUE,
+  -- The account which approved the login, if it has been approved yet
+  account_name TEXT REFERENCES users (account_name) ON DELETE CASCADE,
+  expires_at DATETIME NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Suspended accounts can no longer get a JWT, while their data is kept until they are unsuspended
+-- or deleted.
+ALTER TABLE users ADD COLUMN suspended BOOLEAN NOT NULL DEFAULT FALSE;
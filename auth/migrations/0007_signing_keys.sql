@@ -0,0 +1,7 @@
+-- Keys the JWTs are signed with. The private keys are kept so that several instances sharing this
+-- state can sign with the same key, and keep verifying tokens across restarts.
+CREATE TABLE IF NOT EXISTS signing_keys (
+  kid TEXT PRIMARY KEY,
+  pkcs8 BLOB NOT NULL,
+  created_at DATETIME NOT NULL
+);
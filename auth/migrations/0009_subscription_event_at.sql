@@ -0,0 +1,3 @@
+-- When the last subscription event applied to an account was created, so events delivered out
+-- of order do not undo newer changes
+ALTER TABLE users ADD COLUMN subscription_event_at DATETIME;
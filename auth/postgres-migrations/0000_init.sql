@@ -0,0 +1,64 @@
+-- The same schema as the SQLite migrations, for running several instances on shared state
+CREATE TABLE IF NOT EXISTS users (
+  account_name TEXT PRIMARY KEY,
+  key TEXT UNIQUE,
+  account_tier TEXT NOT NULL DEFAULT 'basic',
+  subscription_id TEXT,
+  suspended BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS api_tokens (
+  key TEXT PRIMARY KEY,
+  account_name TEXT NOT NULL REFERENCES users (account_name) ON DELETE CASCADE,
+  name TEXT NOT NULL,
+  scopes JSONB NOT NULL,
+  project_name TEXT,
+  expires_at TIMESTAMPTZ,
+  created_at TIMESTAMPTZ NOT NULL,
+  UNIQUE (account_name, name)
+);
+
+CREATE TABLE IF NOT EXISTS refresh_tokens (
+  token_hash TEXT PRIMARY KEY,
+  account_name TEXT NOT NULL REFERENCES users (account_name) ON DELETE CASCADE,
+  api_token_name TEXT,
+  expires_at TIMESTAMPTZ NOT NULL,
+  revoked BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS refresh_tokens_account_name ON refresh_tokens (account_name);
+
+-- Timestamps are in seconds since the UNIX epoch
+CREATE TABLE IF NOT EXISTS audit_log (
+  id BIGSERIAL PRIMARY KEY,
+  timestamp BIGINT NOT NULL,
+  actor TEXT,
+  action TEXT NOT NULL,
+  target TEXT NOT NULL,
+  source_ip TEXT,
+  outcome INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS audit_log_actor ON audit_log (actor);
+
+CREATE OR REPLACE FUNCTION audit_log_append_only() RETURNS TRIGGER AS $$
+BEGIN
+  RAISE EXCEPTION 'the audit log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only BEFORE UPDATE OR DELETE ON audit_log
+  FOR EACH ROW EXECUTE FUNCTION audit_log_append_only();
+
+CREATE TABLE IF NOT EXISTS device_codes (
+  device_code_hash TEXT PRIMARY KEY,
+  user_code TEXT NOT NULL UNIQUE,
+  account_name TEXT REFERENCES users (account_name) ON DELETE CASCADE,
+  expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS signing_keys (
+  kid TEXT PRIMARY KEY,
+  pkcs8 BYTEA NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL
+);
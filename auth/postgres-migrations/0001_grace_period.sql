@@ -0,0 +1,2 @@
+-- Accounts whose subscription payment failed keep their tier until the grace period ends
+ALTER TABLE users ADD COLUMN grace_period_ends_at TIMESTAMPTZ;
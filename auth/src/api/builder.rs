@@ -1,31 +1,45 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
 
 use axum::{
     extract::FromRef,
-    middleware::from_extractor,
-    routing::{get, post, put},
+    middleware::{from_extractor, from_fn_with_state},
+    routing::{delete, get, post, put},
     Router, Server,
 };
 use axum_sessions::{async_session::MemoryStore, SessionLayer};
 use rand::RngCore;
 use shuttle_common::{
-    backends::metrics::{Metrics, TraceLayer},
+    backends::{
+        audit::AuditLayer,
+        metrics::{Metrics, TraceLayer},
+    },
     request_span,
 };
 use sqlx::SqlitePool;
-use tracing::field;
+use tracing::{error, field};
 
 use crate::{
+    dal::{Dal, Sqlite},
     secrets::{EdDsaManager, KeyManager},
     user::{UserManagement, UserManager},
     COOKIE_EXPIRATION,
 };
 
 use super::handlers::{
-    convert_cookie, convert_key, get_public_key, get_user, health_check, logout, post_user,
-    put_user_reset_key, refresh_token, update_user_tier,
+    audit_actor, convert_cookie, convert_key, delete_token, delete_user, delete_user_suspension,
+    get_audit_log, get_jwks, get_public_key, get_tokens, get_user, head_user, health_check, logout,
+    post_device_approve, post_device_code, post_device_token, post_local_login, post_rotate_key,
+    post_stripe_webhook, post_token, post_user, put_user_reset_key, put_user_suspension,
+    refresh_token, revoke_refresh_token, update_user_tier,
 };
 
+/// How often to pick up signing keys rotated by other instances
+const KEY_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
 pub type UserManagerState = Arc<Box<dyn UserManagement>>;
 pub type KeyManagerState = Arc<Box<dyn KeyManager>>;
 
@@ -33,6 +47,10 @@ pub type KeyManagerState = Arc<Box<dyn KeyManager>>;
 pub struct RouterState {
     pub user_manager: UserManagerState,
     pub key_manager: KeyManagerState,
+    /// Page where users approve the login of a device
+    pub device_verification_uri: String,
+    /// Secret Stripe signs its webhooks with
+    pub stripe_webhook_secret: Option<String>,
 }
 
 // Allow getting a user management state directly
@@ -51,9 +69,13 @@ impl FromRef<RouterState> for KeyManagerState {
 
 pub struct ApiBuilder {
     router: Router<RouterState>,
-    pool: Option<SqlitePool>,
+    dal: Option<Arc<dyn Dal>>,
     session_layer: Option<SessionLayer<MemoryStore>>,
     stripe_client: Option<stripe::Client>,
+    key_rotation_interval: Option<Duration>,
+    device_verification_uri: Option<String>,
+    stripe_webhook_secret: Option<String>,
+    trusted_proxies: Vec<IpAddr>,
 }
 
 impl Default for ApiBuilder {
@@ -70,13 +92,30 @@ impl ApiBuilder {
             .route("/auth/session", get(convert_cookie))
             .route("/auth/key", get(convert_key))
             .route("/auth/refresh", post(refresh_token))
+            .route("/auth/revoke", post(revoke_refresh_token))
+            .route("/auth/rotate-key", post(post_rotate_key))
+            .route("/auth/device/code", post(post_device_code))
+            .route("/auth/device/approve", post(post_device_approve))
+            .route("/auth/device/token", post(post_device_token))
             .route("/public-key", get(get_public_key))
-            .route("/users/:account_name", get(get_user))
+            .route("/.well-known/jwks.json", get(get_jwks))
+            .route("/billing/stripe/webhook", post(post_stripe_webhook))
+            .route(
+                "/users/:account_name",
+                get(get_user).head(head_user).delete(delete_user),
+            )
+            .route(
+                "/users/:account_name/suspension",
+                put(put_user_suspension).delete(delete_user_suspension),
+            )
             .route(
                 "/users/:account_name/:account_tier",
                 post(post_user).put(update_user_tier),
             )
             .route("/users/reset-api-key", put(put_user_reset_key))
+            .route("/users/audit", get(get_audit_log))
+            .route("/users/tokens", get(get_tokens).post(post_token))
+            .route("/users/tokens/:token_name", delete(delete_token))
             .route_layer(from_extractor::<Metrics>())
             .layer(
                 TraceLayer::new(|request| {
@@ -92,14 +131,23 @@ impl ApiBuilder {
 
         Self {
             router,
-            pool: None,
+            dal: None,
             session_layer: None,
             stripe_client: None,
+            key_rotation_interval: None,
+            device_verification_uri: None,
+            stripe_webhook_secret: None,
+            trusted_proxies: Vec::new(),
         }
     }
 
-    pub fn with_sqlite_pool(mut self, pool: SqlitePool) -> Self {
-        self.pool = Some(pool);
+    pub fn with_sqlite_pool(self, pool: SqlitePool) -> Self {
+        self.with_dal(Arc::new(Sqlite::from_pool(pool)))
+    }
+
+    /// Keep the auth state in this storage
+    pub fn with_dal(mut self, dal: Arc<dyn Dal>) -> Self {
+        self.dal = Some(dal);
         self
     }
 
@@ -122,28 +170,98 @@ impl ApiBuilder {
         self
     }
 
-    pub fn into_router(self) -> Router {
-        let pool = self.pool.expect("an sqlite pool is required");
+    /// Accept Stripe webhooks signed with this secret, to keep account tiers in sync with
+    /// their subscriptions
+    pub fn with_stripe_webhook_secret(mut self, secret: String) -> Self {
+        self.stripe_webhook_secret = Some(secret);
+        self
+    }
+
+    /// Rotate the signing key at this interval. It should be longer than the lifetime of the
+    /// signed tokens, since only the previous key is kept for verification.
+    pub fn with_key_rotation(mut self, interval: Duration) -> Self {
+        self.key_rotation_interval = Some(interval);
+        self
+    }
+
+    pub fn with_device_verification_uri(mut self, uri: String) -> Self {
+        self.device_verification_uri = Some(uri);
+        self
+    }
+
+    /// Proxies whose `x-forwarded-for` header is trusted for the address of the client in the
+    /// audit log
+    pub fn with_trusted_proxies(mut self, trusted_proxies: Vec<IpAddr>) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+
+    /// Allow logging in to a browser session by account name alone, standing in for the identity
+    /// provider of the console. This should only be used for local development and tests.
+    pub fn with_local_identity_provider(mut self) -> Self {
+        self.router = self.router.route("/login", post(post_local_login));
+        self
+    }
+
+    pub async fn into_router(self) -> Router {
+        let dal = self.dal.expect("a storage backend is required");
         let session_layer = self.session_layer.expect("a session layer is required");
         let stripe_client = self.stripe_client.expect("a stripe client is required");
-        let user_manager = UserManager {
-            pool,
-            stripe_client,
-        };
-        let key_manager = EdDsaManager::new();
+        let device_verification_uri = self
+            .device_verification_uri
+            .expect("a device verification uri is required");
+        let key_manager: KeyManagerState = Arc::new(Box::new(
+            EdDsaManager::load(dal.clone())
+                .await
+                .expect("the signing keys to load"),
+        ));
+        let user_manager = UserManager { dal, stripe_client };
+
+        // Keys can also be rotated by other instances sharing the storage, so check for new keys
+        // more often than they are rotated
+        let max_key_age = self.key_rotation_interval;
+        let sync_interval =
+            max_key_age.map_or(KEY_SYNC_INTERVAL, |max_age| max_age.min(KEY_SYNC_INTERVAL));
+        let sync_key_manager = key_manager.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(sync_interval);
+            // The first tick completes immediately
+            interval.tick().await;
+
+            loop {
+                interval.tick().await;
+
+                if let Err(error) = sync_key_manager.sync(max_key_age).await {
+                    error!(
+                        error = &error as &dyn std::error::Error,
+                        "failed to sync signing keys"
+                    );
+                }
+            }
+        });
 
+        let audit_sink = Arc::new(user_manager.clone());
         let state = RouterState {
             user_manager: Arc::new(Box::new(user_manager)),
-            key_manager: Arc::new(Box::new(key_manager)),
+            key_manager,
+            device_verification_uri,
+            stripe_webhook_secret: self.stripe_webhook_secret,
         };
 
-        self.router.layer(session_layer).with_state(state)
+        // The actor is resolved from the session, so the session layer has to run first
+        self.router
+            .route_layer(AuditLayer::new(audit_sink).with_trusted_proxies(self.trusted_proxies))
+            .layer(from_fn_with_state(state.clone(), audit_actor))
+            .layer(session_layer)
+            .with_state(state)
     }
 }
 
 pub async fn serve(router: Router, address: SocketAddr) {
     Server::bind(&address)
-        .serve(router.into_make_service())
+        // The address of the client is needed for the audit log
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap_or_else(|_| panic!("Failed to bind to address: {}", address));
 }
@@ -1,17 +1,37 @@
+use std::str::FromStr;
+
 use crate::{
+    billing,
     error::Error,
-    user::{AccountName, AccountTier, Admin, Key, User},
+    user::{
+        AccountName, AccountTier, Admin, ApiToken, ApprovedDevice, DeviceCode, Key, RefreshToken,
+        User,
+    },
+    DEVICE_CODE_EXPIRATION, DEVICE_CODE_INTERVAL,
 };
 use axum::{
-    extract::{Path, State},
+    body::Bytes,
+    extract::{Path, Query, State},
+    middleware::Next,
+    response::Response,
     Json,
 };
 use axum_sessions::extractors::{ReadableSession, WritableSession};
-use http::StatusCode;
+use chrono::Utc;
+use http::{HeaderMap, Method, Request, StatusCode};
 use serde::{Deserialize, Serialize};
-use shuttle_common::{claims::Claim, models::user};
+use shuttle_common::{
+    backends::{
+        audit::AuditActor,
+        auth::{ConvertResponse, JwkSet, RefreshRequest},
+    },
+    claims::{Claim, Scope},
+    models::{audit, user},
+    project::ProjectName,
+    ApiKey,
+};
 use stripe::CheckoutSession;
-use tracing::instrument;
+use tracing::{instrument, warn};
 
 use super::{
     builder::{KeyManagerState, UserManagerState},
@@ -29,6 +49,20 @@ pub(crate) async fn get_user(
     Ok(Json(user.into()))
 }
 
+/// Check that an account exists, for any authenticated account to be able to refer to it
+#[instrument(skip(session, user_manager, key))]
+pub(crate) async fn head_user(
+    session: ReadableSession,
+    State(user_manager): State<UserManagerState>,
+    key: Option<Key>,
+    Path(account_name): Path<AccountName>,
+) -> Result<(), Error> {
+    caller(&session, &user_manager, key).await?;
+    user_manager.get_user(account_name).await?;
+
+    Ok(())
+}
+
 #[instrument(skip(user_manager))]
 pub(crate) async fn post_user(
     _: Admin,
@@ -65,22 +99,241 @@ pub(crate) async fn update_user_tier(
     Ok(())
 }
 
-pub(crate) async fn put_user_reset_key(
-    session: ReadableSession,
+#[instrument(skip(user_manager))]
+pub(crate) async fn delete_user(
+    _: Admin,
+    State(user_manager): State<UserManagerState>,
+    Path(account_name): Path<AccountName>,
+) -> Result<Json<user::Response>, Error> {
+    let user = user_manager.get_user(account_name).await?;
+    user_manager.delete_user(&user.name).await?;
+
+    Ok(Json(user.into()))
+}
+
+#[instrument(skip(user_manager))]
+pub(crate) async fn put_user_suspension(
+    _: Admin,
     State(user_manager): State<UserManagerState>,
+    Path(account_name): Path<AccountName>,
+) -> Result<Json<user::Response>, Error> {
+    user_manager.set_suspended(&account_name, true).await?;
+    let user = user_manager.get_user(account_name).await?;
+
+    Ok(Json(user.into()))
+}
+
+#[instrument(skip(user_manager))]
+pub(crate) async fn delete_user_suspension(
+    _: Admin,
+    State(user_manager): State<UserManagerState>,
+    Path(account_name): Path<AccountName>,
+) -> Result<Json<user::Response>, Error> {
+    user_manager.set_suspended(&account_name, false).await?;
+    let user = user_manager.get_user(account_name).await?;
+
+    Ok(Json(user.into()))
+}
+
+/// Get the calling account from its session cookie, or else from its API key. Suspended accounts
+/// can't do anything with their account.
+async fn caller(
+    session: &ReadableSession,
+    user_manager: &UserManagerState,
     key: Option<Key>,
-) -> Result<(), Error> {
-    let account_name = match session.get::<String>("account_name") {
-        Some(account_name) => account_name.into(),
+) -> Result<User, Error> {
+    let user = match session.get::<String>("account_name") {
+        Some(account_name) => user_manager.get_user(account_name.into()).await?,
         None => match key {
-            Some(key) => user_manager.get_user_by_key(key.into()).await?.name,
+            Some(key) => user_manager.get_user_by_key(key.into()).await?,
             None => return Err(Error::Unauthorized),
         },
     };
 
+    if user.suspended {
+        return Err(Error::AccountSuspended);
+    }
+
+    Ok(user)
+}
+
+pub(crate) async fn put_user_reset_key(
+    session: ReadableSession,
+    State(user_manager): State<UserManagerState>,
+    key: Option<Key>,
+) -> Result<(), Error> {
+    let account_name = caller(&session, &user_manager, key).await?.name;
+
     user_manager.reset_key(account_name).await
 }
 
+pub(crate) async fn get_tokens(
+    session: ReadableSession,
+    State(user_manager): State<UserManagerState>,
+    key: Option<Key>,
+) -> Result<Json<Vec<user::TokenResponse>>, Error> {
+    let account_name = caller(&session, &user_manager, key).await?.name;
+    let tokens = user_manager.get_tokens(account_name).await?;
+
+    Ok(Json(tokens.into_iter().map(Into::into).collect()))
+}
+
+pub(crate) async fn post_token(
+    session: ReadableSession,
+    State(user_manager): State<UserManagerState>,
+    key: Option<Key>,
+    Json(request): Json<user::TokenRequest>,
+) -> Result<Json<user::TokenResponse>, Error> {
+    let User {
+        name: account_name,
+        account_tier,
+        ..
+    } = caller(&session, &user_manager, key).await?;
+
+    if request.name.trim().is_empty() {
+        return Err(Error::InvalidToken("the token needs a name".to_string()));
+    }
+
+    if request.scopes.is_empty() {
+        return Err(Error::InvalidToken(
+            "the token needs at least one scope".to_string(),
+        ));
+    }
+
+    let tier_scopes: Vec<Scope> = account_tier.into();
+    let scopes = request
+        .scopes
+        .iter()
+        .map(|scope| match Scope::from_str(scope) {
+            Ok(scope) if tier_scopes.contains(&scope) => Ok(scope),
+            Ok(_) => Err(Error::InvalidToken(format!(
+                "your account tier does not allow the `{scope}` scope"
+            ))),
+            Err(_) => Err(Error::InvalidToken(format!("unknown scope `{scope}`"))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let project = request
+        .project
+        .map(|project| {
+            ProjectName::from_str(&project)
+                .map(|project| project.to_string())
+                .map_err(|err| Error::InvalidToken(err.to_string()))
+        })
+        .transpose()?;
+
+    if request
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= Utc::now())
+    {
+        return Err(Error::InvalidToken(
+            "the expiry date should be in the future".to_string(),
+        ));
+    }
+
+    let token = ApiToken::new(
+        account_name,
+        request.name,
+        scopes,
+        project,
+        request.expires_at,
+    );
+    user_manager.create_token(&token).await?;
+
+    let key = token.key.expose().as_ref().to_owned();
+    let mut response: user::TokenResponse = token.into();
+    response.key = Some(key);
+
+    Ok(Json(response))
+}
+
+pub(crate) async fn delete_token(
+    session: ReadableSession,
+    State(user_manager): State<UserManagerState>,
+    key: Option<Key>,
+    Path(token_name): Path<String>,
+) -> Result<Json<String>, Error> {
+    let account_name = caller(&session, &user_manager, key).await?.name;
+
+    user_manager.revoke_token(account_name, &token_name).await?;
+
+    Ok(Json(format!("API token `{token_name}` was revoked")))
+}
+
+#[derive(Deserialize)]
+pub(crate) struct AuditParams {
+    /// Get the actions of all accounts rather than only those of the caller. Only admins can
+    /// do this.
+    #[serde(default)]
+    all: bool,
+    limit: Option<u32>,
+}
+
+pub(crate) async fn get_audit_log(
+    session: ReadableSession,
+    State(user_manager): State<UserManagerState>,
+    Query(AuditParams { all, limit }): Query<AuditParams>,
+    key: Option<Key>,
+) -> Result<Json<Vec<audit::Response>>, Error> {
+    let user = caller(&session, &user_manager, key).await?;
+
+    let actor = if all {
+        if !user.is_admin() {
+            return Err(Error::Forbidden);
+        }
+
+        None
+    } else {
+        Some(user.name)
+    };
+
+    let events = user_manager
+        .get_audit_events(actor.as_ref(), limit.unwrap_or(u32::MAX))
+        .await?;
+
+    Ok(Json(events.into_iter().map(Into::into).collect()))
+}
+
+/// Find the account taking an action so the audit log can record it. Requests by API tokens
+/// are recorded against the account owning the token.
+pub(crate) async fn audit_actor<B>(
+    session: ReadableSession,
+    State(user_manager): State<UserManagerState>,
+    key: Option<Key>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    // Only changes are audited, so don't look up the caller of every read
+    if !matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    ) {
+        let actor = match session.get::<String>("account_name") {
+            Some(account_name) => Some(account_name),
+            None => match key.map(ApiKey::from) {
+                Some(key) => match user_manager.get_user_by_key(key.clone()).await {
+                    Ok(user) => Some(user.name.to_string()),
+                    Err(_) => user_manager
+                        .get_user_by_token(key)
+                        .await
+                        .ok()
+                        .map(|(user, _)| user.name.to_string()),
+                },
+                None => None,
+            },
+        };
+
+        if let Some(actor) = actor {
+            request.extensions_mut().insert(AuditActor(actor));
+        }
+    }
+
+    // Release the session, as handlers like logging in need to write to it
+    drop(session);
+
+    next.run(request).await
+}
+
 pub(crate) async fn logout(mut session: WritableSession) {
     session.destroy();
 }
@@ -92,8 +345,12 @@ pub(crate) async fn health_check() -> Result<(), Error> {
 
 pub(crate) async fn convert_cookie(
     session: ReadableSession,
-    State(key_manager): State<KeyManagerState>,
-) -> Result<Json<shuttle_common::backends::auth::ConvertResponse>, StatusCode> {
+    State(RouterState {
+        key_manager,
+        user_manager,
+        ..
+    }): State<RouterState>,
+) -> Result<Json<ConvertResponse>, StatusCode> {
     let account_name = session
         .get::<String>("account_name")
         .ok_or(StatusCode::UNAUTHORIZED)?;
@@ -102,43 +359,344 @@ pub(crate) async fn convert_cookie(
         .get::<AccountTier>("account_tier")
         .ok_or(StatusCode::UNAUTHORIZED)?;
 
-    let claim = Claim::new(account_name, account_tier.into());
+    // The account might have been suspended since the session was started
+    let user = user_manager
+        .get_user(account_name.clone().into())
+        .await
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+    if user.suspended {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
-    let token = claim.into_token(key_manager.private_key())?;
+    let claim = Claim::new(account_name, account_tier.into()).with_tier(account_tier);
 
-    let response = shuttle_common::backends::auth::ConvertResponse { token };
+    let token = key_manager.sign(claim)?;
+
+    let response = ConvertResponse {
+        token,
+        refresh_token: None,
+    };
 
     Ok(Json(response))
 }
 
+#[derive(Deserialize)]
+pub(crate) struct ConvertKeyParams {
+    /// Also return a refresh token to get a new JWT once it expires
+    #[serde(default)]
+    refresh: bool,
+}
+
 /// Convert a valid API-key bearer token to a JWT.
+///
+/// The key can either be the main key of an account, or one of its named API tokens. In the
+/// latter case the claim is limited to the scopes and project of the token.
 pub(crate) async fn convert_key(
     State(RouterState {
         key_manager,
         user_manager,
+        ..
     }): State<RouterState>,
+    Query(ConvertKeyParams { refresh }): Query<ConvertKeyParams>,
     key: Key,
-) -> Result<Json<shuttle_common::backends::auth::ConvertResponse>, StatusCode> {
+) -> Result<Json<ConvertResponse>, StatusCode> {
+    let key: ApiKey = key.into();
+
+    let (claim, account_name, api_token_name) =
+        match user_manager.get_user_by_key(key.clone()).await {
+            Ok(User {
+                suspended: true, ..
+            }) => return Err(StatusCode::FORBIDDEN),
+            Ok(User {
+                name, account_tier, ..
+            }) => (
+                Claim::new(name.to_string(), account_tier.into()).with_tier(account_tier),
+                name,
+                None,
+            ),
+            Err(_) => {
+                let (
+                    User {
+                        account_tier,
+                        suspended,
+                        ..
+                    },
+                    token,
+                ) = user_manager
+                    .get_user_by_token(key)
+                    .await
+                    .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
+                if suspended {
+                    return Err(StatusCode::FORBIDDEN);
+                }
+
+                (
+                    token.claim(account_tier),
+                    token.account_name,
+                    Some(token.name),
+                )
+            }
+        };
+
+    let token = key_manager.sign(claim)?;
+    let refresh_token = if refresh {
+        let refresh_token = user_manager
+            .create_refresh_token(&account_name, api_token_name.as_deref())
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        Some(refresh_token)
+    } else {
+        None
+    };
+
+    let response = ConvertResponse {
+        token,
+        refresh_token,
+    };
+
+    Ok(Json(response))
+}
+
+/// Exchange a refresh token for a new JWT and a new refresh token. The used refresh token is
+/// revoked.
+pub(crate) async fn refresh_token(
+    State(RouterState {
+        key_manager,
+        user_manager,
+        ..
+    }): State<RouterState>,
+    Json(RefreshRequest { refresh_token }): Json<RefreshRequest>,
+) -> Result<Json<ConvertResponse>, StatusCode> {
+    let RefreshToken {
+        account_name,
+        api_token_name,
+        ..
+    } = user_manager
+        .use_refresh_token(&refresh_token)
+        .await
+        .map_err(|_| StatusCode::UNAUTHORIZED)?;
+
     let User {
-        name, account_tier, ..
+        account_tier,
+        suspended,
+        ..
     } = user_manager
-        .get_user_by_key(key.into())
+        .get_user(account_name.clone())
         .await
         .map_err(|_| StatusCode::UNAUTHORIZED)?;
 
-    let claim = Claim::new(name.to_string(), account_tier.into());
+    if suspended {
+        return Err(StatusCode::FORBIDDEN);
+    }
 
-    let token = claim.into_token(key_manager.private_key())?;
+    // Check the tier and API token again, as they might have changed since the last refresh
+    let claim = match &api_token_name {
+        None => Claim::new(account_name.to_string(), account_tier.into()).with_tier(account_tier),
+        Some(api_token_name) => user_manager
+            .get_tokens(account_name.clone())
+            .await
+            .map_err(|_| StatusCode::UNAUTHORIZED)?
+            .into_iter()
+            .find(|token| &token.name == api_token_name && !token.is_expired())
+            .ok_or(StatusCode::UNAUTHORIZED)?
+            .claim(account_tier),
+    };
+
+    let token = key_manager.sign(claim)?;
+    let refresh_token = user_manager
+        .create_refresh_token(&account_name, api_token_name.as_deref())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let response = shuttle_common::backends::auth::ConvertResponse { token };
+    let response = ConvertResponse {
+        token,
+        refresh_token: Some(refresh_token),
+    };
 
     Ok(Json(response))
 }
 
-pub(crate) async fn refresh_token() {}
+pub(crate) async fn revoke_refresh_token(
+    State(user_manager): State<UserManagerState>,
+    Json(RefreshRequest { refresh_token }): Json<RefreshRequest>,
+) -> Result<(), Error> {
+    user_manager.revoke_refresh_token(&refresh_token).await
+}
+
+/// Start the login of a device, such as the CLI. The device polls for its API token with the
+/// device code, while the user approves the login in the browser by entering the user code.
+pub(crate) async fn post_device_code(
+    State(RouterState {
+        user_manager,
+        device_verification_uri,
+        ..
+    }): State<RouterState>,
+) -> Result<Json<user::DeviceCodeResponse>, Error> {
+    let DeviceCode {
+        device_code,
+        user_code,
+        ..
+    } = user_manager.create_device_code().await?;
+
+    let response = user::DeviceCodeResponse {
+        device_code,
+        verification_uri_complete: format!("{device_verification_uri}?code={user_code}"),
+        user_code,
+        verification_uri: device_verification_uri,
+        expires_in: DEVICE_CODE_EXPIRATION.as_secs(),
+        interval: DEVICE_CODE_INTERVAL.as_secs(),
+    };
+
+    Ok(Json(response))
+}
+
+/// Approve the login of a device. This can only be done from a browser session.
+#[instrument(skip_all)]
+pub(crate) async fn post_device_approve(
+    session: ReadableSession,
+    State(user_manager): State<UserManagerState>,
+    Json(user::DeviceApproveRequest { user_code }): Json<user::DeviceApproveRequest>,
+) -> Result<Json<String>, Error> {
+    let account_name: AccountName = session
+        .get::<String>("account_name")
+        .ok_or(Error::Unauthorized)?
+        .into();
+
+    user_manager
+        .approve_device_code(&user_code, &account_name)
+        .await?;
+
+    Ok(Json(
+        "The device is logged in, you can close this window".to_string(),
+    ))
+}
+
+/// Poll for the API token of a device. Once the login is approved, an API token with the scopes
+/// of the account tier is created for the device and returned exactly once.
+pub(crate) async fn post_device_token(
+    State(user_manager): State<UserManagerState>,
+    Json(user::DeviceTokenRequest { device_code }): Json<user::DeviceTokenRequest>,
+) -> Result<Json<user::DeviceTokenResponse>, Error> {
+    let Some(ApprovedDevice {
+        account_name,
+        user_code,
+    }) = user_manager.use_device_code(&device_code).await?
+    else {
+        return Ok(Json(user::DeviceTokenResponse { key: None }));
+    };
+
+    let User {
+        account_tier,
+        suspended,
+        ..
+    } = user_manager.get_user(account_name.clone()).await?;
+
+    if suspended {
+        return Err(Error::AccountSuspended);
+    }
+
+    let token = ApiToken::new(
+        account_name,
+        format!("cli-{}", user_code.to_lowercase()),
+        account_tier.into(),
+        None,
+        None,
+    );
+    user_manager.create_token(&token).await?;
+
+    Ok(Json(user::DeviceTokenResponse {
+        key: Some(token.key.expose().as_ref().to_owned()),
+    }))
+}
+
+/// Log in to a browser session by account name alone. This stands in for the identity provider
+/// of the console, so it is only enabled for local development and tests.
+#[instrument(skip_all)]
+pub(crate) async fn post_local_login(
+    mut session: WritableSession,
+    State(user_manager): State<UserManagerState>,
+    Json(LoginRequest { account_name }): Json<LoginRequest>,
+) -> Result<(), Error> {
+    let User {
+        name,
+        account_tier,
+        suspended,
+        ..
+    } = user_manager.get_user(account_name).await?;
+
+    if suspended {
+        return Err(Error::AccountSuspended);
+    }
+
+    session
+        .insert("account_name", name.to_string())
+        .map_err(|err| Error::UnexpectedError(err.into()))?;
+    session
+        .insert("account_tier", account_tier)
+        .map_err(|err| Error::UnexpectedError(err.into()))?;
+
+    Ok(())
+}
 
 pub(crate) async fn get_public_key(State(key_manager): State<KeyManagerState>) -> Vec<u8> {
-    key_manager.public_key().to_vec()
+    key_manager.public_key()
+}
+
+/// Publish the public keys tokens can be signed with, so verifiers can pick one by key id
+pub(crate) async fn get_jwks(State(key_manager): State<KeyManagerState>) -> Json<JwkSet> {
+    Json(key_manager.public_keys())
+}
+
+#[instrument(skip_all)]
+pub(crate) async fn post_rotate_key(
+    _: Admin,
+    State(key_manager): State<KeyManagerState>,
+) -> Result<(), Error> {
+    key_manager.rotate().await
+}
+
+/// Keep account tiers in sync with their Stripe subscriptions
+#[instrument(skip_all)]
+pub(crate) async fn post_stripe_webhook(
+    State(RouterState {
+        user_manager,
+        stripe_webhook_secret,
+        ..
+    }): State<RouterState>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<(), Error> {
+    let secret = stripe_webhook_secret
+        .ok_or_else(|| Error::InvalidWebhook("webhooks are not configured".to_string()))?;
+    let signature = headers
+        .get("Stripe-Signature")
+        .and_then(|signature| signature.to_str().ok())
+        .ok_or_else(|| Error::InvalidWebhook("missing signature".to_string()))?;
+
+    billing::verify_signature(&payload, signature, &secret, Utc::now())?;
+
+    let Some((event, created_at)) = billing::parse_event(&payload)? else {
+        return Ok(());
+    };
+
+    match user_manager
+        .apply_subscription_event(&event, created_at)
+        .await
+    {
+        // Stripe retries failed deliveries, which will not help when the account is gone
+        Err(Error::UserNotFound) => {
+            warn!(
+                subscription_id = event.subscription_id(),
+                "no account found for subscription event"
+            );
+
+            Ok(())
+        }
+        result => result,
+    }
 }
 
 #[derive(Deserialize, Serialize)]
@@ -1,4 +1,7 @@
-use std::{net::SocketAddr, path::PathBuf};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
 
 use clap::{Parser, Subcommand};
 
@@ -8,6 +11,11 @@ pub struct Args {
     #[arg(long, default_value = "./")]
     pub state: PathBuf,
 
+    /// Keep the auth state in this Postgres database instead of the SQLite file in `--state`,
+    /// such as to run several instances
+    #[arg(long)]
+    pub db_connection_uri: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -17,6 +25,8 @@ pub enum Commands {
     Start(StartArgs),
     InitAdmin(InitArgs),
     InitDeployer(InitArgs),
+    /// Copy the auth state in `--state` over to a Postgres database
+    MigrateToPostgres(MigrateArgs),
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -28,6 +38,28 @@ pub struct StartArgs {
     /// Stripe client secret key
     #[arg(long, default_value = "")]
     pub stripe_secret_key: String,
+
+    /// Secret Stripe signs its webhooks with. Webhooks are rejected when it is not set
+    #[arg(long)]
+    pub stripe_webhook_secret: Option<String>,
+
+    /// How often to rotate the key used to sign tokens, in hours. Use 0 to never rotate it
+    #[arg(long, default_value_t = 24)]
+    pub key_rotation_hours: u64,
+
+    /// Page where users approve the login of a device, such as the CLI, by entering its code
+    #[arg(long, default_value = "https://console.shuttle.rs/device")]
+    pub device_verification_uri: String,
+
+    /// Allow logging in to a browser session by account name alone, standing in for the
+    /// identity provider of the console. Only use this for local development
+    #[arg(long)]
+    pub local_identity_provider: bool,
+
+    /// Addresses of the proxies in front of this service. Only their `x-forwarded-for` header is
+    /// trusted when recording the address of the client in the audit log
+    #[arg(long, value_delimiter = ',')]
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -39,3 +71,10 @@ pub struct InitArgs {
     #[arg(long)]
     pub key: Option<String>,
 }
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MigrateArgs {
+    /// Postgres database to copy the auth state to
+    #[arg(long)]
+    pub db_connection_uri: String,
+}
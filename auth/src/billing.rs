@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, TimeZone, Utc};
+use ring::hmac;
+use serde::Deserialize;
+use stripe::SubscriptionStatus;
+
+use crate::{error::Error, user::AccountName, WEBHOOK_TOLERANCE};
+
+/// Subscription metadata key holding the account a subscription was created for
+const ACCOUNT_NAME_METADATA: &str = "account_name";
+
+/// A change to a subscription which Stripe notified us of
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SubscriptionEvent {
+    /// A subscription was created or its status changed
+    Changed {
+        subscription_id: String,
+        status: SubscriptionStatus,
+        /// The account from the subscription metadata, for new subscriptions which are not
+        /// linked to an account yet
+        account_name: Option<AccountName>,
+    },
+    /// A subscription was cancelled for good
+    Deleted { subscription_id: String },
+    /// Paying the latest invoice of a subscription failed
+    PaymentFailed { subscription_id: String },
+}
+
+impl SubscriptionEvent {
+    pub fn subscription_id(&self) -> &str {
+        match self {
+            Self::Changed {
+                subscription_id, ..
+            }
+            | Self::Deleted { subscription_id }
+            | Self::PaymentFailed { subscription_id } => subscription_id,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum StripeEvent {
+    #[serde(
+        rename = "customer.subscription.created",
+        alias = "customer.subscription.updated"
+    )]
+    SubscriptionChanged { data: EventData<Subscription> },
+    #[serde(rename = "customer.subscription.deleted")]
+    SubscriptionDeleted { data: EventData<Subscription> },
+    #[serde(rename = "invoice.payment_failed")]
+    PaymentFailed { data: EventData<Invoice> },
+    #[serde(other)]
+    Other,
+}
+
+/// When Stripe created an event. Events can be delivered out of order, so this tells which change
+/// is the latest.
+#[derive(Deserialize)]
+struct EventCreated {
+    created: i64,
+}
+
+#[derive(Deserialize)]
+struct EventData<T> {
+    object: T,
+}
+
+#[derive(Deserialize)]
+struct Subscription {
+    id: String,
+    status: SubscriptionStatus,
+    #[serde(default)]
+    metadata: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct Invoice {
+    subscription: Option<String>,
+}
+
+/// Parse the body of a Stripe webhook into the event and the time Stripe created it. Returns
+/// `None` for events which do not change a subscription.
+pub fn parse_event(payload: &[u8]) -> Result<Option<(SubscriptionEvent, DateTime<Utc>)>, Error> {
+    let event: StripeEvent = serde_json::from_slice(payload)
+        .map_err(|error| Error::InvalidWebhook(error.to_string()))?;
+    let EventCreated { created } = serde_json::from_slice(payload)
+        .map_err(|error| Error::InvalidWebhook(error.to_string()))?;
+    let created_at = Utc
+        .timestamp_opt(created, 0)
+        .single()
+        .ok_or_else(|| Error::InvalidWebhook("creation time is out of range".to_string()))?;
+
+    let event = match event {
+        StripeEvent::SubscriptionChanged {
+            data: EventData {
+                object: mut subscription,
+            },
+        } => Some(SubscriptionEvent::Changed {
+            account_name: subscription
+                .metadata
+                .remove(ACCOUNT_NAME_METADATA)
+                .map(AccountName::from),
+            subscription_id: subscription.id,
+            status: subscription.status,
+        }),
+        StripeEvent::SubscriptionDeleted {
+            data: EventData {
+                object: subscription,
+            },
+        } => Some(SubscriptionEvent::Deleted {
+            subscription_id: subscription.id,
+        }),
+        // One-off invoices do not belong to a subscription
+        StripeEvent::PaymentFailed {
+            data: EventData { object: invoice },
+        } => invoice
+            .subscription
+            .map(|subscription_id| SubscriptionEvent::PaymentFailed { subscription_id }),
+        StripeEvent::Other => None,
+    };
+
+    Ok(event.map(|event| (event, created_at)))
+}
+
+/// Check the `Stripe-Signature` header of a webhook, which holds a timestamp and HMACs of the
+/// timestamp and payload. See <https://stripe.com/docs/webhooks#verify-manually>.
+pub fn verify_signature(
+    payload: &[u8],
+    header: &str,
+    secret: &str,
+    now: DateTime<Utc>,
+) -> Result<(), Error> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+
+    for (key, value) in header.split(',').filter_map(|part| part.split_once('=')) {
+        match key.trim() {
+            "t" => timestamp = value.parse::<i64>().ok(),
+            // Stripe sends one signature per active secret while a secret is being rolled
+            "v1" => signatures.extend(decode_hex(value)),
+            _ => {}
+        }
+    }
+
+    let timestamp =
+        timestamp.ok_or_else(|| Error::InvalidWebhook("missing timestamp".to_string()))?;
+
+    if now.timestamp().abs_diff(timestamp) > WEBHOOK_TOLERANCE.as_secs() {
+        return Err(Error::InvalidWebhook(
+            "timestamp is outside the tolerance".to_string(),
+        ));
+    }
+
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let signed_payload = [format!("{timestamp}.").as_bytes(), payload].concat();
+
+    if signatures
+        .iter()
+        .any(|signature| hmac::verify(&key, &signed_payload, signature).is_ok())
+    {
+        Ok(())
+    } else {
+        Err(Error::InvalidWebhook("no matching signature".to_string()))
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+    use ring::hmac;
+    use stripe::SubscriptionStatus;
+
+    use super::{parse_event, verify_signature, SubscriptionEvent};
+
+    fn sign(payload: &str, secret: &str, timestamp: i64) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let tag = hmac::sign(&key, format!("{timestamp}.{payload}").as_bytes());
+        let signature: String = tag
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        format!("t={timestamp},v1={signature}")
+    }
+
+    #[test]
+    fn verifies_signatures() {
+        let payload = r#"{"type":"customer.subscription.deleted"}"#;
+        let now = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let header = sign(payload, "whsec_test", now.timestamp());
+
+        assert!(verify_signature(payload.as_bytes(), &header, "whsec_test", now).is_ok());
+
+        // Wrong secret
+        assert!(verify_signature(payload.as_bytes(), &header, "whsec_other", now).is_err());
+
+        // Changed payload
+        assert!(verify_signature(b"{}", &header, "whsec_test", now).is_err());
+
+        // Replayed too late
+        assert!(verify_signature(
+            payload.as_bytes(),
+            &header,
+            "whsec_test",
+            now + Duration::minutes(10)
+        )
+        .is_err());
+
+        // Missing parts
+        assert!(verify_signature(payload.as_bytes(), "v1=abcd", "whsec_test", now).is_err());
+        assert!(verify_signature(payload.as_bytes(), "", "whsec_test", now).is_err());
+    }
+
+    #[test]
+    fn parses_events() {
+        let event = parse_event(
+            br#"{
+                "type": "customer.subscription.updated",
+                "created": 1700000000,
+                "data": { "object": { "id": "sub_1", "status": "past_due", "metadata": { "account_name": "ferries" } } }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            event,
+            Some((
+                SubscriptionEvent::Changed {
+                    subscription_id: "sub_1".to_string(),
+                    status: SubscriptionStatus::PastDue,
+                    account_name: Some("ferries".to_string().into()),
+                },
+                Utc.timestamp_opt(1_700_000_000, 0).unwrap()
+            ))
+        );
+
+        let event = parse_event(
+            br#"{ "type": "invoice.payment_failed", "created": 1700000000, "data": { "object": { "subscription": "sub_1" } } }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            event,
+            Some((
+                SubscriptionEvent::PaymentFailed {
+                    subscription_id: "sub_1".to_string()
+                },
+                Utc.timestamp_opt(1_700_000_000, 0).unwrap()
+            ))
+        );
+
+        // Events we do not act on are accepted and ignored
+        let event = parse_event(
+            br#"{ "type": "customer.created", "created": 1700000000, "data": { "object": {} } }"#,
+        )
+        .unwrap();
+        assert_eq!(event, None);
+
+        // Without a creation time, there is no telling whether the event is stale
+        assert!(parse_event(
+            br#"{ "type": "invoice.payment_failed", "data": { "object": { "subscription": "sub_1" } } }"#,
+        )
+        .is_err());
+
+        assert!(parse_event(b"not json").is_err());
+    }
+}
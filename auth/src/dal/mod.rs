@@ -0,0 +1,203 @@
+mod postgres;
+mod sqlite;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use shuttle_common::{backends::audit::AuditEvent, ApiKey};
+use thiserror::Error;
+
+use crate::user::{AccountName, AccountTier, ApiToken, RefreshToken, User};
+
+pub use self::postgres::Postgres;
+pub use self::sqlite::Sqlite;
+
+#[derive(Error, Debug)]
+pub enum DalError {
+    #[error("database request failed: {0}")]
+    Sqlx(#[from] sqlx::Error),
+    #[error("failed to run migrations: {0}")]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+}
+
+/// Storage of the auth state. Implemented for SQLite to run a single node, and for Postgres to
+/// run several nodes sharing the same state.
+#[async_trait]
+pub trait Dal: Send + Sync {
+    /// Add a new user
+    async fn insert_user(&self, user: &User) -> Result<(), DalError>;
+
+    async fn get_user(&self, name: &AccountName) -> Result<Option<User>, DalError>;
+
+    async fn get_user_by_key(&self, key: &ApiKey) -> Result<Option<User>, DalError>;
+
+    async fn get_user_by_subscription_id(
+        &self,
+        subscription_id: &str,
+    ) -> Result<Option<User>, DalError>;
+
+    /// Get every user, such as to copy them to other storage
+    async fn get_users(&self) -> Result<Vec<User>, DalError>;
+
+    /// Change the tier of a user, and its subscription when one is given. Returns whether the
+    /// user exists.
+    async fn update_tier(
+        &self,
+        name: &AccountName,
+        tier: AccountTier,
+        subscription_id: Option<&str>,
+    ) -> Result<bool, DalError>;
+
+    /// Set the tier, subscription and grace period of a user from a billing event created at
+    /// `event_created_at`. Returns `false` when the user does not exist, or when an event created
+    /// later was already applied to it.
+    async fn update_subscription(
+        &self,
+        name: &AccountName,
+        tier: AccountTier,
+        subscription_id: Option<&str>,
+        grace_period_ends_at: Option<DateTime<Utc>>,
+        event_created_at: DateTime<Utc>,
+    ) -> Result<bool, DalError>;
+
+    /// Replace the key of a user, revoking the refresh tokens of the old key. Returns whether the
+    /// user exists.
+    async fn update_key(&self, name: &AccountName, key: &ApiKey) -> Result<bool, DalError>;
+
+    /// Suspend a user or lift its suspension. Suspending revokes all its refresh tokens. Returns
+    /// whether the user exists.
+    async fn update_suspended(&self, name: &AccountName, suspended: bool)
+        -> Result<bool, DalError>;
+
+    /// Delete a user together with its API tokens, refresh tokens and device codes. Returns
+    /// whether the user existed.
+    async fn delete_user(&self, name: &AccountName) -> Result<bool, DalError>;
+
+    /// Add an API token. Returns `false` when the user already has a token with this name.
+    async fn insert_api_token(&self, token: &ApiToken) -> Result<bool, DalError>;
+
+    async fn get_api_tokens(&self, name: &AccountName) -> Result<Vec<ApiToken>, DalError>;
+
+    async fn get_api_token_by_key(&self, key: &ApiKey) -> Result<Option<ApiToken>, DalError>;
+
+    /// Delete an API token together with its refresh tokens. Returns whether the token existed.
+    async fn delete_api_token(
+        &self,
+        name: &AccountName,
+        token_name: &str,
+    ) -> Result<bool, DalError>;
+
+    /// Add the hash of a refresh token, cleaning up the tokens that have expired
+    async fn insert_refresh_token(
+        &self,
+        token_hash: &str,
+        token: &RefreshToken,
+    ) -> Result<(), DalError>;
+
+    /// Get a refresh token by its hash, together with whether it was revoked
+    async fn get_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<(RefreshToken, bool)>, DalError>;
+
+    /// Revoke a refresh token. Returns `false` when it was already revoked.
+    async fn revoke_refresh_token(&self, token_hash: &str) -> Result<bool, DalError>;
+
+    /// Revoke all the refresh tokens of an account
+    async fn revoke_refresh_tokens(&self, name: &AccountName) -> Result<(), DalError>;
+
+    async fn delete_refresh_token(&self, token_hash: &str) -> Result<(), DalError>;
+
+    async fn insert_audit_event(&self, event: &AuditEvent) -> Result<(), DalError>;
+
+    /// Get the most recent audit events first, optionally only those of one actor
+    async fn get_audit_events(
+        &self,
+        actor: Option<&AccountName>,
+        limit: u32,
+    ) -> Result<Vec<AuditEvent>, DalError>;
+
+    /// Add a device code, cleaning up the codes that have expired
+    async fn insert_device_code(
+        &self,
+        device_code_hash: &str,
+        user_code: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), DalError>;
+
+    /// Get the user code of a device code that has not expired, and the account that approved
+    /// it if any
+    async fn get_device_code(
+        &self,
+        device_code_hash: &str,
+    ) -> Result<Option<(String, Option<AccountName>)>, DalError>;
+
+    /// Approve a device code that has not expired and was not approved yet. Returns whether one
+    /// was approved.
+    async fn approve_device_code(
+        &self,
+        user_code: &str,
+        name: &AccountName,
+    ) -> Result<bool, DalError>;
+
+    /// Returns whether the code existed, so only one caller gets to use it
+    async fn delete_device_code(&self, device_code_hash: &str) -> Result<bool, DalError>;
+
+    /// Add a signing key, unless another one was created after `unless_created_after`. This keeps
+    /// instances sharing the storage from rotating at the same time. Returns whether the key was
+    /// added.
+    async fn insert_signing_key(
+        &self,
+        key: &StoredSigningKey,
+        unless_created_after: DateTime<Utc>,
+    ) -> Result<bool, DalError>;
+
+    /// Get the most recently created signing keys first
+    async fn get_signing_keys(&self, limit: u32) -> Result<Vec<StoredSigningKey>, DalError>;
+}
+
+/// A signing key as it is kept in storage.
+///
+/// The private key is stored as is, without being encrypted. Anyone able to read the storage, or
+/// a backup of it, can sign tokens for any account, so access to it has to be as restricted as
+/// access to the keys themselves.
+#[derive(Clone)]
+pub struct StoredSigningKey {
+    pub kid: String,
+    /// The private key in PKCS#8 form
+    pub pkcs8: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The state worth keeping when moving to other storage
+pub struct State {
+    pub users: Vec<User>,
+    pub api_tokens: Vec<ApiToken>,
+    /// Oldest first
+    pub audit_events: Vec<AuditEvent>,
+    pub signing_keys: Vec<StoredSigningKey>,
+}
+
+/// Copy all the state in `from` over to `to`, such as to move from SQLite to Postgres. The state
+/// is copied in a single transaction, and copying it again only adds what is still missing.
+pub async fn copy_state(from: &Sqlite, to: &Postgres) -> Result<(), DalError> {
+    let users = from.get_users().await?;
+
+    let mut api_tokens = Vec::new();
+    for user in &users {
+        api_tokens.extend(from.get_api_tokens(&user.name).await?);
+    }
+
+    let mut audit_events = from.get_audit_events(None, u32::MAX).await?;
+    audit_events.reverse();
+
+    // Refresh tokens and device codes are short-lived, so they are left behind. Clients get new
+    // ones by logging in again.
+    let state = State {
+        users,
+        api_tokens,
+        audit_events,
+        signing_keys: from.get_signing_keys(u32::MAX).await?,
+    };
+
+    to.import_state(&state).await
+}
@@ -0,0 +1,645 @@
+use std::{collections::HashMap, str::FromStr};
+
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use shuttle_common::{backends::audit::AuditEvent, claims::Scope, secrets::Secret, ApiKey};
+use sqlx::{migrate::Migrator, postgres::PgRow, query, types::Json, FromRow, PgPool, Row};
+use stripe::SubscriptionId;
+
+use super::{Dal, DalError, State, StoredSigningKey};
+use crate::user::{AccountName, AccountTier, ApiToken, RefreshToken, User};
+
+pub static MIGRATIONS: Migrator = sqlx::migrate!("./postgres-migrations");
+
+#[derive(Clone)]
+pub struct Postgres {
+    pool: PgPool,
+}
+
+impl Postgres {
+    /// Connect to a Postgres database and bring its schema up to date
+    pub async fn new(connection_uri: &str) -> Result<Self, DalError> {
+        let pool = PgPool::connect(connection_uri).await?;
+
+        Self::from_pool(pool).await
+    }
+
+    /// Use an existing pool, running the migrations on it
+    pub async fn from_pool(pool: PgPool) -> Result<Self, DalError> {
+        MIGRATIONS.run(&pool).await?;
+
+        Ok(Self { pool })
+    }
+
+    /// Add the state copied from other storage in a single transaction. Rows that are already
+    /// here are left as they are, so importing the same state again changes nothing.
+    pub(super) async fn import_state(&self, state: &State) -> Result<(), DalError> {
+        let mut transaction = self.pool.begin().await?;
+
+        for user in &state.users {
+            query(
+                "INSERT INTO users (account_name, key, account_tier, subscription_id, suspended, grace_period_ends_at) VALUES ($1, $2, $3, $4, $5, $6) ON CONFLICT DO NOTHING",
+            )
+            .bind(&user.name)
+            .bind(user.key.expose())
+            .bind(user.account_tier.to_string())
+            .bind(user.subscription_id.as_ref().map(ToString::to_string))
+            .bind(user.suspended)
+            .bind(user.grace_period_ends_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        for token in &state.api_tokens {
+            query(
+                "INSERT INTO api_tokens (key, account_name, name, scopes, project_name, expires_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT DO NOTHING",
+            )
+            .bind(token.key.expose())
+            .bind(&token.account_name)
+            .bind(&token.name)
+            .bind(Json(&token.scopes))
+            .bind(&token.project)
+            .bind(token.expires_at)
+            .bind(token.created_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        // Audit events have no key of their own, so an event is only added when there are fewer
+        // copies of it here than have been seen in the imported state so far
+        let mut seen: HashMap<_, i64> = HashMap::new();
+        let mut existing: HashMap<_, i64> = HashMap::new();
+        for event in &state.audit_events {
+            let key = (
+                event.timestamp.timestamp(),
+                event.actor.as_deref(),
+                event.action.as_str(),
+                event.target.as_str(),
+                event.source_ip.as_deref(),
+                i32::from(event.outcome),
+            );
+
+            let count = match existing.get(&key) {
+                Some(count) => *count,
+                None => {
+                    let count: i64 = query(
+                        "SELECT COUNT(*) FROM audit_log WHERE timestamp = $1 AND actor IS NOT DISTINCT FROM $2 AND action = $3 AND target = $4 AND source_ip IS NOT DISTINCT FROM $5 AND outcome = $6",
+                    )
+                    .bind(key.0)
+                    .bind(key.1)
+                    .bind(key.2)
+                    .bind(key.3)
+                    .bind(key.4)
+                    .bind(key.5)
+                    .fetch_one(&mut *transaction)
+                    .await?
+                    .get(0);
+                    existing.insert(key, count);
+                    count
+                }
+            };
+
+            let copies = seen.entry(key).or_default();
+            *copies += 1;
+            if *copies <= count {
+                continue;
+            }
+
+            query("INSERT INTO audit_log (timestamp, actor, action, target, source_ip, outcome) VALUES ($1, $2, $3, $4, $5, $6)")
+                .bind(key.0)
+                .bind(key.1)
+                .bind(key.2)
+                .bind(key.3)
+                .bind(key.4)
+                .bind(key.5)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        // Every key is kept, even when newer keys were already created here, so tokens signed
+        // by the old keys can still be verified
+        for key in &state.signing_keys {
+            query(
+                "INSERT INTO signing_keys (kid, pkcs8, created_at) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING",
+            )
+            .bind(&key.kid)
+            .bind(&key.pkcs8)
+            .bind(key.created_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Dal for Postgres {
+    async fn insert_user(&self, user: &User) -> Result<(), DalError> {
+        query(
+            "INSERT INTO users (account_name, key, account_tier, subscription_id, suspended, grace_period_ends_at) VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(&user.name)
+        .bind(user.key.expose())
+        .bind(user.account_tier.to_string())
+        .bind(user.subscription_id.as_ref().map(ToString::to_string))
+        .bind(user.suspended)
+        .bind(user.grace_period_ends_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_user(&self, name: &AccountName) -> Result<Option<User>, DalError> {
+        let user = sqlx::query_as(
+            "SELECT account_name, key, account_tier, subscription_id, suspended, grace_period_ends_at FROM users WHERE account_name = $1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn get_user_by_key(&self, key: &ApiKey) -> Result<Option<User>, DalError> {
+        let user = sqlx::query_as(
+            "SELECT account_name, key, account_tier, subscription_id, suspended, grace_period_ends_at FROM users WHERE key = $1",
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn get_user_by_subscription_id(
+        &self,
+        subscription_id: &str,
+    ) -> Result<Option<User>, DalError> {
+        let user = sqlx::query_as(
+            "SELECT account_name, key, account_tier, subscription_id, suspended, grace_period_ends_at FROM users WHERE subscription_id = $1",
+        )
+        .bind(subscription_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(user)
+    }
+
+    async fn get_users(&self) -> Result<Vec<User>, DalError> {
+        let users = sqlx::query_as(
+            "SELECT account_name, key, account_tier, subscription_id, suspended, grace_period_ends_at FROM users",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(users)
+    }
+
+    async fn update_tier(
+        &self,
+        name: &AccountName,
+        tier: AccountTier,
+        subscription_id: Option<&str>,
+    ) -> Result<bool, DalError> {
+        let rows_affected = match subscription_id {
+            Some(subscription_id) => query(
+                "UPDATE users SET account_tier = $1, subscription_id = $2 WHERE account_name = $3",
+            )
+            .bind(tier.to_string())
+            .bind(subscription_id)
+            .bind(name)
+            .execute(&self.pool)
+            .await?,
+            None => {
+                query("UPDATE users SET account_tier = $1 WHERE account_name = $2")
+                    .bind(tier.to_string())
+                    .bind(name)
+                    .execute(&self.pool)
+                    .await?
+            }
+        }
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn update_subscription(
+        &self,
+        name: &AccountName,
+        tier: AccountTier,
+        subscription_id: Option<&str>,
+        grace_period_ends_at: Option<DateTime<Utc>>,
+        event_created_at: DateTime<Utc>,
+    ) -> Result<bool, DalError> {
+        let rows_affected = query(
+            "UPDATE users SET account_tier = $1, subscription_id = $2, grace_period_ends_at = $3, subscription_event_at = $4 WHERE account_name = $5 AND (subscription_event_at IS NULL OR subscription_event_at <= $4)",
+        )
+        .bind(tier.to_string())
+        .bind(subscription_id)
+        .bind(grace_period_ends_at)
+        .bind(event_created_at)
+        .bind(name)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn update_key(&self, name: &AccountName, key: &ApiKey) -> Result<bool, DalError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let rows_affected = query("UPDATE users SET key = $1 WHERE account_name = $2")
+            .bind(key)
+            .bind(name)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+
+        query("DELETE FROM refresh_tokens WHERE account_name = $1 AND api_token_name IS NULL")
+            .bind(name)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn update_suspended(
+        &self,
+        name: &AccountName,
+        suspended: bool,
+    ) -> Result<bool, DalError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let rows_affected = query("UPDATE users SET suspended = $1 WHERE account_name = $2")
+            .bind(suspended)
+            .bind(name)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+
+        if suspended {
+            query("DELETE FROM refresh_tokens WHERE account_name = $1")
+                .bind(name)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn delete_user(&self, name: &AccountName) -> Result<bool, DalError> {
+        let mut transaction = self.pool.begin().await?;
+
+        for table in ["api_tokens", "refresh_tokens", "device_codes"] {
+            query(&format!("DELETE FROM {table} WHERE account_name = $1"))
+                .bind(name)
+                .execute(&mut *transaction)
+                .await?;
+        }
+
+        let rows_affected = query("DELETE FROM users WHERE account_name = $1")
+            .bind(name)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+
+        transaction.commit().await?;
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn insert_api_token(&self, token: &ApiToken) -> Result<bool, DalError> {
+        let rows_affected = query(
+            "INSERT INTO api_tokens (key, account_name, name, scopes, project_name, expires_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT DO NOTHING",
+        )
+        .bind(token.key.expose())
+        .bind(&token.account_name)
+        .bind(&token.name)
+        .bind(Json(&token.scopes))
+        .bind(&token.project)
+        .bind(token.expires_at)
+        .bind(token.created_at)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn get_api_tokens(&self, name: &AccountName) -> Result<Vec<ApiToken>, DalError> {
+        let tokens = sqlx::query_as(
+            "SELECT key, account_name, name, scopes, project_name, expires_at, created_at FROM api_tokens WHERE account_name = $1 ORDER BY created_at",
+        )
+        .bind(name)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    async fn get_api_token_by_key(&self, key: &ApiKey) -> Result<Option<ApiToken>, DalError> {
+        let token = sqlx::query_as(
+            "SELECT key, account_name, name, scopes, project_name, expires_at, created_at FROM api_tokens WHERE key = $1",
+        )
+        .bind(key)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    async fn delete_api_token(
+        &self,
+        name: &AccountName,
+        token_name: &str,
+    ) -> Result<bool, DalError> {
+        let mut transaction = self.pool.begin().await?;
+
+        let rows_affected = query("DELETE FROM api_tokens WHERE account_name = $1 AND name = $2")
+            .bind(name)
+            .bind(token_name)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+
+        query("DELETE FROM refresh_tokens WHERE account_name = $1 AND api_token_name = $2")
+            .bind(name)
+            .bind(token_name)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn insert_refresh_token(
+        &self,
+        token_hash: &str,
+        token: &RefreshToken,
+    ) -> Result<(), DalError> {
+        query("DELETE FROM refresh_tokens WHERE expires_at < $1")
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+
+        query(
+            "INSERT INTO refresh_tokens (token_hash, account_name, api_token_name, expires_at) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(token_hash)
+        .bind(&token.account_name)
+        .bind(&token.api_token_name)
+        .bind(token.expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_refresh_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<(RefreshToken, bool)>, DalError> {
+        let token = query(
+            "SELECT account_name, api_token_name, expires_at, revoked FROM refresh_tokens WHERE token_hash = $1",
+        )
+        .bind(token_hash)
+        .try_map(|row: PgRow| {
+            Ok((
+                RefreshToken {
+                    account_name: row.try_get("account_name")?,
+                    api_token_name: row.try_get("api_token_name")?,
+                    expires_at: row.try_get("expires_at")?,
+                },
+                row.try_get("revoked")?,
+            ))
+        })
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    async fn revoke_refresh_token(&self, token_hash: &str) -> Result<bool, DalError> {
+        let rows_affected = query(
+            "UPDATE refresh_tokens SET revoked = TRUE WHERE token_hash = $1 AND revoked = FALSE",
+        )
+        .bind(token_hash)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn revoke_refresh_tokens(&self, name: &AccountName) -> Result<(), DalError> {
+        query("UPDATE refresh_tokens SET revoked = TRUE WHERE account_name = $1")
+            .bind(name)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_refresh_token(&self, token_hash: &str) -> Result<(), DalError> {
+        query("DELETE FROM refresh_tokens WHERE token_hash = $1")
+            .bind(token_hash)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn insert_audit_event(&self, event: &AuditEvent) -> Result<(), DalError> {
+        query("INSERT INTO audit_log (timestamp, actor, action, target, source_ip, outcome) VALUES ($1, $2, $3, $4, $5, $6)")
+            .bind(event.timestamp.timestamp())
+            .bind(&event.actor)
+            .bind(&event.action)
+            .bind(&event.target)
+            .bind(&event.source_ip)
+            .bind(i32::from(event.outcome))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn get_audit_events(
+        &self,
+        actor: Option<&AccountName>,
+        limit: u32,
+    ) -> Result<Vec<AuditEvent>, DalError> {
+        let events = query(
+            "SELECT timestamp, actor, action, target, source_ip, outcome FROM audit_log WHERE $1::TEXT IS NULL OR actor = $1 ORDER BY id DESC LIMIT $2",
+        )
+        .bind(actor)
+        .bind(i64::from(limit))
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| AuditEvent {
+            actor: row.get("actor"),
+            action: row.get("action"),
+            target: row.get("target"),
+            source_ip: row.get("source_ip"),
+            outcome: row.get::<i32, _>("outcome").try_into().unwrap_or_default(),
+            timestamp: Utc
+                .timestamp_opt(row.get("timestamp"), 0)
+                .single()
+                .unwrap_or_default(),
+        })
+        .collect();
+
+        Ok(events)
+    }
+
+    async fn insert_device_code(
+        &self,
+        device_code_hash: &str,
+        user_code: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), DalError> {
+        query("DELETE FROM device_codes WHERE expires_at < $1")
+            .bind(Utc::now())
+            .execute(&self.pool)
+            .await?;
+
+        query(
+            "INSERT INTO device_codes (device_code_hash, user_code, expires_at) VALUES ($1, $2, $3)",
+        )
+        .bind(device_code_hash)
+        .bind(user_code)
+        .bind(expires_at)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_device_code(
+        &self,
+        device_code_hash: &str,
+    ) -> Result<Option<(String, Option<AccountName>)>, DalError> {
+        let device_code = query(
+            "SELECT user_code, account_name FROM device_codes WHERE device_code_hash = $1 AND expires_at > $2",
+        )
+        .bind(device_code_hash)
+        .bind(Utc::now())
+        .try_map(|row: PgRow| Ok((row.try_get("user_code")?, row.try_get("account_name")?)))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(device_code)
+    }
+
+    async fn approve_device_code(
+        &self,
+        user_code: &str,
+        name: &AccountName,
+    ) -> Result<bool, DalError> {
+        let rows_affected = query(
+            "UPDATE device_codes SET account_name = $1 WHERE user_code = $2 AND account_name IS NULL AND expires_at > $3",
+        )
+        .bind(name)
+        .bind(user_code)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn delete_device_code(&self, device_code_hash: &str) -> Result<bool, DalError> {
+        let rows_affected = query("DELETE FROM device_codes WHERE device_code_hash = $1")
+            .bind(device_code_hash)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn insert_signing_key(
+        &self,
+        key: &StoredSigningKey,
+        unless_created_after: DateTime<Utc>,
+    ) -> Result<bool, DalError> {
+        let rows_affected = query(
+            "INSERT INTO signing_keys (kid, pkcs8, created_at) SELECT $1, $2, $3 WHERE NOT EXISTS (SELECT 1 FROM signing_keys WHERE created_at > $4)",
+        )
+        .bind(&key.kid)
+        .bind(&key.pkcs8)
+        .bind(key.created_at)
+        .bind(unless_created_after)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+
+        Ok(rows_affected > 0)
+    }
+
+    async fn get_signing_keys(&self, limit: u32) -> Result<Vec<StoredSigningKey>, DalError> {
+        let keys = query(
+            "SELECT kid, pkcs8, created_at FROM signing_keys ORDER BY created_at DESC LIMIT $1",
+        )
+        .bind(i64::from(limit))
+        .try_map(|row: PgRow| {
+            Ok(StoredSigningKey {
+                kid: row.try_get("kid")?,
+                pkcs8: row.try_get("pkcs8")?,
+                created_at: row.try_get("created_at")?,
+            })
+        })
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(keys)
+    }
+}
+
+impl FromRow<'_, PgRow> for User {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        let account_tier: String = row.try_get("account_tier")?;
+
+        Ok(User {
+            name: row.try_get("account_name")?,
+            key: Secret::new(row.try_get("key")?),
+            account_tier: AccountTier::from_str(&account_tier).map_err(|err| {
+                sqlx::Error::ColumnDecode {
+                    index: "account_tier".to_string(),
+                    source: Box::new(err),
+                }
+            })?,
+            subscription_id: row
+                .try_get("subscription_id")
+                .ok()
+                .and_then(|inner| SubscriptionId::from_str(inner).ok()),
+            suspended: row.try_get("suspended")?,
+            grace_period_ends_at: row.try_get("grace_period_ends_at")?,
+        })
+    }
+}
+
+impl FromRow<'_, PgRow> for ApiToken {
+    fn from_row(row: &PgRow) -> Result<Self, sqlx::Error> {
+        Ok(ApiToken {
+            key: Secret::new(row.try_get("key")?),
+            account_name: row.try_get("account_name")?,
+            name: row.try_get("name")?,
+            scopes: row.try_get::<Json<Vec<Scope>>, _>("scopes")?.0,
+            project: row.try_get("project_name")?,
+            expires_at: row.try_get("expires_at")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
@@ -5,9 +5,11 @@ use axum::response::{IntoResponse, Response};
 use axum::Json;
 
 use serde::{ser::SerializeMap, Serialize};
-use shuttle_common::models::error::ApiError;
+use shuttle_common::models::error::{ApiError, ErrorKind};
 use stripe::StripeError;
 
+use crate::dal::DalError;
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error("User could not be found")]
@@ -30,6 +32,18 @@ pub enum Error {
     StripeError(#[from] StripeError),
     #[error("Missing subscription ID from the checkout session.")]
     MissingSubscriptionId,
+    #[error("API token could not be found.")]
+    TokenNotFound,
+    #[error("An API token with this name already exists.")]
+    TokenAlreadyExists,
+    #[error("Invalid API token: {0}")]
+    InvalidToken(String),
+    #[error("Device code could not be found or has expired.")]
+    DeviceCodeNotFound,
+    #[error("This account has been suspended.")]
+    AccountSuspended,
+    #[error("Invalid Stripe webhook: {0}")]
+    InvalidWebhook(String),
 }
 
 impl Serialize for Error {
@@ -48,14 +62,38 @@ impl Serialize for Error {
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         let code = match self {
-            Error::Forbidden => StatusCode::FORBIDDEN,
+            Error::Forbidden | Error::AccountSuspended => StatusCode::FORBIDDEN,
             Error::Unauthorized | Error::KeyMissing => StatusCode::UNAUTHORIZED,
-            Error::Database(_) | Error::UserNotFound => StatusCode::NOT_FOUND,
+            Error::Database(_)
+            | Error::UserNotFound
+            | Error::TokenNotFound
+            | Error::DeviceCodeNotFound => StatusCode::NOT_FOUND,
             Error::MissingCheckoutSession
             | Error::MissingSubscriptionId
-            | Error::IncompleteCheckoutSession => StatusCode::BAD_REQUEST,
+            | Error::IncompleteCheckoutSession
+            | Error::TokenAlreadyExists
+            | Error::InvalidToken(_)
+            | Error::InvalidWebhook(_) => StatusCode::BAD_REQUEST,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
+        let kind = match self {
+            Error::UserNotFound => ErrorKind::UserNotFound,
+            Error::KeyMissing => ErrorKind::KeyMissing,
+            Error::Unauthorized => ErrorKind::Unauthorized,
+            Error::Forbidden => ErrorKind::Forbidden,
+            Error::AccountSuspended => ErrorKind::AccountSuspended,
+            Error::TokenNotFound => ErrorKind::TokenNotFound,
+            Error::TokenAlreadyExists => ErrorKind::TokenAlreadyExists,
+            Error::DeviceCodeNotFound => ErrorKind::DeviceCodeNotFound,
+            Error::MissingCheckoutSession
+            | Error::MissingSubscriptionId
+            | Error::IncompleteCheckoutSession
+            | Error::InvalidToken(_)
+            | Error::InvalidWebhook(_) => ErrorKind::InvalidOperation,
+            Error::Database(_) | Error::UnexpectedError(_) | Error::StripeError(_) => {
+                ErrorKind::Internal
+            }
+        };
 
         (
             code,
@@ -63,11 +101,17 @@ impl IntoResponse for Error {
                 header::CONTENT_TYPE,
                 HeaderValue::from_static("application/json"),
             )],
-            Json(ApiError {
-                message: self.to_string(),
-                status_code: code.as_u16(),
-            }),
+            Json(ApiError::with_kind(self, code, kind)),
         )
             .into_response()
     }
 }
+
+impl From<DalError> for Error {
+    fn from(error: DalError) -> Self {
+        match error {
+            DalError::Sqlx(error) => Error::Database(error),
+            DalError::Migrate(error) => Error::UnexpectedError(error.into()),
+        }
+    }
+}
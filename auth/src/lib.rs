@@ -1,36 +1,63 @@
 mod api;
 mod args;
+mod billing;
+mod dal;
 mod error;
 mod secrets;
 mod user;
 
-use std::{io, str::FromStr, time::Duration};
+use std::{io, str::FromStr, sync::Arc, time::Duration};
 
 use args::StartArgs;
+use dal::copy_state;
 use shuttle_common::ApiKey;
 use sqlx::{
     migrate::Migrator,
-    query,
     sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteSynchronous},
     SqlitePool,
 };
-use tracing::info;
+use tracing::{info, warn};
 
-use crate::api::serve;
+use crate::{api::serve, user::User};
 pub use api::ApiBuilder;
-pub use args::{Args, Commands, InitArgs};
+pub use args::{Args, Commands, InitArgs, MigrateArgs};
+pub use dal::{Dal, Postgres, Sqlite};
 pub use user::AccountTier;
 
 pub const COOKIE_EXPIRATION: Duration = Duration::from_secs(60 * 60 * 24); // One day
+pub const REFRESH_TOKEN_EXPIRATION: Duration = Duration::from_secs(60 * 60 * 24 * 30); // 30 days
+pub const DEVICE_CODE_EXPIRATION: Duration = Duration::from_secs(60 * 15); // 15 minutes
+/// How long devices should wait between polls for the approval of their device code
+pub const DEVICE_CODE_INTERVAL: Duration = Duration::from_secs(5);
+/// How long accounts keep their tier after a subscription payment fails
+pub const PAYMENT_GRACE_PERIOD: Duration = Duration::from_secs(60 * 60 * 24 * 7); // 7 days
+/// How old a Stripe webhook can be before it is rejected, to stop replays
+pub const WEBHOOK_TOLERANCE: Duration = Duration::from_secs(60 * 5); // 5 minutes
 
 pub static MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
 
-pub async fn start(pool: SqlitePool, args: StartArgs) -> io::Result<()> {
-    let router = api::ApiBuilder::new()
-        .with_sqlite_pool(pool)
+pub async fn start(dal: Arc<dyn Dal>, args: StartArgs) -> io::Result<()> {
+    let mut builder = api::ApiBuilder::new()
+        .with_dal(dal)
         .with_sessions()
         .with_stripe_client(stripe::Client::new(args.stripe_secret_key))
-        .into_router();
+        .with_device_verification_uri(args.device_verification_uri)
+        .with_trusted_proxies(args.trusted_proxies);
+
+    if let Some(secret) = args.stripe_webhook_secret {
+        builder = builder.with_stripe_webhook_secret(secret);
+    }
+
+    if args.key_rotation_hours > 0 {
+        builder = builder.with_key_rotation(Duration::from_secs(args.key_rotation_hours * 60 * 60));
+    }
+
+    if args.local_identity_provider {
+        warn!("logging in by account name alone is enabled, this should only be used locally");
+        builder = builder.with_local_identity_provider();
+    }
+
+    let router = builder.into_router().await;
 
     info!(address=%args.address, "Binding to and listening at address");
 
@@ -39,19 +66,20 @@ pub async fn start(pool: SqlitePool, args: StartArgs) -> io::Result<()> {
     Ok(())
 }
 
-pub async fn init(pool: SqlitePool, args: InitArgs, tier: AccountTier) -> io::Result<()> {
+pub async fn init(dal: Arc<dyn Dal>, args: InitArgs, tier: AccountTier) -> io::Result<()> {
     let key = match args.key {
         Some(ref key) => ApiKey::parse(key).unwrap(),
         None => ApiKey::generate(),
     };
 
-    query("INSERT INTO users (account_name, key, account_tier) VALUES (?1, ?2, ?3)")
-        .bind(&args.name)
-        .bind(&key)
-        .bind(tier)
-        .execute(&pool)
-        .await
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    dal.insert_user(&User::new(
+        args.name.clone().into(),
+        key.clone(),
+        tier,
+        None,
+    ))
+    .await
+    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
 
     println!(
         "`{}` created as {} with key: {}",
@@ -62,6 +90,21 @@ pub async fn init(pool: SqlitePool, args: InitArgs, tier: AccountTier) -> io::Re
     Ok(())
 }
 
+/// Copy all the state in the SQLite database over to a Postgres database
+pub async fn migrate_to_postgres(pool: SqlitePool, args: MigrateArgs) -> io::Result<()> {
+    let to = Postgres::new(&args.db_connection_uri)
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    copy_state(&Sqlite::from_pool(pool), &to)
+        .await
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+
+    info!("copied the auth state over to Postgres");
+
+    Ok(())
+}
+
 /// Initialize an SQLite database at the given URI, creating it if it does not
 /// already exist. To create an in-memory database for tests, simply pass in
 /// `sqlite::memory:` for the `db_uri`.
@@ -1,11 +1,14 @@
-use std::io;
+use std::{io, path::Path, sync::Arc};
 
 use clap::Parser;
 use shuttle_common::{backends::tracing::setup_tracing, log::Backend};
-use sqlx::migrate::Migrator;
+use sqlx::{migrate::Migrator, SqlitePool};
 use tracing::{info, trace};
 
-use shuttle_auth::{init, sqlite_init, start, AccountTier, Args, Commands};
+use shuttle_auth::{
+    init, migrate_to_postgres, sqlite_init, start, AccountTier, Args, Commands, Dal, Postgres,
+    Sqlite,
+};
 
 pub static MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
 
@@ -17,7 +20,42 @@ async fn main() -> io::Result<()> {
 
     setup_tracing(tracing_subscriber::registry(), Backend::Auth, None);
 
-    let db_path = args.state.join("authentication.sqlite");
+    match args.command {
+        Commands::Start(start_args) => {
+            let dal = open_dal(&args.state, args.db_connection_uri.as_deref()).await?;
+            start(dal, start_args).await
+        }
+        Commands::InitAdmin(init_args) => {
+            let dal = open_dal(&args.state, args.db_connection_uri.as_deref()).await?;
+            init(dal, init_args, AccountTier::Admin).await
+        }
+        Commands::InitDeployer(init_args) => {
+            let dal = open_dal(&args.state, args.db_connection_uri.as_deref()).await?;
+            init(dal, init_args, AccountTier::Deployer).await
+        }
+        Commands::MigrateToPostgres(migrate_args) => {
+            migrate_to_postgres(open_sqlite(&args.state).await, migrate_args).await
+        }
+    }
+}
+
+/// Keep the state in Postgres when a database is given, and in the SQLite file in `state` otherwise
+async fn open_dal(state: &Path, db_connection_uri: Option<&str>) -> io::Result<Arc<dyn Dal>> {
+    match db_connection_uri {
+        Some(db_connection_uri) => {
+            info!("keeping state in postgres");
+            Ok(Arc::new(
+                Postgres::new(db_connection_uri)
+                    .await
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?,
+            ))
+        }
+        None => Ok(Arc::new(Sqlite::from_pool(open_sqlite(state).await))),
+    }
+}
+
+async fn open_sqlite(state: &Path) -> SqlitePool {
+    let db_path = state.join("authentication.sqlite");
 
     let db_uri = db_path.to_str().unwrap();
 
@@ -25,14 +63,8 @@ async fn main() -> io::Result<()> {
 
     info!(
         "state db: {}",
-        std::fs::canonicalize(&args.state)
-            .unwrap()
-            .to_string_lossy()
+        std::fs::canonicalize(state).unwrap().to_string_lossy()
     );
 
-    match args.command {
-        Commands::Start(args) => start(pool, args).await,
-        Commands::InitAdmin(args) => init(pool, args, AccountTier::Admin).await,
-        Commands::InitDeployer(args) => init(pool, args, AccountTier::Deployer).await,
-    }
+    pool
 }
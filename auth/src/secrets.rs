@@ -1,40 +1,283 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::Utc;
+use http::StatusCode;
 use jsonwebtoken::EncodingKey;
-use ring::signature::{Ed25519KeyPair, KeyPair};
+use ring::{
+    digest,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use shuttle_common::{
+    backends::auth::{Jwk, JwkSet},
+    claims::Claim,
+};
+use tracing::info;
+
+use crate::{
+    dal::{Dal, StoredSigningKey},
+    error::Error,
+};
 
+#[async_trait]
 pub trait KeyManager: Send + Sync {
-    /// Get a private key for signing secrets
-    fn private_key(&self) -> &EncodingKey;
+    /// Sign a claim with the current private key, setting the key id on the token
+    fn sign(&self, claim: Claim) -> Result<String, StatusCode>;
+
+    /// Get the current public key to verify signed secrets
+    fn public_key(&self) -> Vec<u8>;
+
+    /// Get the current and previous public keys, as secrets signed just before a rotation
+    /// are still valid until they expire
+    fn public_keys(&self) -> JwkSet;
+
+    /// Start signing with a new key. The current key is kept to verify secrets until the
+    /// next rotation.
+    async fn rotate(&self) -> Result<(), Error>;
 
-    /// Get a public key to verify signed secrets
-    fn public_key(&self) -> &[u8];
+    /// Pick up keys rotated by other instances sharing the same storage, and rotate when the
+    /// current key is older than `max_age`
+    async fn sync(&self, max_age: Option<Duration>) -> Result<(), Error>;
 }
 
-pub struct EdDsaManager {
+struct SigningKey {
+    kid: String,
     encoding_key: EncodingKey,
     public_key: Vec<u8>,
 }
 
-impl EdDsaManager {
-    pub fn new() -> Self {
+impl SigningKey {
+    fn generate() -> StoredSigningKey {
         let doc = Ed25519KeyPair::generate_pkcs8(&ring::rand::SystemRandom::new())
             .expect("to create a PKCS8 for edDSA");
-        let encoding_key = EncodingKey::from_ed_der(doc.as_ref());
         let pair = Ed25519KeyPair::from_pkcs8(doc.as_ref()).expect("to create a key pair");
-        let public_key = pair.public_key();
 
-        Self {
-            encoding_key,
-            public_key: public_key.as_ref().to_vec(),
+        StoredSigningKey {
+            kid: Self::kid(pair.public_key().as_ref()),
+            pkcs8: doc.as_ref().to_vec(),
+            created_at: Utc::now(),
         }
     }
+
+    fn from_stored(stored: &StoredSigningKey) -> Result<Self, Error> {
+        let pair = Ed25519KeyPair::from_pkcs8(&stored.pkcs8)
+            .map_err(|error| anyhow!("failed to load signing key {}: {error}", stored.kid))?;
+        let public_key = pair.public_key().as_ref().to_vec();
+
+        Ok(Self {
+            kid: Self::kid(&public_key),
+            encoding_key: EncodingKey::from_ed_der(&stored.pkcs8),
+            public_key,
+        })
+    }
+
+    /// Derive the key id from the public key so it is stable for a key
+    fn kid(public_key: &[u8]) -> String {
+        digest::digest(&digest::SHA256, public_key)
+            .as_ref()
+            .iter()
+            .take(8)
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn jwk(&self) -> Jwk {
+        Jwk::from_ed25519(self.kid.clone(), &self.public_key)
+    }
 }
 
+struct SigningKeys {
+    current: SigningKey,
+    previous: Option<SigningKey>,
+}
+
+/// Signs with the newest key in storage, so that all instances sharing the storage sign with
+/// the same key
+pub struct EdDsaManager {
+    dal: Arc<dyn Dal>,
+    keys: RwLock<SigningKeys>,
+}
+
+impl EdDsaManager {
+    /// Load the keys from storage, creating the first key if there is none yet
+    pub async fn load(dal: Arc<dyn Dal>) -> Result<Self, Error> {
+        let mut stored = dal.get_signing_keys(2).await?;
+
+        if stored.is_empty() {
+            // Another instance might create the first key at the same time, in which case
+            // its key is used instead
+            dal.insert_signing_key(&SigningKey::generate(), Default::default())
+                .await?;
+            stored = dal.get_signing_keys(2).await?;
+        }
+
+        let keys = Self::signing_keys(&stored)?;
+
+        Ok(Self {
+            dal,
+            keys: RwLock::new(keys),
+        })
+    }
+
+    fn signing_keys(stored: &[StoredSigningKey]) -> Result<SigningKeys, Error> {
+        let mut stored = stored.iter();
+        let current = stored
+            .next()
+            .ok_or_else(|| anyhow!("there are no signing keys in storage"))?;
+
+        Ok(SigningKeys {
+            current: SigningKey::from_stored(current)?,
+            previous: stored.next().map(SigningKey::from_stored).transpose()?,
+        })
+    }
+
+    async fn reload(&self) -> Result<(), Error> {
+        let stored = self.dal.get_signing_keys(2).await?;
+        let new = Self::signing_keys(&stored)?;
+        let mut keys = self.keys.write().expect("key lock should not be poisoned");
+
+        if new.current.kid != keys.current.kid {
+            info!(
+                kid = %new.current.kid,
+                previous = %keys.current.kid,
+                "rotating signing key"
+            );
+        }
+
+        *keys = new;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
 impl KeyManager for EdDsaManager {
-    fn private_key(&self) -> &EncodingKey {
-        &self.encoding_key
+    fn sign(&self, claim: Claim) -> Result<String, StatusCode> {
+        let keys = self.keys.read().expect("key lock should not be poisoned");
+
+        claim.into_token_with_kid(&keys.current.encoding_key, &keys.current.kid)
+    }
+
+    fn public_key(&self) -> Vec<u8> {
+        self.keys
+            .read()
+            .expect("key lock should not be poisoned")
+            .current
+            .public_key
+            .clone()
+    }
+
+    fn public_keys(&self) -> JwkSet {
+        let keys = self.keys.read().expect("key lock should not be poisoned");
+
+        JwkSet {
+            keys: std::iter::once(&keys.current)
+                .chain(keys.previous.as_ref())
+                .map(SigningKey::jwk)
+                .collect(),
+        }
+    }
+
+    async fn rotate(&self) -> Result<(), Error> {
+        let new = SigningKey::generate();
+
+        // No other key can be newer than this one, so it is always added
+        self.dal.insert_signing_key(&new, new.created_at).await?;
+
+        self.reload().await
+    }
+
+    async fn sync(&self, max_age: Option<Duration>) -> Result<(), Error> {
+        if let Some(max_age) = max_age {
+            let newest = self.dal.get_signing_keys(1).await?;
+            let created_at = newest.first().map(|key| key.created_at).unwrap_or_default();
+            let expired = (Utc::now() - created_at)
+                .to_std()
+                .is_ok_and(|age| age >= max_age);
+
+            if expired {
+                // Instances racing to rotate only add one key between them
+                self.dal
+                    .insert_signing_key(&SigningKey::generate(), created_at)
+                    .await?;
+            }
+        }
+
+        self.reload().await
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use shuttle_common::claims::{Claim, Scope};
+
+    use super::{EdDsaManager, KeyManager};
+    use crate::dal::Sqlite;
+
+    #[tokio::test]
+    async fn rotation_keeps_previous_key() {
+        let manager = EdDsaManager::load(Arc::new(Sqlite::new_in_memory().await))
+            .await
+            .unwrap();
+        let token = manager
+            .sign(Claim::new("ferries".to_string(), vec![Scope::Project]))
+            .unwrap();
+        let kid = jsonwebtoken::decode_header(&token).unwrap().kid.unwrap();
+
+        assert_eq!(manager.public_keys().keys.len(), 1);
+
+        manager.rotate().await.unwrap();
+
+        let keys = manager.public_keys().keys;
+        assert_eq!(keys.len(), 2);
+        assert_ne!(keys[0].kid, kid);
+        assert_eq!(keys[1].kid, kid);
+
+        // Tokens signed before the rotation still verify with the previous key
+        let previous = keys[1].public_key().unwrap();
+        assert_eq!(Claim::from_token(&token, &previous).unwrap().sub, "ferries");
+
+        // Only one previous key is kept
+        manager.rotate().await.unwrap();
+
+        let kids: Vec<_> = manager
+            .public_keys()
+            .keys
+            .into_iter()
+            .map(|k| k.kid)
+            .collect();
+        assert_eq!(kids.len(), 2);
+        assert!(!kids.contains(&kid));
+    }
+
+    #[tokio::test]
+    async fn instances_share_keys() {
+        let dal = Arc::new(Sqlite::new_in_memory().await);
+        let first = EdDsaManager::load(dal.clone()).await.unwrap();
+        let second = EdDsaManager::load(dal).await.unwrap();
+
+        assert_eq!(first.public_key(), second.public_key());
+
+        first.rotate().await.unwrap();
+        assert_ne!(first.public_key(), second.public_key());
+
+        // Syncing picks up the rotation without rotating again
+        second.sync(Some(Duration::from_secs(60))).await.unwrap();
+        assert_eq!(first.public_key(), second.public_key());
+        assert_eq!(second.public_keys().keys.len(), 2);
+
+        // Keys past their age are rotated, and the other instances follow
+        first.sync(Some(Duration::ZERO)).await.unwrap();
+        let rotated = first.public_key();
+        assert_ne!(rotated, second.public_key());
 
-    fn public_key(&self) -> &[u8] {
-        &self.public_key
+        second.sync(None).await.unwrap();
+        assert_eq!(rotated, second.public_key());
     }
 }
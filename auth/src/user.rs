@@ -1,4 +1,4 @@
-use std::{fmt::Formatter, str::FromStr};
+use std::{fmt::Formatter, str::FromStr, sync::Arc};
 
 use async_trait::async_trait;
 use axum::{
@@ -7,16 +7,27 @@ use axum::{
     http::request::Parts,
     TypedHeader,
 };
+use chrono::{DateTime, Utc};
+use rand::{
+    distributions::{Alphanumeric, DistString},
+    Rng,
+};
+use ring::digest;
 use serde::{Deserialize, Deserializer, Serialize};
+pub use shuttle_common::claims::AccountTier;
 use shuttle_common::{
-    claims::{Scope, ScopeBuilder},
+    backends::audit::{AuditEvent, AuditSink},
+    claims::{Claim, Scope},
+    models::user,
     secrets::Secret,
     ApiKey,
 };
-use sqlx::{query, sqlite::SqliteRow, FromRow, Row, SqlitePool};
-use tracing::{debug, error, trace, Span};
+use tracing::{debug, error, info, trace, warn, Span};
 
-use crate::{api::UserManagerState, error::Error};
+use crate::{
+    api::UserManagerState, billing::SubscriptionEvent, dal::Dal, error::Error,
+    DEVICE_CODE_EXPIRATION, PAYMENT_GRACE_PERIOD, REFRESH_TOKEN_EXPIRATION,
+};
 use stripe::{
     CheckoutSession, CheckoutSessionStatus, Expandable, SubscriptionId, SubscriptionStatus,
 };
@@ -33,27 +44,63 @@ pub trait UserManagement: Send + Sync {
     async fn get_user(&self, name: AccountName) -> Result<User, Error>;
     async fn get_user_by_key(&self, key: ApiKey) -> Result<User, Error>;
     async fn reset_key(&self, name: AccountName) -> Result<(), Error>;
+    async fn create_token(&self, token: &ApiToken) -> Result<(), Error>;
+    async fn get_tokens(&self, name: AccountName) -> Result<Vec<ApiToken>, Error>;
+    async fn revoke_token(&self, name: AccountName, token_name: &str) -> Result<(), Error>;
+    /// Get an API token that has not expired yet, together with the user it belongs to
+    async fn get_user_by_token(&self, key: ApiKey) -> Result<(User, ApiToken), Error>;
+    /// Create a refresh token for an account, or for one of its API tokens. Returns the secret
+    /// refresh token, of which only a hash is stored.
+    async fn create_refresh_token(
+        &self,
+        name: &AccountName,
+        api_token_name: Option<&str>,
+    ) -> Result<String, Error>;
+    /// Use up a refresh token. Refresh tokens can only be used once, and reusing one revokes
+    /// all the refresh tokens of its account since it has probably leaked.
+    async fn use_refresh_token(&self, refresh_token: &str) -> Result<RefreshToken, Error>;
+    async fn revoke_refresh_token(&self, refresh_token: &str) -> Result<(), Error>;
+    /// Append an action to the audit log
+    async fn record_audit_event(&self, event: &AuditEvent) -> Result<(), Error>;
+    /// Get the most recent actions in the audit log, optionally only those taken by one account
+    async fn get_audit_events(
+        &self,
+        actor: Option<&AccountName>,
+        limit: u32,
+    ) -> Result<Vec<AuditEvent>, Error>;
+    /// Create a code for a device, such as the CLI, to log in with once a user approves it
+    async fn create_device_code(&self) -> Result<DeviceCode, Error>;
+    /// Approve the login of the device showing this user code on behalf of an account
+    async fn approve_device_code(&self, user_code: &str, name: &AccountName) -> Result<(), Error>;
+    /// Use up an approved device code. Returns `None` while the code is waiting for approval.
+    async fn use_device_code(&self, device_code: &str) -> Result<Option<ApprovedDevice>, Error>;
+    /// Suspend an account, or lift its suspension
+    async fn set_suspended(&self, name: &AccountName, suspended: bool) -> Result<(), Error>;
+    /// Delete an account together with its API tokens, refresh tokens and device codes
+    async fn delete_user(&self, name: &AccountName) -> Result<(), Error>;
+    /// Update the tier of the account a Stripe subscription belongs to. Events created before the
+    /// last event applied to the account are ignored.
+    async fn apply_subscription_event(
+        &self,
+        event: &SubscriptionEvent,
+        created_at: DateTime<Utc>,
+    ) -> Result<(), Error>;
 }
 
 #[derive(Clone)]
 pub struct UserManager {
-    pub pool: SqlitePool,
+    pub dal: Arc<dyn Dal>,
     pub stripe_client: stripe::Client,
 }
 
 #[async_trait]
 impl UserManagement for UserManager {
     async fn create_user(&self, name: AccountName, tier: AccountTier) -> Result<User, Error> {
-        let key = ApiKey::generate();
+        let user = User::new(name, ApiKey::generate(), tier, None);
 
-        query("INSERT INTO users (account_name, key, account_tier) VALUES (?1, ?2, ?3)")
-            .bind(&name)
-            .bind(&key)
-            .bind(tier)
-            .execute(&self.pool)
-            .await?;
+        self.dal.insert_user(&user).await?;
 
-        Ok(User::new(name, key, tier, None))
+        Ok(user)
     }
 
     // Update user tier to pro and update the subscription id.
@@ -79,19 +126,13 @@ impl UserManagement for UserManager {
                 })
                 .ok_or(Error::MissingSubscriptionId)?;
 
-            // Update the user account tier and subscription_id.
-            let rows_affected = query(
-                "UPDATE users SET account_tier = ?1, subscription_id = ?2 WHERE account_name = ?3",
-            )
-            .bind(AccountTier::Pro)
-            .bind(subscription_id)
-            .bind(name)
-            .execute(&self.pool)
-            .await?
-            .rows_affected();
-
-            // In case no rows were updated, this means the account doesn't exist.
-            if rows_affected > 0 {
+            // Update the user account tier and subscription_id. In case no rows were updated,
+            // this means the account doesn't exist.
+            if self
+                .dal
+                .update_tier(name, AccountTier::Pro, Some(&subscription_id))
+                .await?
+            {
                 Ok(())
             } else {
                 Err(Error::UserNotFound)
@@ -103,14 +144,7 @@ impl UserManagement for UserManager {
 
     // Update tier leaving the subscription_id untouched.
     async fn update_tier(&self, name: &AccountName, tier: AccountTier) -> Result<(), Error> {
-        let rows_affected = query("UPDATE users SET account_tier = ?1 WHERE account_name = ?2")
-            .bind(tier)
-            .bind(name)
-            .execute(&self.pool)
-            .await?
-            .rows_affected();
-
-        if rows_affected > 0 {
+        if self.dal.update_tier(name, tier, None).await? {
             Ok(())
         } else {
             Err(Error::UserNotFound)
@@ -118,12 +152,7 @@ impl UserManagement for UserManager {
     }
 
     async fn get_user(&self, name: AccountName) -> Result<User, Error> {
-        let mut user: User =
-            sqlx::query_as("SELECT account_name, key, account_tier, subscription_id FROM users WHERE account_name = ?")
-                .bind(&name)
-                .fetch_optional(&self.pool)
-                .await?
-                .ok_or(Error::UserNotFound)?;
+        let mut user = self.dal.get_user(&name).await?.ok_or(Error::UserNotFound)?;
 
         // Sync the user tier based on the subscription validity, if any.
         if let Err(err) = user.sync_tier(self).await {
@@ -137,13 +166,11 @@ impl UserManagement for UserManager {
     }
 
     async fn get_user_by_key(&self, key: ApiKey) -> Result<User, Error> {
-        let mut user: User = sqlx::query_as(
-            "SELECT account_name, key, account_tier, subscription_id FROM users WHERE key = ?",
-        )
-        .bind(&key)
-        .fetch_optional(&self.pool)
-        .await?
-        .ok_or(Error::UserNotFound)?;
+        let mut user = self
+            .dal
+            .get_user_by_key(&key)
+            .await?
+            .ok_or(Error::UserNotFound)?;
 
         // Sync the user tier based on the subscription validity, if any.
         if user.sync_tier(self).await? {
@@ -154,21 +181,384 @@ impl UserManagement for UserManager {
     }
 
     async fn reset_key(&self, name: AccountName) -> Result<(), Error> {
-        let key = ApiKey::generate();
+        // Refresh tokens of the old key stop working too
+        if self.dal.update_key(&name, &ApiKey::generate()).await? {
+            Ok(())
+        } else {
+            Err(Error::UserNotFound)
+        }
+    }
 
-        let rows_affected = query("UPDATE users SET key = ?1 WHERE account_name = ?2")
-            .bind(&key)
-            .bind(&name)
-            .execute(&self.pool)
+    async fn create_token(&self, token: &ApiToken) -> Result<(), Error> {
+        if self.dal.insert_api_token(token).await? {
+            Ok(())
+        } else {
+            Err(Error::TokenAlreadyExists)
+        }
+    }
+
+    async fn get_tokens(&self, name: AccountName) -> Result<Vec<ApiToken>, Error> {
+        Ok(self.dal.get_api_tokens(&name).await?)
+    }
+
+    async fn revoke_token(&self, name: AccountName, token_name: &str) -> Result<(), Error> {
+        if self.dal.delete_api_token(&name, token_name).await? {
+            Ok(())
+        } else {
+            Err(Error::TokenNotFound)
+        }
+    }
+
+    async fn get_user_by_token(&self, key: ApiKey) -> Result<(User, ApiToken), Error> {
+        let token = self
+            .dal
+            .get_api_token_by_key(&key)
             .await?
-            .rows_affected();
+            .ok_or(Error::TokenNotFound)?;
+
+        if token.is_expired() {
+            trace!(token.name = %token.name, "API token has expired");
+            return Err(Error::Unauthorized);
+        }
+
+        let user = self.get_user(token.account_name.clone()).await?;
+
+        Ok((user, token))
+    }
+
+    async fn create_refresh_token(
+        &self,
+        name: &AccountName,
+        api_token_name: Option<&str>,
+    ) -> Result<String, Error> {
+        let refresh_token = Alphanumeric.sample_string(&mut rand::thread_rng(), 48);
+        let token = RefreshToken {
+            account_name: name.clone(),
+            api_token_name: api_token_name.map(ToString::to_string),
+            expires_at: Utc::now()
+                + chrono::Duration::from_std(REFRESH_TOKEN_EXPIRATION)
+                    .expect("refresh token expiration to be in range"),
+        };
+
+        self.dal
+            .insert_refresh_token(&hash_secret(&refresh_token), &token)
+            .await?;
+
+        Ok(refresh_token)
+    }
 
-        if rows_affected > 0 {
+    async fn use_refresh_token(&self, refresh_token: &str) -> Result<RefreshToken, Error> {
+        let token_hash = hash_secret(refresh_token);
+
+        let (token, revoked) = self
+            .dal
+            .get_refresh_token(&token_hash)
+            .await?
+            .ok_or(Error::Unauthorized)?;
+
+        if revoked {
+            warn!(
+                account.name = %token.account_name,
+                "refresh token was reused, revoking all refresh tokens of the account"
+            );
+
+            self.dal.revoke_refresh_tokens(&token.account_name).await?;
+
+            return Err(Error::Unauthorized);
+        }
+
+        if token.expires_at <= Utc::now() {
+            return Err(Error::Unauthorized);
+        }
+
+        // Someone else used the token at the same time
+        if !self.dal.revoke_refresh_token(&token_hash).await? {
+            return Err(Error::Unauthorized);
+        }
+
+        Ok(token)
+    }
+
+    async fn revoke_refresh_token(&self, refresh_token: &str) -> Result<(), Error> {
+        self.dal
+            .delete_refresh_token(&hash_secret(refresh_token))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn record_audit_event(&self, event: &AuditEvent) -> Result<(), Error> {
+        self.dal.insert_audit_event(event).await?;
+
+        Ok(())
+    }
+
+    async fn get_audit_events(
+        &self,
+        actor: Option<&AccountName>,
+        limit: u32,
+    ) -> Result<Vec<AuditEvent>, Error> {
+        Ok(self.dal.get_audit_events(actor, limit).await?)
+    }
+
+    async fn create_device_code(&self) -> Result<DeviceCode, Error> {
+        let device_code = DeviceCode {
+            device_code: Alphanumeric.sample_string(&mut rand::thread_rng(), 48),
+            user_code: generate_user_code(),
+            expires_at: Utc::now()
+                + chrono::Duration::from_std(DEVICE_CODE_EXPIRATION)
+                    .expect("device code expiration to be in range"),
+        };
+
+        self.dal
+            .insert_device_code(
+                &hash_secret(&device_code.device_code),
+                &device_code.user_code,
+                device_code.expires_at,
+            )
+            .await?;
+
+        Ok(device_code)
+    }
+
+    async fn approve_device_code(&self, user_code: &str, name: &AccountName) -> Result<(), Error> {
+        if self
+            .dal
+            .approve_device_code(&normalize_user_code(user_code), name)
+            .await?
+        {
+            Ok(())
+        } else {
+            Err(Error::DeviceCodeNotFound)
+        }
+    }
+
+    async fn use_device_code(&self, device_code: &str) -> Result<Option<ApprovedDevice>, Error> {
+        let device_code_hash = hash_secret(device_code);
+
+        let (user_code, account_name) = self
+            .dal
+            .get_device_code(&device_code_hash)
+            .await?
+            .ok_or(Error::DeviceCodeNotFound)?;
+
+        let Some(account_name) = account_name else {
+            return Ok(None);
+        };
+
+        // Deleting the code makes sure it can only be used once, even by concurrent polls
+        if !self.dal.delete_device_code(&device_code_hash).await? {
+            return Err(Error::DeviceCodeNotFound);
+        }
+
+        Ok(Some(ApprovedDevice {
+            account_name,
+            user_code,
+        }))
+    }
+
+    async fn set_suspended(&self, name: &AccountName, suspended: bool) -> Result<(), Error> {
+        // Outstanding refresh tokens would otherwise outlive the suspension, so they are
+        // deleted when suspending
+        if self.dal.update_suspended(name, suspended).await? {
             Ok(())
         } else {
             Err(Error::UserNotFound)
         }
     }
+
+    async fn delete_user(&self, name: &AccountName) -> Result<(), Error> {
+        if self.dal.delete_user(name).await? {
+            Ok(())
+        } else {
+            Err(Error::UserNotFound)
+        }
+    }
+
+    async fn apply_subscription_event(
+        &self,
+        event: &SubscriptionEvent,
+        created_at: DateTime<Utc>,
+    ) -> Result<(), Error> {
+        let subscription_id = event.subscription_id();
+        let user = match event {
+            SubscriptionEvent::Changed {
+                account_name: Some(account_name),
+                ..
+            } => self.dal.get_user(account_name).await?,
+            _ => {
+                self.dal
+                    .get_user_by_subscription_id(subscription_id)
+                    .await?
+            }
+        }
+        .ok_or(Error::UserNotFound)?;
+
+        // Admins, deployers and teams are not billed through a subscription
+        if !matches!(
+            user.account_tier,
+            AccountTier::Basic | AccountTier::PendingPaymentPro | AccountTier::Pro
+        ) {
+            debug!(account.name = %user.name, "ignoring subscription event for unbilled tier");
+            return Ok(());
+        }
+
+        // Events for a subscription the account has since replaced should not touch it
+        if user
+            .subscription_id
+            .as_ref()
+            .is_some_and(|current| current.as_str() != subscription_id)
+        {
+            debug!(
+                account.name = %user.name,
+                "ignoring event for a subscription that is no longer current"
+            );
+            return Ok(());
+        }
+
+        // Retried payments failing again should not extend a running grace period
+        let grace_period_ends_at = || {
+            if user.in_grace_period() {
+                user.grace_period_ends_at
+            } else {
+                Some(
+                    Utc::now()
+                        + chrono::Duration::from_std(PAYMENT_GRACE_PERIOD)
+                            .expect("payment grace period to be in range"),
+                )
+            }
+        };
+
+        let (tier, subscription_id, grace_period_ends_at) = match event {
+            SubscriptionEvent::Changed { status, .. } => match status {
+                SubscriptionStatus::Active | SubscriptionStatus::Trialing => {
+                    (AccountTier::Pro, Some(subscription_id), None)
+                }
+                // Payments are being retried, so only start a grace period for accounts which
+                // had already paid
+                SubscriptionStatus::PastDue | SubscriptionStatus::Unpaid
+                    if user.account_tier == AccountTier::Pro =>
+                {
+                    (
+                        AccountTier::Pro,
+                        Some(subscription_id),
+                        grace_period_ends_at(),
+                    )
+                }
+                SubscriptionStatus::PastDue
+                | SubscriptionStatus::Unpaid
+                | SubscriptionStatus::Incomplete => {
+                    (AccountTier::PendingPaymentPro, Some(subscription_id), None)
+                }
+                _ => (AccountTier::Basic, None, None),
+            },
+            SubscriptionEvent::Deleted { .. } => (AccountTier::Basic, None, None),
+            SubscriptionEvent::PaymentFailed { .. } if user.account_tier == AccountTier::Pro => (
+                AccountTier::Pro,
+                Some(subscription_id),
+                grace_period_ends_at(),
+            ),
+            SubscriptionEvent::PaymentFailed { .. } => return Ok(()),
+        };
+
+        info!(
+            account.name = %user.name,
+            account.tier = %tier,
+            "updating account from subscription event"
+        );
+
+        let updated = self
+            .dal
+            .update_subscription(
+                &user.name,
+                tier,
+                subscription_id,
+                grace_period_ends_at,
+                created_at,
+            )
+            .await?;
+
+        if !updated {
+            debug!(
+                account.name = %user.name,
+                "ignoring subscription event older than the last one"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl AuditSink for UserManager {
+    async fn record(&self, event: AuditEvent) -> anyhow::Result<()> {
+        self.record_audit_event(&event).await?;
+
+        Ok(())
+    }
+}
+
+/// Refresh tokens and device codes are random enough that a plain hash is safe to store
+fn hash_secret(secret: &str) -> String {
+    digest::digest(&digest::SHA256, secret.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Letters which are hard to confuse with each other, and cannot spell words without vowels
+const USER_CODE_CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+
+/// Generate a short code for users to type in, such as `WDJB-MJHT`
+fn generate_user_code() -> String {
+    let mut rng = rand::thread_rng();
+    let chars: String = (0..8)
+        .map(|_| USER_CODE_CHARSET[rng.gen_range(0..USER_CODE_CHARSET.len())] as char)
+        .collect();
+
+    normalize_user_code(&chars)
+}
+
+/// Users might type in a code in lower case or without the dash
+fn normalize_user_code(user_code: &str) -> String {
+    let mut normalized: String = user_code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+
+    if normalized.len() > 4 {
+        normalized.insert(4, '-');
+    }
+
+    normalized
+}
+
+/// A code for a device to log in with
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeviceCode {
+    /// The secret the device polls with. Only a hash of it is stored
+    pub device_code: String,
+    /// The code the user enters to approve the login
+    pub user_code: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A device whose login was approved
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApprovedDevice {
+    pub account_name: AccountName,
+    pub user_code: String,
+}
+
+/// A refresh token that was used to get a new JWT
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefreshToken {
+    pub account_name: AccountName,
+    /// Name of the API token the refresh token was issued for, if it was not for the account's key
+    pub api_token_name: Option<String>,
+    pub expires_at: DateTime<Utc>,
 }
 
 #[derive(Clone, Deserialize, PartialEq, Eq, Serialize, Debug)]
@@ -177,6 +567,10 @@ pub struct User {
     pub key: Secret<ApiKey>,
     pub account_tier: AccountTier,
     pub subscription_id: Option<SubscriptionId>,
+    /// Suspended accounts keep their data, but can no longer log in or get a JWT
+    pub suspended: bool,
+    /// When a failed subscription payment stops being forgiven, if one failed
+    pub grace_period_ends_at: Option<DateTime<Utc>>,
 }
 
 impl User {
@@ -195,9 +589,17 @@ impl User {
             key: Secret::new(key),
             account_tier,
             subscription_id,
+            suspended: false,
+            grace_period_ends_at: None,
         }
     }
 
+    /// Whether a failed payment is still being forgiven
+    fn in_grace_period(&self) -> bool {
+        self.grace_period_ends_at
+            .is_some_and(|ends_at| ends_at > Utc::now())
+    }
+
     /// In case of an existing subscription, check if valid.
     async fn subscription_is_valid(&self, client: &stripe::Client) -> Result<bool, Error> {
         if let Some(subscription_id) = self.subscription_id.as_ref() {
@@ -216,7 +618,10 @@ impl User {
             .subscription_is_valid(&user_manager.stripe_client)
             .await?;
 
-        if self.account_tier == AccountTier::Pro && !subscription_is_valid {
+        if self.account_tier == AccountTier::Pro
+            && !subscription_is_valid
+            && !self.in_grace_period()
+        {
             self.account_tier = AccountTier::PendingPaymentPro;
             user_manager
                 .update_tier(&self.name, self.account_tier)
@@ -236,17 +641,70 @@ impl User {
     }
 }
 
-impl FromRow<'_, SqliteRow> for User {
-    fn from_row(row: &SqliteRow) -> Result<Self, sqlx::Error> {
-        Ok(User {
-            name: row.try_get("account_name").unwrap(),
-            key: Secret::new(row.try_get("key").unwrap()),
-            account_tier: row.try_get("account_tier").unwrap(),
-            subscription_id: row
-                .try_get("subscription_id")
-                .ok()
-                .and_then(|inner| SubscriptionId::from_str(inner).ok()),
-        })
+/// A named API token of a user, limited to some scopes and optionally to a single project
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiToken {
+    pub key: Secret<ApiKey>,
+    pub account_name: AccountName,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub project: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiToken {
+    /// Create a new token with a freshly generated key
+    pub fn new(
+        account_name: AccountName,
+        name: String,
+        scopes: Vec<Scope>,
+        project: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Self {
+        Self {
+            key: Secret::new(ApiKey::generate()),
+            account_name,
+            name,
+            scopes,
+            project,
+            expires_at,
+            created_at: Utc::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    /// A claim limited to the scopes of this token that the account's current tier still
+    /// allows, and to the token's project
+    pub fn claim(&self, account_tier: AccountTier) -> Claim {
+        let tier_scopes: Vec<Scope> = account_tier.into();
+        let scopes = self
+            .scopes
+            .iter()
+            .filter(|scope| tier_scopes.contains(scope))
+            .cloned()
+            .collect();
+
+        Claim::new(self.account_name.to_string(), scopes)
+            .with_tier(account_tier)
+            .with_project(self.project.clone())
+    }
+}
+
+impl From<ApiToken> for user::TokenResponse {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            name: token.name,
+            scopes: token.scopes.iter().map(ToString::to_string).collect(),
+            project: token.project,
+            expires_at: token.expires_at,
+            created_at: token.created_at,
+            key: None,
+        }
     }
 }
 
@@ -269,6 +727,10 @@ where
             // Absorb any error into `Unauthorized`
             .map_err(|_| Error::Unauthorized)?;
 
+        if user.suspended {
+            return Err(Error::AccountSuspended);
+        }
+
         // Record current account name for tracing purposes
         Span::current().record("account.name", &user.name.to_string());
 
@@ -276,13 +738,15 @@ where
     }
 }
 
-impl From<User> for shuttle_common::models::user::Response {
+impl From<User> for user::Response {
     fn from(user: User) -> Self {
         Self {
             name: user.name.to_string(),
             key: user.key.expose().as_ref().to_owned(),
             account_tier: user.account_tier.to_string(),
             subscription_id: user.subscription_id.map(|inner| inner.to_string()),
+            suspended: user.suspended,
+            grace_period_ends_at: user.grace_period_ends_at,
         }
     }
 }
@@ -321,40 +785,6 @@ where
     }
 }
 
-#[derive(Clone, Copy, Deserialize, PartialEq, Eq, Serialize, Debug, sqlx::Type, strum::Display)]
-#[sqlx(rename_all = "lowercase")]
-#[serde(rename_all = "lowercase")]
-#[strum(serialize_all = "lowercase")]
-#[derive(Default)]
-pub enum AccountTier {
-    #[default]
-    Basic,
-    // A basic user that is pending a payment on the backend.
-    PendingPaymentPro,
-    Pro,
-    Team,
-    Admin,
-    Deployer,
-}
-
-impl From<AccountTier> for Vec<Scope> {
-    fn from(tier: AccountTier) -> Self {
-        let mut builder = ScopeBuilder::new();
-
-        if tier == AccountTier::Admin {
-            builder = builder.with_admin()
-        }
-
-        if tier == AccountTier::Deployer {
-            builder = builder.with_deploy_rights();
-        } else {
-            builder = builder.with_basic();
-        }
-
-        builder.build()
-    }
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, sqlx::Type, Serialize)]
 #[sqlx(transparent)]
 pub struct AccountName(String);
@@ -552,4 +982,27 @@ mod tests {
             );
         }
     }
+
+    mod user_codes {
+        use super::super::{generate_user_code, normalize_user_code, USER_CODE_CHARSET};
+
+        #[test]
+        fn generated_codes_are_normalized() {
+            let user_code = generate_user_code();
+
+            assert_eq!(user_code.len(), 9);
+            assert_eq!(normalize_user_code(&user_code), user_code);
+            assert!(user_code
+                .chars()
+                .filter(|c| *c != '-')
+                .all(|c| USER_CODE_CHARSET.contains(&(c as u8))));
+        }
+
+        #[test]
+        fn typed_codes_are_normalized() {
+            assert_eq!(normalize_user_code("wdjb-mjht"), "WDJB-MJHT");
+            assert_eq!(normalize_user_code("wdjbmjht"), "WDJB-MJHT");
+            assert_eq!(normalize_user_code(" WDJB MJHT "), "WDJB-MJHT");
+        }
+    }
 }
@@ -0,0 +1,68 @@
+use axum::body::Body;
+use http::header::CONTENT_TYPE;
+use hyper::http::{header::AUTHORIZATION, Request, StatusCode};
+use serde_json::{json, Value};
+
+use crate::helpers::{app, ADMIN_KEY};
+
+#[tokio::test]
+async fn audit_log_records_changes() {
+    let app = app().await;
+
+    let response = app.post_user("test-user", "basic").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let user: Value = serde_json::from_slice(&body).unwrap();
+    let user_key = user["key"].as_str().unwrap();
+
+    let request = Request::builder()
+        .uri("/users/tokens")
+        .method("POST")
+        .header(AUTHORIZATION, format!("Bearer {user_key}"))
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({ "name": "ci", "scopes": ["logs"] }).to_string(),
+        ))
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let get_audit_log = |key: &str, all: bool| {
+        Request::builder()
+            .uri(format!("/users/audit?all={all}"))
+            .header(AUTHORIZATION, format!("Bearer {key}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Users only see their own actions.
+    let response = app.send_request(get_audit_log(user_key, false)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let events: Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(events.as_array().unwrap().len(), 1);
+    assert_eq!(events[0]["actor"], "test-user");
+    assert_eq!(events[0]["action"], "POST /users/tokens");
+    assert_eq!(events[0]["outcome"], 200);
+
+    let response = app.send_request(get_audit_log(user_key, true)).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Admins can see the actions of everyone, most recent first.
+    let response = app.send_request(get_audit_log(ADMIN_KEY, true)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let events: Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(events.as_array().unwrap().len(), 2);
+    assert_eq!(events[1]["actor"], "admin");
+    assert_eq!(
+        events[1]["action"],
+        "POST /users/:account_name/:account_tier"
+    );
+    assert_eq!(events[1]["target"], "/users/test-user/basic");
+}
@@ -1,6 +1,8 @@
-use http::header::AUTHORIZATION;
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
 use http::{Request, StatusCode};
 use hyper::Body;
+use serde_json::{json, Value};
+use shuttle_common::{backends::auth::JwkSet, claims::Claim};
 
 use crate::helpers::{app, ADMIN_KEY};
 
@@ -47,3 +49,129 @@ async fn convert_api_key_to_jwt() {
 
     // TODO: decode the JWT?
 }
+
+#[tokio::test]
+async fn refresh_token_flow() {
+    let app = app().await;
+
+    let convert = |uri: &str| {
+        Request::builder()
+            .uri(uri)
+            .header(AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let refresh = |uri: &str, refresh_token: &str| {
+        Request::builder()
+            .uri(uri)
+            .method("POST")
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                json!({ "refresh_token": refresh_token }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    // Refresh tokens are only returned when asked for.
+    let response = app.send_request(convert("/auth/key")).await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let convert_response: Value = serde_json::from_slice(&body).unwrap();
+
+    assert!(convert_response.get("refresh_token").is_none());
+
+    let response = app.send_request(convert("/auth/key?refresh=true")).await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let convert_response: Value = serde_json::from_slice(&body).unwrap();
+    let first = convert_response["refresh_token"].as_str().unwrap();
+
+    // Exchange the refresh token for a new JWT and refresh token.
+    let response = app.send_request(refresh("/auth/refresh", first)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let refresh_response: Value = serde_json::from_slice(&body).unwrap();
+    let second = refresh_response["refresh_token"].as_str().unwrap();
+
+    assert!(refresh_response["token"].is_string());
+    assert_ne!(first, second);
+
+    // Reusing a refresh token fails, and revokes the other refresh tokens of the account.
+    let response = app.send_request(refresh("/auth/refresh", first)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.send_request(refresh("/auth/refresh", second)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Revoked refresh tokens cannot be used.
+    let response = app.send_request(convert("/auth/key?refresh=true")).await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let convert_response: Value = serde_json::from_slice(&body).unwrap();
+    let third = convert_response["refresh_token"].as_str().unwrap();
+
+    let response = app.send_request(refresh("/auth/revoke", third)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.send_request(refresh("/auth/refresh", third)).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn rotated_keys_are_published() {
+    let app = app().await;
+
+    let get_jwks = || {
+        Request::builder()
+            .uri("/.well-known/jwks.json")
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.send_request(get_jwks()).await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let jwks: JwkSet = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(jwks.keys.len(), 1);
+
+    // Sign a token with the current key.
+    let response = app
+        .send_request(
+            Request::builder()
+                .uri("/auth/key")
+                .header(AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let convert: Value = serde_json::from_slice(&body).unwrap();
+    let token = convert["token"].as_str().unwrap();
+    let kid = jsonwebtoken::decode_header(token).unwrap().kid.unwrap();
+
+    assert_eq!(jwks.keys[0].kid, kid);
+
+    // Only admins can rotate the key.
+    let response = app
+        .send_request(
+            Request::builder()
+                .uri("/auth/rotate-key")
+                .method("POST")
+                .header(AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.send_request(get_jwks()).await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let jwks: JwkSet = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(jwks.keys.len(), 2);
+    assert_ne!(jwks.keys[0].kid, kid);
+
+    // The token signed before the rotation still verifies with the previous key.
+    let previous = jwks.keys.iter().find(|jwk| jwk.kid == kid).unwrap();
+    let claim = Claim::from_token(token, &previous.public_key().unwrap()).unwrap();
+
+    assert_eq!(claim.sub, "admin");
+}
@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+use axum::body::Body;
+use http::{header::CONTENT_TYPE, Request, StatusCode};
+use serde_json::{json, Value};
+
+use crate::{helpers::app, stripe::MOCKED_SUBSCRIPTIONS};
+
+/// A webhook event for one of the mocked subscriptions, created for `account_name`
+fn subscription_event(kind: &str, subscription: &str, status: &str, account_name: &str) -> Value {
+    let mut subscription: Value = serde_json::from_str(subscription).unwrap();
+    subscription["status"] = json!(status);
+    subscription["metadata"] = json!({ "account_name": account_name });
+
+    json!({
+        "id": "evt_1NwObED8t1tt0S3D",
+        "object": "event",
+        "type": kind,
+        "created": chrono::Utc::now().timestamp(),
+        "data": { "object": subscription }
+    })
+}
+
+fn payment_failed_event(subscription: &str) -> Value {
+    let subscription: Value = serde_json::from_str(subscription).unwrap();
+
+    json!({
+        "id": "evt_1NwObED8t1tt0S3E",
+        "object": "event",
+        "type": "invoice.payment_failed",
+        "created": chrono::Utc::now().timestamp(),
+        "data": { "object": { "id": "in_1NwObED8t1tt0S3D", "object": "invoice", "subscription": subscription["id"] } }
+    })
+}
+
+async fn get_user(app: &crate::helpers::TestApp, name: &str) -> Value {
+    let response = app.get_user(name).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn webhook_needs_valid_signature() {
+    let app = app().await;
+
+    let event = subscription_event(
+        "customer.subscription.created",
+        MOCKED_SUBSCRIPTIONS[0],
+        "active",
+        "test-user",
+    );
+
+    // Without a signature.
+    let request = Request::builder()
+        .uri("/billing/stripe/webhook")
+        .method("POST")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(event.to_string()))
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // With a signature made with another secret.
+    let request = Request::builder()
+        .uri("/billing/stripe/webhook")
+        .method("POST")
+        .header(CONTENT_TYPE, "application/json")
+        .header(
+            "Stripe-Signature",
+            format!(
+                "t={},v1=5257a869e7ecebeda32affa62cdca3fa51cad7e77a0e56ff536d0ce8e108d8bd",
+                chrono::Utc::now().timestamp()
+            ),
+        )
+        .body(Body::from(event.to_string()))
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn subscription_lifecycle() {
+    let app = app().await;
+
+    // Wait for the mocked Stripe server to start.
+    tokio::task::spawn(app.mocked_stripe_server.clone().serve());
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let response = app.post_user("test-user", "basic").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // A new subscription upgrades the account it was created for.
+    let response = app
+        .post_stripe_webhook(&subscription_event(
+            "customer.subscription.created",
+            MOCKED_SUBSCRIPTIONS[0],
+            "active",
+            "test-user",
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let user = get_user(&app, "test-user").await;
+    let subscription: Value = serde_json::from_str(MOCKED_SUBSCRIPTIONS[0]).unwrap();
+    assert_eq!(user["account_tier"], "pro");
+    assert_eq!(user["subscription_id"], subscription["id"]);
+    assert!(user.get("grace_period_ends_at").is_none());
+
+    // A failed payment starts a grace period without changing the tier.
+    let response = app
+        .post_stripe_webhook(&payment_failed_event(MOCKED_SUBSCRIPTIONS[0]))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let user = get_user(&app, "test-user").await;
+    assert_eq!(user["account_tier"], "pro");
+    assert!(user["grace_period_ends_at"].is_string());
+
+    // Paying again ends the grace period.
+    let response = app
+        .post_stripe_webhook(&subscription_event(
+            "customer.subscription.updated",
+            MOCKED_SUBSCRIPTIONS[0],
+            "active",
+            "test-user",
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let user = get_user(&app, "test-user").await;
+    assert_eq!(user["account_tier"], "pro");
+    assert!(user.get("grace_period_ends_at").is_none());
+
+    // Cancelling the subscription downgrades the account.
+    let response = app
+        .post_stripe_webhook(&subscription_event(
+            "customer.subscription.deleted",
+            MOCKED_SUBSCRIPTIONS[0],
+            "canceled",
+            "test-user",
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let user = get_user(&app, "test-user").await;
+    assert_eq!(user["account_tier"], "basic");
+    assert!(user["subscription_id"].is_null());
+}
+
+#[tokio::test]
+async fn grace_period_keeps_tier() {
+    let app = app().await;
+
+    // Wait for the mocked Stripe server to start.
+    tokio::task::spawn(app.mocked_stripe_server.clone().serve());
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let response = app.post_user("test-user", "basic").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The mocked Stripe API reports this subscription as past due.
+    let response = app
+        .post_stripe_webhook(&subscription_event(
+            "customer.subscription.created",
+            MOCKED_SUBSCRIPTIONS[1],
+            "active",
+            "test-user",
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .post_stripe_webhook(&payment_failed_event(MOCKED_SUBSCRIPTIONS[1]))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Syncing with Stripe does not downgrade the account while it is in its grace period.
+    let user = get_user(&app, "test-user").await;
+    assert_eq!(user["account_tier"], "pro");
+
+    // Events for accounts that do not exist are acknowledged, so Stripe stops retrying them.
+    let response = app
+        .post_stripe_webhook(&subscription_event(
+            "customer.subscription.created",
+            MOCKED_SUBSCRIPTIONS[0],
+            "active",
+            "not-a-user",
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn out_of_order_events() {
+    let app = app().await;
+
+    // Wait for the mocked Stripe server to start.
+    tokio::task::spawn(app.mocked_stripe_server.clone().serve());
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let response = app.post_user("test-user", "basic").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .post_stripe_webhook(&subscription_event(
+            "customer.subscription.created",
+            MOCKED_SUBSCRIPTIONS[0],
+            "active",
+            "test-user",
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The payment was retried successfully, but Stripe delivered that before the failure
+    let mut recovered = subscription_event(
+        "customer.subscription.updated",
+        MOCKED_SUBSCRIPTIONS[0],
+        "active",
+        "test-user",
+    );
+    recovered["created"] = json!(chrono::Utc::now().timestamp() + 60);
+    let response = app.post_stripe_webhook(&recovered).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .post_stripe_webhook(&subscription_event(
+            "customer.subscription.updated",
+            MOCKED_SUBSCRIPTIONS[0],
+            "past_due",
+            "test-user",
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The stale failure does not start a grace period
+    let user = get_user(&app, "test-user").await;
+    assert_eq!(user["account_tier"], "pro");
+    assert!(user.get("grace_period_ends_at").is_none());
+
+    // Nor does a stale update bring back a subscription which was cancelled since
+    let mut deleted = subscription_event(
+        "customer.subscription.deleted",
+        MOCKED_SUBSCRIPTIONS[0],
+        "canceled",
+        "test-user",
+    );
+    deleted["created"] = json!(chrono::Utc::now().timestamp() + 120);
+    let response = app.post_stripe_webhook(&deleted).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut stale_active = subscription_event(
+        "customer.subscription.updated",
+        MOCKED_SUBSCRIPTIONS[0],
+        "active",
+        "test-user",
+    );
+    stale_active["created"] = json!(chrono::Utc::now().timestamp() + 90);
+    let response = app.post_stripe_webhook(&stale_active).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let user = get_user(&app, "test-user").await;
+    assert_eq!(user["account_tier"], "basic");
+    assert!(user["subscription_id"].is_null());
+}
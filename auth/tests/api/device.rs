@@ -0,0 +1,110 @@
+use axum::body::Body;
+use http::header::CONTENT_TYPE;
+use hyper::http::{header::AUTHORIZATION, Request, StatusCode};
+use serde_json::{json, Value};
+
+use crate::helpers::app;
+
+fn post_json(uri: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .uri(uri)
+        .method("POST")
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn device_login_flow() {
+    let app = app().await;
+
+    let response = app.post_user("device-user", "basic").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The CLI starts the login.
+    let response = app
+        .send_request(post_json("/auth/device/code", json!({})))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let codes: Value = serde_json::from_slice(&body).unwrap();
+    let device_code = codes["device_code"].as_str().unwrap().to_string();
+    let user_code = codes["user_code"].as_str().unwrap().to_string();
+
+    assert_eq!(
+        codes["verification_uri_complete"],
+        format!("http://localhost:8000/device?code={user_code}")
+    );
+
+    let poll = || post_json("/auth/device/token", json!({ "device_code": device_code }));
+
+    // Nothing is returned until the login is approved.
+    let response = app.send_request(poll()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let token: Value = serde_json::from_slice(&body).unwrap();
+    assert!(token["key"].is_null());
+
+    // Approving needs a browser session.
+    let approve = || post_json("/auth/device/approve", json!({ "user_code": user_code }));
+
+    let response = app.send_request(approve()).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // The user logs in with the stand-in identity provider and approves the login.
+    let response = app
+        .send_request(post_json(
+            "/login",
+            json!({ "account_name": "device-user" }),
+        ))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let cookie = response
+        .headers()
+        .get("set-cookie")
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .split(';')
+        .next()
+        .unwrap()
+        .to_string();
+
+    let mut request = approve();
+    request
+        .headers_mut()
+        .insert("Cookie", cookie.parse().unwrap());
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // The CLI now gets its API token, exactly once.
+    let response = app.send_request(poll()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let token: Value = serde_json::from_slice(&body).unwrap();
+    let key = token["key"].as_str().unwrap();
+
+    let response = app.send_request(poll()).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // The API token belongs to the user that approved the login.
+    let request = Request::builder()
+        .uri("/auth/key")
+        .header(AUTHORIZATION, format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Unknown user codes cannot be approved.
+    let mut request = post_json("/auth/device/approve", json!({ "user_code": "BCDF-GHJK" }));
+    request
+        .headers_mut()
+        .insert("Cookie", cookie.parse().unwrap());
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
@@ -6,6 +6,7 @@ use hyper::{
     http::{header::AUTHORIZATION, Request},
     Server,
 };
+use ring::hmac;
 use serde_json::Value;
 use shuttle_auth::{sqlite_init, ApiBuilder};
 use sqlx::query;
@@ -14,6 +15,7 @@ use tower::ServiceExt;
 use crate::stripe::MOCKED_SUBSCRIPTIONS;
 
 pub(crate) const ADMIN_KEY: &str = "ndh9z58jttoes3qv";
+pub(crate) const STRIPE_WEBHOOK_SECRET: &str = "whsec_test";
 
 pub(crate) struct TestApp {
     pub router: Router,
@@ -40,7 +42,11 @@ pub(crate) async fn app() -> TestApp {
             mocked_stripe_server.uri.to_string().as_str(),
             "",
         ))
-        .into_router();
+        .with_stripe_webhook_secret(STRIPE_WEBHOOK_SECRET.to_string())
+        .with_device_verification_uri("http://localhost:8000/device".to_string())
+        .with_local_identity_provider()
+        .into_router()
+        .await;
 
     TestApp {
         router,
@@ -94,6 +100,28 @@ impl TestApp {
 
         self.send_request(request).await
     }
+
+    /// Send a webhook event the way Stripe signs it
+    pub async fn post_stripe_webhook(&self, event: &Value) -> Response {
+        let payload = event.to_string();
+        let timestamp = chrono::Utc::now().timestamp();
+        let key = hmac::Key::new(hmac::HMAC_SHA256, STRIPE_WEBHOOK_SECRET.as_bytes());
+        let signature: String = hmac::sign(&key, format!("{timestamp}.{payload}").as_bytes())
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+
+        let request = Request::builder()
+            .uri("/billing/stripe/webhook")
+            .method("POST")
+            .header(CONTENT_TYPE, "application/json")
+            .header("Stripe-Signature", format!("t={timestamp},v1={signature}"))
+            .body(Body::from(payload))
+            .unwrap();
+
+        self.send_request(request).await
+    }
 }
 
 #[derive(Clone)]
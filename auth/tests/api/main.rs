@@ -1,5 +1,9 @@
+mod audit;
 mod auth;
+mod billing;
+mod device;
 mod helpers;
 mod session;
 mod stripe;
+mod tokens;
 mod users;
@@ -0,0 +1,137 @@
+use axum::body::Body;
+use http::header::CONTENT_TYPE;
+use hyper::http::{header::AUTHORIZATION, Request, StatusCode};
+use serde_json::{json, Value};
+use shuttle_common::claims::{Claim, Scope};
+
+use crate::helpers::app;
+
+#[tokio::test]
+async fn create_use_and_revoke_token() {
+    let app = app().await;
+
+    let response = app.post_user("test-user", "basic").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let user: Value = serde_json::from_slice(&body).unwrap();
+    let user_key = user["key"].as_str().unwrap();
+
+    let post_token = |body: Value| {
+        Request::builder()
+            .uri("/users/tokens")
+            .method("POST")
+            .header(AUTHORIZATION, format!("Bearer {user_key}"))
+            .header(CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    };
+
+    // A basic user cannot give a token admin scopes.
+    let response = app
+        .send_request(post_token(json!({
+            "name": "ci",
+            "scopes": ["admin"],
+        })))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Expiry dates should be in the future.
+    let response = app
+        .send_request(post_token(json!({
+            "name": "ci",
+            "scopes": ["logs"],
+            "expires_at": "2020-01-01T00:00:00Z",
+        })))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = app
+        .send_request(post_token(json!({
+            "name": "ci",
+            "scopes": ["deployment_push", "logs"],
+            "project": "my-project",
+        })))
+        .await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let token: Value = serde_json::from_slice(&body).unwrap();
+    let token_key = token["key"].as_str().unwrap().to_string();
+
+    assert_eq!(token["name"], "ci");
+    assert_eq!(token["scopes"], json!(["deployment_push", "logs"]));
+
+    // Names are unique per account.
+    let response = app
+        .send_request(post_token(json!({
+            "name": "ci",
+            "scopes": ["logs"],
+        })))
+        .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // The key of the token is not returned when listing tokens.
+    let request = Request::builder()
+        .uri("/users/tokens")
+        .header(AUTHORIZATION, format!("Bearer {user_key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let tokens: Value = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(tokens.as_array().unwrap().len(), 1);
+    assert!(tokens[0].get("key").is_none());
+
+    // The token converts to a claim limited to its scopes and project.
+    let request = Request::builder()
+        .uri("/public-key")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+    let public_key = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    let convert_token = || {
+        Request::builder()
+            .uri("/auth/key")
+            .header(AUTHORIZATION, format!("Bearer {token_key}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.send_request(convert_token()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let convert: Value = serde_json::from_slice(&body).unwrap();
+    let claim = Claim::from_token(convert["token"].as_str().unwrap(), &public_key).unwrap();
+
+    assert_eq!(claim.sub, "test-user");
+    assert_eq!(claim.scopes, vec![Scope::DeploymentPush, Scope::Logs]);
+    assert_eq!(claim.project, Some("my-project".to_string()));
+
+    // Tokens cannot be used to manage other tokens.
+    let request = Request::builder()
+        .uri("/users/tokens")
+        .header(AUTHORIZATION, format!("Bearer {token_key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_ne!(response.status(), StatusCode::OK);
+
+    // Revoked tokens can no longer be converted.
+    let request = Request::builder()
+        .uri("/users/tokens/ci")
+        .method("DELETE")
+        .header(AUTHORIZATION, format!("Bearer {user_key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.send_request(convert_token()).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
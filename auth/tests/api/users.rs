@@ -109,6 +109,35 @@ async fn get_user() {
     assert_eq!(user, persisted_user);
 }
 
+#[tokio::test]
+async fn head_user() {
+    let app = app().await;
+
+    let response = app.post_user("test-user", "basic").await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let user: Value = serde_json::from_slice(&body).unwrap();
+    let key = user["key"].as_str().unwrap();
+
+    let head_user = |name: &str, key: &str| {
+        Request::builder()
+            .method("HEAD")
+            .uri(format!("/users/{name}"))
+            .header(AUTHORIZATION, format!("Bearer {key}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    // Any account can check that another account exists
+    let response = app.send_request(head_user("admin", key)).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.send_request(head_user("not-test-user", key)).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.send_request(head_user("admin", "notakey")).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
 #[tokio::test]
 async fn successful_upgrade_to_pro() {
     let app = app().await;
@@ -249,3 +278,91 @@ async fn test_reset_key() {
     let response = app.send_request(request).await;
     assert_eq!(response.status(), StatusCode::OK);
 }
+
+#[tokio::test]
+async fn suspend_and_delete_user() {
+    let app = app().await;
+
+    let response = app.post_user("test-user", "basic").await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let user: Value = serde_json::from_slice(&body).unwrap();
+    let key = user["key"].as_str().unwrap().to_string();
+
+    let convert_key = || {
+        Request::builder()
+            .uri("/auth/key")
+            .header(AUTHORIZATION, format!("Bearer {key}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let suspension = |method: &str| {
+        Request::builder()
+            .uri("/users/test-user/suspension")
+            .method(method)
+            .header(AUTHORIZATION, format!("Bearer {}", helpers::ADMIN_KEY))
+            .body(Body::empty())
+            .unwrap()
+    };
+
+    let response = app.send_request(convert_key()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Only admins can suspend accounts.
+    let request = Request::builder()
+        .uri("/users/test-user/suspension")
+        .method("PUT")
+        .header(AUTHORIZATION, format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // A suspended account can no longer get a JWT.
+    let response = app.send_request(suspension("PUT")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.send_request(convert_key()).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = app.get_user("test-user").await;
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    let user: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(user["suspended"], true);
+
+    // Nor can it manage its account.
+    let request = Request::builder()
+        .uri("/users/tokens")
+        .header(AUTHORIZATION, format!("Bearer {key}"))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Lifting the suspension restores access.
+    let response = app.send_request(suspension("DELETE")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.send_request(convert_key()).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Deleting the account removes it and its key.
+    let request = Request::builder()
+        .uri("/users/test-user")
+        .method("DELETE")
+        .header(AUTHORIZATION, format!("Bearer {}", helpers::ADMIN_KEY))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send_request(request).await;
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.get_user("test-user").await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = app.send_request(convert_key()).await;
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = app.send_request(suspension("PUT")).await;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
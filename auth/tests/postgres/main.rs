@@ -0,0 +1,223 @@
+use std::sync::Arc;
+
+use axum::{body::Body, Router};
+use ctor::dtor;
+use http::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Request, StatusCode,
+};
+use once_cell::sync::Lazy;
+use serde_json::{json, Value};
+use shuttle_auth::{
+    init, migrate_to_postgres, sqlite_init, AccountTier, ApiBuilder, Dal, InitArgs, MigrateArgs,
+    Postgres, Sqlite,
+};
+use tower::ServiceExt;
+use uuid::Uuid;
+
+mod helpers;
+use helpers::{exec_psql, DockerInstance};
+
+const ADMIN_KEY: &str = "ndh9z58jttoes3qv";
+
+static PG: Lazy<DockerInstance> = Lazy::new(DockerInstance::default);
+
+#[dtor]
+fn cleanup() {
+    PG.cleanup();
+}
+
+mod needs_docker {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn keeps_state_in_postgres() {
+        let dal: Arc<dyn Dal> = Arc::new(Postgres::new(&new_database()).await.unwrap());
+        add_admin(dal.clone()).await;
+        let router = router(dal).await;
+
+        let user = send(&router, post_user("neo")).await;
+        let user_key = user["key"].as_str().unwrap();
+
+        let token = send(&router, post_token(user_key, "ci")).await;
+        assert_eq!(token["name"], "ci");
+
+        // Names of tokens are unique per user
+        let response = router
+            .clone()
+            .oneshot(post_token(user_key, "ci"))
+            .await
+            .unwrap();
+        assert!(response.status().is_client_error());
+
+        let user = send(&router, get_user("neo")).await;
+        assert_eq!(user["name"], "neo");
+        assert_eq!(user["account_tier"], "basic");
+
+        let events = send(&router, get_audit_log()).await;
+        let actions: Vec<_> = events
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|event| event["action"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                "POST /users/tokens",
+                "POST /users/tokens",
+                "POST /users/:account_name/:account_tier"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn migrates_to_postgres() {
+        let pool = sqlite_init("sqlite::memory:").await;
+        let sqlite: Arc<dyn Dal> = Arc::new(Sqlite::from_pool(pool.clone()));
+        add_admin(sqlite.clone()).await;
+        let sqlite_router = router(sqlite).await;
+
+        let user = send(&sqlite_router, post_user("neo")).await;
+        let user_key = user["key"].as_str().unwrap().to_string();
+        send(&sqlite_router, post_token(&user_key, "ci")).await;
+
+        let sqlite_kids = kids(&sqlite_router).await;
+        let sqlite_events = send(&sqlite_router, get_audit_log()).await;
+
+        // An instance on Postgres already created a newer signing key of its own
+        let db_connection_uri = new_database();
+        let postgres: Arc<dyn Dal> = Arc::new(Postgres::new(&db_connection_uri).await.unwrap());
+        let postgres_kid = kids(&router(postgres.clone()).await).await[0].clone();
+
+        // Copying again only adds what is missing, which is nothing
+        for _ in 0..2 {
+            migrate_to_postgres(
+                pool.clone(),
+                MigrateArgs {
+                    db_connection_uri: db_connection_uri.clone(),
+                },
+            )
+            .await
+            .unwrap();
+        }
+
+        let postgres_router = router(postgres).await;
+
+        // Users came along with their keys and tokens
+        let user = send(&postgres_router, get_user("neo")).await;
+        assert_eq!(user["key"], user_key);
+
+        let request = Request::builder()
+            .uri("/users/tokens")
+            .header(AUTHORIZATION, format!("Bearer {user_key}"))
+            .body(Body::empty())
+            .unwrap();
+        let tokens = send(&postgres_router, request).await;
+        assert_eq!(tokens.as_array().unwrap().len(), 1);
+
+        assert_eq!(send(&postgres_router, get_audit_log()).await, sqlite_events);
+
+        // The older key is kept next to the newer one, so tokens signed by it can be verified
+        assert_eq!(
+            kids(&postgres_router).await,
+            vec![postgres_kid, sqlite_kids[0].clone()]
+        );
+    }
+}
+
+/// Create a new database, so every test has its own
+fn new_database() -> String {
+    let db_name = Uuid::new_v4().to_string();
+
+    // Get the PG uri first so the static PG is initialized.
+    let uri = format!("{}/{}", PG.uri, db_name);
+
+    exec_psql(&format!(r#"CREATE DATABASE "{db_name}";"#));
+
+    uri
+}
+
+async fn add_admin(dal: Arc<dyn Dal>) {
+    init(
+        dal,
+        InitArgs {
+            name: "admin".to_string(),
+            key: Some(ADMIN_KEY.to_string()),
+        },
+        AccountTier::Admin,
+    )
+    .await
+    .unwrap();
+}
+
+async fn router(dal: Arc<dyn Dal>) -> Router {
+    ApiBuilder::new()
+        .with_dal(dal)
+        .with_sessions()
+        .with_stripe_client(stripe::Client::new(""))
+        .with_device_verification_uri("http://localhost:8000/device".to_string())
+        .into_router()
+        .await
+}
+
+async fn send(router: &Router, request: Request<Body>) -> Value {
+    let response = router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// The ids of the signing keys, most recent first
+async fn kids(router: &Router) -> Vec<String> {
+    let request = Request::builder()
+        .uri("/.well-known/jwks.json")
+        .body(Body::empty())
+        .unwrap();
+
+    send(router, request).await["keys"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|key| key["kid"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn post_user(name: &str) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/users/{name}/basic"))
+        .method("POST")
+        .header(AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn get_user(name: &str) -> Request<Body> {
+    Request::builder()
+        .uri(format!("/users/{name}"))
+        .header(AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn post_token(key: &str, name: &str) -> Request<Body> {
+    Request::builder()
+        .uri("/users/tokens")
+        .method("POST")
+        .header(AUTHORIZATION, format!("Bearer {key}"))
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(
+            json!({ "name": name, "scopes": ["logs"] }).to_string(),
+        ))
+        .unwrap()
+}
+
+fn get_audit_log() -> Request<Body> {
+    Request::builder()
+        .uri("/users/audit?all=true")
+        .header(AUTHORIZATION, format!("Bearer {ADMIN_KEY}"))
+        .body(Body::empty())
+        .unwrap()
+}
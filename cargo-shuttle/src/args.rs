@@ -7,12 +7,17 @@ use std::{
 
 use anyhow::{bail, Context};
 use cargo_metadata::MetadataCommand;
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
 use clap::{
     builder::{OsStringValueParser, PossibleValue, TypedValueParser},
     Parser, ValueEnum,
 };
 use clap_complete::Shell;
-use shuttle_common::{models::project::DEFAULT_IDLE_MINUTES, project::ProjectName, resource};
+use shuttle_common::{
+    models::{project::DEFAULT_IDLE_MINUTES, team::TeamRole},
+    project::ProjectName,
+    resource,
+};
 use uuid::Uuid;
 
 #[derive(Parser)]
@@ -32,10 +37,29 @@ pub struct ShuttleArgs {
     /// (allows targeting a custom deployed instance for this command only, mainly for development)
     #[arg(long, env = "SHUTTLE_API")]
     pub api_url: Option<String>,
+    /// Use the API URL and key of this profile instead of the one picked by Shuttle.toml or
+    /// `cargo shuttle profile use`
+    #[arg(global = true, long, env = "SHUTTLE_PROFILE")]
+    pub profile: Option<String>,
+    /// Format to print command output and errors in. `json` and `yaml` print the API
+    /// responses, for use in scripts
+    #[arg(global = true, long, value_enum, default_value_t = OutputMode::Table)]
+    pub output: OutputMode,
     #[command(subcommand)]
     pub cmd: Command,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Human readable tables and messages
+    #[default]
+    Table,
+    /// JSON, with streamed items such as log lines printed one per line
+    Json,
+    /// YAML, with streamed items printed as separate documents
+    Yaml,
+}
+
 // Common args for subcommands that deal with projects.
 #[derive(Parser, Debug)]
 pub struct ProjectArgs {
@@ -118,6 +142,24 @@ pub enum Command {
     /// Manage resources of a Shuttle project
     #[command(subcommand)]
     Resource(ResourceCommand),
+    /// Manage teams and their members
+    #[command(subcommand)]
+    Team(TeamCommand),
+    /// Manage named API tokens, such as ones limited to deploying from CI
+    #[command(subcommand)]
+    Token(TokenCommand),
+    /// View the actions recently taken by this account on projects, deployments and tokens
+    Audit {
+        /// How many actions to display
+        #[arg(long, default_value = "50")]
+        limit: u32,
+    },
+    /// Manage the data kept for this account
+    #[command(subcommand)]
+    Account(AccountCommand),
+    /// Manage profiles, which hold the API URL and key to use, such as for a self-hosted Shuttle
+    #[command(subcommand)]
+    Profile(ProfileCommand),
     /// Manage secrets for this Shuttle service
     Secrets {
         #[arg(long, default_value_t = false)]
@@ -136,8 +178,8 @@ pub enum Command {
         #[arg(short, long, env, default_value_t = Shell::Bash)]
         shell: Shell,
         /// Output to a file (stdout by default)
-        #[arg(short, long, env)]
-        output: Option<PathBuf>,
+        #[arg(short = 'o', long, env = "OUTPUT")]
+        output_file: Option<PathBuf>,
     },
     /// Open an issue on GitHub and provide feedback
     Feedback,
@@ -220,6 +262,119 @@ pub enum ProjectCommand {
     },
     /// Delete project. This also deletes associated Secrets and Persist data.
     Delete,
+    /// Transfer the ownership of this project to a team, or back to your account
+    Transfer {
+        /// Name of the team to transfer the project to. Leave it out to move the project out of
+        /// its team and back to the account which created it.
+        team: Option<String>,
+    },
+    /// View the requests, bandwidth, uptime and build minutes used by this project
+    Usage {
+        #[arg(long, value_parser = parse_date_time)]
+        /// Start of the period, as a date (2023-10-01) or RFC 3339 timestamp. Defaults to 30 days before --to
+        from: Option<DateTime<Utc>>,
+
+        #[arg(long, value_parser = parse_date_time)]
+        /// End of the period, as a date (2023-10-31) or RFC 3339 timestamp. Defaults to now
+        to: Option<DateTime<Utc>>,
+    },
+}
+
+#[derive(Parser)]
+pub enum TeamCommand {
+    /// List the teams the calling account is a member of
+    List,
+    /// View the members of a team
+    Status {
+        /// Name of the team
+        name: String,
+    },
+    /// Create a new team, with the calling account as its owner
+    Create {
+        /// Name of the team
+        name: String,
+    },
+    /// Add an account to a team, or change its role in the team
+    AddMember {
+        /// Name of the team
+        team: String,
+        /// Name of the account to add
+        account: String,
+        #[arg(long, default_value_t = TeamRole::Deployer)]
+        /// Role of the account in the team: owner, deployer or viewer
+        role: TeamRole,
+    },
+    /// Remove an account from a team
+    RemoveMember {
+        /// Name of the team
+        team: String,
+        /// Name of the account to remove
+        account: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum TokenCommand {
+    /// Create a new API token. The token is only shown once
+    Create {
+        /// Name of the token
+        name: String,
+        #[arg(long = "scope", required = true)]
+        /// Scope the token is limited to, such as `deployment_push` or `logs`. Can be repeated
+        scopes: Vec<String>,
+        #[arg(long)]
+        /// Only allow the token to be used on this project
+        project: Option<String>,
+        #[arg(long, value_parser = parse_date_time)]
+        /// When the token expires, as a date (2023-10-01) or RFC 3339 timestamp. Defaults to never
+        expires: Option<DateTime<Utc>>,
+    },
+    /// List the API tokens of the calling account
+    List,
+    /// Revoke an API token
+    Revoke {
+        /// Name of the token
+        name: String,
+    },
+}
+
+#[derive(Parser)]
+pub enum AccountCommand {
+    /// Export the projects, deployments and secret names of this account as JSON
+    Export {
+        #[arg(long)]
+        /// File to write the export to, instead of stdout
+        output_file: Option<PathBuf>,
+    },
+}
+
+#[derive(Parser)]
+pub enum ProfileCommand {
+    /// Add a profile, or update an existing one. Log in with `--profile <name> login` to set
+    /// its API key later
+    Add {
+        /// Name of the profile
+        name: String,
+        #[arg(long)]
+        /// URL of the API to use, such as the one of a self-hosted instance
+        api_url: Option<String>,
+        #[arg(long)]
+        /// API key to use
+        api_key: Option<String>,
+    },
+    /// Use a profile for commands which do not pick one with `--profile`, `SHUTTLE_PROFILE` or
+    /// Shuttle.toml
+    Use {
+        /// Name of the profile. `default` uses the API key and URL set outside of any profile
+        name: String,
+    },
+    /// List the profiles and which one is in use
+    List,
+    /// Remove a profile and its API key
+    Remove {
+        /// Name of the profile
+        name: String,
+    },
 }
 
 #[derive(Parser, Debug)]
@@ -251,6 +406,9 @@ pub struct DeployArgs {
     /// Don't run pre-deploy tests
     #[arg(long, alias = "nt")]
     pub no_test: bool,
+    /// List the files which would be uploaded, and their sizes, instead of deploying
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -264,6 +422,13 @@ pub struct RunArgs {
     /// Use release mode for building the project
     #[arg(long, short = 'r')]
     pub release: bool,
+    /// Rebuild the project and restart the services it affects whenever a file changes
+    #[arg(long)]
+    pub watch: bool,
+    /// Load the secrets from this file instead of the ones in the crate directory. Files named
+    /// like `.env` are read as `KEY=value` lines, any other file as TOML
+    #[arg(long, value_parser = OsStringValueParser::new().try_map(parse_path))]
+    pub secrets: Option<PathBuf>,
 }
 
 #[derive(Parser, Clone, Debug)]
@@ -372,6 +537,17 @@ fn parse_path(path: OsString) -> Result<PathBuf, String> {
     dunce::canonicalize(&path).map_err(|e| format!("could not turn {path:?} into a real path: {e}"))
 }
 
+/// Helper function to parse a date or an RFC 3339 timestamp. Dates are taken as the start of the day in UTC
+fn parse_date_time(s: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(date_time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(date_time.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|date| Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap()))
+        .map_err(|_| format!("'{s}' is not a date (YYYY-MM-DD) or an RFC 3339 timestamp"))
+}
+
 /// Helper function to parse, create if not exists, and return the absolute path
 pub(crate) fn parse_init_path(path: OsString) -> Result<PathBuf, io::Error> {
     // Create the directory if does not exist
@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
 use headers::{Authorization, HeaderMapExt};
 use percent_encoding::utf8_percent_encode;
 use reqwest::Response;
@@ -7,7 +8,9 @@ use reqwest_retry::policies::ExponentialBackoff;
 use reqwest_retry::RetryTransientMiddleware;
 use serde::{Deserialize, Serialize};
 use shuttle_common::models::deployment::DeploymentRequest;
-use shuttle_common::models::{deployment, project, secret, service, ToJson};
+use shuttle_common::models::{
+    account, audit, deployment, project, secret, service, stats, team, user, ToJson,
+};
 use shuttle_common::project::ProjectName;
 use shuttle_common::secrets::Secret;
 use shuttle_common::{resource, ApiKey, ApiUrl, LogItem, VersionInfo};
@@ -199,6 +202,90 @@ impl Client {
         self.delete(path).await
     }
 
+    pub async fn get_project_usage(
+        &self,
+        project: &ProjectName,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<stats::ProjectUsage> {
+        let params: Vec<String> = [("from", from), ("to", to)]
+            .into_iter()
+            .filter_map(|(name, value)| {
+                value.map(|value| {
+                    format!(
+                        "{name}={}",
+                        utf8_percent_encode(
+                            &value.to_rfc3339_opts(SecondsFormat::Secs, true),
+                            percent_encoding::NON_ALPHANUMERIC
+                        )
+                    )
+                })
+            })
+            .collect();
+        let path = if params.is_empty() {
+            format!("/projects/{}/usage", project.as_str())
+        } else {
+            format!("/projects/{}/usage?{}", project.as_str(), params.join("&"))
+        };
+
+        self.get(path).await
+    }
+
+    pub async fn transfer_project(
+        &self,
+        project: &ProjectName,
+        team_name: Option<String>,
+    ) -> Result<project::Response> {
+        let path = format!("/projects/{}/transfer", project.as_str());
+
+        self.post(path, Some(team::TransferRequest { team_name }))
+            .await
+            .context("failed to make transfer project request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn get_teams_list(&self) -> Result<Vec<team::Response>> {
+        self.get("/teams".to_string()).await
+    }
+
+    pub async fn get_team(&self, team_name: &str) -> Result<team::Response> {
+        let path = format!("/teams/{team_name}");
+
+        self.get(path).await
+    }
+
+    pub async fn create_team(&self, team_name: &str) -> Result<team::Response> {
+        let path = format!("/teams/{team_name}");
+
+        self.post(path, Option::<String>::None)
+            .await
+            .context("failed to make create team request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn set_team_member(
+        &self,
+        team_name: &str,
+        account_name: &str,
+        role: team::TeamRole,
+    ) -> Result<team::Response> {
+        let path = format!("/teams/{team_name}/members/{account_name}");
+
+        self.put(path, Some(team::MemberRequest { role }))
+            .await
+            .context("failed to make set team member request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn remove_team_member(&self, team_name: &str, account_name: &str) -> Result<String> {
+        let path = format!("/teams/{team_name}/members/{account_name}");
+
+        self.delete(path).await
+    }
+
     pub async fn get_secrets(&self, project: &ProjectName) -> Result<Vec<secret::Response>> {
         let path = format!(
             "/projects/{}/secrets/{}",
@@ -274,6 +361,60 @@ impl Client {
             .await
     }
 
+    pub async fn create_token(&self, request: user::TokenRequest) -> Result<user::TokenResponse> {
+        self.post("/users/tokens".to_string(), Some(request))
+            .await
+            .context("failed to make create token request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn get_tokens_list(&self) -> Result<Vec<user::TokenResponse>> {
+        self.get("/users/tokens".to_string()).await
+    }
+
+    pub async fn revoke_token(&self, token_name: &str) -> Result<String> {
+        let path = format!("/users/tokens/{token_name}");
+
+        self.delete(path).await
+    }
+
+    pub async fn start_device_login(&self) -> Result<user::DeviceCodeResponse> {
+        self.post("/auth/device/code".to_string(), Option::<String>::None)
+            .await
+            .context("failed to make device code request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn poll_device_login(&self, device_code: &str) -> Result<user::DeviceTokenResponse> {
+        let request = user::DeviceTokenRequest {
+            device_code: device_code.to_string(),
+        };
+
+        self.post("/auth/device/token".to_string(), Some(request))
+            .await
+            .context("failed to make device token request")?
+            .to_json()
+            .await
+    }
+
+    pub async fn get_account_export(&self) -> Result<account::ExportBundle> {
+        self.get("/account/export".to_string()).await
+    }
+
+    pub async fn get_audit_log(&self, limit: u32) -> Result<Vec<audit::Response>> {
+        let path = format!("/audit?limit={limit}");
+
+        self.get(path).await
+    }
+
+    pub async fn get_user_audit_log(&self, limit: u32) -> Result<Vec<audit::Response>> {
+        let path = format!("/users/audit?limit={limit}");
+
+        self.get(path).await
+    }
+
     async fn ws_get(&self, path: String) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
         let ws_scheme = self.api_url.clone().replace("http", "ws");
         let url = format!("{ws_scheme}{path}");
@@ -1,8 +1,9 @@
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use serde::{Deserialize, Serialize};
 use shuttle_common::{constants::API_URL_DEFAULT, project::ProjectName, ApiKey, ApiUrl};
 use tracing::trace;
@@ -118,11 +119,18 @@ impl ConfigManager for LocalConfigManager {
     }
 }
 
+/// Name of the profile made up of the API key and URL at the top of the global config
+pub const DEFAULT_PROFILE: &str = "default";
+
 /// Global client config for things like API keys.
 #[derive(Deserialize, Serialize, Default)]
 pub struct GlobalConfig {
     api_key: Option<String>,
     pub api_url: Option<ApiUrl>,
+    /// Profile chosen with `cargo shuttle profile use`
+    pub default_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
 }
 
 impl GlobalConfig {
@@ -143,11 +151,48 @@ impl GlobalConfig {
     }
 }
 
+/// A named API key and URL, such as for a self-hosted Shuttle instance
+#[derive(Deserialize, Serialize, Default)]
+pub struct Profile {
+    api_key: Option<String>,
+    pub api_url: Option<ApiUrl>,
+}
+
+impl Profile {
+    pub fn api_key(&self) -> Option<Result<ApiKey>> {
+        self.api_key.as_ref().map(|key| ApiKey::parse(key))
+    }
+
+    pub fn set_api_key(&mut self, api_key: ApiKey) -> Option<String> {
+        self.api_key.replace(api_key.as_ref().to_string())
+    }
+
+    pub fn clear_api_key(&mut self) {
+        self.api_key = None;
+    }
+
+    pub fn api_url(&self) -> Option<ApiUrl> {
+        self.api_url.clone()
+    }
+}
+
+/// A profile as listed by `cargo shuttle profile list`
+#[derive(Serialize)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub api_url: ApiUrl,
+    pub logged_in: bool,
+    pub active: bool,
+}
+
 /// Project-local config for things like customizing project name
 #[derive(Deserialize, Serialize, Default)]
 pub struct ProjectConfig {
     pub name: Option<ProjectName>,
     pub assets: Option<Vec<String>>,
+    /// Profile to use for commands run in this project, unless another is chosen with
+    /// `--profile` or `SHUTTLE_PROFILE`
+    pub profile: Option<String>,
 }
 
 /// A handler for configuration files. The type parameter `M` is the [`ConfigManager`] which handles
@@ -233,6 +278,7 @@ pub struct RequestContext {
     global: Config<GlobalConfigManager, GlobalConfig>,
     project: Option<Config<LocalConfigManager, ProjectConfig>>,
     api_url: Option<String>,
+    profile: Option<String>,
 }
 
 impl RequestContext {
@@ -249,6 +295,7 @@ impl RequestContext {
             global,
             project: None,
             api_url: None,
+            profile: None,
         })
     }
 
@@ -315,16 +362,142 @@ impl RequestContext {
         self.api_url = api_url;
     }
 
-    pub fn api_url(&self) -> ApiUrl {
+    pub fn api_url(&self) -> Result<ApiUrl> {
         if let Some(api_url) = self.api_url.clone() {
-            api_url
-        } else if let Some(api_url) = self.global.as_ref().unwrap().api_url() {
-            api_url
-        } else {
-            API_URL_DEFAULT.to_string()
+            return Ok(api_url);
+        }
+
+        let api_url = match self.profile()? {
+            Some(profile) => profile.api_url(),
+            None => self.global.as_ref().unwrap().api_url(),
+        };
+
+        Ok(api_url.unwrap_or_else(|| API_URL_DEFAULT.to_string()))
+    }
+
+    pub fn set_profile(&mut self, profile: Option<String>) {
+        self.profile = profile;
+    }
+
+    /// Get the name of the profile in use, which is picked in this order:
+    /// 1. Profile given with `--profile` or `SHUTTLE_PROFILE`
+    /// 2. Profile from the Shuttle.toml file, if the project configuration has been loaded
+    /// 3. Profile chosen with `cargo shuttle profile use`
+    /// 4. The default profile
+    pub fn profile_name(&self) -> &str {
+        self.profile
+            .as_deref()
+            .or_else(|| {
+                self.project
+                    .as_ref()
+                    .and_then(|project| project.as_ref())
+                    .and_then(|config| config.profile.as_deref())
+            })
+            .or(self.global.as_ref().unwrap().default_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+    }
+
+    /// Get the profile in use. Returns `None` for the default profile, whose settings are at the
+    /// top of the global configuration.
+    fn profile(&self) -> Result<Option<&Profile>> {
+        match self.profile_name() {
+            DEFAULT_PROFILE => Ok(None),
+            name => self
+                .global
+                .as_ref()
+                .unwrap()
+                .profiles
+                .get(name)
+                .map(Some)
+                .ok_or_else(|| unknown_profile(name)),
         }
     }
 
+    /// Add a profile, or update the settings of an existing one. Will persist the file.
+    pub fn add_profile(
+        &mut self,
+        name: &str,
+        api_url: Option<ApiUrl>,
+        api_key: Option<ApiKey>,
+    ) -> Result<()> {
+        if name.is_empty() || name == DEFAULT_PROFILE {
+            bail!("`{name}` cannot be used as a profile name");
+        }
+
+        let profile = self
+            .global
+            .as_mut()
+            .unwrap()
+            .profiles
+            .entry(name.to_string())
+            .or_default();
+
+        if api_url.is_some() {
+            profile.api_url = api_url;
+        }
+        if let Some(api_key) = api_key {
+            profile.set_api_key(api_key);
+        }
+
+        self.global.save()
+    }
+
+    /// Use a profile for commands which do not pick one themselves. Will persist the file.
+    pub fn use_profile(&mut self, name: &str) -> Result<()> {
+        let global = self.global.as_mut().unwrap();
+
+        global.default_profile = match name {
+            DEFAULT_PROFILE => None,
+            name if global.profiles.contains_key(name) => Some(name.to_string()),
+            name => return Err(unknown_profile(name)),
+        };
+
+        self.global.save()
+    }
+
+    /// Remove a profile and its API key. Will persist the file.
+    pub fn remove_profile(&mut self, name: &str) -> Result<()> {
+        if name == DEFAULT_PROFILE {
+            bail!("The default profile cannot be removed. Log out to remove its API key instead");
+        }
+
+        let global = self.global.as_mut().unwrap();
+
+        if global.profiles.remove(name).is_none() {
+            return Err(unknown_profile(name));
+        }
+        if global.default_profile.as_deref() == Some(name) {
+            global.default_profile = None;
+        }
+
+        self.global.save()
+    }
+
+    /// List the default profile followed by all the named ones
+    pub fn profiles(&self) -> Vec<ProfileSummary> {
+        let global = self.global.as_ref().unwrap();
+        let active = self.profile_name();
+        let summary = |name: &str, api_url: Option<ApiUrl>, logged_in: bool| ProfileSummary {
+            name: name.to_string(),
+            api_url: api_url.unwrap_or_else(|| API_URL_DEFAULT.to_string()),
+            logged_in,
+            active: name == active,
+        };
+
+        std::iter::once(summary(
+            DEFAULT_PROFILE,
+            global.api_url(),
+            global.api_key.is_some(),
+        ))
+        .chain(
+            global
+                .profiles
+                .iter()
+                .map(|(name, profile)| summary(name, profile.api_url(), profile.api_key.is_some())),
+        )
+        .collect()
+    }
+
     /// Get the API key from the `SHUTTLE_API_KEY` env variable, or
     /// otherwise from the global configuration. Returns an error if
     /// an API key is not set.
@@ -334,7 +507,12 @@ impl RequestContext {
         if let Ok(key) = api_key {
             ApiKey::parse(&key).context("environment variable SHUTTLE_API_KEY is invalid")
         } else {
-            match self.global.as_ref().unwrap().api_key() {
+            let api_key = match self.profile()? {
+                Some(profile) => profile.api_key(),
+                None => self.global.as_ref().unwrap().api_key(),
+            };
+
+            match api_key {
                 Some(key) => key,
                 None => Err(anyhow!(
                     "Configuration file: `{}`",
@@ -360,16 +538,35 @@ impl RequestContext {
             .as_path()
     }
 
-    /// Set the API key to the global configuration. Will persist the file.
+    /// Set the API key of the profile in use to the global configuration. Will persist the file.
     pub fn set_api_key(&mut self, api_key: ApiKey) -> Result<()> {
-        self.global.as_mut().unwrap().set_api_key(api_key);
+        let name = self.profile_name().to_string();
+        if name == DEFAULT_PROFILE {
+            self.global.as_mut().unwrap().set_api_key(api_key);
+        } else {
+            self.profile_mut(&name)?.set_api_key(api_key);
+        }
         self.global.save()
     }
 
     pub fn clear_api_key(&mut self) -> Result<()> {
-        self.global.as_mut().unwrap().clear_api_key();
+        let name = self.profile_name().to_string();
+        if name == DEFAULT_PROFILE {
+            self.global.as_mut().unwrap().clear_api_key();
+        } else {
+            self.profile_mut(&name)?.clear_api_key();
+        }
         self.global.save()
     }
+
+    fn profile_mut(&mut self, name: &str) -> Result<&mut Profile> {
+        self.global
+            .as_mut()
+            .unwrap()
+            .profiles
+            .get_mut(name)
+            .ok_or_else(|| unknown_profile(name))
+    }
     /// Get the current project name.
     ///
     /// # Panics
@@ -398,6 +595,12 @@ impl RequestContext {
     }
 }
 
+fn unknown_profile(name: &str) -> anyhow::Error {
+    anyhow!(
+        "Profile `{name}` does not exist. Add it first with:\n\tcargo shuttle profile add {name}"
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use std::{path::PathBuf, str::FromStr};
@@ -406,7 +609,9 @@ mod tests {
 
     use crate::{args::ProjectArgs, config::RequestContext};
 
-    use super::{Config, LocalConfigManager, ProjectConfig};
+    use super::{
+        Config, GlobalConfig, GlobalConfigManager, LocalConfigManager, Profile, ProjectConfig,
+    };
 
     fn path_from_workspace_root(path: &str) -> PathBuf {
         PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
@@ -453,4 +658,62 @@ mod tests {
 
         assert_eq!(unwrap_project_name(&local_config), "my-fancy-project-name");
     }
+
+    #[test]
+    fn profiles_are_picked_in_order() {
+        let mut global = Config::new(GlobalConfigManager);
+        global.replace(GlobalConfig {
+            api_url: Some("https://api.example.com".to_string()),
+            default_profile: Some("personal".to_string()),
+            profiles: [
+                (
+                    "personal".to_string(),
+                    Profile {
+                        api_url: Some("https://api.personal.example.com".to_string()),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "self-hosted".to_string(),
+                    Profile {
+                        api_url: Some("http://localhost:8001".to_string()),
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+            ..Default::default()
+        });
+        let mut local = Config::new(LocalConfigManager::new(".", "Shuttle.toml".to_string()));
+        local.replace(ProjectConfig {
+            profile: Some("self-hosted".to_string()),
+            ..Default::default()
+        });
+        let mut ctx = RequestContext {
+            global,
+            project: None,
+            api_url: None,
+            profile: None,
+        };
+
+        // Chosen with `cargo shuttle profile use`
+        assert_eq!(ctx.profile_name(), "personal");
+        assert_eq!(ctx.api_url().unwrap(), "https://api.personal.example.com");
+
+        // Shuttle.toml overrides the profile in use
+        ctx.project = Some(local);
+        assert_eq!(ctx.profile_name(), "self-hosted");
+        assert_eq!(ctx.api_url().unwrap(), "http://localhost:8001");
+
+        // `--profile` overrides everything
+        ctx.set_profile(Some("default".to_string()));
+        assert_eq!(ctx.api_url().unwrap(), "https://api.example.com");
+
+        ctx.set_profile(Some("unknown".to_string()));
+        assert!(ctx.api_url().is_err());
+
+        // `--api-url` still wins over any profile
+        ctx.set_api_url(Some("http://localhost:8000".to_string()));
+        assert_eq!(ctx.api_url().unwrap(), "http://localhost:8000");
+    }
 }
@@ -2,30 +2,33 @@ mod args;
 mod client;
 pub mod config;
 mod init;
+mod output;
 mod provisioner_server;
 mod suggestions;
 
 use std::collections::{BTreeMap, HashMap};
-use std::ffi::OsString;
+use std::ffi::{OsStr, OsString};
 use std::fs::{read_to_string, File};
 use std::io::stdout;
 use std::net::{Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::str::FromStr;
+use std::time::SystemTime;
 
 use shuttle_common::{
     claims::{ClaimService, InjectPropagation},
     constants::{API_URL_DEFAULT, EXECUTABLE_DIRNAME, STORAGE_DIRNAME},
     deployment::{DEPLOYER_END_MESSAGES_BAD, DEPLOYER_END_MESSAGES_GOOD},
     models::{
+        audit,
         deployment::{
             get_deployments_table, DeploymentRequest, CREATE_SERVICE_BODY_LIMIT,
             GIT_STRINGS_MAX_LENGTH,
         },
         project::{self, DEFAULT_IDLE_MINUTES},
         resource::get_resources_table,
-        secret,
+        secret, team, user,
     },
     project::ProjectName,
     resource, semvers_are_compatible, ApiKey, LogItem, VersionInfo,
@@ -40,11 +43,12 @@ use shuttle_service::{
 
 use anyhow::{anyhow, bail, Context, Result};
 use cargo_metadata::Message;
+use chrono::{DateTime, Utc};
 use clap::{parser::ValueSource, CommandFactory, FromArgMatches};
 use clap_complete::{generate, Shell};
 use config::RequestContext;
 use crossterm::style::Stylize;
-use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect, Input, Password};
+use dialoguer::{theme::ColorfulTheme, Confirm, FuzzySelect, Input};
 use flate2::write::GzEncoder;
 use flate2::Compression;
 use futures::{StreamExt, TryFutureExt};
@@ -54,6 +58,7 @@ use ignore::overrides::OverrideBuilder;
 use ignore::WalkBuilder;
 use indicatif::ProgressBar;
 use indoc::{formatdoc, printdoc};
+use serde::Serialize;
 use std::fmt::Write as FmtWrite;
 use strum::IntoEnumIterator;
 use tar::Builder;
@@ -65,26 +70,39 @@ use tonic::Status;
 use tracing::{debug, error, trace, warn};
 use uuid::Uuid;
 
-pub use crate::args::{Command, ProjectArgs, RunArgs, ShuttleArgs};
+pub use crate::args::{Command, OutputMode, ProjectArgs, RunArgs, ShuttleArgs};
 use crate::args::{
-    DeployArgs, DeploymentCommand, InitArgs, LoginArgs, LogoutArgs, ProjectCommand,
-    ProjectStartArgs, ResourceCommand, EXAMPLES_REPO,
+    AccountCommand, DeployArgs, DeploymentCommand, InitArgs, LoginArgs, LogoutArgs, ProfileCommand,
+    ProjectCommand, ProjectStartArgs, ResourceCommand, TeamCommand, TokenCommand, EXAMPLES_REPO,
 };
 use crate::client::Client;
+pub use crate::output::exit_code;
 use crate::provisioner_server::LocalProvisioner;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const MANIFEST_DIR: &str = env!("CARGO_MANIFEST_DIR");
-const SHUTTLE_LOGIN_URL: &str = "https://console.shuttle.rs/new-project";
 const SHUTTLE_GH_ISSUE_URL: &str = "https://github.com/shuttle-hq/shuttle/issues/new/choose";
 const SHUTTLE_CLI_DOCS_URL: &str = "https://docs.shuttle.rs/getting-started/shuttle-commands";
 const SHUTTLE_IDLE_DOCS_URL: &str = "https://docs.shuttle.rs/getting-started/idle-projects";
+/// Number of files listed as the largest by `cargo shuttle deploy --dry-run`
+const DRY_RUN_LARGEST_FILES: usize = 10;
+/// Like `.gitignore`, but for leaving files out of the archive which is deployed
+const SHUTTLE_IGNORE_FILENAME: &str = ".shuttleignore";
+/// Secrets which are shipped with a deployment
+const SECRETS_FILENAME: &str = "Secrets.toml";
+/// Secrets which are only for local runs, in order of precedence. These are never shipped.
+const LOCAL_SECRETS_FILENAMES: [&str; 2] = ["Secrets.local.toml", "Secrets.dev.toml"];
+/// Secrets for deployments, which are shipped as the `Secrets.toml` next to them instead of it
+const PRODUCTION_SECRETS_FILENAME: &str = "Secrets.production.toml";
+/// How often `cargo shuttle run --watch` looks for changed files
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 
 pub struct Shuttle {
     ctx: RequestContext,
     client: Option<Client>,
     version_info: Option<VersionInfo>,
     version_warnings: Vec<String>,
+    output: OutputMode,
 }
 
 impl Shuttle {
@@ -95,6 +113,7 @@ impl Shuttle {
             client: None,
             version_info: None,
             version_warnings: vec![],
+            output: OutputMode::default(),
         })
     }
 
@@ -110,7 +129,14 @@ impl Shuttle {
                     init_matches.value_source("path") == Some(ValueSource::CommandLine)
                 });
 
-        self.run(args, provided_path_to_init).await
+        let output = args.output;
+
+        self.run(args, provided_path_to_init)
+            .await
+            .map_err(|error| {
+                output.print_error(&error);
+                error
+            })
     }
 
     fn find_available_port(run_args: &mut RunArgs, services_len: usize) {
@@ -144,15 +170,18 @@ impl Shuttle {
         args: ShuttleArgs,
         provided_path_to_init: bool,
     ) -> Result<CommandOutcome> {
+        self.output = args.output;
+
         if let Some(ref url) = args.api_url {
             if url != API_URL_DEFAULT {
-                println!("INFO: Targetting non-standard API: {url}");
+                eprintln!("INFO: Targetting non-standard API: {url}");
             }
             if url.ends_with('/') {
                 eprintln!("WARNING: API URL is probably incorrect. Ends with '/': {url}");
             }
         }
         self.ctx.set_api_url(args.api_url);
+        self.ctx.set_profile(args.profile);
 
         // All commands that need to know which project is being handled
         if matches!(
@@ -167,6 +196,8 @@ impl Shuttle {
                         | ProjectCommand::Restart { .. }
                         | ProjectCommand::Status { .. }
                         | ProjectCommand::Delete
+                        | ProjectCommand::Usage { .. }
+                        | ProjectCommand::Transfer { .. }
                 )
                 | Command::Stop
                 | Command::Clean
@@ -182,7 +213,7 @@ impl Shuttle {
         if matches!(
             args.cmd,
             Command::Init(..)
-                | Command::Deploy(..)
+                | Command::Deploy(DeployArgs { dry_run: false, .. })
                 | Command::Status
                 | Command::Logs { .. }
                 | Command::Logout(..)
@@ -192,8 +223,12 @@ impl Shuttle {
                 | Command::Clean
                 | Command::Secrets { .. }
                 | Command::Project(..)
+                | Command::Team(..)
+                | Command::Token(..)
+                | Command::Audit { .. }
+                | Command::Account(..)
         ) {
-            let mut client = Client::new(self.ctx.api_url());
+            let mut client = Client::new(self.ctx.api_url()?);
             if !matches!(args.cmd, Command::Init(..)) {
                 // init command will handle this by itself (log in and set key) if there is no key yet
                 client.set_api_key(self.ctx.api_key()?);
@@ -207,11 +242,13 @@ impl Shuttle {
                 self.init(init_args, args.project_args, provided_path_to_init)
                     .await
             }
-            Command::Generate { shell, output } => self.complete(shell, output),
+            Command::Generate { shell, output_file } => self.complete(shell, output_file),
             Command::Login(login_args) => self.login(login_args).await,
             Command::Logout(logout_args) => self.logout(logout_args).await,
             Command::Feedback => self.feedback(),
+            Command::Run(run_args) if run_args.watch => self.local_run_watch(run_args).await,
             Command::Run(run_args) => self.local_run(run_args).await,
+            Command::Deploy(deploy_args) if deploy_args.dry_run => self.deploy_dry_run(),
             Command::Deploy(deploy_args) => self.deploy(deploy_args).await,
             Command::Status => self.status().await,
             Command::Logs { id, latest, follow } => self.logs(id, latest, follow).await,
@@ -242,10 +279,52 @@ impl Shuttle {
             }
             Command::Project(ProjectCommand::Stop) => self.project_stop().await,
             Command::Project(ProjectCommand::Delete) => self.project_delete().await,
+            Command::Project(ProjectCommand::Usage { from, to }) => {
+                self.project_usage(from, to).await
+            }
+            Command::Project(ProjectCommand::Transfer { team }) => {
+                self.project_transfer(team).await
+            }
+            Command::Team(TeamCommand::List) => self.teams_list().await,
+            Command::Team(TeamCommand::Status { name }) => self.team_status(&name).await,
+            Command::Team(TeamCommand::Create { name }) => self.team_create(&name).await,
+            Command::Team(TeamCommand::AddMember {
+                team,
+                account,
+                role,
+            }) => self.team_add_member(&team, &account, role).await,
+            Command::Team(TeamCommand::RemoveMember { team, account }) => {
+                self.team_remove_member(&team, &account).await
+            }
+            Command::Token(TokenCommand::Create {
+                name,
+                scopes,
+                project,
+                expires,
+            }) => self.token_create(name, scopes, project, expires).await,
+            Command::Token(TokenCommand::List) => self.tokens_list().await,
+            Command::Token(TokenCommand::Revoke { name }) => self.token_revoke(&name).await,
+            Command::Audit { limit } => self.audit(limit).await,
+            Command::Account(AccountCommand::Export { output_file }) => {
+                self.account_export(output_file).await
+            }
+            Command::Profile(ProfileCommand::Add {
+                name,
+                api_url,
+                api_key,
+            }) => self.profile_add(&name, api_url, api_key),
+            Command::Profile(ProfileCommand::Use { name }) => self.profile_use(&name),
+            Command::Profile(ProfileCommand::List) => self.profiles_list(),
+            Command::Profile(ProfileCommand::Remove { name }) => self.profile_remove(&name),
         };
 
         for w in self.version_warnings {
-            println!("{w}");
+            // Keep stdout parsable when a structured output format is used
+            if self.output == OutputMode::Table {
+                println!("{w}");
+            } else {
+                eprintln!("{w}");
+            }
         }
 
         res
@@ -525,15 +604,7 @@ impl Shuttle {
     async fn login(&mut self, login_args: LoginArgs) -> Result<CommandOutcome> {
         let api_key_str = match login_args.api_key {
             Some(api_key) => api_key,
-            None => {
-                let _ = webbrowser::open(SHUTTLE_LOGIN_URL);
-                println!("If your browser did not automatically open, go to {SHUTTLE_LOGIN_URL}");
-
-                Password::with_theme(&ColorfulTheme::default())
-                    .with_prompt("API key")
-                    .validate_with(|input: &String| ApiKey::parse(input).map(|_| ()))
-                    .interact()?
-            }
+            None => self.device_login().await?,
         };
 
         let api_key = ApiKey::parse(&api_key_str)?;
@@ -547,13 +618,51 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
+    /// Log in by approving this device in the browser, which gives the CLI its own API token
+    async fn device_login(&self) -> Result<String> {
+        let client = Client::new(self.ctx.api_url()?);
+        let codes = client
+            .start_device_login()
+            .await
+            .context("failed to start the login")?;
+
+        let _ = webbrowser::open(&codes.verification_uri_complete);
+        println!(
+            "If your browser did not automatically open, go to {} and enter the code {}",
+            codes.verification_uri,
+            codes.user_code.clone().bold()
+        );
+
+        let pb = create_spinner();
+        pb.set_message("Waiting for the login to be approved");
+
+        // Polling fails once the codes expire
+        let key = loop {
+            tokio::time::sleep(std::time::Duration::from_secs(codes.interval)).await;
+
+            let response = client
+                .poll_device_login(&codes.device_code)
+                .await
+                .context("the login was not approved in time")?;
+
+            if let Some(key) = response.key {
+                break key;
+            }
+        };
+        pb.finish_and_clear();
+
+        println!("{}", "Successfully logged in".bold());
+
+        Ok(key)
+    }
+
     async fn logout(&mut self, logout_args: LogoutArgs) -> Result<CommandOutcome> {
         if logout_args.reset_api_key {
             self.reset_api_key()
                 .await
                 .map_err(suggestions::api_key::reset_api_key_failed)?;
             println!("Successfully reset the API key.");
-            println!(" -> Go to {SHUTTLE_LOGIN_URL} to get a new one.\n");
+            println!(" -> Run `cargo shuttle login` to get a new one.\n");
         }
         self.ctx.clear_api_key()?;
         println!("Successfully logged out of shuttle.");
@@ -575,28 +684,24 @@ impl Shuttle {
     async fn stop(&self) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         let p = self.ctx.project_name();
-        wait_with_spinner(|i, pb| async move {
+        let service = wait_with_spinner(|i, pb| async move {
             let service = if i == 0 {
                 client.stop_service(p).await?
             } else {
                 client.get_service(p).await?
             };
 
-            let service_str = format!("{service}");
-            let cleanup = move || {
-                println!("{}", "Successfully stopped service".bold());
-                println!("{service_str}");
-                println!("Run `cargo shuttle deploy` to re-deploy your service.");
-            };
+            let stopped = match service.deployment {
+                Some(ref deployment) => {
+                    pb.set_message(format!("Stopping {}", deployment.id));
 
-            let Some(ref deployment) = service.deployment else {
-                return Ok(Some(cleanup));
+                    deployment.state == shuttle_common::deployment::State::Stopped
+                }
+                None => true,
             };
 
-            pb.set_message(format!("Stopping {}", deployment.id));
-
-            if deployment.state == shuttle_common::deployment::State::Stopped {
-                Ok(Some(cleanup))
+            if stopped {
+                Ok(Some(move || service))
             } else {
                 Ok(None)
             }
@@ -604,13 +709,19 @@ impl Shuttle {
         .await
         .map_err(suggestions::deployment::stop_deployment_failure)?;
 
+        if !self.output.print(&service)? {
+            println!("{}", "Successfully stopped service".bold());
+            println!("{service}");
+            println!("Run `cargo shuttle deploy` to re-deploy your service.");
+        }
+
         Ok(CommandOutcome::Ok)
     }
 
-    fn complete(&self, shell: Shell, output: Option<PathBuf>) -> Result<CommandOutcome> {
+    fn complete(&self, shell: Shell, output_file: Option<PathBuf>) -> Result<CommandOutcome> {
         let name = env!("CARGO_PKG_NAME");
         let mut app = Command::command();
-        match output {
+        match output_file {
             Some(v) => generate(shell, &mut app, name, &mut File::create(v)?),
             None => generate(shell, &mut app, name, &mut stdout()),
         };
@@ -622,7 +733,9 @@ impl Shuttle {
         let client = self.client.as_ref().unwrap();
         let summary = client.get_service(self.ctx.project_name()).await?;
 
-        println!("{summary}");
+        if !self.output.print(&summary)? {
+            println!("{summary}");
+        }
 
         Ok(CommandOutcome::Ok)
     }
@@ -633,9 +746,13 @@ impl Shuttle {
             .get_secrets(self.ctx.project_name())
             .await
             .map_err(suggestions::resources::get_secrets_failure)?;
-        let table = secret::get_secrets_table(&secrets, raw);
 
-        println!("{table}");
+        if self.output == OutputMode::Table {
+            println!("{}", secret::get_secrets_table(&secrets, raw));
+        } else {
+            let secrets: Vec<_> = secrets.into_iter().map(output::redact_secret).collect();
+            self.output.print(&secrets)?;
+        }
 
         Ok(CommandOutcome::Ok)
     }
@@ -654,11 +771,13 @@ impl Shuttle {
                 )
             })?;
 
-        for line in lines {
-            println!("{line}");
-        }
+        if !self.output.print(&lines)? {
+            for line in lines {
+                println!("{line}");
+            }
 
-        println!("Cleaning done!");
+            println!("Cleaning done!");
+        }
 
         Ok(CommandOutcome::Ok)
     }
@@ -709,7 +828,9 @@ impl Shuttle {
                 if let tokio_tungstenite::tungstenite::Message::Text(line) = msg {
                     let log_item: shuttle_common::LogItem = serde_json::from_str(&line)
                         .context("Failed parsing logs. Is your cargo-shuttle outdated?")?;
-                    println!("{log_item}")
+                    if !self.output.print_item(&log_item)? {
+                        println!("{log_item}")
+                    }
                 }
             }
         } else {
@@ -720,8 +841,10 @@ impl Shuttle {
                     suggestions::logs::get_logs_failure(err, "Fetching the deployment failed")
                 })?;
 
-            for log in logs.into_iter() {
-                println!("{log}");
+            if !self.output.print(&logs)? {
+                for log in logs.into_iter() {
+                    println!("{log}");
+                }
             }
         }
 
@@ -731,7 +854,12 @@ impl Shuttle {
     async fn deployments_list(&self, page: u32, limit: u32, raw: bool) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         if limit == 0 {
-            println!();
+            if !self
+                .output
+                .print(&Vec::<shuttle_common::models::deployment::Response>::new())?
+            {
+                println!();
+            }
             return Ok(CommandOutcome::Ok);
         }
 
@@ -740,10 +868,13 @@ impl Shuttle {
             .get_deployments(proj_name, page, limit)
             .await
             .map_err(suggestions::deployment::get_deployments_list_failure)?;
-        let table = get_deployments_table(&deployments, proj_name.as_str(), page, raw);
 
-        println!("{table}");
-        println!("Run `cargo shuttle logs <id>` to get logs for a given deployment.");
+        if !self.output.print(&deployments)? {
+            let table = get_deployments_table(&deployments, proj_name.as_str(), page, raw);
+
+            println!("{table}");
+            println!("Run `cargo shuttle logs <id>` to get logs for a given deployment.");
+        }
 
         Ok(CommandOutcome::Ok)
     }
@@ -755,7 +886,9 @@ impl Shuttle {
             .await
             .map_err(suggestions::deployment::get_deployment_status_failure)?;
 
-        println!("{deployment}");
+        if !self.output.print(&deployment)? {
+            println!("{deployment}");
+        }
 
         Ok(CommandOutcome::Ok)
     }
@@ -766,14 +899,22 @@ impl Shuttle {
             .get_service_resources(self.ctx.project_name())
             .await
             .map_err(suggestions::resources::get_service_resources_failure)?;
-        let table = get_resources_table(
-            &resources,
-            self.ctx.project_name().as_str(),
-            raw,
-            show_secrets,
-        );
 
-        println!("{table}");
+        if self.output == OutputMode::Table {
+            let table = get_resources_table(
+                &resources,
+                self.ctx.project_name().as_str(),
+                raw,
+                show_secrets,
+            );
+
+            println!("{table}");
+        } else if show_secrets {
+            self.output.print(&resources)?;
+        } else {
+            let resources: Vec<_> = resources.into_iter().map(output::redact_resource).collect();
+            self.output.print(&resources)?;
+        }
 
         Ok(CommandOutcome::Ok)
     }
@@ -819,10 +960,11 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
+    /// Start the runtime of a service and load the service into it. The caller is responsible
+    /// for stopping the provisioner if this fails.
     async fn spin_local_runtime(
         run_args: &RunArgs,
         service: &BuiltService,
-        provisioner_server: &JoinHandle<Result<(), tonic::transport::Error>>,
         idx: u16,
         provisioner_port: u16,
     ) -> Result<
@@ -831,18 +973,16 @@ impl Shuttle {
             RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
         )>,
     > {
-        let crate_directory = service.crate_directory();
-        let secrets_path = if crate_directory.join("Secrets.dev.toml").exists() {
-            crate_directory.join("Secrets.dev.toml")
-        } else {
-            crate_directory.join("Secrets.toml")
-        };
-        trace!("Loading secrets from {}", secrets_path.display());
+        let secrets_path = Shuttle::local_secrets_files(run_args, service.crate_directory())
+            .into_iter()
+            .find(|path| path.exists());
 
-        let secrets: HashMap<String, String> = if let Ok(secrets_str) = read_to_string(secrets_path)
-        {
-            let secrets: HashMap<String, String> =
-                secrets_str.parse::<toml::Value>()?.try_into()?;
+        let secrets: HashMap<String, String> = if let Some(secrets_path) = secrets_path {
+            trace!("Loading secrets from {}", secrets_path.display());
+
+            let secrets = read_secrets(&secrets_path).with_context(|| {
+                format!("failed to read secrets from {}", secrets_path.display())
+            })?;
 
             trace!(keys = ?secrets.keys(), "available secrets");
 
@@ -928,15 +1068,12 @@ impl Shuttle {
             Environment::Local,
             &format!("http://localhost:{provisioner_port}"),
             None,
+            None,
             portpicker::pick_unused_port().expect("unable to find available port for gRPC server"),
             runtime_executable,
             service.workspace_path.as_path(),
         )
-        .await
-        .map_err(|err| {
-            provisioner_server.abort();
-            err
-        })?;
+        .await?;
 
         let service_name = service.service_name()?;
         let deployment_id: Uuid = Default::default();
@@ -976,7 +1113,6 @@ impl Shuttle {
         let response = runtime_client
             .load(load_request)
             .or_else(|err| async {
-                provisioner_server.abort();
                 runtime.kill().await?;
                 Err(err)
             })
@@ -1024,7 +1160,6 @@ impl Shuttle {
         let response = runtime_client
             .start(tonic::Request::new(start_request))
             .or_else(|err| async {
-                provisioner_server.abort();
                 runtime.kill().await?;
                 Err(err)
             })
@@ -1162,12 +1297,15 @@ impl Shuttle {
             // We must cover the case of starting multiple workspace services and receiving a signal in parallel.
             // This must stop all the existing runtimes and creating new ones.
             signal_received = tokio::select! {
-                res = Shuttle::spin_local_runtime(&run_args, service, &provisioner_server, i as u16, provisioner_port) => {
+                res = Shuttle::spin_local_runtime(&run_args, service, i as u16, provisioner_port) => {
                     match res {
                         Ok(runtime) => {
                             Shuttle::add_runtime_info(runtime, &mut runtimes, &[&provisioner_server]).await?;
                         },
-                        Err(e) => println!("Runtime error: {e:?}"),
+                        Err(e) => {
+                            provisioner_server.abort();
+                            println!("Runtime error: {e:?}");
+                        }
                     }
                     false
                 },
@@ -1314,8 +1452,12 @@ impl Shuttle {
         let mut signal_received = false;
         for (i, service) in services.iter().enumerate() {
             signal_received = tokio::select! {
-                res = Shuttle::spin_local_runtime(&run_args, service, &provisioner_server, i as u16, provisioner_port) => {
-                    Shuttle::add_runtime_info(res.unwrap(), &mut runtimes, &[&provisioner_server]).await?;
+                res = Shuttle::spin_local_runtime(&run_args, service, i as u16, provisioner_port) => {
+                    let runtime = res.map_err(|err| {
+                        provisioner_server.abort();
+                        err
+                    });
+                    Shuttle::add_runtime_info(runtime.unwrap(), &mut runtimes, &[&provisioner_server]).await?;
                     false
                 },
                 _ = Shuttle::handle_signals() => {
@@ -1389,6 +1531,285 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
+    /// Run the services of the workspace, rebuilding them whenever a project file changes and
+    /// restarting the ones affected by the change. The local provisioner keeps running the whole
+    /// time, so the databases it provisioned stay up between restarts.
+    async fn local_run_watch(&self, mut run_args: RunArgs) -> Result<CommandOutcome> {
+        let (provisioner_server, provisioner_port) = Shuttle::setup_local_provisioner().await?;
+
+        let (shutdown_tx, mut shutdown) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                let _ = shutdown_tx.send(());
+            }
+        });
+
+        // Runtimes and the index of the port they listen on, by service package name
+        let mut runtimes: HashMap<String, WatchedRuntime> = HashMap::new();
+        let mut port_indices: HashMap<String, u16> = HashMap::new();
+        let mut secrets_files = Vec::new();
+        let mut files =
+            Shuttle::watched_files(self.watched_project_files()?.into_keys(), &secrets_files);
+        let mut changed: Vec<PathBuf> = Vec::new();
+
+        'watch: loop {
+            let services = tokio::select! {
+                services = self.pre_local_run(&run_args) => services,
+                _ = &mut shutdown => break 'watch,
+            };
+
+            match services {
+                Ok(services) => {
+                    if port_indices.is_empty() {
+                        Shuttle::find_available_port(&mut run_args, services.len());
+                    }
+
+                    // Services removed from the workspace
+                    let removed: Vec<_> = runtimes
+                        .keys()
+                        .filter(|name| !services.iter().any(|s| &s.package_name == *name))
+                        .cloned()
+                        .collect();
+                    for name in removed {
+                        if let Some(runtime) = runtimes.remove(&name) {
+                            runtime.stop().await;
+                        }
+                    }
+
+                    for service in &services {
+                        let executable_modified = modified_time(&service.executable_path);
+                        let affected = match runtimes.get(&service.package_name) {
+                            Some(runtime) => {
+                                runtime.executable_modified != executable_modified
+                                    || changed
+                                        .iter()
+                                        .any(|path| path.starts_with(service.crate_directory()))
+                            }
+                            None => true,
+                        };
+
+                        if !affected {
+                            continue;
+                        }
+
+                        if let Some(runtime) = runtimes.remove(&service.package_name) {
+                            println!("{} {}", "  Restarting".bold().green(), service.package_name);
+                            runtime.stop().await;
+                        }
+
+                        let next_index = port_indices.len() as u16;
+                        let idx = *port_indices
+                            .entry(service.package_name.clone())
+                            .or_insert(next_index);
+
+                        match Shuttle::spin_local_runtime(&run_args, service, idx, provisioner_port)
+                            .await
+                        {
+                            Ok(Some((runtime, client))) => {
+                                runtimes.insert(
+                                    service.package_name.clone(),
+                                    WatchedRuntime {
+                                        runtime,
+                                        client,
+                                        executable_modified,
+                                    },
+                                );
+                            }
+                            // The reason has already been logged
+                            Ok(None) => {}
+                            Err(error) => println!("Runtime error: {error:?}"),
+                        }
+                    }
+
+                    secrets_files = services
+                        .iter()
+                        .flat_map(|service| {
+                            Shuttle::local_secrets_files(&run_args, service.crate_directory())
+                        })
+                        .collect();
+                    changed.clear();
+                }
+                // Keep watching so that the next change can fix the build. The changes so far are
+                // kept, since the services they affect have not been restarted yet.
+                Err(error) => println!("{} {error:?}", "Build failed:".red().bold()),
+            }
+
+            println!("Watching for changes...");
+
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(WATCH_INTERVAL) => {},
+                    _ = &mut shutdown => break 'watch,
+                }
+
+                // A service which stopped by itself is started again on the next change
+                let exited: Vec<_> = runtimes
+                    .iter_mut()
+                    .filter_map(|(name, runtime)| match runtime.runtime.try_wait() {
+                        Ok(Some(status)) => Some((name.clone(), status)),
+                        _ => None,
+                    })
+                    .collect();
+                for (name, status) in exited {
+                    runtimes.remove(&name);
+                    println!(
+                        "{} {name} exited with {status}",
+                        "Service stopped:".red().bold()
+                    );
+                }
+
+                // A file can disappear or become unreadable while the project files are listed,
+                // such as during a `git checkout`. Try again on the next tick instead of ending
+                // the session.
+                let project_files = match self.watched_project_files() {
+                    Ok(project_files) => project_files,
+                    Err(error) => {
+                        println!("{} {error:?}", "Listing project files failed:".red().bold());
+                        continue;
+                    }
+                };
+                let current = Shuttle::watched_files(project_files.into_keys(), &secrets_files);
+                let new_changes = Shuttle::changed_files(&files, &current);
+                files = current;
+
+                if !new_changes.is_empty() {
+                    trace!(changed = ?new_changes, "files changed");
+                    changed.extend(new_changes);
+                    break;
+                }
+            }
+        }
+
+        println!("cargo-shuttle received ctrl-c. Killing all the runtimes...");
+        provisioner_server.abort();
+        for runtime in runtimes.into_values() {
+            runtime.stop().await;
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    /// The files local runs can load the secrets of a service from, in order of precedence. Only
+    /// the first one which exists is loaded, so that the `Secrets.toml` shipped on deploy can be
+    /// kept out of local runs entirely.
+    fn local_secrets_files(run_args: &RunArgs, crate_directory: &Path) -> Vec<PathBuf> {
+        match &run_args.secrets {
+            Some(path) => vec![path.clone()],
+            None => LOCAL_SECRETS_FILENAMES
+                .iter()
+                .chain(std::iter::once(&SECRETS_FILENAME))
+                .map(|filename| crate_directory.join(filename))
+                .collect(),
+        }
+    }
+
+    /// Get the modification times of the files to watch for changes. Secrets files are watched
+    /// separately, since they are not part of the project files and may not exist yet.
+    fn watched_files(
+        project_files: impl Iterator<Item = PathBuf>,
+        secrets_files: &[PathBuf],
+    ) -> BTreeMap<PathBuf, Option<SystemTime>> {
+        project_files
+            .chain(secrets_files.iter().cloned())
+            .map(|path| {
+                let modified = modified_time(&path);
+                (path, modified)
+            })
+            .collect()
+    }
+
+    /// The files which were added, modified or removed between two calls of
+    /// [Shuttle::watched_files]
+    fn changed_files(
+        previous: &BTreeMap<PathBuf, Option<SystemTime>>,
+        current: &BTreeMap<PathBuf, Option<SystemTime>>,
+    ) -> Vec<PathBuf> {
+        current
+            .iter()
+            .filter(|(path, modified)| previous.get(*path) != Some(*modified))
+            .map(|(path, _)| path.clone())
+            .chain(
+                previous
+                    .keys()
+                    .filter(|path| !current.contains_key(*path))
+                    .cloned(),
+            )
+            .collect()
+    }
+
+    /// Report what `deploy` would upload: every file with its size and the rule which included
+    /// it, the largest files and the size of the compressed archive
+    fn deploy_dry_run(&self) -> Result<CommandOutcome> {
+        let project_files = self.project_files()?;
+        let working_directory = self.ctx.working_directory();
+
+        let mut files = Vec::with_capacity(project_files.len());
+        for (path, rule) in &project_files {
+            let size = path
+                .metadata()
+                .context(format!("reading metadata of {}", path.display()))?
+                .len();
+            let path = path
+                .strip_prefix(working_directory)
+                .context("strip prefix of path")?
+                .display()
+                .to_string();
+
+            files.push(ArchiveFile {
+                path,
+                size,
+                rule: rule.clone(),
+            });
+        }
+
+        let report = ArchiveReport {
+            total_size: files.iter().map(|file| file.size).sum(),
+            compressed_size: self.pack_archive(&project_files)?.len() as u64,
+            size_limit: CREATE_SERVICE_BODY_LIMIT as u64,
+            files,
+        };
+
+        if self.output.print(&report)? {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        for file in &report.files {
+            println!(
+                "{:>10}  {}  ({})",
+                format_size(file.size),
+                file.path,
+                file.rule.to_string().dim()
+            );
+        }
+
+        let mut largest: Vec<_> = report.files.iter().collect();
+        largest.sort_by(|a, b| b.size.cmp(&a.size));
+        println!();
+        println!("{}", "Largest files:".bold());
+        for file in largest.iter().take(DRY_RUN_LARGEST_FILES) {
+            println!("{:>10}  {}", format_size(file.size), file.path);
+        }
+
+        println!();
+        println!(
+            "{} files, {} uncompressed, {} compressed (the limit is {})",
+            report.files.len(),
+            format_size(report.total_size),
+            format_size(report.compressed_size).bold(),
+            format_size(report.size_limit),
+        );
+        if report.compressed_size > report.size_limit {
+            println!(
+                "{}",
+                "The archive is over the limit and would be rejected. \
+                Exclude files with a `.shuttleignore` file, which uses the same syntax as `.gitignore`."
+                    .red()
+            );
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
     async fn deploy(&mut self, args: DeployArgs) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         let working_directory = self.ctx.working_directory();
@@ -1432,7 +1853,7 @@ impl Shuttle {
             bail!(
                 r#"The project is too large - the limit is {} MB. \
                 Your project archive is {:.1} MB. \
-                Run with `RUST_LOG="cargo_shuttle=debug"` to see which files are being packed."#,
+                Run `cargo shuttle deploy --dry-run` to see which files are being packed."#,
                 CREATE_SERVICE_BODY_LIMIT / 1_000_000,
                 deployment_req.data.len() as f32 / 1_000_000f32,
             );
@@ -1462,7 +1883,12 @@ impl Shuttle {
                     let log_item: shuttle_common::LogItem =
                         serde_json::from_str(&line).expect("to parse log line");
 
-                    println!("{log_item}");
+                    // Only the deployment is printed to stdout when a structured output format is used
+                    if self.output == OutputMode::Table {
+                        println!("{log_item}");
+                    } else {
+                        eprintln!("{log_item}");
+                    }
 
                     // Detect versions of deployer and runtime, and print warnings of outdated.
                     if !deployer_version_checked
@@ -1526,6 +1952,15 @@ impl Shuttle {
                         .iter()
                         .any(|m| log_item.line.contains(m))
                     {
+                        if self.output != OutputMode::Table {
+                            let deployment = client
+                                .get_deployment_details(self.ctx.project_name(), &deployment.id)
+                                .await?;
+                            self.output.print(&deployment)?;
+
+                            return Ok(CommandOutcome::DeploymentFailure);
+                        }
+
                         println!();
                         println!("{}", "Deployment crashed".red());
                         println!();
@@ -1575,6 +2010,16 @@ impl Shuttle {
                 )
             })?;
 
+        if self.output.print(&deployment)? {
+            return Ok(
+                if deployment.state == shuttle_common::deployment::State::Running {
+                    CommandOutcome::Ok
+                } else {
+                    CommandOutcome::DeploymentFailure
+                },
+            );
+        }
+
         // A deployment will only exist if there is currently one in the running state
         if deployment.state != shuttle_common::deployment::State::Running {
             println!("{}", "Deployment has not entered the running state".red());
@@ -1629,7 +2074,7 @@ impl Shuttle {
         let config = &project::Config { idle_minutes };
 
         let p = self.ctx.project_name();
-        wait_with_spinner(|i, pb| async move {
+        let project = wait_with_spinner(|i, pb| async move {
             let project = if i == 0 {
                 client.create_project(p, config).await?
             } else {
@@ -1646,9 +2091,7 @@ impl Shuttle {
             .contains(&project.state);
 
             if done {
-                Ok(Some(move || {
-                    println!("{project}");
-                }))
+                Ok(Some(move || project))
             } else {
                 Ok(None)
             }
@@ -1663,6 +2106,12 @@ impl Shuttle {
             )
         })?;
 
+        if self.output.print(&project)? {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        println!("{project}");
+
         if idle_minutes > 0 {
             let idle_msg = format!(
                 "Your project will sleep if it is idle for {} minutes.",
@@ -1679,9 +2128,15 @@ impl Shuttle {
     }
 
     async fn project_recreate(&self, idle_minutes: u64) -> Result<CommandOutcome> {
-        self.project_stop()
-            .await
-            .map_err(suggestions::project::project_restart_failure)?;
+        if self.output == OutputMode::Table {
+            self.project_stop().await
+        } else {
+            // Only the restarted project is printed
+            self.stop_project_environment()
+                .await
+                .map(|_| CommandOutcome::Ok)
+        }
+        .map_err(suggestions::project::project_restart_failure)?;
         self.project_create(idle_minutes)
             .await
             .map_err(suggestions::project::project_restart_failure)?;
@@ -1692,7 +2147,9 @@ impl Shuttle {
     async fn projects_list(&self, page: u32, limit: u32, raw: bool) -> Result<CommandOutcome> {
         let client = self.client.as_ref().unwrap();
         if limit == 0 {
-            println!();
+            if !self.output.print(&Vec::<project::Response>::new())? {
+                println!();
+            }
             return Ok(CommandOutcome::Ok);
         }
 
@@ -1704,9 +2161,12 @@ impl Shuttle {
                 "getting the projects list fails repeteadly",
             )
         })?;
-        let projects_table = project::get_projects_table(&projects, page, raw);
 
-        println!("{projects_table}");
+        if !self.output.print(&projects)? {
+            let projects_table = project::get_projects_table(&projects, page, raw);
+
+            println!("{projects_table}");
+        }
 
         Ok(CommandOutcome::Ok)
     }
@@ -1715,7 +2175,7 @@ impl Shuttle {
         let client = self.client.as_ref().unwrap();
         if follow {
             let p = self.ctx.project_name();
-            wait_with_spinner(|_, pb| async move {
+            let project = wait_with_spinner(|_, pb| async move {
                 let project = client.get_project(p).await?;
                 pb.set_message(format!("{project}"));
 
@@ -1729,14 +2189,16 @@ impl Shuttle {
                 .contains(&project.state);
 
                 if done {
-                    Ok(Some(move || {
-                        println!("{project}");
-                    }))
+                    Ok(Some(move || project))
                 } else {
                     Ok(None)
                 }
             })
             .await?;
+
+            if !self.output.print(&project)? {
+                println!("{project}");
+            }
         } else {
             let project = client
                 .get_project(self.ctx.project_name())
@@ -1749,11 +2211,20 @@ impl Shuttle {
                         "getting project status failed repeteadly",
                     )
                 })?;
+
+            if self.output.print(&project)? {
+                return Ok(CommandOutcome::Ok);
+            }
+
             println!(
-                "{project}\nIdle minutes: {}",
+                "{project}\nIdle minutes: {}\nLimits: {}",
                 project
                     .idle_minutes
                     .map(|i| i.to_string())
+                    .unwrap_or("<unknown>".to_owned()),
+                project
+                    .container_limits
+                    .map(|l| l.to_string())
                     .unwrap_or("<unknown>".to_owned())
             );
         }
@@ -1762,6 +2233,19 @@ impl Shuttle {
     }
 
     async fn project_stop(&self) -> Result<CommandOutcome> {
+        let project = self.stop_project_environment().await?;
+
+        if !self.output.print(&project)? {
+            println!("{project}");
+            println!(
+                "Run `cargo shuttle project start` to recreate project environment on Shuttle."
+            );
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn stop_project_environment(&self) -> Result<project::Response> {
         let client = self.client.as_ref().unwrap();
 
         let p = self.ctx.project_name();
@@ -1782,9 +2266,7 @@ impl Shuttle {
             .contains(&project.state);
 
             if done {
-                Ok(Some(move || {
-                    println!("{project}");
-                }))
+                Ok(Some(move || project))
             } else {
                 Ok(None)
             }
@@ -1797,10 +2279,7 @@ impl Shuttle {
                 true,
                 "stopping the project or getting project status fails repeteadly",
             )
-        })?;
-        println!("Run `cargo shuttle project start` to recreate project environment on Shuttle.");
-
-        Ok(CommandOutcome::Ok)
+        })
     }
 
     async fn project_delete(&self) -> Result<CommandOutcome> {
@@ -1849,77 +2328,306 @@ impl Shuttle {
         Ok(CommandOutcome::Ok)
     }
 
-    fn make_archive(&self) -> Result<Vec<u8>> {
-        let include_patterns = self.ctx.assets();
-        let encoder = GzEncoder::new(Vec::new(), Compression::new(3));
-        let mut tar = Builder::new(encoder);
-
-        let working_directory = self.ctx.working_directory();
-
-        //
-        // Mixing include and exclude overrides messes up the .ignore and .gitignore etc,
-        // therefore these "ignore" walk and the "include" walk are separate.
-        //
-        let mut entries = Vec::new();
+    async fn project_usage(
+        &self,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let usage = client
+            .get_project_usage(self.ctx.project_name(), from, to)
+            .await
+            .map_err(|err| {
+                suggestions::project::project_request_failure(
+                    err,
+                    "Getting project usage failed",
+                    false,
+                    "getting project usage failed repeteadly",
+                )
+            })?;
 
-        // Default excludes
-        let ignore_overrides = OverrideBuilder::new(working_directory)
-            .add("!.git/")
-            .context("adding override `!.git/`")?
-            .add("!target/")
-            .context("adding override `!target/`")?
-            // these should always be ignored when unpacked in deployment, so ignore them here as well
-            .add(&format!("!{EXECUTABLE_DIRNAME}/"))
-            .context(format!("adding override `!{EXECUTABLE_DIRNAME}/`"))?
-            .add(&format!("!{STORAGE_DIRNAME}/"))
-            .context(format!("adding override `!{STORAGE_DIRNAME}/`"))?
-            .build()
-            .context("building archive override rules")?;
-        for r in WalkBuilder::new(working_directory)
-            .hidden(false)
-            .overrides(ignore_overrides)
-            .build()
-        {
-            entries.push(r.context("list dir entry")?.into_path())
+        if !self.output.print(&usage)? {
+            println!("{usage}");
         }
 
-        let mut globs = GlobSetBuilder::new();
+        Ok(CommandOutcome::Ok)
+    }
 
-        // Always include secrets
-        globs.add(Glob::new("**/Secrets.toml").unwrap());
+    async fn project_transfer(&self, team_name: Option<String>) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let project = client
+            .transfer_project(self.ctx.project_name(), team_name)
+            .await
+            .map_err(|err| {
+                suggestions::project::project_request_failure(
+                    err,
+                    "Transferring project failed",
+                    false,
+                    "transferring the project fails repeatedly",
+                )
+            })?;
 
-        // User provided includes
-        if let Some(rules) = include_patterns {
-            for r in rules {
-                globs.add(Glob::new(r.as_str()).context(format!("parsing glob pattern {:?}", r))?);
+        if !self.output.print(&project)? {
+            match project.team {
+                Some(team) => println!(
+                    "Project {} now belongs to team {}",
+                    project.name.bold(),
+                    team.bold()
+                ),
+                None => println!(
+                    "Project {} now belongs to the account which created it",
+                    project.name.bold()
+                ),
             }
         }
 
-        // Find the files
-        let globs = globs.build().context("glob glob")?;
-        for entry in walkdir::WalkDir::new(working_directory) {
-            let path = entry.context("list dir")?.into_path();
-            if globs.is_match(
-                path.strip_prefix(working_directory)
-                    .context("strip prefix of path")?,
-            ) {
-                entries.push(path);
-            }
-        }
+        Ok(CommandOutcome::Ok)
+    }
 
-        let mut archive_files = BTreeMap::new();
-        for path in entries {
-            // It's not possible to add a directory to an archive
-            // and symlinks == chaos
-            if path.is_dir() || path.is_symlink() {
-                trace!("Skipping {:?}", path);
-                continue;
+    async fn teams_list(&self) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let teams = client.get_teams_list().await?;
+
+        if !self.output.print(&teams)? {
+            println!("{}", team::get_teams_table(&teams));
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn team_status(&self, team_name: &str) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let team = client.get_team(team_name).await?;
+
+        if !self.output.print(&team)? {
+            println!("{team}");
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn team_create(&self, team_name: &str) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let team = client.create_team(team_name).await?;
+
+        if !self.output.print(&team)? {
+            println!("{team}");
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn team_add_member(
+        &self,
+        team_name: &str,
+        account_name: &str,
+        role: team::TeamRole,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let team = client
+            .set_team_member(team_name, account_name, role)
+            .await?;
+
+        if !self.output.print(&team)? {
+            println!("{team}");
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn team_remove_member(
+        &self,
+        team_name: &str,
+        account_name: &str,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let message = client.remove_team_member(team_name, account_name).await?;
+
+        if !self.output.print(&message)? {
+            println!("{message}");
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn token_create(
+        &self,
+        name: String,
+        scopes: Vec<String>,
+        project: Option<String>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let token = client
+            .create_token(user::TokenRequest {
+                name,
+                scopes,
+                project,
+                expires_at,
+            })
+            .await?;
+
+        if self.output.print(&token)? {
+            return Ok(CommandOutcome::Ok);
+        }
+
+        println!(
+            "Created API token {} with scopes: {}",
+            token.name.as_str().bold(),
+            token.scopes.join(", ")
+        );
+        println!(
+            "{}",
+            "Make sure to copy it now, as it will not be shown again:"
+                .yellow()
+                .bold()
+        );
+        println!("{}", token.key.unwrap_or_default());
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn tokens_list(&self) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let tokens = client.get_tokens_list().await?;
+
+        if !self.output.print(&tokens)? {
+            println!("{}", user::get_tokens_table(&tokens));
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn token_revoke(&self, name: &str) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+        let message = client.revoke_token(name).await?;
+
+        if !self.output.print(&message)? {
+            println!("{message}");
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn audit(&self, limit: u32) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+
+        // Project and deployment actions are recorded by the gateway, while token and key
+        // actions are recorded by the auth service
+        let mut events = client.get_audit_log(limit).await?;
+        events.extend(client.get_user_audit_log(limit).await?);
+        events.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        events.truncate(limit as usize);
+
+        if !self.output.print(&events)? {
+            println!("{}", audit::get_audit_table(&events));
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    async fn account_export(&self, output_file: Option<PathBuf>) -> Result<CommandOutcome> {
+        let client = self.client.as_ref().unwrap();
+
+        let bundle = client.get_account_export().await?;
+        let json = serde_json::to_string_pretty(&bundle)?;
+
+        match output_file {
+            Some(path) => {
+                std::fs::write(&path, json)
+                    .with_context(|| format!("failed to write export to {}", path.display()))?;
+                println!(
+                    "Exported {} projects to {}",
+                    bundle.projects.len(),
+                    path.display()
+                );
+            }
+            None => {
+                if !self.output.print(&bundle)? {
+                    println!("{json}");
+                }
             }
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    fn profile_add(
+        &mut self,
+        name: &str,
+        api_url: Option<String>,
+        api_key: Option<String>,
+    ) -> Result<CommandOutcome> {
+        let api_key = api_key.as_deref().map(ApiKey::parse).transpose()?;
+
+        self.ctx.add_profile(name, api_url, api_key)?;
+
+        println!("Saved profile {}", name.bold());
+        println!(
+            "Run `cargo shuttle profile use {name}` to use it, or pass `--profile {name}` to a command."
+        );
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    fn profile_use(&mut self, name: &str) -> Result<CommandOutcome> {
+        self.ctx.use_profile(name)?;
+
+        println!("Using profile {}", name.bold());
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    fn profiles_list(&self) -> Result<CommandOutcome> {
+        let profiles = self.ctx.profiles();
+
+        if !self.output.print(&profiles)? {
+            for profile in profiles {
+                let marker = if profile.active { "*" } else { " " };
+                let login = if profile.logged_in {
+                    ""
+                } else {
+                    " (not logged in)"
+                };
+
+                println!(
+                    "{marker} {} {}{login}",
+                    profile.name.bold(),
+                    profile.api_url
+                );
+            }
+        }
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    fn profile_remove(&mut self, name: &str) -> Result<CommandOutcome> {
+        self.ctx.remove_profile(name)?;
+
+        println!("Removed profile {}", name.bold());
+
+        Ok(CommandOutcome::Ok)
+    }
+
+    fn make_archive(&self) -> Result<Vec<u8>> {
+        self.pack_archive(&self.project_files()?)
+    }
+
+    fn pack_archive(&self, project_files: &BTreeMap<PathBuf, IncludeRule>) -> Result<Vec<u8>> {
+        let encoder = GzEncoder::new(Vec::new(), Compression::new(3));
+        let mut tar = Builder::new(encoder);
+
+        let working_directory = self.ctx.working_directory();
 
-            let name = path
+        let mut archive_files = BTreeMap::new();
+        for path in project_files.keys() {
+            let mut name = path
                 .strip_prefix(working_directory.parent().context("get parent dir")?)
                 .context("strip prefix of path")?
                 .to_owned();
+            if name.file_name() == Some(OsStr::new(PRODUCTION_SECRETS_FILENAME)) {
+                name.set_file_name(SECRETS_FILENAME);
+            }
 
             archive_files.insert(path, name);
         }
@@ -1941,6 +2649,282 @@ impl Shuttle {
 
         Ok(bytes)
     }
+
+    /// Find the files which make up the project, with the rule which included each of them: the
+    /// ones not ignored by `.gitignore`, `.shuttleignore` and the like, `Secrets.toml` and the
+    /// ones matching the `assets` globs in Shuttle.toml. A `Secrets.production.toml` is shipped
+    /// instead of the `Secrets.toml` next to it, and other secrets files are never shipped.
+    fn project_files(&self) -> Result<BTreeMap<PathBuf, IncludeRule>> {
+        self.find_project_files(true)
+    }
+
+    /// Like [Shuttle::project_files], but without looking for assets in `.git/`, `target/` and
+    /// the other directories which are always ignored. Those hold many files that change on
+    /// every build or run, so `run --watch` would be slow to walk them and restart for nothing.
+    fn watched_project_files(&self) -> Result<BTreeMap<PathBuf, IncludeRule>> {
+        self.find_project_files(false)
+    }
+
+    fn find_project_files(
+        &self,
+        assets_in_ignored_dirs: bool,
+    ) -> Result<BTreeMap<PathBuf, IncludeRule>> {
+        let include_patterns = self.ctx.assets();
+        let working_directory = self.ctx.working_directory();
+
+        //
+        // Mixing include and exclude overrides messes up the .ignore and .gitignore etc,
+        // therefore these "ignore" walk and the "include" walk are separate.
+        //
+        let mut entries = BTreeMap::new();
+
+        // Default excludes
+        let ignore_overrides = OverrideBuilder::new(working_directory)
+            .add("!.git/")
+            .context("adding override `!.git/`")?
+            .add("!target/")
+            .context("adding override `!target/`")?
+            // these should always be ignored when unpacked in deployment, so ignore them here as well
+            .add(&format!("!{EXECUTABLE_DIRNAME}/"))
+            .context(format!("adding override `!{EXECUTABLE_DIRNAME}/`"))?
+            .add(&format!("!{STORAGE_DIRNAME}/"))
+            .context(format!("adding override `!{STORAGE_DIRNAME}/`"))?
+            .build()
+            .context("building archive override rules")?;
+        for r in WalkBuilder::new(working_directory)
+            .hidden(false)
+            // Lets files tracked by git be left out of the archive
+            .add_custom_ignore_filename(SHUTTLE_IGNORE_FILENAME)
+            .overrides(ignore_overrides)
+            .build()
+        {
+            entries.insert(
+                r.context("list dir entry")?.into_path(),
+                IncludeRule::NotIgnored,
+            );
+        }
+
+        let mut globs = GlobSetBuilder::new();
+        // The rule for each glob, in the order they are added
+        let mut glob_rules = Vec::new();
+
+        // Always include secrets
+        globs.add(Glob::new(&format!("**/{SECRETS_FILENAME}")).unwrap());
+        glob_rules.push(IncludeRule::Secrets);
+        globs.add(Glob::new(&format!("**/{PRODUCTION_SECRETS_FILENAME}")).unwrap());
+        glob_rules.push(IncludeRule::Secrets);
+
+        // User provided includes
+        if let Some(rules) = include_patterns {
+            for r in rules {
+                globs.add(Glob::new(r.as_str()).context(format!("parsing glob pattern {:?}", r))?);
+                glob_rules.push(IncludeRule::Asset(r.clone()));
+            }
+        }
+
+        // Find the files
+        let globs = globs.build().context("glob glob")?;
+        let ignored_dirs = [".git", "target", EXECUTABLE_DIRNAME, STORAGE_DIRNAME];
+        for entry in walkdir::WalkDir::new(working_directory)
+            .into_iter()
+            .filter_entry(|entry| {
+                assets_in_ignored_dirs
+                    || entry.depth() == 0
+                    || !entry.file_type().is_dir()
+                    || !ignored_dirs.iter().any(|dir| entry.file_name() == *dir)
+            })
+        {
+            let path = entry.context("list dir")?.into_path();
+            let matches = globs.matches(
+                path.strip_prefix(working_directory)
+                    .context("strip prefix of path")?,
+            );
+            if let Some(index) = matches.first() {
+                entries
+                    .entry(path)
+                    .or_insert_with(|| glob_rules[*index].clone());
+            }
+        }
+
+        // It's not possible to add a directory to an archive
+        // and symlinks == chaos
+        // Secrets for other environments and `.env` files should never be shipped, even when asked
+        // for. A `Secrets.toml` is left out when its production version takes its place.
+        let production_secrets: Vec<_> = entries
+            .keys()
+            .filter(|path| path.file_name() == Some(OsStr::new(PRODUCTION_SECRETS_FILENAME)))
+            .map(|path| path.with_file_name(SECRETS_FILENAME))
+            .collect();
+        entries.retain(|path, _| {
+            let skip = path.is_dir()
+                || path.is_symlink()
+                || production_secrets.contains(path)
+                || path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| {
+                        is_dotenv(name)
+                            || (secrets_environment(name).is_some()
+                                && name != PRODUCTION_SECRETS_FILENAME)
+                    });
+            if skip {
+                trace!("Skipping {:?}", path);
+            }
+            !skip
+        });
+
+        Ok(entries)
+    }
+}
+
+/// Why a file is part of the project archive
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "rule", content = "pattern", rename_all = "snake_case")]
+enum IncludeRule {
+    /// Not ignored by `.gitignore`, `.ignore` or `.shuttleignore`
+    NotIgnored,
+    /// `Secrets.toml` and `Secrets.production.toml` files are always included
+    Secrets,
+    /// Matches a glob in the `assets` of Shuttle.toml
+    Asset(String),
+}
+
+impl std::fmt::Display for IncludeRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotIgnored => write!(f, "not ignored"),
+            Self::Secrets => write!(f, "secrets"),
+            Self::Asset(pattern) => write!(f, "asset `{pattern}`"),
+        }
+    }
+}
+
+/// The report of `cargo shuttle deploy --dry-run`
+#[derive(Serialize)]
+struct ArchiveReport {
+    files: Vec<ArchiveFile>,
+    /// Total size of the files, in bytes
+    total_size: u64,
+    /// Size of the archive which would be uploaded, in bytes
+    compressed_size: u64,
+    /// Size the archive must not exceed, in bytes
+    size_limit: u64,
+}
+
+#[derive(Serialize)]
+struct ArchiveFile {
+    path: String,
+    size: u64,
+    rule: IncludeRule,
+}
+
+/// Read a secrets file, as `KEY=value` lines when it is named like `.env` and as TOML otherwise
+fn read_secrets(path: &Path) -> Result<HashMap<String, String>> {
+    let contents = read_to_string(path)?;
+
+    let is_dotenv = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(is_dotenv);
+
+    if is_dotenv {
+        parse_dotenv(&contents)
+    } else {
+        Ok(contents.parse::<toml::Value>()?.try_into()?)
+    }
+}
+
+/// Whether a file is named like a `.env` file
+fn is_dotenv(name: &str) -> bool {
+    name == ".env" || name.starts_with(".env.") || name.ends_with(".env")
+}
+
+/// The environment of a secrets file named like `Secrets.<env>.toml`
+fn secrets_environment(name: &str) -> Option<&str> {
+    name.strip_prefix("Secrets.")?
+        .strip_suffix(".toml")
+        .filter(|env| !env.is_empty())
+}
+
+/// Parse the `KEY=value` lines of a `.env` file. Values can be quoted, lines can start with
+/// `export`, and comments start with `#`.
+fn parse_dotenv(contents: &str) -> Result<HashMap<String, String>> {
+    let mut secrets = HashMap::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {} is not a `KEY=value` pair", index + 1);
+        };
+
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("line {} has an empty key", index + 1);
+        }
+
+        let value = value.trim();
+        let value = match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let Some((quoted, _)) = value[1..].split_once(quote) else {
+                    bail!("line {} has an unterminated quote", index + 1);
+                };
+
+                if quote == '"' {
+                    quoted.replace("\\n", "\n")
+                } else {
+                    quoted.to_string()
+                }
+            }
+            _ => match value.split_once(" #") {
+                Some((value, _comment)) => value.trim_end().to_string(),
+                None => value.to_string(),
+            },
+        };
+
+        secrets.insert(key.to_string(), value);
+    }
+
+    Ok(secrets)
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        0..=999 => format!("{bytes} B"),
+        1_000..=999_999 => format!("{:.1} kB", bytes as f64 / 1_000f64),
+        _ => format!("{:.1} MB", bytes as f64 / 1_000_000f64),
+    }
+}
+
+/// A service runtime started by `cargo shuttle run --watch`
+struct WatchedRuntime {
+    runtime: Child,
+    client: RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
+    /// When the executable the runtime was started with was last modified
+    executable_modified: Option<SystemTime>,
+}
+
+impl WatchedRuntime {
+    async fn stop(mut self) {
+        Shuttle::stop_runtime(&mut self.runtime, &mut self.client)
+            .await
+            .unwrap_or_else(|err| {
+                trace!(status = ?err, "stopping the runtime errored out");
+            });
+
+        // Make sure the port of the service is free before it is started again
+        let _ = self.runtime.kill().await;
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
 }
 
 fn is_dirty(repo: &Repository) -> Result<()> {
@@ -2096,10 +3080,13 @@ mod tests {
     use tar::Archive;
 
     use crate::args::ProjectArgs;
-    use crate::Shuttle;
+    use crate::{is_dotenv, parse_dotenv, secrets_environment, IncludeRule, Shuttle};
+    use std::collections::{BTreeMap, HashMap};
     use std::fs::{self, canonicalize};
+    use std::io::Read;
     use std::path::PathBuf;
     use std::str::FromStr;
+    use std::time::{Duration, SystemTime};
 
     pub fn path_from_workspace_root(path: &str) -> PathBuf {
         let path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap())
@@ -2164,8 +3151,10 @@ mod tests {
             vec![
                 ".gitignore",
                 ".ignore",
+                ".shuttleignore",
                 "Cargo.toml",
                 "Secrets.toml", // always included by default
+                // Secrets.dev.toml is never included, even when it is in Shuttle.toml
                 "Secrets.toml.example",
                 "Shuttle.toml",
                 "asset1", // normal file
@@ -2176,11 +3165,226 @@ mod tests {
                 "dist/dist1",            // .gitignore'd, but included in Shuttle.toml
                 "nested/static/nested1", // normal file
                 // nested/static/nestedignore is .gitignore'd
+                // nested/static/nestedshuttleignore is .shuttleignore'd
                 "src/main.rs",
             ]
         );
     }
 
+    #[test]
+    fn make_archive_ships_production_secrets() {
+        let working_directory = canonicalize(path_from_workspace_root(
+            "cargo-shuttle/tests/resources/production-secrets",
+        ))
+        .unwrap();
+
+        fs::write(working_directory.join("Secrets.toml"), "KEY = 'value'").unwrap();
+        fs::write(
+            working_directory.join("Secrets.production.toml"),
+            "KEY = 'production'",
+        )
+        .unwrap();
+        fs::write(
+            working_directory.join("Secrets.staging.toml"),
+            "KEY = 'staging'",
+        )
+        .unwrap();
+        fs::write(working_directory.join(".env"), "KEY=dotenv").unwrap();
+
+        let project_args = ProjectArgs {
+            working_directory,
+            name: Some(ProjectName::from_str("production-secrets-test").unwrap()),
+        };
+        let mut shuttle = Shuttle::new().unwrap();
+        shuttle.load_project(&project_args).unwrap();
+
+        let archive = shuttle.make_archive().unwrap();
+        let mut archive = Archive::new(GzDecoder::new(&archive[..]));
+
+        let mut entries = HashMap::new();
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let path = entry
+                .path()
+                .unwrap()
+                .components()
+                .skip(1)
+                .collect::<PathBuf>()
+                .display()
+                .to_string();
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+
+            entries.insert(path, contents);
+        }
+
+        // Other environments and `.env` files are left out, even when they are in Shuttle.toml
+        let mut paths: Vec<_> = entries.keys().map(String::as_str).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                ".gitignore",
+                "Cargo.toml",
+                "Secrets.toml",
+                "Shuttle.toml",
+                "src/main.rs"
+            ]
+        );
+        assert_eq!(entries["Secrets.toml"], "KEY = 'production'");
+    }
+
+    #[test]
+    fn secrets_file_names() {
+        assert_eq!(
+            secrets_environment("Secrets.production.toml"),
+            Some("production")
+        );
+        assert_eq!(secrets_environment("Secrets.dev.toml"), Some("dev"));
+        assert_eq!(secrets_environment("Secrets.toml"), None);
+        assert_eq!(secrets_environment("Secrets.toml.example"), None);
+
+        assert!(is_dotenv(".env"));
+        assert!(is_dotenv(".env.local"));
+        assert!(is_dotenv("production.env"));
+        assert!(!is_dotenv("environment.rs"));
+    }
+
+    #[test]
+    fn parse_dotenv_secrets() {
+        let secrets = parse_dotenv(
+            r#"
+            # database
+            DATABASE_URL=postgres://localhost:5432 # local only
+            export API_KEY = 'se#cret'
+            GREETING="hello\nworld"
+            EMPTY=
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            secrets,
+            HashMap::from([
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgres://localhost:5432".to_string()
+                ),
+                ("API_KEY".to_string(), "se#cret".to_string()),
+                ("GREETING".to_string(), "hello\nworld".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ])
+        );
+
+        assert!(parse_dotenv("NOT_A_PAIR").is_err());
+        assert!(parse_dotenv("KEY='unterminated").is_err());
+    }
+
+    #[test]
+    fn project_files_have_their_include_rule() {
+        let working_directory = canonicalize(path_from_workspace_root(
+            "cargo-shuttle/tests/resources/archiving",
+        ))
+        .unwrap();
+
+        fs::write(working_directory.join("Secrets.toml"), "KEY = 'value'").unwrap();
+        fs::write(working_directory.join("asset2"), "").unwrap();
+
+        let project_args = ProjectArgs {
+            working_directory: working_directory.clone(),
+            name: Some(ProjectName::from_str("archiving-test").unwrap()),
+        };
+        let mut shuttle = Shuttle::new().unwrap();
+        shuttle.load_project(&project_args).unwrap();
+
+        let files = shuttle.project_files().unwrap();
+
+        assert_eq!(
+            files.get(&working_directory.join("asset1")),
+            Some(&IncludeRule::NotIgnored)
+        );
+        assert_eq!(
+            files.get(&working_directory.join("Secrets.toml")),
+            Some(&IncludeRule::Secrets)
+        );
+        assert_eq!(
+            files.get(&working_directory.join("asset2")),
+            Some(&IncludeRule::Asset("asset2".to_string()))
+        );
+        assert_eq!(
+            files.get(&working_directory.join("nested/static/nestedshuttleignore")),
+            None
+        );
+    }
+
+    #[test]
+    fn watched_project_files_skip_build_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let working_directory = canonicalize(dir.path()).unwrap();
+
+        fs::create_dir_all(working_directory.join("src")).unwrap();
+        fs::write(working_directory.join("src/Secrets.toml"), "KEY = 'value'").unwrap();
+        fs::create_dir_all(working_directory.join("target/debug")).unwrap();
+        fs::write(
+            working_directory.join("target/debug/Secrets.toml"),
+            "KEY = 'value'",
+        )
+        .unwrap();
+
+        let project_args = ProjectArgs {
+            working_directory: working_directory.clone(),
+            name: Some(ProjectName::from_str("watching-test").unwrap()),
+        };
+        let mut shuttle = Shuttle::new().unwrap();
+        shuttle.load_project(&project_args).unwrap();
+
+        let secrets = working_directory.join("src/Secrets.toml");
+        let built_secrets = working_directory.join("target/debug/Secrets.toml");
+
+        let files = shuttle.project_files().unwrap();
+        assert!(files.contains_key(&secrets));
+        assert!(files.contains_key(&built_secrets));
+
+        let files = shuttle.watched_project_files().unwrap();
+        assert!(files.contains_key(&secrets));
+        assert!(!files.contains_key(&built_secrets));
+    }
+
+    #[test]
+    fn changed_files_are_detected() {
+        let earlier = SystemTime::UNIX_EPOCH;
+        let later = earlier + Duration::from_secs(1);
+        let path = |name: &str| PathBuf::from("/project").join(name);
+
+        let previous = BTreeMap::from([
+            (path("Cargo.toml"), Some(earlier)),
+            (path("src/main.rs"), Some(earlier)),
+            (path("src/removed.rs"), Some(earlier)),
+            (path("Secrets.dev.toml"), None),
+        ]);
+        let current = BTreeMap::from([
+            (path("Cargo.toml"), Some(earlier)),
+            (path("src/main.rs"), Some(later)),
+            (path("src/added.rs"), Some(later)),
+            (path("Secrets.dev.toml"), Some(later)),
+        ]);
+
+        assert_eq!(
+            Shuttle::changed_files(&previous, &current),
+            vec![
+                path("Secrets.dev.toml"),
+                path("src/added.rs"),
+                path("src/main.rs"),
+                path("src/removed.rs"),
+            ]
+        );
+        assert!(Shuttle::changed_files(&current, &current).is_empty());
+
+        // Secrets files which do not exist yet are watched too
+        let watched = Shuttle::watched_files(std::iter::empty(), &[path("Secrets.dev.toml")]);
+        assert_eq!(watched, BTreeMap::from([(path("Secrets.dev.toml"), None)]));
+    }
+
     #[test]
     fn load_project_returns_proper_working_directory_in_project_args() {
         let project_args = ProjectArgs {
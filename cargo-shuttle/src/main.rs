@@ -1,11 +1,18 @@
 use anyhow::Result;
-use cargo_shuttle::{CommandOutcome, Shuttle};
+use cargo_shuttle::{exit_code, CommandOutcome, Shuttle};
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
 
-    let outcome = Shuttle::new()?.parse_args_and_run().await?;
+    let outcome = match Shuttle::new()?.parse_args_and_run().await {
+        Ok(outcome) => outcome,
+        Err(error) => {
+            // The error was already printed in the chosen output format. Errors from the API exit
+            // with a code per error kind, so scripts can tell them apart.
+            std::process::exit(exit_code(&error));
+        }
+    };
 
     if outcome == CommandOutcome::DeploymentFailure {
         // Deployment failure results in a shell error exit code being returned (this allows
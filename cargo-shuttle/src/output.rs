@@ -0,0 +1,242 @@
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::{json, Value};
+use shuttle_common::{
+    models::{
+        error::{ApiError, ErrorKindDiscriminants},
+        secret,
+    },
+    resource,
+    secrets::Secret,
+};
+
+use crate::args::OutputMode;
+
+/// Exit code for errors which did not come from the API, or came without an error kind
+const GENERIC_EXIT_CODE: i32 = 1;
+
+impl OutputMode {
+    /// Print a response if a structured format was chosen. Returns `false` when the caller
+    /// should print its human readable output instead.
+    pub fn print<T: Serialize>(self, value: &T) -> Result<bool> {
+        match self {
+            Self::Table => return Ok(false),
+            Self::Json => println!("{}", serde_json::to_string_pretty(value)?),
+            Self::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        }
+
+        Ok(true)
+    }
+
+    /// Like [Self::print], but for one item of a stream, such as a log line
+    pub fn print_item<T: Serialize>(self, value: &T) -> Result<bool> {
+        match self {
+            Self::Table => return Ok(false),
+            Self::Json => println!("{}", serde_json::to_string(value)?),
+            Self::Yaml => print!("---\n{}", serde_yaml::to_string(value)?),
+        }
+
+        Ok(true)
+    }
+
+    /// Print a failed command's error, as an [ApiError] if a structured format was chosen
+    pub fn print_error(self, error: &anyhow::Error) {
+        if self == Self::Table {
+            eprintln!("Error: {error:?}");
+            return;
+        }
+
+        let error = match find_api_error(error) {
+            Some(api_error) => ApiError {
+                message: api_error.message.clone(),
+                status_code: api_error.status_code,
+                kind: api_error.kind.clone(),
+            },
+            None => ApiError {
+                message: format!("{error:#}"),
+                status_code: reqwest::StatusCode::INTERNAL_SERVER_ERROR.as_u16(),
+                kind: None,
+            },
+        };
+
+        // Nothing useful can be done if the error itself fails to serialize
+        let _ = self.print(&error);
+    }
+}
+
+fn find_api_error(error: &anyhow::Error) -> Option<&ApiError> {
+    error
+        .chain()
+        .find_map(|error| error.downcast_ref::<ApiError>())
+}
+
+/// The exit code for a failed command. Each [shuttle_common::models::error::ErrorKind] has its
+/// own code, which must not change once released so that scripts can rely on them. The match is
+/// exhaustive, so a new kind does not build until it is given the next free code.
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    let Some(kind) = find_api_error(error).and_then(ApiError::error_kind) else {
+        return GENERIC_EXIT_CODE;
+    };
+
+    match kind {
+        ErrorKindDiscriminants::KeyMissing => 10,
+        ErrorKindDiscriminants::BadHost => 11,
+        ErrorKindDiscriminants::KeyMalformed => 12,
+        ErrorKindDiscriminants::Unauthorized => 13,
+        ErrorKindDiscriminants::Forbidden => 14,
+        ErrorKindDiscriminants::UserNotFound => 15,
+        ErrorKindDiscriminants::UserAlreadyExists => 16,
+        ErrorKindDiscriminants::ProjectNotFound => 17,
+        ErrorKindDiscriminants::InvalidProjectName => 18,
+        ErrorKindDiscriminants::ProjectAlreadyExists => 19,
+        ErrorKindDiscriminants::OwnProjectAlreadyExists => 20,
+        ErrorKindDiscriminants::ProjectNotReady => 21,
+        ErrorKindDiscriminants::ProjectUnavailable => 22,
+        ErrorKindDiscriminants::ProjectHasResources => 23,
+        ErrorKindDiscriminants::ProjectHasRunningDeployment => 24,
+        ErrorKindDiscriminants::QuotaExceeded => 25,
+        ErrorKindDiscriminants::CustomDomainNotFound => 26,
+        ErrorKindDiscriminants::InvalidCustomDomain => 27,
+        ErrorKindDiscriminants::TeamNotFound => 28,
+        ErrorKindDiscriminants::InvalidTeamName => 29,
+        ErrorKindDiscriminants::TeamAlreadyExists => 30,
+        ErrorKindDiscriminants::CustomDomainAlreadyExists => 31,
+        ErrorKindDiscriminants::InvalidOperation => 32,
+        ErrorKindDiscriminants::Internal => 33,
+        ErrorKindDiscriminants::NotReady => 34,
+        ErrorKindDiscriminants::ServiceUnavailable => 35,
+        ErrorKindDiscriminants::DeleteProjectFailed => 36,
+        ErrorKindDiscriminants::TokenNotFound => 37,
+        ErrorKindDiscriminants::TokenAlreadyExists => 38,
+        ErrorKindDiscriminants::DeviceCodeNotFound => 39,
+        ErrorKindDiscriminants::AccountSuspended => 40,
+        ErrorKindDiscriminants::NotFound => 41,
+        ErrorKindDiscriminants::MetricsDisabled => 42,
+    }
+}
+
+/// Hide the value of a secret, like the secrets table does
+pub fn redact_secret(mut secret: secret::Response) -> secret::Response {
+    secret.value = Secret::new(secret.value.redacted().to_string());
+
+    secret
+}
+
+/// Hide the secret values and database passwords in a resource, like the resources table does
+/// unless `--show-secrets` is given. Custom resources, like a config merged with secrets, can hold
+/// secrets anywhere, so only their keys are kept.
+pub fn redact_resource(mut resource: resource::Response) -> resource::Response {
+    let redacted = json!(Secret::new(String::new()).redacted());
+
+    match resource.r#type {
+        resource::Type::Database(_) => {
+            if let Some(Value::Object(info)) = resource.data.get_mut("Info") {
+                info.insert("role_password".to_string(), redacted);
+            }
+        }
+        resource::Type::Secrets => {
+            if let Some(Value::Object(secrets)) = resource.data.get_mut("secrets") {
+                for value in secrets.values_mut() {
+                    *value = redacted.clone();
+                }
+            }
+        }
+        resource::Type::Custom => redact_values(&mut resource.data, &redacted),
+        _ => {}
+    }
+
+    resource
+}
+
+/// Replace every value in a JSON document, keeping the keys of its objects
+fn redact_values(value: &mut Value, redacted: &Value) {
+    match value {
+        Value::Object(object) => {
+            for value in object.values_mut() {
+                redact_values(value, redacted);
+            }
+        }
+        Value::Array(array) => {
+            for value in array.iter_mut() {
+                redact_values(value, redacted);
+            }
+        }
+        value => *value = redacted.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Context;
+    use shuttle_common::models::error::{ApiError, ErrorKind};
+
+    use serde_json::json;
+    use shuttle_common::resource;
+
+    use super::{exit_code, redact_resource, GENERIC_EXIT_CODE};
+
+    #[test]
+    fn exit_codes_per_error_kind() {
+        let error = anyhow::Error::from(ApiError::from(ErrorKind::ProjectNotFound));
+        assert_eq!(exit_code(&error), 17);
+
+        // Kinds holding data are matched on their name only
+        let error = anyhow::Error::from(ApiError::from(ErrorKind::ProjectHasResources(vec![
+            "database::shared::postgres".to_string(),
+        ])));
+        assert_eq!(exit_code(&error), 23);
+
+        // Suggestions wrap API errors in more context
+        let error = Err::<(), _>(ApiError::from(ErrorKind::Unauthorized))
+            .context("Fetching the deployment failed")
+            .unwrap_err();
+        assert_eq!(exit_code(&error), 13);
+
+        // The auth service and deployers send their kinds too
+        let error = anyhow::Error::from(ApiError::with_kind(
+            "deployment not found, try running `cargo shuttle deploy`",
+            reqwest::StatusCode::NOT_FOUND,
+            ErrorKind::NotFound,
+        ));
+        assert_eq!(exit_code(&error), 41);
+
+        // Errors from older servers come without a kind
+        let error = anyhow::Error::from(ApiError::from(reqwest::StatusCode::NOT_FOUND));
+        assert_eq!(exit_code(&error), GENERIC_EXIT_CODE);
+
+        // Kinds added by newer servers are not known yet
+        let error = anyhow::Error::from(ApiError {
+            message: "something new".to_string(),
+            status_code: 400,
+            kind: Some("SomethingNew".to_string()),
+        });
+        assert_eq!(exit_code(&error), GENERIC_EXIT_CODE);
+
+        let error = anyhow::anyhow!("could not find `Cargo.toml`");
+        assert_eq!(exit_code(&error), GENERIC_EXIT_CODE);
+    }
+
+    #[test]
+    fn redact_custom_resources() {
+        let resource = resource::Response {
+            r#type: resource::Type::Custom,
+            config: json!({}),
+            data: json!({
+                "greeting": "Hello",
+                "database": { "port": 5432, "password": "hunter2" },
+                "hosts": ["db1.example.com"],
+            }),
+        };
+
+        let redacted = redact_resource(resource);
+
+        assert_eq!(
+            redacted.data,
+            json!({
+                "greeting": "********",
+                "database": { "port": "********", "password": "********" },
+                "hosts": ["********"],
+            })
+        );
+    }
+}
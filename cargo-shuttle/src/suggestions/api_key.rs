@@ -11,9 +11,9 @@ use crossterm::style::Stylize;
 
 /// Used when logging out and resetting API key fails
 pub fn reset_api_key_failed(err: anyhow::Error) -> anyhow::Error {
-    println!();
-    println!("{}", "Logging out failed".red());
-    println!();
-    println!("If trying to log out and reset the API key at the same time fails repeteadly, please check Shuttle status at https://status.shuttle.rs or open a help thread on the Discord server.");
+    eprintln!();
+    eprintln!("{}", "Logging out failed".red());
+    eprintln!();
+    eprintln!("If trying to log out and reset the API key at the same time fails repeteadly, please check Shuttle status at https://status.shuttle.rs or open a help thread on the Discord server.");
     err
 }
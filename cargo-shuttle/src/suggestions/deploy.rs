@@ -2,20 +2,20 @@ use crossterm::style::Stylize;
 
 /// Used when the deploy request doesn't succeed.
 pub fn deploy_request_failure(err: anyhow::Error) -> anyhow::Error {
-    println!();
-    println!("{}", "Deploy request failed".red());
-    println!();
-    println!("Please check your project status and deployments:");
-    println!();
-    println!("1. cargo shuttle project status");
-    println!();
-    println!("2. cargo shuttle deployment list");
-    println!();
-    println!(
+    eprintln!();
+    eprintln!("{}", "Deploy request failed".red());
+    eprintln!();
+    eprintln!("Please check your project status and deployments:");
+    eprintln!();
+    eprintln!("1. cargo shuttle project status");
+    eprintln!();
+    eprintln!("2. cargo shuttle deployment list");
+    eprintln!();
+    eprintln!(
         "If deploying fails repeteadly, please try restarting your project before deploying again or contacting the team on the Discord server:"
     );
-    println!();
-    println!("cargo shuttle project restart");
+    eprintln!();
+    eprintln!("cargo shuttle project restart");
     err
 }
 
@@ -23,30 +23,30 @@ pub fn deploy_request_failure(err: anyhow::Error) -> anyhow::Error {
 /// deploy request went through (e.g. following the deployment logs, checking
 /// the deployment state).
 pub fn deployment_setup_failure(err: anyhow::Error, title: &str) -> anyhow::Error {
-    println!();
-    println!("{}", title.dark_red());
-    println!();
-    println!(
+    eprintln!();
+    eprintln!("{}", title.dark_red());
+    eprintln!();
+    eprintln!(
         "Please check your project status and if the last deployment is recent and is running:"
     );
-    println!();
-    println!("1. cargo shuttle project status");
-    println!();
-    println!("2. cargo shuttle deployment list");
-    println!();
-    println!("You should be able to get the logs of the deployment by running:");
-    println!();
-    println!("cargo shuttle logs");
-    println!();
-    println!("Or follow the logs of the deployment by running:");
-    println!();
-    println!("cargo shuttle logs --follow");
-    println!("If the last deployment is not recent or is not running, please try deploying again  or contacting the team on the Discord server:");
-    println!();
-    println!("cargo shuttle deploy");
-    println!();
-    println!("Or restart the project before deploying again:");
-    println!();
-    println!("cargo shuttle project restart");
+    eprintln!();
+    eprintln!("1. cargo shuttle project status");
+    eprintln!();
+    eprintln!("2. cargo shuttle deployment list");
+    eprintln!();
+    eprintln!("You should be able to get the logs of the deployment by running:");
+    eprintln!();
+    eprintln!("cargo shuttle logs");
+    eprintln!();
+    eprintln!("Or follow the logs of the deployment by running:");
+    eprintln!();
+    eprintln!("cargo shuttle logs --follow");
+    eprintln!("If the last deployment is not recent or is not running, please try deploying again  or contacting the team on the Discord server:");
+    eprintln!();
+    eprintln!("cargo shuttle deploy");
+    eprintln!();
+    eprintln!("Or restart the project before deploying again:");
+    eprintln!();
+    eprintln!("cargo shuttle project restart");
     err
 }
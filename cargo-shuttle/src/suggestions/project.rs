@@ -7,18 +7,18 @@ pub fn project_request_failure(
     show_status_suggestion: bool,
     final_suggestion: &str,
 ) -> anyhow::Error {
-    println!();
-    println!("{}", title.red());
+    eprintln!();
+    eprintln!("{}", title.red());
 
     if show_status_suggestion {
-        println!();
-        println!("Please double-check the project status before retrying:");
-        println!();
-        println!("cargo shuttle project status");
+        eprintln!();
+        eprintln!("Please double-check the project status before retrying:");
+        eprintln!();
+        eprintln!("cargo shuttle project status");
     }
 
-    println!();
-    println!(
+    eprintln!();
+    eprintln!(
         "If {}, please check Shuttle status at https://status.shuttle.rs before contacting the team on the Discord server.",
         final_suggestion
     );
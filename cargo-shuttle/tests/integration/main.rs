@@ -1,7 +1,7 @@
 mod init;
 mod run;
 
-use cargo_shuttle::{Command, CommandOutcome, ProjectArgs, Shuttle, ShuttleArgs};
+use cargo_shuttle::{Command, CommandOutcome, OutputMode, ProjectArgs, Shuttle, ShuttleArgs};
 use std::path::Path;
 
 /// creates a `cargo-shuttle` run instance with some reasonable defaults set.
@@ -16,10 +16,12 @@ async fn cargo_shuttle_command(
         .run(
             ShuttleArgs {
                 api_url: Some("http://shuttle.invalid:80".to_string()),
+                profile: None,
                 project_args: ProjectArgs {
                     working_directory,
                     name: None,
                 },
+                output: OutputMode::Table,
                 cmd,
             },
             false,
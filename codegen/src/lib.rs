@@ -101,6 +101,15 @@ pub fn main(
 ///         });
 ///         Response::new(StreamBody::new(chunk_stream))
 ///     }
+///
+///     // Resources are extracted into parameters marked with `#[shuttle_next::resource]`.
+///     // They are provisioned by the runtime before the app starts:
+///     #[shuttle_next::endpoint(method = get, route = "/secret")]
+///     async fn secret(
+///         #[shuttle_next::resource] secrets: shuttle_next::resources::Secrets,
+///     ) -> String {
+///         secrets.get("GREETING").unwrap_or_default()
+///     }
 /// }
 /// ```
 #[cfg(feature = "next")]
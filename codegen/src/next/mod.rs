@@ -3,8 +3,8 @@ use std::collections::HashMap;
 use proc_macro_error::emit_error;
 use quote::{quote, ToTokens};
 use syn::{
-    parse::Parse, punctuated::Punctuated, Expr, ExprLit, File, Ident, Item, ItemFn, Lit, LitStr,
-    Token,
+    parse::Parse, punctuated::Punctuated, Expr, ExprLit, File, FnArg, Ident, Item, ItemFn, Lit,
+    LitStr, Meta, Token, Type,
 };
 
 #[derive(Debug, Eq, PartialEq)]
@@ -253,13 +253,52 @@ impl<'a> ToTokens for EndpointChain<'a> {
     }
 }
 
+/// Find the types of the parameters marked as resources with `#[shuttle_next::resource]`, and
+/// strip the marker attributes
+fn resources_from_item_fn(item: &mut ItemFn) -> Vec<Type> {
+    let mut resources = Vec::new();
+
+    for input in item.sig.inputs.iter_mut() {
+        let FnArg::Typed(param) = input else {
+            continue;
+        };
+
+        let resource_index = param.attrs.iter().position(|attr| {
+            attr.path()
+                .segments
+                .last()
+                .map_or(false, |segment| segment.ident == "resource")
+        });
+
+        if let Some(index) = resource_index {
+            let attr = param.attrs.remove(index);
+
+            if !matches!(attr.meta, Meta::Path(_)) {
+                emit_error!(
+                    attr,
+                    "resource attribute takes no arguments";
+                    hint = "The resource is picked by the type of the parameter: `#[shuttle_next::resource] db: Postgres`"
+                );
+            }
+
+            resources.push(param.ty.as_ref().clone());
+        }
+    }
+
+    resources
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub(crate) struct App {
     endpoints: Vec<Endpoint>,
+    /// Types of the resources the endpoints use
+    resources: Vec<Type>,
 }
 
 impl App {
     pub(crate) fn from_file(file: &mut File) -> Self {
+        let mut resources = Vec::new();
+
         let endpoints = file
             .items
             .iter_mut()
@@ -270,16 +309,29 @@ impl App {
                     None
                 }
             })
-            .filter_map(Endpoint::from_item_fn)
+            .filter_map(|item_fn| {
+                let endpoint = Endpoint::from_item_fn(item_fn)?;
+
+                for resource in resources_from_item_fn(item_fn) {
+                    if !resources.contains(&resource) {
+                        resources.push(resource);
+                    }
+                }
+
+                Some(endpoint)
+            })
             .collect();
 
-        Self { endpoints }
+        Self {
+            endpoints,
+            resources,
+        }
     }
 }
 
 impl ToTokens for App {
     fn to_tokens(&self, tokens: &mut proc_macro2::TokenStream) {
-        let Self { endpoints } = self;
+        let Self { endpoints, .. } = self;
 
         let mut endpoint_chains = endpoints
             .iter()
@@ -333,9 +385,28 @@ impl ToTokens for App {
 }
 
 pub(crate) fn wasi_bindings(app: App) -> proc_macro2::TokenStream {
+    let resources = &app.resources;
+
     quote!(
         #app
 
+        #[cfg(not(test))]
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "C" fn __SHUTTLE_Resources(resources_fd: std::os::wasi::prelude::RawFd) {
+            use shuttle_next::resources::Resource;
+            use std::os::wasi::io::FromRawFd;
+
+            // file descriptor 3 for writing the resources the app needs
+            let mut resources_fd = unsafe { std::fs::File::from_raw_fd(resources_fd) };
+
+            shuttle_next::resources::write_resources(
+                &mut resources_fd,
+                &[#(<#resources as Resource>::TYPE),*],
+            )
+            .unwrap();
+        }
+
         #[cfg(not(test))]
         #[no_mangle]
         #[allow(non_snake_case)]
@@ -392,13 +463,30 @@ pub(crate) fn wasi_bindings(app: App) -> proc_macro2::TokenStream {
                 body_stream.write_all(body.unwrap().as_ref()).unwrap();
             }
         }
+
+        #[cfg(not(test))]
+        #[no_mangle]
+        #[allow(non_snake_case)]
+        pub extern "C" fn __SHUTTLE_Axum_stream_call(
+            parts_fd: std::os::wasi::prelude::RawFd,
+            body_fd: std::os::wasi::prelude::RawFd,
+        ) {
+            use shuttle_next::tracing_prelude::*;
+
+            shuttle_next::tracing_registry()
+                .with(shuttle_next::tracing_fmt::layer().without_time())
+                .init();
+
+            // Runtimes which support streaming call this instead of `__SHUTTLE_Axum_call`
+            shuttle_next::stream::serve(parts_fd, body_fd, __app);
+        }
     )
 }
 
 #[cfg(test)]
 mod tests {
     use pretty_assertions::assert_eq;
-    use quote::quote;
+    use quote::{quote, ToTokens};
     use syn::parse_quote;
 
     use crate::next::{App, Parameter};
@@ -437,6 +525,7 @@ mod tests {
                             function: parse_quote!(goodbye),
                         },
                     ],
+                    resources: Vec::new(),
                 },
                 quote!(
                     async fn __app(
@@ -473,6 +562,7 @@ mod tests {
                             function: parse_quote!(post_goodbye),
                         },
                     ],
+                    resources: Vec::new(),
                 },
                 quote!(
                     async fn __app(
@@ -626,11 +716,61 @@ mod tests {
                     function: parse_quote!(goodbye),
                 },
             ],
+            resources: Vec::new(),
         };
 
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn parse_app_with_resources() {
+        let mut input = parse_quote! {
+            #[shuttle_codegen::endpoint(method = get, route = "/todos")]
+            async fn todos(
+                #[shuttle_next::resource] db: shuttle_next::resources::Postgres,
+                #[shuttle_next::resource] secrets: Secrets
+            ) -> String {
+                String::new()
+            }
+
+            #[shuttle_codegen::endpoint(method = post, route = "/todos")]
+            async fn add_todo(#[shuttle_next::resource] db: shuttle_next::resources::Postgres, body: String) {}
+        };
+
+        let actual = App::from_file(&mut input);
+        let expected = App {
+            endpoints: vec![
+                Endpoint {
+                    route: parse_quote!("/todos"),
+                    method: parse_quote!(get),
+                    function: parse_quote!(todos),
+                },
+                Endpoint {
+                    route: parse_quote!("/todos"),
+                    method: parse_quote!(post),
+                    function: parse_quote!(add_todo),
+                },
+            ],
+            resources: vec![
+                parse_quote!(shuttle_next::resources::Postgres),
+                parse_quote!(Secrets),
+            ],
+        };
+
+        assert_eq!(actual, expected);
+
+        // The resource attributes should be stripped
+        let expected_todos: syn::ItemFn = parse_quote! {
+            async fn todos(db: shuttle_next::resources::Postgres, secrets: Secrets) -> String {
+                String::new()
+            }
+        };
+        assert_eq!(
+            quote!(#expected_todos).to_string(),
+            input.items[0].to_token_stream().to_string()
+        );
+    }
+
     #[test]
     fn ui() {
         let t = trybuild::TestCases::new();
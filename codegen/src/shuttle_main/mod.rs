@@ -1,7 +1,7 @@
 use proc_macro::TokenStream;
 use proc_macro2::Span;
 use proc_macro_error::emit_error;
-use quote::{quote, ToTokens};
+use quote::{quote, quote_spanned, ToTokens};
 use syn::{
     parse::Parse, parse_macro_input, parse_quote, punctuated::Punctuated, spanned::Spanned,
     Attribute, Expr, ExprLit, FnArg, Ident, ItemFn, Lit, Pat, PatIdent, Path, ReturnType,
@@ -12,6 +12,9 @@ pub(crate) fn r#impl(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let mut fn_decl = parse_macro_input!(item as ItemFn);
 
     let loader = Loader::from_item_fn(&mut fn_decl);
+    let assert_service = loader
+        .as_ref()
+        .map(|loader| assert_service(&loader.fn_return));
 
     quote! {
         fn main() {
@@ -25,6 +28,8 @@ pub(crate) fn r#impl(_attr: TokenStream, item: TokenStream) -> TokenStream {
                 })
         }
 
+        #assert_service
+
         #loader
 
         #fn_decl
@@ -32,6 +37,18 @@ pub(crate) fn r#impl(_attr: TokenStream, item: TokenStream) -> TokenStream {
     .into()
 }
 
+/// Assert that the return type is a service result. Aliases of the return types can only be
+/// resolved by the compiler, so the macro leaves the check to it. The assertion is spanned to the
+/// return type, so that it is what gets reported.
+fn assert_service(return_type: &TypePath) -> proc_macro2::TokenStream {
+    quote_spanned! {return_type.span()=>
+        const _: fn() = || {
+            fn assert_service<T: ::shuttle_runtime::__internals::ServiceResult>() {}
+            assert_service::<#return_type>();
+        };
+    }
+}
+
 struct Loader {
     fn_ident: Ident,
     fn_inputs: Vec<Input>,
@@ -123,6 +140,8 @@ impl Loader {
             })
             .collect();
 
+        check_builders(&inputs);
+
         check_return_type(item_fn.sig.clone()).map(|type_path| Self {
             fn_ident: item_fn.sig.ident.clone(),
             fn_inputs: inputs,
@@ -157,6 +176,200 @@ fn check_return_type(signature: Signature) -> Option<TypePath> {
     }
 }
 
+/// A builder from one of the resource crates maintained alongside this one. These are checked
+/// against the sources of the resource crates by the `known_builders_match_resources` test.
+struct KnownBuilder {
+    /// Path to the builder
+    path: &'static str,
+
+    /// Name of the resource it gets, of which a service can only have one
+    resource: &'static str,
+
+    /// Names of the options it takes, which are all strings
+    options: &'static [&'static str],
+}
+
+const KNOWN_BUILDERS: &[KnownBuilder] = &[
+    KnownBuilder {
+        path: "shuttle_shared_db::Postgres",
+        resource: "shared Postgres database",
+        options: &["local_uri"],
+    },
+    KnownBuilder {
+        path: "shuttle_shared_db::MongoDb",
+        resource: "shared MongoDB database",
+        options: &["local_uri"],
+    },
+    KnownBuilder {
+        path: "shuttle_aws_rds::Postgres",
+        resource: "AWS RDS Postgres database",
+        options: &["local_uri"],
+    },
+    KnownBuilder {
+        path: "shuttle_aws_rds::MySql",
+        resource: "AWS RDS MySQL database",
+        options: &["local_uri"],
+    },
+    KnownBuilder {
+        path: "shuttle_aws_rds::MariaDB",
+        resource: "AWS RDS MariaDB database",
+        options: &["local_uri"],
+    },
+    KnownBuilder {
+        path: "shuttle_turso::Turso",
+        resource: "Turso database",
+        options: &["addr", "token", "local_addr"],
+    },
+    KnownBuilder {
+        path: "shuttle_persist::Persist",
+        resource: "persist instance",
+        options: &[],
+    },
+    KnownBuilder {
+        path: "shuttle_secrets::Secrets",
+        resource: "secret store",
+        options: &[],
+    },
+    KnownBuilder {
+        path: "shuttle_config::Config",
+        resource: "config",
+        options: &[],
+    },
+    KnownBuilder {
+        path: "shuttle_metadata::ShuttleMetadata",
+        resource: "metadata",
+        options: &[],
+    },
+];
+
+impl Builder {
+    fn known(&self) -> Option<&'static KnownBuilder> {
+        let path = path_to_string(&self.path);
+
+        KNOWN_BUILDERS.iter().find(|known| known.path == path)
+    }
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// Check the options of the builders against the ones they take, and that no resource is asked
+/// for twice. Custom builders can take anything, so only the values of their options are checked.
+fn check_builders(inputs: &[Input]) {
+    for (index, input) in inputs.iter().enumerate() {
+        let known = input.builder.known();
+
+        for option in input.builder.options.options.iter() {
+            check_option(option, known);
+        }
+
+        let Some(known) = known else {
+            continue;
+        };
+
+        if let Some(first) = inputs[..index].iter().find(|other| {
+            other
+                .builder
+                .known()
+                .is_some_and(|other| other.path == known.path)
+        }) {
+            emit_error!(
+                input.ident,
+                "the {} resource is already used by `{}`", known.resource, first.ident;
+                hint = first.ident.span() => "Use this resource instead, cloning it if needed"
+            );
+        }
+    }
+}
+
+fn check_option(option: &BuilderOption, known: Option<&KnownBuilder>) {
+    let lit = match &option.value {
+        Expr::Lit(ExprLit { lit, .. }) => Some(lit),
+        _ => None,
+    };
+
+    if let Some(lit) = lit {
+        if !matches!(
+            lit,
+            Lit::Str(_) | Lit::Bool(_) | Lit::Int(_) | Lit::Float(_)
+        ) {
+            emit_error!(
+                lit,
+                "unsupported literal type for a builder option";
+                hint = "Options can be set to strings, booleans, integers, floats or enum variants"
+            );
+            return;
+        }
+    }
+
+    let Some(known) = known else {
+        return;
+    };
+
+    let name = option.ident.to_string();
+
+    if !known.options.contains(&name.as_str()) {
+        match closest(&name, known.options) {
+            Some(suggestion) => emit_error!(
+                option.ident,
+                "unknown option `{}` for `{}`", name, known.path;
+                hint = "Did you mean `{}`?", suggestion
+            ),
+            None if known.options.is_empty() => emit_error!(
+                option.ident,
+                "unknown option `{}` for `{}`", name, known.path;
+                hint = "`{}` does not take any options", known.path
+            ),
+            None => emit_error!(
+                option.ident,
+                "unknown option `{}` for `{}`", name, known.path;
+                hint = "The options it takes are: {}", known.options.join(", ")
+            ),
+        }
+    } else if !matches!(lit, None | Some(Lit::Str(_))) {
+        emit_error!(
+            option.value,
+            "the `{}` option of `{}` needs to be a string",
+            name,
+            known.path
+        );
+    }
+}
+
+/// Find the option closest to a misspelled one, if it is close enough to be a typo
+fn closest<'a>(name: &str, options: &[&'a str]) -> Option<&'a str> {
+    options
+        .iter()
+        .map(|option| (edit_distance(name, option), *option))
+        .filter(|(distance, option)| *distance <= option.len().max(3) / 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, option)| option)
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
 fn attribute_to_builder(pat_ident: &PatIdent, attrs: Vec<Attribute>) -> syn::Result<Builder> {
     if attrs.is_empty() {
         return Err(syn::Error::new_spanned(
@@ -238,6 +451,13 @@ impl ToTokens for Loader {
             ))
         };
 
+        // only getting the secrets needs the factory to be mutable
+        let factory_mut: Option<Token![mut]> = if needs_vars {
+            Some(parse_quote!(mut))
+        } else {
+            None
+        };
+
         // variables for string interpolating secrets into the attribute macros
         let (vars, drop_vars): (Option<Stmt>, Option<Stmt>) = if needs_vars {
             (
@@ -258,23 +478,38 @@ impl ToTokens for Loader {
             (None, None)
         };
 
+        // the resources do not depend on each other, so they are all provisioned concurrently
+        let get_resources: Option<proc_macro2::TokenStream> = if self.fn_inputs.is_empty() {
+            None
+        } else {
+            Some(quote! {
+                let (#(#fn_inputs,)*) = ::shuttle_runtime::tokio::join!(#(
+                    async {
+                        ::shuttle_runtime::__internals::get_resource(
+                            #fn_inputs,
+                            &#factory_ident,
+                            &#resource_tracker_ident,
+                        )
+                        .await.context(format!("failed to provision {}", stringify!(#fn_inputs_builder)))
+                    }
+                ),*);
+                #(let #fn_inputs = #fn_inputs?;)*
+            })
+        };
+
         let loader = quote! {
             async fn loader(
-                mut #factory_ident: ::shuttle_runtime::__internals::ProvisionerFactory,
-                mut #resource_tracker_ident: ::shuttle_runtime::__internals::ResourceTracker,
+                #factory_mut #factory_ident: ::shuttle_runtime::__internals::ProvisionerFactory,
+                #resource_tracker_ident: ::shuttle_runtime::__internals::ResourceTracker,
             ) -> #return_type {
                 use ::shuttle_runtime::__internals::Context;
                 #extra_imports
                 #vars
-                #(let #fn_inputs = ::shuttle_runtime::__internals::get_resource(
-                    #fn_inputs_builder::new()#fn_inputs_builder_options,
-                    &mut #factory_ident,
-                    &mut #resource_tracker_ident,
-                )
-                .await.context(format!("failed to provision {}", stringify!(#fn_inputs_builder)))?;)*
-
+                #(let #fn_inputs = #fn_inputs_builder::new()#fn_inputs_builder_options;)*
                 #drop_vars
 
+                #get_resources
+
                 #fn_ident(#(#fn_inputs),*).await
             }
         };
@@ -289,7 +524,11 @@ mod tests {
     use quote::quote;
     use syn::{parse_quote, Ident, TypePath};
 
-    use super::{Builder, BuilderOptions, Input, Loader};
+    use std::collections::BTreeSet;
+    use std::fs;
+    use std::path::Path;
+
+    use super::{assert_service, closest, Builder, BuilderOptions, Input, Loader, KNOWN_BUILDERS};
 
     #[test]
     fn from_with_return() {
@@ -306,6 +545,28 @@ mod tests {
         assert_eq!(actual.fn_return, expected_return);
     }
 
+    #[test]
+    fn from_with_alias_return() {
+        let mut input = parse_quote!(
+            async fn aliased() -> MyService {}
+        );
+
+        let actual = Loader::from_item_fn(&mut input).unwrap();
+        let expected_return: TypePath = parse_quote!(MyService);
+
+        assert_eq!(actual.fn_return, expected_return);
+
+        let actual = assert_service(&actual.fn_return);
+        let expected = quote! {
+            const _: fn() = || {
+                fn assert_service<T: ::shuttle_runtime::__internals::ServiceResult>() {}
+                assert_service::<MyService>();
+            };
+        };
+
+        assert_eq!(actual.to_string(), expected.to_string());
+    }
+
     #[test]
     fn from_with_main() {
         let mut input = parse_quote!(
@@ -329,8 +590,8 @@ mod tests {
         let actual = quote!(#input);
         let expected = quote! {
             async fn loader(
-                mut _factory: ::shuttle_runtime::__internals::ProvisionerFactory,
-                mut _resource_tracker: ::shuttle_runtime::__internals::ResourceTracker,
+                _factory: ::shuttle_runtime::__internals::ProvisionerFactory,
+                _resource_tracker: ::shuttle_runtime::__internals::ResourceTracker,
             ) -> ShuttleSimple {
                 use ::shuttle_runtime::__internals::Context;
                 simple().await
@@ -397,21 +658,32 @@ mod tests {
         let actual = quote!(#input);
         let expected = quote! {
             async fn loader(
-                mut factory: ::shuttle_runtime::__internals::ProvisionerFactory,
-                mut resource_tracker: ::shuttle_runtime::__internals::ResourceTracker,
+                factory: ::shuttle_runtime::__internals::ProvisionerFactory,
+                resource_tracker: ::shuttle_runtime::__internals::ResourceTracker,
             ) -> ShuttleComplex {
                 use ::shuttle_runtime::__internals::Context;
                 use ::shuttle_runtime::{Factory, ResourceBuilder};
-                let pool = ::shuttle_runtime::__internals::get_resource(
-                    shuttle_shared_db::Postgres::new(),
-                    &mut factory,
-                    &mut resource_tracker,
-                ).await.context(format!("failed to provision {}", stringify!(shuttle_shared_db::Postgres)))?;
-                let redis = ::shuttle_runtime::__internals::get_resource(
-                    shuttle_shared_db::Redis::new(),
-                    &mut factory,
-                    &mut resource_tracker,
-                ).await.context(format!("failed to provision {}", stringify!(shuttle_shared_db::Redis)))?;
+                let pool = shuttle_shared_db::Postgres::new();
+                let redis = shuttle_shared_db::Redis::new();
+
+                let (pool, redis,) = ::shuttle_runtime::tokio::join!(
+                    async {
+                        ::shuttle_runtime::__internals::get_resource(
+                            pool,
+                            &factory,
+                            &resource_tracker,
+                        ).await.context(format!("failed to provision {}", stringify!(shuttle_shared_db::Postgres)))
+                    },
+                    async {
+                        ::shuttle_runtime::__internals::get_resource(
+                            redis,
+                            &factory,
+                            &resource_tracker,
+                        ).await.context(format!("failed to provision {}", stringify!(shuttle_shared_db::Redis)))
+                    }
+                );
+                let pool = pool?;
+                let redis = redis?;
 
                 __shuttle_complex(pool, redis).await
             }
@@ -420,6 +692,43 @@ mod tests {
         assert_eq!(actual.to_string(), expected.to_string());
     }
 
+    #[test]
+    fn output_with_config_input() {
+        let mut input = parse_quote!(
+            async fn configured(#[shuttle_config::Config] config: MyConfig) -> ShuttleAxum {}
+        );
+
+        let loader = Loader::from_item_fn(&mut input).unwrap();
+
+        // The type to deserialize the config into is inferred from the argument
+        let actual = quote!(#loader);
+        let expected = quote! {
+            async fn loader(
+                factory: ::shuttle_runtime::__internals::ProvisionerFactory,
+                resource_tracker: ::shuttle_runtime::__internals::ResourceTracker,
+            ) -> ShuttleAxum {
+                use ::shuttle_runtime::__internals::Context;
+                use ::shuttle_runtime::{Factory, ResourceBuilder};
+                let config = shuttle_config::Config::new();
+
+                let (config,) = ::shuttle_runtime::tokio::join!(
+                    async {
+                        ::shuttle_runtime::__internals::get_resource(
+                            config,
+                            &factory,
+                            &resource_tracker,
+                        ).await.context(format!("failed to provision {}", stringify!(shuttle_config::Config)))
+                    }
+                );
+                let config = config?;
+
+                __shuttle_configured(config).await
+            }
+        };
+
+        assert_eq!(actual.to_string(), expected.to_string());
+    }
+
     #[test]
     fn parse_builder_options() {
         let input: BuilderOptions = parse_quote!(
@@ -509,18 +818,25 @@ mod tests {
         let expected = quote! {
             async fn loader(
                 mut factory: ::shuttle_runtime::__internals::ProvisionerFactory,
-                mut resource_tracker: ::shuttle_runtime::__internals::ResourceTracker,
+                resource_tracker: ::shuttle_runtime::__internals::ResourceTracker,
             ) -> ShuttleComplex {
                 use ::shuttle_runtime::__internals::Context;
                 use ::shuttle_runtime::{Factory, ResourceBuilder};
                 let vars = std::collections::HashMap::from_iter(factory.get_secrets().await?.into_iter().map(|(key, value)| (format!("secrets.{}", key), value.expose().clone())));
-                let pool = ::shuttle_runtime::__internals::get_resource (
-                    shuttle_shared_db::Postgres::new().size(&::shuttle_runtime::__internals::strfmt("10Gb", &vars)?).public(false),
-                    &mut factory,
-                    &mut resource_tracker,
-                ).await.context(format!("failed to provision {}", stringify!(shuttle_shared_db::Postgres)))?;
+                let pool = shuttle_shared_db::Postgres::new().size(&::shuttle_runtime::__internals::strfmt("10Gb", &vars)?).public(false);
                 std::mem::drop(vars);
 
+                let (pool,) = ::shuttle_runtime::tokio::join!(
+                    async {
+                        ::shuttle_runtime::__internals::get_resource(
+                            pool,
+                            &factory,
+                            &resource_tracker,
+                        ).await.context(format!("failed to provision {}", stringify!(shuttle_shared_db::Postgres)))
+                    }
+                );
+                let pool = pool?;
+
                 complex(pool).await
             }
         };
@@ -528,6 +844,64 @@ mod tests {
         assert_eq!(actual.to_string(), expected.to_string());
     }
 
+    #[test]
+    fn suggest_closest_option() {
+        let options = ["addr", "token", "local_addr"];
+
+        assert_eq!(closest("adr", &options), Some("addr"));
+        assert_eq!(closest("tokn", &options), Some("token"));
+        assert_eq!(closest("local_adr", &options), Some("local_addr"));
+        assert_eq!(closest("address", &options), None);
+        assert_eq!(closest("size", &options), None);
+    }
+
+    /// The options of the known builders are the methods of the resource crates which configure
+    /// a builder, and every resource crate has its builders known
+    #[test]
+    fn known_builders_match_resources() {
+        let resources = Path::new(env!("CARGO_MANIFEST_DIR")).join("../resources");
+
+        for entry in fs::read_dir(resources).unwrap() {
+            let dir = entry.unwrap().path();
+            if !dir.is_dir() {
+                continue;
+            }
+
+            let crate_name = format!(
+                "shuttle_{}",
+                dir.file_name().unwrap().to_str().unwrap().replace('-', "_")
+            );
+
+            let known: Vec<_> = KNOWN_BUILDERS
+                .iter()
+                .filter(|known| known.path.split("::").next() == Some(crate_name.as_str()))
+                .collect();
+            assert!(!known.is_empty(), "{crate_name} has no known builders");
+
+            let mut methods = BTreeSet::new();
+            for file in fs::read_dir(dir.join("src")).unwrap() {
+                let source = fs::read_to_string(file.unwrap().path()).unwrap();
+
+                for line in source.lines() {
+                    let Some((name, _)) = line
+                        .trim()
+                        .strip_prefix("pub fn ")
+                        .and_then(|rest| rest.split_once("(mut self"))
+                    else {
+                        continue;
+                    };
+                    methods.insert(name);
+                }
+            }
+
+            let options: BTreeSet<_> = known
+                .iter()
+                .flat_map(|known| known.options.iter().copied())
+                .collect();
+            assert_eq!(options, methods, "options of the {crate_name} builders");
+        }
+    }
+
     #[test]
     fn ui() {
         let t = trybuild::TestCases::new();
@@ -0,0 +1,6 @@
+#[shuttle_codegen::main]
+async fn duplicate_resource(
+    #[shuttle_shared_db::Postgres] pool: PgPool,
+    #[shuttle_shared_db::Postgres(local_uri = "postgres://localhost")] other_pool: PgPool,
+) -> ShuttleAxum {
+}
@@ -0,0 +1,7 @@
+#[shuttle_codegen::main]
+async fn unknown_option(
+    #[shuttle_shared_db::Postgres(loca_uri = "postgres://localhost")] pool: PgPool,
+    #[shuttle_secrets::Secrets(path = "Secrets.toml")] secrets: SecretStore,
+    #[shuttle_turso::Turso(address = "libsql://localhost")] client: Client,
+) -> ShuttleAxum {
+}
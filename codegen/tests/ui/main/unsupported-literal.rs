@@ -0,0 +1,6 @@
+#[shuttle_codegen::main]
+async fn unsupported_literal(
+    #[shuttle_turso::Turso(addr = b"libsql://localhost", token = true)] client: Client,
+    #[custom::Resource(separator = ';')] custom: Custom,
+) -> ShuttleAxum {
+}
@@ -1,4 +1,4 @@
-use cargo_shuttle::{Command, ProjectArgs, RunArgs, Shuttle, ShuttleArgs};
+use cargo_shuttle::{Command, OutputMode, ProjectArgs, RunArgs, Shuttle, ShuttleArgs};
 use portpicker::pick_unused_port;
 use std::{fs::canonicalize, process::exit, time::Duration};
 use tokio::time::sleep;
@@ -30,15 +30,19 @@ pub async fn cargo_shuttle_run(working_directory: &str, external: bool) -> Strin
         port,
         external,
         release: false,
+        watch: false,
+        secrets: None,
     };
 
     let runner = Shuttle::new().unwrap().run(
         ShuttleArgs {
             api_url: Some("http://shuttle.invalid:80".to_string()),
+            profile: None,
             project_args: ProjectArgs {
                 working_directory: working_directory.clone(),
                 name: None,
             },
+            output: OutputMode::Table,
             cmd: Command::Run(run_args),
         },
         false,
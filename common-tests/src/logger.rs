@@ -8,7 +8,8 @@ use shuttle_common::claims::{ClaimLayer, InjectPropagationLayer};
 use shuttle_proto::logger::{
     logger_client::LoggerClient,
     logger_server::{Logger, LoggerServer},
-    LogLine, LogsRequest, LogsResponse, StoreLogsRequest, StoreLogsResponse,
+    DeleteLogsRequest, DeleteLogsResponse, LogLine, LogsRequest, LogsResponse, StoreLogsRequest,
+    StoreLogsResponse,
 };
 use tokio::sync::mpsc;
 use tokio_stream::wrappers::ReceiverStream;
@@ -36,6 +37,13 @@ impl Logger for MockedLogger {
         }))
     }
 
+    async fn delete_logs(
+        &self,
+        _: Request<DeleteLogsRequest>,
+    ) -> Result<Response<DeleteLogsResponse>, Status> {
+        Ok(Response::new(DeleteLogsResponse { deleted: 0 }))
+    }
+
     type GetLogsStreamStream = ReceiverStream<Result<LogLine, Status>>;
 
     async fn get_logs_stream(
@@ -0,0 +1,270 @@
+use std::{
+    future::Future,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use axum::extract::{ConnectInfo, MatchedPath};
+use chrono::{DateTime, Utc};
+use http::{Method, Request, Response};
+use tower::{Layer, Service};
+use tracing::error;
+
+use crate::claims::Claim;
+
+/// An action taken on one of the control APIs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AuditEvent {
+    /// Account that took the action, if it could be identified
+    pub actor: Option<String>,
+    /// The method and route of the request, such as `DELETE /projects/:project_name`
+    pub action: String,
+    /// The path the action was taken on, such as `/projects/my-project`
+    pub target: String,
+    pub source_ip: Option<String>,
+    /// Status code of the response
+    pub outcome: u16,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Storage for audit events. Stores should only ever append events, never change or remove them
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    async fn record(&self, event: AuditEvent) -> anyhow::Result<()>;
+}
+
+/// Request extension naming the account taking an action, for services that don't use a [Claim]
+#[derive(Clone, Debug)]
+pub struct AuditActor(pub String);
+
+/// Layer to record all the requests changing state, such as `POST` and `DELETE` requests, to an
+/// [AuditSink]. The actor is taken from the [Claim] or [AuditActor] request extension.
+///
+/// It should be added with `route_layer` so the matched route is known.
+#[derive(Clone)]
+pub struct AuditLayer {
+    sink: Arc<dyn AuditSink>,
+    trusted_proxies: Arc<Vec<IpAddr>>,
+}
+
+impl AuditLayer {
+    pub fn new(sink: Arc<dyn AuditSink>) -> Self {
+        Self {
+            sink,
+            trusted_proxies: Default::default(),
+        }
+    }
+
+    /// Proxies allowed to forward the address of the client in the `x-forwarded-for` header. The
+    /// header is ignored on requests from any other address, since clients can set it to anything.
+    pub fn with_trusted_proxies(mut self, trusted_proxies: Vec<IpAddr>) -> Self {
+        self.trusted_proxies = Arc::new(trusted_proxies);
+        self
+    }
+}
+
+impl<S> Layer<S> for AuditLayer {
+    type Service = Audit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Audit {
+            inner,
+            sink: self.sink.clone(),
+            trusted_proxies: self.trusted_proxies.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Audit<S> {
+    inner: S,
+    sink: Arc<dyn AuditSink>,
+    trusted_proxies: Arc<Vec<IpAddr>>,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for Audit<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>> + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+            return Box::pin(self.inner.call(req));
+        }
+
+        let extensions = req.extensions();
+        let actor = extensions
+            .get::<Claim>()
+            .map(|claim| claim.sub.clone())
+            .or_else(|| extensions.get::<AuditActor>().map(|actor| actor.0.clone()));
+        let route = extensions
+            .get::<MatchedPath>()
+            .map(|path| path.as_str())
+            .unwrap_or_else(|| req.uri().path());
+        let action = format!("{} {route}", req.method());
+        let target = req.uri().path().to_string();
+        let source_ip = source_ip(&req, &self.trusted_proxies);
+
+        let future = self.inner.call(req);
+        let sink = self.sink.clone();
+
+        Box::pin(async move {
+            let response = future.await?;
+
+            let event = AuditEvent {
+                actor,
+                action,
+                target,
+                source_ip,
+                outcome: response.status().as_u16(),
+                timestamp: Utc::now(),
+            };
+
+            if let Err(error) = sink.record(event).await {
+                error!(error = %error, "failed to record audit event");
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// The address of the client. The address forwarded by a proxy is only used when the request came
+/// from one of the trusted proxies.
+fn source_ip<B>(req: &Request<B>, trusted_proxies: &[IpAddr]) -> Option<String> {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+
+    let forwarded = peer
+        .filter(|peer| trusted_proxies.contains(peer))
+        .and_then(|_| req.headers().get("x-forwarded-for"))
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|ip| ip.trim().to_string());
+
+    forwarded.or_else(|| peer.map(|ip| ip.to_string()))
+}
+
+#[cfg(feature = "models")]
+impl From<AuditEvent> for crate::models::audit::Response {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            actor: event.actor,
+            action: event.action,
+            target: event.target,
+            source_ip: event.source_ip,
+            outcome: event.outcome,
+            timestamp: event.timestamp,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+    };
+
+    use async_trait::async_trait;
+    use axum::{extract::ConnectInfo, routing::get, Extension, Router};
+    use http::{Request, StatusCode};
+    use hyper::Body;
+    use tower::ServiceExt;
+
+    use crate::claims::{Claim, Scope};
+
+    use super::{AuditEvent, AuditLayer, AuditSink};
+
+    #[derive(Default)]
+    struct MemorySink(Mutex<Vec<AuditEvent>>);
+
+    #[async_trait]
+    impl AuditSink for MemorySink {
+        async fn record(&self, event: AuditEvent) -> anyhow::Result<()> {
+            self.0.lock().unwrap().push(event);
+
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn records_changes() {
+        let sink = Arc::new(MemorySink::default());
+        let proxy: SocketAddr = "10.1.0.1:443".parse().unwrap();
+        let client: SocketAddr = "192.0.2.7:52000".parse().unwrap();
+
+        let router = Router::new()
+            .route(
+                "/projects/:project_name",
+                get(|| async {}).post(|| async { StatusCode::FORBIDDEN }),
+            )
+            .route_layer(AuditLayer::new(sink.clone()).with_trusted_proxies(vec![proxy.ip()]))
+            .layer(Extension(Claim::new(
+                "ferries".to_string(),
+                vec![Scope::Project],
+            )));
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::get("/projects/matrix")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = router
+            .clone()
+            .oneshot(
+                Request::post("/projects/matrix")
+                    .header("x-forwarded-for", "10.0.0.1, 10.0.0.2")
+                    .extension(ConnectInfo(proxy))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        // Clients can't pick the address that is recorded
+        router
+            .oneshot(
+                Request::post("/projects/matrix")
+                    .header("x-forwarded-for", "10.0.0.1")
+                    .extension(ConnectInfo(client))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        let events = sink.0.lock().unwrap();
+
+        // Reads are not recorded
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].actor, Some("ferries".to_string()));
+        assert_eq!(events[0].action, "POST /projects/:project_name");
+        assert_eq!(events[0].target, "/projects/matrix");
+        assert_eq!(events[0].source_ip, Some("10.0.0.1".to_string()));
+        assert_eq!(events[0].outcome, 403);
+        assert_eq!(events[1].source_ip, Some("192.0.2.7".to_string()));
+    }
+}
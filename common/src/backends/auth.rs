@@ -1,4 +1,11 @@
-use std::{convert::Infallible, future::Future, pin::Pin, sync::Arc, task::Poll};
+use std::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::Poll,
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
@@ -11,6 +18,7 @@ use opentelemetry_http::HeaderInjector;
 use pin_project::pin_project;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+use tokio::sync::Mutex;
 use tower::{Layer, Service};
 use tracing::{error, trace, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -24,6 +32,9 @@ use super::{
 };
 
 const PUBLIC_KEY_CACHE_KEY: &str = "shuttle.public-key";
+/// How long to wait before fetching the key set again for a key id that is not in it, so tokens
+/// with made up key ids don't cause a fetch on every request
+const JWKS_REFETCH_INTERVAL: Duration = Duration::from_secs(1);
 
 /// Layer to check the admin secret set by deployer is correct
 #[derive(Clone)]
@@ -89,6 +100,51 @@ where
 /// Response used internally to pass around JWT token
 pub struct ConvertResponse {
     pub token: String,
+    /// Token that can be exchanged for a new JWT once this one expires. Only set when asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+/// Request to exchange a refresh token for a new JWT, or to revoke it
+#[derive(Deserialize, Serialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+/// An Ed25519 public key in the JSON Web Key format
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Jwk {
+    pub kty: String,
+    pub crv: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub use_: String,
+    pub kid: String,
+    /// The public key, base64url encoded
+    pub x: String,
+}
+
+impl Jwk {
+    pub fn from_ed25519(kid: String, public_key: &[u8]) -> Self {
+        Self {
+            kty: "OKP".to_string(),
+            crv: "Ed25519".to_string(),
+            alg: "EdDSA".to_string(),
+            use_: "sig".to_string(),
+            kid,
+            x: base64::encode_config(public_key, base64::URL_SAFE_NO_PAD),
+        }
+    }
+
+    pub fn public_key(&self) -> Result<Vec<u8>, base64::DecodeError> {
+        base64::decode_config(&self.x, base64::URL_SAFE_NO_PAD)
+    }
+}
+
+/// The set of public keys that tokens can currently be signed with
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct JwkSet {
+    pub keys: Vec<Jwk>,
 }
 
 /// Trait to get a public key asynchronously
@@ -97,6 +153,16 @@ pub trait PublicKeyFn: Send + Sync + Clone {
     type Error: std::error::Error + Send;
 
     async fn public_key(&self) -> Result<Vec<u8>, Self::Error>;
+
+    /// Get the public key with the given key id, or `None` if no key has this id. Tokens
+    /// without a key id use the key from [PublicKeyFn::public_key].
+    async fn public_key_for(&self, kid: Option<String>) -> Result<Option<Vec<u8>>, Self::Error> {
+        if kid.is_some() {
+            trace!(?kid, "key ids are not supported, using the only public key");
+        }
+
+        self.public_key().await.map(Some)
+    }
 }
 
 #[async_trait]
@@ -116,16 +182,39 @@ where
 pub struct AuthPublicKey {
     auth_uri: Uri,
     cache_manager: Arc<Box<dyn CacheManagement<Value = Vec<u8>>>>,
+    /// When the key set was last fetched. The lock is held while fetching, so requests with a new
+    /// key id wait for the same fetch.
+    jwks_fetched_at: Arc<Mutex<Option<Instant>>>,
 }
 
 impl AuthPublicKey {
     pub fn new(auth_uri: Uri) -> Self {
-        let public_key_cache_manager = CacheManager::new(1);
+        // Room for the legacy public key and a few rotations worth of key ids
+        let public_key_cache_manager = CacheManager::new(8);
         Self {
             auth_uri,
             cache_manager: Arc::new(Box::new(public_key_cache_manager)),
+            jwks_fetched_at: Default::default(),
         }
     }
+
+    async fn get(&self, path: &str) -> Result<Bytes, PublicKeyFnError> {
+        let client = Client::new();
+        let uri: Uri = format!("{}{path}", self.auth_uri).parse()?;
+        let mut request = Request::builder().uri(uri);
+
+        // Safe to unwrap since we just build it
+        let headers = request.headers_mut().unwrap();
+
+        let cx = Span::current().context();
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&cx, &mut HeaderInjector(headers))
+        });
+
+        let res = client.request(request.body(Body::empty())?).await?;
+
+        Ok(body::to_bytes(res).await?)
+    }
 }
 
 #[async_trait]
@@ -138,30 +227,69 @@ impl PublicKeyFn for AuthPublicKey {
 
             Ok(public_key)
         } else {
-            let client = Client::new();
-            let uri: Uri = format!("{}public-key", self.auth_uri).parse()?;
-            let mut request = Request::builder().uri(uri);
+            let buf = self.get("public-key").await?;
+
+            trace!("inserting public key from auth service into cache");
+            self.cache_manager
+                .insert(PUBLIC_KEY_CACHE_KEY, buf.to_vec(), Duration::from_secs(60));
+
+            Ok(buf.to_vec())
+        }
+    }
 
-            // Safe to unwrap since we just build it
-            let headers = request.headers_mut().unwrap();
+    async fn public_key_for(&self, kid: Option<String>) -> Result<Option<Vec<u8>>, Self::Error> {
+        let Some(kid) = kid else {
+            return self.public_key().await.map(Some);
+        };
 
-            let cx = Span::current().context();
-            global::get_text_map_propagator(|propagator| {
-                propagator.inject_context(&cx, &mut HeaderInjector(headers))
-            });
+        let cache_key = format!("{PUBLIC_KEY_CACHE_KEY}.{kid}");
 
-            let res = client.request(request.body(Body::empty())?).await?;
-            let buf = body::to_bytes(res).await?;
+        if let Some(public_key) = self.cache_manager.get(&cache_key) {
+            trace!(kid, "found public key in the cache, returning it");
 
-            trace!("inserting public key from auth service into cache");
+            return Ok(Some(public_key));
+        }
+
+        let mut jwks_fetched_at = self.jwks_fetched_at.lock().await;
+
+        // The key set might have been fetched while waiting for the lock
+        if let Some(public_key) = self.cache_manager.get(&cache_key) {
+            trace!(kid, "found public key in the cache, returning it");
+
+            return Ok(Some(public_key));
+        }
+
+        if jwks_fetched_at.is_some_and(|fetched_at| fetched_at.elapsed() < JWKS_REFETCH_INTERVAL) {
+            trace!(kid, "key id is not in the key set that was just fetched");
+
+            return Ok(None);
+        }
+
+        *jwks_fetched_at = Some(Instant::now());
+
+        let buf = self.get(".well-known/jwks.json").await?;
+        let jwks: JwkSet = serde_json::from_slice(&buf)?;
+
+        trace!("inserting key set from auth service into cache");
+
+        let mut found = None;
+
+        for jwk in jwks.keys {
+            let public_key = jwk.public_key()?;
+
+            // Keys never change for a key id, so they can be cached for longer
             self.cache_manager.insert(
-                PUBLIC_KEY_CACHE_KEY,
-                buf.to_vec(),
-                std::time::Duration::from_secs(60),
+                &format!("{PUBLIC_KEY_CACHE_KEY}.{}", jwk.kid),
+                public_key.clone(),
+                Duration::from_secs(60 * 60),
             );
 
-            Ok(buf.to_vec())
+            if jwk.kid == kid {
+                found = Some(public_key);
+            }
         }
+
+        Ok(found)
     }
 }
 
@@ -175,6 +303,12 @@ pub enum PublicKeyFnError {
 
     #[error("http error: {0}")]
     Http(#[from] http::Error),
+
+    #[error("invalid key set: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid public key in key set: {0}")]
+    Base64(#[from] base64::DecodeError),
 }
 
 /// Layer to validate JWT tokens with a public key. Valid claims are added to the request extension
@@ -233,7 +367,7 @@ pub enum JwtAuthenticationFuture<
         bearer: Authorization<Bearer>,
         request: Request<Body>,
         #[pin]
-        public_key_future: AsyncTraitFuture<Result<Vec<u8>, PubKeyFn::Error>>,
+        public_key_future: AsyncTraitFuture<Result<Option<Vec<u8>>, PubKeyFn::Error>>,
         service: TService,
     },
 }
@@ -275,7 +409,16 @@ where
 
                         Poll::Ready(Ok(response))
                     }
-                    Poll::Ready(Ok(public_key)) => {
+                    Poll::Ready(Ok(None)) => {
+                        error!("JWT is signed with an unknown key");
+                        let response = Response::builder()
+                            .status(StatusCode::UNAUTHORIZED)
+                            .body(Default::default())
+                            .unwrap();
+
+                        Poll::Ready(Ok(response))
+                    }
+                    Poll::Ready(Ok(Some(public_key))) => {
                         let claim_result = Claim::from_token(bearer.token().trim(), &public_key);
                         match claim_result {
                             Err(code) => {
@@ -337,7 +480,11 @@ where
         match req.headers().typed_try_get::<Authorization<Bearer>>() {
             Ok(Some(bearer)) => {
                 let public_key_fn = self.public_key_fn.clone();
-                let public_key_future = Box::pin(async move { public_key_fn.public_key().await });
+                let kid = jsonwebtoken::decode_header(bearer.token().trim())
+                    .ok()
+                    .and_then(|header| header.kid);
+                let public_key_future =
+                    Box::pin(async move { public_key_fn.public_key_for(kid).await });
                 Self::Future::HasTokenWaitingForPublicKey {
                     bearer,
                     request: req,
@@ -379,6 +526,11 @@ impl<S> Layer<S> for ScopedLayer {
     }
 }
 
+/// The scopes required by a route, which [ScopedLayer] sets on the requests it lets through. This
+/// allows handlers to also check the required scopes against other permissions of the caller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RequiredScopes(pub Vec<Scope>);
+
 #[derive(Clone)]
 pub struct Scoped<S> {
     inner: S,
@@ -404,7 +556,7 @@ where
         self.inner.poll_ready(cx)
     }
 
-    fn call(&mut self, req: Request<Body>) -> Self::Future {
+    fn call(&mut self, mut req: Request<Body>) -> Self::Future {
         let Some(claim) = req.extensions().get::<Claim>() else {
             error!("claim extension is not set");
 
@@ -416,6 +568,9 @@ where
             .iter()
             .all(|scope| claim.scopes.contains(scope))
         {
+            req.extensions_mut()
+                .insert(RequiredScopes(self.required.clone()));
+
             let response_future = self.inner.call(req);
             StatusCodeFuture::Poll(response_future)
         } else {
@@ -457,9 +612,23 @@ impl<B> VerifyClaim for tonic::Request<B> {
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::HashMap,
+        convert::Infallible,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use async_trait::async_trait;
     use axum::{routing::get, Extension, Router};
     use http::{Request, StatusCode};
-    use hyper::{body, Body};
+    use hyper::{
+        body,
+        service::{make_service_fn, service_fn},
+        Body, Server,
+    };
     use jsonwebtoken::EncodingKey;
     use ring::{
         hmac, rand,
@@ -468,9 +637,12 @@ mod tests {
     use serde_json::json;
     use tower::{ServiceBuilder, ServiceExt};
 
-    use crate::claims::{Claim, Scope};
+    use crate::claims::{AccountTier, Claim, Scope};
 
-    use super::{JwtAuthenticationLayer, ScopedLayer};
+    use super::{
+        AuthPublicKey, Jwk, JwkSet, JwtAuthenticationLayer, PublicKeyFn, ScopedLayer,
+        JWKS_REFETCH_INTERVAL,
+    };
 
     #[test]
     fn to_token_and_back() {
@@ -494,6 +666,33 @@ mod tests {
         assert_eq!(claim, new);
     }
 
+    #[test]
+    fn token_without_tier() {
+        let doc = signature::Ed25519KeyPair::generate_pkcs8(&rand::SystemRandom::new()).unwrap();
+        let encoding_key = EncodingKey::from_ed_der(doc.as_ref());
+
+        // Tokens issued before tiers were added to claims
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::EdDSA),
+            &json!({
+                "exp": 4102444800_u64,
+                "iat": 0,
+                "iss": "shuttle",
+                "nbf": 0,
+                "sub": "ferries",
+                "scopes": ["deployment"],
+            }),
+            &encoding_key,
+        )
+        .unwrap();
+
+        let pair = Ed25519KeyPair::from_pkcs8(doc.as_ref()).unwrap();
+        let claim = Claim::from_token(&token, pair.public_key().as_ref()).unwrap();
+
+        assert_eq!(claim.tier, AccountTier::Basic);
+        assert_eq!(claim.scopes, vec![Scope::Deployment]);
+    }
+
     #[tokio::test]
     async fn authorization_layer() {
         let claim = Claim::new(
@@ -590,6 +789,172 @@ mod tests {
         assert_eq!(&body[..], b"Hello, ferries");
     }
 
+    #[derive(Clone)]
+    struct KeySet(HashMap<String, Vec<u8>>);
+
+    #[async_trait]
+    impl PublicKeyFn for KeySet {
+        type Error = Infallible;
+
+        async fn public_key(&self) -> Result<Vec<u8>, Self::Error> {
+            unreachable!("all tokens in this test have a key id")
+        }
+
+        async fn public_key_for(
+            &self,
+            kid: Option<String>,
+        ) -> Result<Option<Vec<u8>>, Self::Error> {
+            Ok(kid.and_then(|kid| self.0.get(&kid).cloned()))
+        }
+    }
+
+    #[tokio::test]
+    async fn authorization_layer_selects_key_by_kid() {
+        let claim = Claim::new("ferries".to_string(), vec![Scope::Project]);
+
+        let mut keys = HashMap::new();
+        let mut encoding_keys = Vec::new();
+
+        for kid in ["previous", "current"] {
+            let doc =
+                signature::Ed25519KeyPair::generate_pkcs8(&rand::SystemRandom::new()).unwrap();
+            let pair = Ed25519KeyPair::from_pkcs8(doc.as_ref()).unwrap();
+
+            keys.insert(kid.to_string(), pair.public_key().as_ref().to_vec());
+            encoding_keys.push((kid, EncodingKey::from_ed_der(doc.as_ref())));
+        }
+
+        let router =
+            Router::new()
+                .route(
+                    "/",
+                    get(|Extension(claim): Extension<Claim>| async move {
+                        format!("Hello, {}", claim.sub)
+                    }),
+                )
+                .layer(JwtAuthenticationLayer::new(KeySet(keys)));
+
+        let send = |token: String| {
+            router.clone().oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header("authorization", format!("Bearer {token}"))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        for (kid, encoding_key) in &encoding_keys {
+            let token = claim
+                .clone()
+                .into_token_with_kid(encoding_key, kid)
+                .unwrap();
+            let response = send(token).await.unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        // A token signed with one key but claiming the id of another is rejected
+        let (_, encoding_key) = &encoding_keys[0];
+        let token = claim
+            .clone()
+            .into_token_with_kid(encoding_key, "current")
+            .unwrap();
+        let response = send(token).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Unknown keys are rejected
+        let token = claim
+            .clone()
+            .into_token_with_kid(encoding_key, "unknown")
+            .unwrap();
+        let response = send(token).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn auth_public_key_fetches_new_key_ids() {
+        let jwk = |kid: &str| {
+            let doc =
+                signature::Ed25519KeyPair::generate_pkcs8(&rand::SystemRandom::new()).unwrap();
+            let pair = Ed25519KeyPair::from_pkcs8(doc.as_ref()).unwrap();
+
+            Jwk::from_ed25519(kid.to_string(), pair.public_key().as_ref())
+        };
+
+        let jwks = Arc::new(Mutex::new(JwkSet {
+            keys: vec![jwk("previous")],
+        }));
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let make_service = {
+            let jwks = jwks.clone();
+            let fetches = fetches.clone();
+
+            make_service_fn(move |_| {
+                let jwks = jwks.clone();
+                let fetches = fetches.clone();
+
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                        fetches.fetch_add(1, Ordering::SeqCst);
+                        let body = serde_json::to_vec(&*jwks.lock().unwrap()).unwrap();
+
+                        async move { Ok::<_, Infallible>(http::Response::new(Body::from(body))) }
+                    }))
+                }
+            })
+        };
+        let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+        let auth_uri = format!("http://{}/", server.local_addr()).parse().unwrap();
+        tokio::spawn(server);
+
+        let public_key = AuthPublicKey::new(auth_uri);
+
+        let found = public_key
+            .public_key_for(Some("previous".to_string()))
+            .await;
+        assert!(found.unwrap().is_some());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // The keys are rotated right after the key set was fetched
+        jwks.lock().unwrap().keys.push(jwk("current"));
+
+        let found = public_key.public_key_for(Some("current".to_string())).await;
+        assert!(found.unwrap().is_none());
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // Then the new key id fetches the key set again
+        tokio::time::sleep(JWKS_REFETCH_INTERVAL).await;
+
+        let found = public_key.public_key_for(Some("current".to_string())).await;
+        assert!(found.unwrap().is_some());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+
+        // And known key ids come from the cache
+        let found = public_key
+            .public_key_for(Some("previous".to_string()))
+            .await;
+        assert!(found.unwrap().is_some());
+
+        let found = public_key.public_key_for(Some("unknown".to_string())).await;
+        assert!(found.unwrap().is_none());
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn jwk_round_trip() {
+        let doc = signature::Ed25519KeyPair::generate_pkcs8(&rand::SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(doc.as_ref()).unwrap();
+        let public_key = pair.public_key().as_ref();
+
+        let jwk = Jwk::from_ed25519("kid".to_string(), public_key);
+
+        assert_eq!(jwk.public_key().unwrap(), public_key);
+    }
+
     // Test changing to a symmetric key is not possible
     #[test]
     #[should_panic(expected = "value: 400")]
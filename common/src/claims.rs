@@ -20,6 +20,8 @@ use tower::{Layer, Service};
 use tracing::{error, trace, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
+use crate::quota::Quota;
+
 /// Minutes before a claim expires
 ///
 /// We don't use the convention of 5 minutes because builds can take longer than 5 minutes. When this happens, requests
@@ -29,8 +31,19 @@ const ISS: &str = "shuttle";
 
 /// The scope of operations that can be performed on shuttle
 /// Every scope defaults to read and will use a suffix for updating tasks
-#[derive(Clone, Debug, Deserialize, Serialize, Eq, PartialEq, EnumMessage)]
+#[derive(
+    Clone,
+    Debug,
+    Deserialize,
+    Serialize,
+    Eq,
+    PartialEq,
+    EnumMessage,
+    strum::Display,
+    strum::EnumString,
+)]
 #[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
 pub enum Scope {
     /// Read the details, such as status and address, of a deployment
     Deployment,
@@ -52,6 +65,7 @@ pub enum Scope {
 
     /// Create and delete projects
     #[serde(rename = "project_create")] // compatibility
+    #[strum(to_string = "project_create", serialize = "project_write")]
     ProjectWrite,
 
     /// Get the resources for a project
@@ -157,6 +171,79 @@ impl Default for ScopeBuilder {
     }
 }
 
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Serialize,
+    Eq,
+    PartialEq,
+    Hash,
+    strum::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[cfg_attr(feature = "persist", derive(sqlx::Type))]
+#[cfg_attr(feature = "persist", sqlx(rename_all = "lowercase"))]
+pub enum AccountTier {
+    #[default]
+    Basic,
+    // A basic user that is pending a payment on the backend.
+    PendingPaymentPro,
+    Pro,
+    Team,
+    Admin,
+    Deployer,
+}
+
+impl AccountTier {
+    /// Limits on the projects, deployments and resources of accounts on this tier
+    pub fn quota(&self) -> Quota {
+        match self {
+            Self::Basic | Self::PendingPaymentPro => Quota {
+                max_projects: Some(3),
+                max_concurrent_builds: Some(1),
+                max_databases: Some(2),
+                max_idle_minutes: Some(60),
+            },
+            Self::Pro => Quota {
+                max_projects: Some(15),
+                max_concurrent_builds: Some(3),
+                max_databases: Some(10),
+                max_idle_minutes: None,
+            },
+            Self::Team => Quota {
+                max_projects: Some(30),
+                max_concurrent_builds: Some(5),
+                max_databases: Some(20),
+                max_idle_minutes: None,
+            },
+            Self::Admin | Self::Deployer => Quota::UNLIMITED,
+        }
+    }
+}
+
+impl From<AccountTier> for Vec<Scope> {
+    fn from(tier: AccountTier) -> Self {
+        let mut builder = ScopeBuilder::new();
+
+        if tier == AccountTier::Admin {
+            builder = builder.with_admin()
+        }
+
+        if tier == AccountTier::Deployer {
+            builder = builder.with_deploy_rights();
+        } else {
+            builder = builder.with_basic();
+        }
+
+        builder.build()
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, Eq, PartialEq)]
 pub struct Claim {
     /// Expiration time (as UTC timestamp).
@@ -171,6 +258,13 @@ pub struct Claim {
     pub sub: String,
     /// Scopes this token can access
     pub scopes: Vec<Scope>,
+    /// The account tier of the subject. Tokens issued before tiers were added to them get the
+    /// lowest tier.
+    #[serde(default)]
+    pub tier: AccountTier,
+    /// The only project this token can access, when it was restricted to one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
     /// The original token that was parsed
     pub(crate) token: Option<String>,
 }
@@ -188,20 +282,50 @@ impl Claim {
             nbf: iat.timestamp() as usize,
             sub,
             scopes,
+            tier: Default::default(),
+            project: None,
             token: None,
         }
     }
 
+    /// Set the account tier of the subject on this claim
+    pub fn with_tier(mut self, tier: AccountTier) -> Self {
+        self.tier = tier;
+        self
+    }
+
+    /// Restrict this claim to a single project
+    pub fn with_project(mut self, project: Option<String>) -> Self {
+        self.project = project;
+        self
+    }
+
     pub fn into_token(self, encoding_key: &EncodingKey) -> Result<String, StatusCode> {
+        self.into_token_with_header(Header::new(jsonwebtoken::Algorithm::EdDSA), encoding_key)
+    }
+
+    /// Convert this claim to a token, setting the id of the signing key in the token header
+    /// so verifiers can pick the right public key when keys are rotated
+    pub fn into_token_with_kid(
+        self,
+        encoding_key: &EncodingKey,
+        kid: &str,
+    ) -> Result<String, StatusCode> {
+        let mut header = Header::new(jsonwebtoken::Algorithm::EdDSA);
+        header.kid = Some(kid.to_string());
+
+        self.into_token_with_header(header, encoding_key)
+    }
+
+    fn into_token_with_header(
+        self,
+        header: Header,
+        encoding_key: &EncodingKey,
+    ) -> Result<String, StatusCode> {
         if let Some(token) = self.token {
             Ok(token)
         } else {
-            encode(
-                &Header::new(jsonwebtoken::Algorithm::EdDSA),
-                &self,
-                encoding_key,
-            )
-            .map_err(|err| {
+            encode(&header, &self, encoding_key).map_err(|err| {
                 error!(
                     error = &err as &dyn std::error::Error,
                     "failed to convert claim to token"
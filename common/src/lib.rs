@@ -18,6 +18,7 @@ pub use log::LogItem;
 pub mod models;
 #[cfg(feature = "service")]
 pub mod project;
+pub mod quota;
 pub mod resource;
 pub mod secrets;
 pub use secrets::{Secret, SecretStore};
@@ -0,0 +1,70 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use super::{deployment, project, secret};
+
+/// A copy of the data kept for an account, for it to take along
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::account::ExportBundle))]
+pub struct ExportBundle {
+    pub account_name: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub exported_at: DateTime<Utc>,
+    /// Projects owned by the account, but not those of its teams
+    pub projects: Vec<ProjectExport>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::account::ProjectExport))]
+pub struct ProjectExport {
+    #[cfg_attr(feature = "openapi", schema(value_type = shuttle_common::models::project::Response))]
+    pub project: project::Response,
+    /// The deployments of the project, if it was running to get them from
+    #[cfg_attr(feature = "openapi", schema(value_type = Option<Vec<shuttle_common::models::deployment::Response>>))]
+    pub deployments: Option<Vec<deployment::Response>>,
+    /// The secrets of the project, if it was running to get them from
+    pub secrets: Option<Vec<SecretMetadata>>,
+}
+
+/// A secret of a project without its value
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::account::SecretMetadata))]
+pub struct SecretMetadata {
+    pub key: String,
+    #[cfg_attr(feature = "openapi", schema(value_type = KnownFormat::DateTime))]
+    pub last_update: DateTime<Utc>,
+}
+
+impl From<secret::Response> for SecretMetadata {
+    fn from(secret: secret::Response) -> Self {
+        Self {
+            key: secret.key,
+            last_update: secret.last_update,
+        }
+    }
+}
+
+/// The outcome of deleting the projects of an account
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::account::DeleteResponse))]
+pub struct DeleteResponse {
+    /// Projects that were deleted
+    pub deleted: Vec<String>,
+    /// Projects that could not be cleaned up and are kept, so the delete can be tried again
+    pub failed: Vec<ProjectFailure>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::account::ProjectFailure))]
+pub struct ProjectFailure {
+    pub project_name: String,
+    pub error: String,
+}
@@ -0,0 +1,83 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment, Color,
+    ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// An action taken on a project, deployment or account
+#[derive(Clone, Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::audit::Response))]
+pub struct Response {
+    /// Account that took the action, if it could be identified
+    pub actor: Option<String>,
+    /// The method and route of the request, such as `DELETE /projects/:project_name`
+    pub action: String,
+    /// The path the action was taken on
+    pub target: String,
+    pub source_ip: Option<String>,
+    /// Status code of the response to the action
+    pub outcome: u16,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub fn get_audit_table(events: &[Response]) -> String {
+    if events.is_empty() {
+        return "No actions have been recorded for this account\n"
+            .yellow()
+            .bold()
+            .to_string();
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(vec![
+            Cell::new("Time")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Actor")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Action")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Target")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Source IP")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Outcome")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+        ]);
+
+    for event in events {
+        let outcome_color = if event.outcome < 400 {
+            Color::Green
+        } else {
+            Color::Red
+        };
+
+        table.add_row(vec![
+            Cell::new(event.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)),
+            Cell::new(event.actor.as_deref().unwrap_or("unknown")),
+            Cell::new(&event.action),
+            Cell::new(&event.target),
+            Cell::new(event.source_ip.as_deref().unwrap_or("")),
+            Cell::new(event.outcome)
+                .fg(outcome_color)
+                .set_alignment(CellAlignment::Center),
+        ]);
+    }
+
+    format!("\nThese actions were recorded\n{table}\n")
+}
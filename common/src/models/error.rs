@@ -5,16 +5,36 @@ use http::StatusCode;
 use serde::{Deserialize, Serialize};
 use tracing::{error, warn};
 
+use crate::quota::QuotaExceeded;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct ApiError {
     pub message: String,
     pub status_code: u16,
+    /// The [ErrorKind] this error was created from, so clients can tell errors apart without
+    /// matching on the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
 }
 
 impl ApiError {
     pub fn status(&self) -> StatusCode {
         StatusCode::from_u16(self.status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
     }
+
+    /// The [ErrorKind] of this error, if it has one this client knows about
+    pub fn error_kind(&self) -> Option<ErrorKindDiscriminants> {
+        self.kind.as_deref()?.parse().ok()
+    }
+
+    /// An error with its own message and status code, which still tells clients its kind
+    pub fn with_kind(message: impl ToString, status_code: StatusCode, kind: ErrorKind) -> Self {
+        Self {
+            message: message.to_string(),
+            status_code: status_code.as_u16(),
+            kind: Some(kind.to_string()),
+        }
+    }
 }
 
 impl Display for ApiError {
@@ -30,7 +50,10 @@ impl Display for ApiError {
 
 impl std::error::Error for ApiError {}
 
-#[derive(Debug, Clone, PartialEq, Eq, strum::Display)]
+/// The kind of an [ApiError]. Its variant name is sent as [ApiError::kind], and can be read back
+/// with [ApiError::error_kind].
+#[derive(Debug, Clone, PartialEq, Eq, strum::Display, strum::EnumDiscriminants)]
+#[strum_discriminants(derive(strum::EnumString))]
 pub enum ErrorKind {
     KeyMissing,
     BadHost,
@@ -50,18 +73,31 @@ pub enum ErrorKind {
     ProjectUnavailable,
     ProjectHasResources(Vec<String>),
     ProjectHasRunningDeployment,
+    /// Contains the quota of the caller's account tier that the request would exceed
+    QuotaExceeded(QuotaExceeded),
     CustomDomainNotFound,
     InvalidCustomDomain,
+    TeamNotFound,
+    InvalidTeamName,
+    TeamAlreadyExists,
     CustomDomainAlreadyExists,
     InvalidOperation,
     Internal,
     NotReady,
     ServiceUnavailable,
     DeleteProjectFailed,
+    TokenNotFound,
+    TokenAlreadyExists,
+    DeviceCodeNotFound,
+    AccountSuspended,
+    /// A service, deployment or resource of a project could not be found
+    NotFound,
+    MetricsDisabled,
 }
 
 impl From<ErrorKind> for ApiError {
     fn from(kind: ErrorKind) -> Self {
+        let name = Some(kind.to_string());
         let (status, error_message) = match kind {
             ErrorKind::Internal => (StatusCode::INTERNAL_SERVER_ERROR, "internal server error"),
             ErrorKind::KeyMissing => (StatusCode::UNAUTHORIZED, "request is missing a key"),
@@ -91,6 +127,14 @@ impl From<ErrorKind> for ApiError {
                 return Self {
                     message: format!("Project has resources: {}. Use `cargo shuttle resource list` and `cargo shuttle resource delete <type>` to delete them.", resources),
                     status_code: StatusCode::FORBIDDEN.as_u16(),
+                    kind: name,
+                }
+            }
+            ErrorKind::QuotaExceeded(quota) => {
+                return Self {
+                    message: format!("Quota exceeded: {quota}. Upgrade your account tier to raise this limit."),
+                    status_code: StatusCode::FORBIDDEN.as_u16(),
+                    kind: name,
                 }
             }
             ErrorKind::InvalidProjectName => (
@@ -111,19 +155,30 @@ impl From<ErrorKind> for ApiError {
                 return Self {
                     message,
                     status_code: StatusCode::BAD_REQUEST.as_u16(),
+                    kind: name,
                 }
             }
             ErrorKind::InvalidCustomDomain => (StatusCode::BAD_REQUEST, "invalid custom domain"),
+            ErrorKind::TeamNotFound => (StatusCode::NOT_FOUND, "team not found. Make sure you are a member of this team."),
+            ErrorKind::InvalidTeamName => (StatusCode::BAD_REQUEST, "invalid team name. Team names can only contain lowercase alphanumeric characters and `-`, and be shorter than 63 characters."),
+            ErrorKind::TeamAlreadyExists => (StatusCode::BAD_REQUEST, "a team with the same name already exists"),
             ErrorKind::CustomDomainNotFound => (StatusCode::NOT_FOUND, "custom domain not found"),
             ErrorKind::CustomDomainAlreadyExists => (StatusCode::BAD_REQUEST, "custom domain already in use"),
             ErrorKind::Unauthorized => (StatusCode::UNAUTHORIZED, "unauthorized"),
             ErrorKind::Forbidden => (StatusCode::FORBIDDEN, "forbidden"),
             ErrorKind::NotReady => (StatusCode::INTERNAL_SERVER_ERROR, "service not ready"),
             ErrorKind::DeleteProjectFailed => (StatusCode::INTERNAL_SERVER_ERROR, "deleting project failed"),
+            ErrorKind::TokenNotFound => (StatusCode::NOT_FOUND, "API token could not be found"),
+            ErrorKind::TokenAlreadyExists => (StatusCode::BAD_REQUEST, "an API token with this name already exists"),
+            ErrorKind::DeviceCodeNotFound => (StatusCode::NOT_FOUND, "device code could not be found or has expired"),
+            ErrorKind::AccountSuspended => (StatusCode::FORBIDDEN, "this account has been suspended"),
+            ErrorKind::NotFound => (StatusCode::NOT_FOUND, "not found"),
+            ErrorKind::MetricsDisabled => (StatusCode::NOT_FOUND, "metrics are not enabled, turn on the `metrics` feature of `shuttle-runtime`"),
         };
         Self {
             message: error_message.to_string(),
             status_code: status.as_u16(),
+            kind: name,
         }
     }
 }
@@ -162,6 +217,7 @@ impl From<StatusCode> for ApiError {
         Self {
             message: message.to_string(),
             status_code: code.as_u16(),
+            kind: None,
         }
     }
 }
@@ -1,3 +1,5 @@
+pub mod account;
+pub mod audit;
 pub mod deployment;
 pub mod error;
 pub mod project;
@@ -5,6 +7,7 @@ pub mod resource;
 pub mod secret;
 pub mod service;
 pub mod stats;
+pub mod team;
 pub mod user;
 
 use anyhow::{Context, Result};
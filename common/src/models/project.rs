@@ -34,6 +34,15 @@ pub struct Response {
     #[cfg_attr(feature = "openapi", schema(value_type = shuttle_common::models::project::State))]
     pub state: State,
     pub idle_minutes: Option<u64>,
+    /// Resource limits currently applied to the project's container
+    #[serde(default)]
+    pub container_limits: Option<ContainerLimits>,
+    /// Team the project belongs to, if it is not owned by a single account
+    #[serde(default)]
+    pub team: Option<String>,
+    /// Why the project stopped, when it was not stopped on request
+    #[serde(default)]
+    pub stop_reason: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, EnumString)]
@@ -90,7 +99,13 @@ impl Display for Response {
                 .to_string()
                 // Unwrap is safe because Color::from_str returns the color white if the argument is not a Color.
                 .with(crossterm::style::Color::from_str(self.state.get_color()).unwrap())
-        )
+        )?;
+
+        if let Some(reason) = &self.stop_reason {
+            write!(f, "\n\treason: {reason}")?;
+        }
+
+        Ok(())
     }
 }
 
@@ -177,6 +192,70 @@ impl State {
     }
 }
 
+/// Resource limits applied to the container of a project
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::project::ContainerLimits))]
+// Use default for backward compatibility with limits stored before a limit was added
+#[serde(default)]
+pub struct ContainerLimits {
+    /// Hard memory limit in bytes
+    pub memory_bytes: i64,
+    /// Relative CPU weight of the container against other containers
+    pub cpu_shares: i64,
+    /// CPU time the container can use in every period of 100ms, in microseconds
+    pub cpu_quota: i64,
+    /// Maximum number of processes and threads in the container
+    pub pids_limit: i64,
+}
+
+impl Default for ContainerLimits {
+    fn default() -> Self {
+        Self {
+            memory_bytes: 6442450000, // 6 GiB
+            cpu_shares: 1024,
+            cpu_quota: 400000, // 4 CPUs
+            pids_limit: 4096,
+        }
+    }
+}
+
+impl ContainerLimits {
+    /// Apply the overrides that are set on top of these limits
+    pub fn with_overrides(self, overrides: &ContainerLimitsOverride) -> Self {
+        Self {
+            memory_bytes: overrides.memory_bytes.unwrap_or(self.memory_bytes),
+            cpu_shares: overrides.cpu_shares.unwrap_or(self.cpu_shares),
+            cpu_quota: overrides.cpu_quota.unwrap_or(self.cpu_quota),
+            pids_limit: overrides.pids_limit.unwrap_or(self.pids_limit),
+        }
+    }
+}
+
+impl Display for ContainerLimits {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "memory: {} MiB, cpu shares: {}, cpu quota: {}us, pids: {}",
+            self.memory_bytes / (1024 * 1024),
+            self.cpu_shares,
+            self.cpu_quota,
+            self.pids_limit
+        )
+    }
+}
+
+/// Per project overrides of the container limits of its account tier
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::project::ContainerLimitsOverride))]
+pub struct ContainerLimitsOverride {
+    pub memory_bytes: Option<i64>,
+    pub cpu_shares: Option<i64>,
+    pub cpu_quota: Option<i64>,
+    pub pids_limit: Option<i64>,
+}
+
 /// Config when creating a new project
 #[derive(Deserialize, Serialize)]
 pub struct Config {
@@ -214,6 +293,7 @@ pub fn get_projects_table(projects: &Vec<Response>, page: u32, raw: bool) -> Str
                 .set_header(vec![
                     Cell::new("Project Name").set_alignment(CellAlignment::Left),
                     Cell::new("Status").set_alignment(CellAlignment::Left),
+                    Cell::new("Team").set_alignment(CellAlignment::Left),
                 ]);
         } else {
             table
@@ -227,12 +307,21 @@ pub fn get_projects_table(projects: &Vec<Response>, page: u32, raw: bool) -> Str
                     Cell::new("Status")
                         .set_alignment(CellAlignment::Center)
                         .add_attribute(Attribute::Bold),
+                    Cell::new("Team")
+                        .set_alignment(CellAlignment::Center)
+                        .add_attribute(Attribute::Bold),
                 ]);
         }
 
         for project in projects.iter() {
+            let team = project.team.as_deref().unwrap_or_default();
+
             if raw {
-                table.add_row(vec![Cell::new(&project.name), Cell::new(&project.state)]);
+                table.add_row(vec![
+                    Cell::new(&project.name),
+                    Cell::new(&project.state),
+                    Cell::new(team),
+                ]);
             } else {
                 table.add_row(vec![
                     Cell::new(&project.name),
@@ -240,6 +329,7 @@ pub fn get_projects_table(projects: &Vec<Response>, page: u32, raw: bool) -> Str
                         // Unwrap is safe because Color::from_str returns the color white if the argument is not a Color.
                         .fg(Color::from_str(project.state.get_color()).unwrap())
                         .set_alignment(CellAlignment::Center),
+                    Cell::new(team).set_alignment(CellAlignment::Center),
                 ]);
             }
         }
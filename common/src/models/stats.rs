@@ -1,3 +1,6 @@
+use std::fmt::{Display, Formatter};
+
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 #[cfg(feature = "openapi")]
 use utoipa::ToSchema;
@@ -8,6 +11,9 @@ use uuid::Uuid;
 #[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::stats::LoadRequest))]
 pub struct LoadRequest {
     pub id: Uuid,
+    /// Project the build is for, used to meter build minutes
+    #[serde(default)]
+    pub project_name: Option<String>,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -17,3 +23,47 @@ pub struct LoadResponse {
     pub builds_count: usize,
     pub has_capacity: bool,
 }
+
+/// Usage of a project over a period of time
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::stats::ProjectUsage))]
+pub struct ProjectUsage {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Number of requests proxied to the project
+    pub requests: u64,
+    /// Number of bytes sent in the responses of the project
+    pub response_bytes: u64,
+    /// Number of seconds the project container was running
+    pub uptime_seconds: u64,
+    /// Number of seconds spent building deployments of the project
+    pub build_seconds: u64,
+}
+
+impl Display for ProjectUsage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Usage from {} to {}",
+            self.from.format("%Y-%m-%d %H:%M UTC"),
+            self.to.format("%Y-%m-%d %H:%M UTC")
+        )?;
+        writeln!(f, "  Requests:       {}", self.requests)?;
+        writeln!(
+            f,
+            "  Bandwidth:      {:.2} MiB",
+            self.response_bytes as f64 / (1024.0 * 1024.0)
+        )?;
+        writeln!(
+            f,
+            "  Uptime:         {:.1} hours",
+            self.uptime_seconds as f64 / 3600.0
+        )?;
+        write!(
+            f,
+            "  Build minutes:  {:.1}",
+            self.build_seconds as f64 / 60.0
+        )
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt::{Display, Formatter};
+
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment,
+    ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+/// Role of an account in a team, deciding what it can do with the team's projects
+#[derive(
+    Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, strum::Display, strum::EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::team::TeamRole))]
+pub enum TeamRole {
+    /// Can do anything with the team's projects, and manage the team's members
+    Owner,
+    /// Can deploy to the team's projects, but not create or delete them
+    Deployer,
+    /// Can only view the status and logs of the team's projects
+    Viewer,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::team::Response))]
+pub struct Response {
+    pub name: String,
+    /// Role of the calling account in this team
+    pub role: TeamRole,
+    /// Members of the team. Only set when getting a single team
+    #[serde(default)]
+    pub members: Vec<Member>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::team::Member))]
+pub struct Member {
+    pub account_name: String,
+    pub role: TeamRole,
+}
+
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::team::MemberRequest))]
+pub struct MemberRequest {
+    pub role: TeamRole,
+}
+
+/// Request to transfer the ownership of a project to a team
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "openapi", schema(as = shuttle_common::models::team::TransferRequest))]
+pub struct TransferRequest {
+    /// Team to transfer the project to. Without one, the project goes back to the account which
+    /// created it.
+    #[serde(default)]
+    pub team_name: Option<String>,
+}
+
+impl Display for Response {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            r#"Team "{}" (your role: {})"#,
+            self.name.as_str().bold(),
+            self.role
+        )?;
+
+        for member in &self.members {
+            writeln!(f, "  {}: {}", member.account_name, member.role)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub fn get_teams_table(teams: &[Response]) -> String {
+    if teams.is_empty() {
+        return "This account is not a member of any team\n"
+            .yellow()
+            .bold()
+            .to_string();
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(vec![
+            Cell::new("Team Name")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Role")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+        ]);
+
+    for team in teams {
+        table.add_row(vec![
+            Cell::new(&team.name),
+            Cell::new(team.role).set_alignment(CellAlignment::Center),
+        ]);
+    }
+
+    format!("\nThis account is a member of these teams\n{table}\n")
+}
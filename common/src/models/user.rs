@@ -1,3 +1,9 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use comfy_table::{
+    modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment,
+    ContentArrangement, Table,
+};
+use crossterm::style::Stylize;
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Serialize)]
@@ -6,4 +12,114 @@ pub struct Response {
     pub key: String,
     pub account_tier: String,
     pub subscription_id: Option<String>,
+    #[serde(default)]
+    pub suspended: bool,
+    /// When the account loses its tier, if a subscription payment failed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grace_period_ends_at: Option<DateTime<Utc>>,
+}
+
+/// Request to create a new named API token
+#[derive(Deserialize, Serialize)]
+pub struct TokenRequest {
+    pub name: String,
+    /// Scopes the token is limited to, such as `deployment_push` or `logs`
+    pub scopes: Vec<String>,
+    /// The only project the token can be used on
+    pub project: Option<String>,
+    /// When the token stops being valid. Tokens without an expiry are valid until revoked
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct TokenResponse {
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub project: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// The secret token. This is only returned when the token is created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+}
+
+/// A code for a device, such as the CLI, to log in with once a user approves it in the browser
+#[derive(Deserialize, Serialize)]
+pub struct DeviceCodeResponse {
+    /// Secret the device polls for its API token with
+    pub device_code: String,
+    /// Code the user enters at the verification URI to approve the login
+    pub user_code: String,
+    pub verification_uri: String,
+    /// Verification URI with the user code filled in already
+    pub verification_uri_complete: String,
+    /// Seconds until the codes expire
+    pub expires_in: u64,
+    /// Seconds to wait between polls
+    pub interval: u64,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeviceApproveRequest {
+    pub user_code: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeviceTokenRequest {
+    pub device_code: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DeviceTokenResponse {
+    /// The API token for the device. This is `None` while the login waits for approval
+    pub key: Option<String>,
+}
+
+pub fn get_tokens_table(tokens: &[TokenResponse]) -> String {
+    if tokens.is_empty() {
+        return "No API tokens have been created for this account\n"
+            .yellow()
+            .bold()
+            .to_string();
+    }
+
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_content_arrangement(ContentArrangement::DynamicFullWidth)
+        .set_header(vec![
+            Cell::new("Name")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Scopes")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Project")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Expires")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+            Cell::new("Created")
+                .set_alignment(CellAlignment::Center)
+                .add_attribute(Attribute::Bold),
+        ]);
+
+    for token in tokens {
+        table.add_row(vec![
+            Cell::new(&token.name),
+            Cell::new(token.scopes.join(", ")),
+            Cell::new(token.project.as_deref().unwrap_or("all")),
+            Cell::new(
+                token
+                    .expires_at
+                    .map(|expires_at| expires_at.to_rfc3339_opts(SecondsFormat::Secs, true))
+                    .unwrap_or_else(|| "never".to_string()),
+            ),
+            Cell::new(token.created_at.to_rfc3339_opts(SecondsFormat::Secs, true)),
+        ]);
+    }
+
+    format!("\nThese API tokens are linked to this account\n{table}\n")
 }
@@ -0,0 +1,140 @@
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+/// Limits on what an account is allowed to create. A limit of `None` means it is unlimited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quota {
+    /// Number of projects an account can own
+    pub max_projects: Option<u64>,
+    /// Number of deployments of a project that can be building at the same time
+    pub max_concurrent_builds: Option<u64>,
+    /// Number of databases a project can have
+    pub max_databases: Option<u64>,
+    /// Longest a project can be inactive before it is idled. Projects that never idle
+    /// are only allowed when this is unlimited.
+    pub max_idle_minutes: Option<u64>,
+}
+
+impl Quota {
+    pub const UNLIMITED: Self = Self {
+        max_projects: None,
+        max_concurrent_builds: None,
+        max_databases: None,
+        max_idle_minutes: None,
+    };
+
+    pub fn check_projects(&self, count: u64) -> Result<(), QuotaExceeded> {
+        check_count(QuotaKind::Projects, self.max_projects, count)
+    }
+
+    pub fn check_concurrent_builds(&self, count: u64) -> Result<(), QuotaExceeded> {
+        check_count(
+            QuotaKind::ConcurrentBuilds,
+            self.max_concurrent_builds,
+            count,
+        )
+    }
+
+    pub fn check_databases(&self, count: u64) -> Result<(), QuotaExceeded> {
+        check_count(QuotaKind::Databases, self.max_databases, count)
+    }
+
+    /// Check the idle minutes of a project, where `0` means the project never idles
+    pub fn check_idle_minutes(&self, idle_minutes: u64) -> Result<(), QuotaExceeded> {
+        match self.max_idle_minutes {
+            Some(limit) if idle_minutes == 0 || idle_minutes > limit => Err(QuotaExceeded {
+                kind: QuotaKind::IdleMinutes,
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Check that one more item can be created when `count` already exist
+fn check_count(kind: QuotaKind, limit: Option<u64>, count: u64) -> Result<(), QuotaExceeded> {
+    match limit {
+        Some(limit) if count >= limit => Err(QuotaExceeded { kind, limit }),
+        _ => Ok(()),
+    }
+}
+
+/// The quota that was hit by a request
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum QuotaKind {
+    Projects,
+    TeamProjects,
+    ConcurrentBuilds,
+    Databases,
+    IdleMinutes,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct QuotaExceeded {
+    pub kind: QuotaKind,
+    pub limit: u64,
+}
+
+impl Display for QuotaExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let limit = self.limit;
+
+        match self.kind {
+            QuotaKind::Projects => write!(f, "your account tier allows at most {limit} projects"),
+            QuotaKind::TeamProjects => write!(f, "a team can have at most {limit} projects"),
+            QuotaKind::ConcurrentBuilds => write!(
+                f,
+                "your account tier allows at most {limit} deployments to be building at the same time"
+            ),
+            QuotaKind::Databases => write!(
+                f,
+                "your account tier allows at most {limit} databases per project"
+            ),
+            QuotaKind::IdleMinutes => write!(
+                f,
+                "your account tier requires projects to idle after at most {limit} minutes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts() {
+        let quota = Quota {
+            max_projects: Some(2),
+            ..Quota::UNLIMITED
+        };
+
+        assert_eq!(quota.check_projects(1), Ok(()));
+        assert_eq!(
+            quota.check_projects(2),
+            Err(QuotaExceeded {
+                kind: QuotaKind::Projects,
+                limit: 2
+            })
+        );
+        assert_eq!(quota.check_databases(100), Ok(()));
+    }
+
+    #[test]
+    fn idle_minutes() {
+        let quota = Quota {
+            max_idle_minutes: Some(60),
+            ..Quota::UNLIMITED
+        };
+
+        assert_eq!(quota.check_idle_minutes(30), Ok(()));
+        assert!(quota.check_idle_minutes(61).is_err());
+        assert!(quota.check_idle_minutes(0).is_err());
+        assert_eq!(Quota::UNLIMITED.check_idle_minutes(0), Ok(()));
+    }
+}
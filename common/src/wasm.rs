@@ -1,6 +1,12 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read, Write};
+
 use http::{HeaderMap, Method, Request, Response, StatusCode, Uri, Version};
 use rmps::Serializer;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::database;
 
 extern crate rmp_serde as rmps;
 
@@ -102,6 +108,70 @@ impl ResponseWrapper {
     }
 }
 
+/// Write a frame of a body streamed between a shuttle-next module and its host: the length of the
+/// frame as a big-endian `u32`, followed by its bytes. An empty frame marks the end of the body.
+pub fn write_frame(writer: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(frame.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large"))?;
+
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(frame)
+}
+
+/// Read a frame written by [write_frame]
+pub fn read_frame(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0; 4];
+    reader.read_exact(&mut len)?;
+
+    let mut frame = vec![0; u32::from_be_bytes(len) as usize];
+    reader.read_exact(&mut frame)?;
+
+    Ok(frame)
+}
+
+/// Where the directory of the persist resource is mounted in a shuttle-next module
+pub const PERSIST_DIR: &str = "/persist";
+
+/// A call from a shuttle-next module to the host running it, to use one of its resources
+#[derive(Serialize, Deserialize, Debug)]
+pub enum HostRequest {
+    /// Get the secrets of the service
+    Secrets,
+    /// Get the metadata of the deployment
+    Metadata,
+    /// Run a query on a database and get the rows it returns as JSON objects. Statements which
+    /// return rows, like `INSERT ... RETURNING`, can be used too.
+    Query {
+        database: database::Type,
+        sql: String,
+        params: Vec<Value>,
+    },
+    /// Run a statement on a database and get the number of rows it affected
+    Execute {
+        database: database::Type,
+        sql: String,
+        params: Vec<Value>,
+    },
+}
+
+/// The answer of the host to a [HostRequest]
+#[derive(Serialize, Deserialize, Debug)]
+pub enum HostResponse {
+    Secrets(BTreeMap<String, String>),
+    Metadata(MetadataWrapper),
+    Rows(Vec<Value>),
+    RowsAffected(u64),
+    Error(String),
+}
+
+/// The metadata of a deployment, as sent to a shuttle-next module
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MetadataWrapper {
+    pub env: String,
+    pub project_name: String,
+    pub service_name: String,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,4 +226,50 @@ mod tests {
         assert_eq!(back.status, StatusCode::NOT_MODIFIED);
         assert_eq!(back.version, Version::HTTP_11);
     }
+
+    #[test]
+    fn host_request_roundtrip() {
+        let request = HostRequest::Query {
+            database: database::Type::Shared(database::SharedEngine::Postgres),
+            sql: "SELECT * FROM todos WHERE id = $1".to_string(),
+            params: vec![serde_json::json!(5)],
+        };
+
+        let rmp = rmps::to_vec(&request).unwrap();
+
+        let back: HostRequest = rmps::from_slice(&rmp).unwrap();
+
+        let HostRequest::Query {
+            database,
+            sql,
+            params,
+        } = back
+        else {
+            panic!("expected a query");
+        };
+        assert_eq!(
+            database,
+            database::Type::Shared(database::SharedEngine::Postgres)
+        );
+        assert_eq!(sql, "SELECT * FROM todos WHERE id = $1");
+        assert_eq!(params, vec![serde_json::json!(5)]);
+    }
+
+    #[test]
+    fn frame_roundtrip() {
+        let mut stream = Vec::new();
+        write_frame(&mut stream, b"hello").unwrap();
+        write_frame(&mut stream, b"world").unwrap();
+        write_frame(&mut stream, &[]).unwrap();
+
+        let mut reader = stream.as_slice();
+
+        assert_eq!(read_frame(&mut reader).unwrap(), b"hello");
+        assert_eq!(read_frame(&mut reader).unwrap(), b"world");
+        assert!(read_frame(&mut reader).unwrap().is_empty());
+        assert_eq!(
+            read_frame(&mut reader).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
 }
@@ -63,6 +63,10 @@ pub struct Args {
     #[clap(long, default_value = "http://builder:8000")]
     pub builder_uri: Endpoint,
 
+    /// Address of the OTLP collector to export the traces of the services of this project to
+    #[clap(long)]
+    pub user_otlp_endpoint: Option<Uri>,
+
     /// Uri to folder to store all artifacts
     #[clap(long, default_value = "/tmp")]
     pub artifacts_path: PathBuf,
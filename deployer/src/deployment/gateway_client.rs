@@ -3,6 +3,7 @@ use opentelemetry::global;
 use opentelemetry_http::HeaderInjector;
 use serde::{de::DeserializeOwned, Serialize};
 use shuttle_common::models::stats;
+use shuttle_common::project::ProjectName;
 use thiserror::Error;
 use tracing::{trace, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -33,13 +34,15 @@ pub trait BuildQueueClient: Clone + Send + Sync + 'static {
 pub struct GatewayClient {
     client: Client<HttpConnector>,
     base: Uri,
+    project_name: ProjectName,
 }
 
 impl GatewayClient {
-    pub fn new(uri: Uri) -> Self {
+    pub fn new(uri: Uri, project_name: ProjectName) -> Self {
         Self {
             client: Client::new(),
             base: uri,
+            project_name,
         }
     }
 
@@ -102,14 +105,20 @@ impl GatewayClient {
 #[async_trait::async_trait]
 impl BuildQueueClient for GatewayClient {
     async fn get_slot(&self, id: Uuid) -> Result<bool, Error> {
-        let body = stats::LoadRequest { id };
+        let body = stats::LoadRequest {
+            id,
+            project_name: Some(self.project_name.to_string()),
+        };
         let load: stats::LoadResponse = self.post("stats/load", Some(body)).await?;
 
         Ok(load.has_capacity)
     }
 
     async fn release_slot(&self, id: Uuid) -> Result<(), Error> {
-        let body = stats::LoadRequest { id };
+        let body = stats::LoadRequest {
+            id,
+            project_name: Some(self.project_name.to_string()),
+        };
         let _load: stats::LoadResponse = self.delete("stats/load", Some(body)).await?;
 
         Ok(())
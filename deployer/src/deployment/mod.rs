@@ -1,6 +1,7 @@
 use std::{
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
 use shuttle_common::log::LogRecorder;
@@ -30,6 +31,8 @@ pub use run::{ActiveDeploymentsGetter, Built};
 
 const QUEUE_BUFFER_SIZE: usize = 100;
 const RUN_BUFFER_SIZE: usize = 100;
+/// How long a runtime has to send the metrics of its deployment
+const METRICS_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct DeploymentManagerBuilder<LR, SR, ADG, DU, SG, RM, QC> {
     build_log_recorder: Option<LR>,
@@ -280,6 +283,23 @@ impl DeploymentManager {
         self.runtime_manager.lock().await.kill(&id).await;
     }
 
+    /// Get the metrics of a running deployment, if its service enabled them. The runtime manager
+    /// is only locked to get the client of the runtime, so a slow runtime does not hold up
+    /// starting and stopping the others.
+    pub async fn metrics(&self, id: Uuid) -> anyhow::Result<Option<String>> {
+        let runtime_client = self.runtime_manager.lock().await.runtime_client(&id);
+        let Some(runtime_client) = runtime_client else {
+            anyhow::bail!("no runtime running for deployment {id}");
+        };
+
+        tokio::time::timeout(
+            METRICS_TIMEOUT,
+            RuntimeManager::scrape_metrics(runtime_client, &id),
+        )
+        .await
+        .map_err(|_| anyhow::anyhow!("runtime of deployment {id} took too long to send metrics"))?
+    }
+
     pub fn builds_path(&self) -> &Path {
         self.builds_path.as_path()
     }
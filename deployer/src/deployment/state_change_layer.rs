@@ -151,8 +151,9 @@ mod tests {
     use shuttle_proto::{
         builder::{builder_server::Builder, BuildRequest, BuildResponse},
         logger::{
-            logger_client::LoggerClient, logger_server::Logger, Batcher, LogLine, LogsRequest,
-            LogsResponse, StoreLogsRequest, StoreLogsResponse,
+            logger_client::LoggerClient, logger_server::Logger, Batcher, DeleteLogsRequest,
+            DeleteLogsResponse, LogLine, LogsRequest, LogsResponse, StoreLogsRequest,
+            StoreLogsResponse,
         },
         provisioner::{
             provisioner_server::{Provisioner, ProvisionerServer},
@@ -312,6 +313,13 @@ mod tests {
             }))
         }
 
+        async fn delete_logs(
+            &self,
+            _: Request<DeleteLogsRequest>,
+        ) -> Result<Response<DeleteLogsResponse>, Status> {
+            Ok(Response::new(DeleteLogsResponse { deleted: 0 }))
+        }
+
         type GetLogsStreamStream = ReceiverStream<Result<LogLine, Status>>;
 
         async fn get_logs_stream(
@@ -369,7 +377,12 @@ mod tests {
                 .unwrap();
         });
 
-        RuntimeManager::new(format!("http://{}", provisioner_addr), logger_client, None)
+        RuntimeManager::new(
+            format!("http://{}", provisioner_addr),
+            logger_client,
+            None,
+            None,
+        )
     }
 
     #[async_trait::async_trait]
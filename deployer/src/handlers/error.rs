@@ -5,7 +5,8 @@ use axum::response::{IntoResponse, Response};
 use axum::Json;
 
 use serde::{ser::SerializeMap, Serialize};
-use shuttle_common::models::error::ApiError;
+use shuttle_common::models::error::{ApiError, ErrorKind};
+use shuttle_common::quota::QuotaExceeded;
 use tracing::error;
 use utoipa::ToSchema;
 
@@ -23,10 +24,14 @@ pub enum Error {
     },
     #[error("{0}, try running `cargo shuttle deploy`")]
     NotFound(String),
+    #[error("Metrics are not enabled, turn on the `metrics` feature of `shuttle-runtime`")]
+    MetricsDisabled,
     #[error("Internal error: {0}")]
     Internal(#[from] anyhow::Error),
     #[error("Missing header: {0}")]
     MissingHeader(String),
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(#[from] QuotaExceeded),
 }
 
 impl Serialize for Error {
@@ -46,21 +51,24 @@ impl IntoResponse for Error {
     fn into_response(self) -> Response {
         error!(error = &self as &dyn std::error::Error, "request error");
 
-        let code = match self {
-            Error::NotFound(_) => StatusCode::NOT_FOUND,
-            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        let error = match self {
+            Error::NotFound(_) => {
+                ApiError::with_kind(self, StatusCode::NOT_FOUND, ErrorKind::NotFound)
+            }
+            Error::MetricsDisabled => {
+                ApiError::with_kind(self, StatusCode::NOT_FOUND, ErrorKind::MetricsDisabled)
+            }
+            Error::QuotaExceeded(quota) => ErrorKind::QuotaExceeded(quota).into(),
+            _ => ApiError::with_kind(self, StatusCode::INTERNAL_SERVER_ERROR, ErrorKind::Internal),
         };
 
         (
-            code,
+            error.status(),
             [(
                 header::CONTENT_TYPE,
                 HeaderValue::from_static("application/json"),
             )],
-            Json(ApiError {
-                message: self.to_string(),
-                status_code: code.as_u16(),
-            }),
+            Json(error),
         )
             .into_response()
     }
@@ -9,7 +9,9 @@ use axum::extract::{
 use axum::extract::{DefaultBodyLimit, Extension, Path, Query};
 use axum::handler::Handler;
 use axum::headers::HeaderMapExt;
+use axum::http::{header, HeaderValue};
 use axum::middleware::{self, from_extractor};
+use axum::response::IntoResponse;
 use axum::routing::{delete, get, post, Router};
 use axum::Json;
 use bytes::Bytes;
@@ -38,7 +40,7 @@ use shuttle_common::models::deployment::{
 use shuttle_common::models::secret;
 use shuttle_common::project::ProjectName;
 use shuttle_common::{request_span, LogItem};
-use shuttle_proto::logger::LogsRequest;
+use shuttle_proto::logger::{DeleteLogsRequest, LogsRequest};
 use shuttle_service::builder::clean_crate;
 
 use crate::persistence::{Deployment, Persistence, SecretGetter, State};
@@ -52,6 +54,9 @@ mod error;
 mod local;
 mod project;
 
+/// The content type of the Prometheus text format, which runtimes report metrics in
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
 #[derive(OpenApi)]
 #[openapi(
     paths(
@@ -59,6 +64,7 @@ mod project;
         get_service,
         create_service,
         stop_service,
+        get_service_metrics,
         get_service_resources,
         delete_service_resource,
         get_deployments,
@@ -68,6 +74,7 @@ mod project;
         get_logs,
         get_secrets,
         clean_project,
+        delete_project_logs,
     ),
     components(schemas(
         shuttle_common::models::service::Summary,
@@ -110,6 +117,8 @@ impl RouterBuilder {
         project_id: Ulid,
         auth_uri: Uri,
     ) -> Self {
+        // The gateway proxies the routes below and keeps the scope of each one in its
+        // `DEPLOYER_ROUTES`, which needs to know about new routes
         let router = Router::new()
             // TODO: The `/swagger-ui` responds with a 303 See Other response which is followed in
             // browsers but leads to 404 Not Found. This must be investigated.
@@ -131,6 +140,10 @@ impl RouterBuilder {
                     )
                     .delete(stop_service.layer(ScopedLayer::new(vec![Scope::ServiceCreate]))),
             )
+            .route(
+                "/projects/:project_name/services/:service_name/metrics",
+                get(get_service_metrics.layer(ScopedLayer::new(vec![Scope::Service]))),
+            )
             .route(
                 "/projects/:project_name/services/:service_name/resources",
                 get(get_service_resources).layer(ScopedLayer::new(vec![Scope::Resources])),
@@ -170,6 +183,10 @@ impl RouterBuilder {
                 "/projects/:project_name/clean",
                 post(clean_project.layer(ScopedLayer::new(vec![Scope::DeploymentPush]))),
             )
+            .route(
+                "/projects/:project_name/logs",
+                delete(delete_project_logs.layer(ScopedLayer::new(vec![Scope::Admin]))),
+            )
             .layer(Extension(persistence))
             .layer(Extension(deployment_manager))
             .layer(Extension(proxy_fqdn))
@@ -396,6 +413,7 @@ pub async fn delete_service_resource(
         (status = 200, description = "Creates a specific service owned by a specific project.", body = shuttle_common::models::deployment::Response),
         (status = 500, description = "Database or streaming error.", body = String),
         (status = 404, description = "Record could not be found.", body = String),
+        (status = 403, description = "The account tier's quota of concurrent builds is exhausted.", body = String),
     ),
     params(
         ("project_name" = String, Path, description = "Name of the project that owns the service."),
@@ -409,6 +427,11 @@ pub async fn create_service(
     Path((project_name, service_name)): Path<(String, String)>,
     Rmp(deployment_req): Rmp<DeploymentRequest>,
 ) -> Result<Json<shuttle_common::models::deployment::Response>> {
+    claim
+        .tier
+        .quota()
+        .check_concurrent_builds(persistence.count_building_deployments().await?)?;
+
     let id = Uuid::new_v4();
     let now = Utc::now();
 
@@ -504,6 +527,44 @@ pub async fn stop_service(
     Ok(Json(response))
 }
 
+#[instrument(skip_all, fields(%project_name, %service_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/services/{service_name}/metrics",
+    responses(
+        (status = 200, description = "Gets the metrics of the running deployment of a service, in the Prometheus text format.", body = String),
+        (status = 500, description = "Database or runtime error.", body = String),
+        (status = 404, description = "Record could not be found, or the service did not enable metrics.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the service."),
+        ("service_name" = String, Path, description = "Name of the service.")
+    )
+)]
+pub async fn get_service_metrics(
+    Extension(persistence): Extension<Persistence>,
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Path((project_name, service_name)): Path<(String, String)>,
+) -> Result<impl IntoResponse> {
+    let Some(service) = persistence.get_service_by_name(&service_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+    let Some(deployment) = persistence.get_active_deployment(&service.id).await? else {
+        return Err(Error::NotFound("no running deployment found".to_string()));
+    };
+    let Some(metrics) = deployment_manager.metrics(deployment.id).await? else {
+        return Err(Error::MetricsDisabled);
+    };
+
+    Ok((
+        [(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(PROMETHEUS_CONTENT_TYPE),
+        )],
+        metrics,
+    ))
+}
+
 #[instrument(skip(persistence))]
 #[utoipa::path(
     get,
@@ -821,6 +882,51 @@ pub async fn clean_project(
     Ok(Json(lines))
 }
 
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    delete,
+    path = "/projects/{project_name}/logs",
+    responses(
+        (status = 200, description = "Deletes the logs of every deployment of a project.", body = u64),
+        (status = 500, description = "Database or logger error.", body = String),
+        (status = 404, description = "Record could not be found.", body = String),
+    ),
+    params(
+        ("project_name" = String, Path, description = "Name of the project that owns the deployments."),
+    )
+)]
+pub async fn delete_project_logs(
+    Extension(persistence): Extension<Persistence>,
+    Extension(deployment_manager): Extension<DeploymentManager>,
+    Extension(claim): Extension<Claim>,
+    Path(project_name): Path<String>,
+) -> Result<Json<u64>> {
+    let Some(service) = persistence.get_service_by_name(&project_name).await? else {
+        return Err(Error::NotFound("service not found".to_string()));
+    };
+
+    let deployment_ids = persistence
+        .get_deployments(&service.id, 0, u32::MAX)
+        .await?
+        .into_iter()
+        .map(|deployment| deployment.id.to_string())
+        .collect();
+
+    let mut logs_request: tonic::Request<DeleteLogsRequest> =
+        tonic::Request::new(DeleteLogsRequest { deployment_ids });
+    logs_request.extensions_mut().insert(claim);
+
+    let mut client = deployment_manager.logs_fetcher().clone();
+    let deleted = client
+        .delete_logs(logs_request)
+        .await
+        .map_err(|error| anyhow!("failed to delete logs: {error}"))?
+        .into_inner()
+        .deleted;
+
+    Ok(Json(deleted))
+}
+
 async fn get_status() -> String {
     "Ok".to_string()
 }
@@ -57,7 +57,7 @@ pub async fn start(
         .secret_getter(persistence.clone())
         .resource_manager(persistence.clone())
         .builder_client(builder_client)
-        .queue_client(GatewayClient::new(args.gateway_uri))
+        .queue_client(GatewayClient::new(args.gateway_uri, args.project.clone()))
         .log_fetcher(log_fetcher)
         .build();
 
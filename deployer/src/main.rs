@@ -10,6 +10,7 @@ use shuttle_deployer::{start, start_proxy, Args, Persistence, RuntimeManager, St
 use shuttle_proto::{
     builder::builder_client::BuilderClient,
     logger::{logger_client::LoggerClient, Batcher},
+    runtime::TraceExport,
 };
 use tokio::select;
 use tower::ServiceBuilder;
@@ -74,10 +75,22 @@ async fn main() {
         None,
     );
 
+    let trace_export = args
+        .user_otlp_endpoint
+        .as_ref()
+        .map(|endpoint| TraceExport {
+            endpoint: endpoint.to_string(),
+            resource_attributes: vec![(
+                "shuttle.project.name".to_string(),
+                args.project.to_string(),
+            )],
+        });
+
     let runtime_manager = RuntimeManager::new(
         args.provisioner_address.to_string(),
         logger_batcher.clone(),
         Some(args.auth_uri.to_string()),
+        trace_export,
     );
 
     select! {
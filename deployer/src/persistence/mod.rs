@@ -289,6 +289,18 @@ impl Persistence {
         Ok(())
     }
 
+    /// Count the deployments that are queued or being built
+    pub async fn count_building_deployments(&self) -> Result<u64> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM deployments WHERE state IN(?, ?)")
+                .bind(State::Queued)
+                .bind(State::Building)
+                .fetch_one(&self.pool)
+                .await?;
+
+        Ok(count as u64)
+    }
+
     pub async fn get_or_create_service(&self, name: &str) -> Result<Service> {
         if let Some(service) = self.get_service_by_name(name).await? {
             Ok(service)
@@ -1016,6 +1028,34 @@ mod tests {
             "invalid states should be moved to the stopped state"
         );
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn count_building_deployments() {
+        let (p, _) = Persistence::new_in_memory().await;
+
+        let service_id = add_service(&p.pool).await.unwrap();
+
+        for state in [
+            State::Queued,
+            State::Building,
+            State::Built,
+            State::Running,
+            State::Stopped,
+        ] {
+            p.insert_deployment(Deployment {
+                id: Uuid::new_v4(),
+                service_id,
+                state,
+                last_update: Utc::now(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(p.count_building_deployments().await.unwrap(), 2);
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn fetching_runnable_deployments() {
         let (p, _) = Persistence::new_in_memory().await;
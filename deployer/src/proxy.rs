@@ -14,7 +14,7 @@ use hyper::{
 use hyper_reverse_proxy::{ProxyError, ReverseProxy};
 use once_cell::sync::Lazy;
 use opentelemetry::global;
-use opentelemetry_http::HeaderExtractor;
+use opentelemetry_http::{HeaderExtractor, HeaderInjector};
 use shuttle_common::backends::headers::XShuttleProject;
 use tracing::{error, field, instrument, trace, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
@@ -137,8 +137,14 @@ pub trait AddressGetter: Clone + Send + Sync + 'static {
 async fn reverse_proxy(
     remote_ip: IpAddr,
     service_address: &str,
-    req: Request<Body>,
+    mut req: Request<Body>,
 ) -> Result<Response<Body>, ProxyError> {
+    // Let the service continue the trace of this request, so its spans connect to ours
+    let cx = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&cx, &mut HeaderInjector(req.headers_mut()))
+    });
+
     let forward_uri = format!("http://{service_address}");
     let mut response = PROXY_CLIENT.call(remote_ip, &forward_uri, req).await?;
 
@@ -146,3 +152,93 @@ async fn reverse_proxy(
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, net::SocketAddr};
+
+    use async_trait::async_trait;
+    use axum::headers::HeaderMapExt;
+    use hyper::{
+        header::HOST,
+        service::{make_service_fn, service_fn},
+        Body, Request, Response, Server,
+    };
+    use opentelemetry::{
+        global,
+        sdk::{propagation::TraceContextPropagator, trace::TracerProvider},
+        trace::TracerProvider as _,
+    };
+    use shuttle_common::backends::headers::XShuttleProject;
+    use tracing_subscriber::prelude::*;
+
+    use super::{handle, AddressGetter};
+
+    const TRACEPARENT: &str = "traceparent";
+
+    #[derive(Clone)]
+    struct StaticAddress(SocketAddr);
+
+    #[async_trait]
+    impl AddressGetter for StaticAddress {
+        async fn get_address_for_service(
+            &self,
+            _service_name: &str,
+        ) -> crate::handlers::Result<Option<SocketAddr>> {
+            Ok(Some(self.0))
+        }
+    }
+
+    #[tokio::test]
+    async fn service_continues_the_trace() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let provider = TracerProvider::builder().build();
+        let _guard = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("proxy")))
+            .set_default();
+
+        // A service which answers with the trace context it got
+        let make_service = make_service_fn(|_| async {
+            Ok::<_, Infallible>(service_fn(|req: Request<Body>| async move {
+                let traceparent = req
+                    .headers()
+                    .get(TRACEPARENT)
+                    .map(|value| value.to_str().unwrap().to_string())
+                    .unwrap_or_default();
+
+                Ok::<_, Infallible>(Response::new(Body::from(traceparent)))
+            }))
+        });
+        let server = Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(make_service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+
+        let mut req = Request::builder()
+            .header(HOST, "hello.shuttleapp.rs")
+            .header(
+                TRACEPARENT,
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )
+            .body(Body::empty())
+            .unwrap();
+        req.headers_mut()
+            .typed_insert(XShuttleProject("hello".to_string()));
+
+        let response = handle(
+            "127.0.0.1:8000".parse().unwrap(),
+            "hello.shuttleapp.rs".parse().unwrap(),
+            req,
+            StaticAddress(address),
+        )
+        .await
+        .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let traceparent = String::from_utf8(body.to_vec()).unwrap();
+
+        // Still the same trace, but the parent is now a span of the proxy
+        let parts: Vec<_> = traceparent.split('-').collect();
+        assert_eq!(parts.len(), 4, "service got traceparent {traceparent:?}");
+        assert_eq!(parts[1], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_ne!(parts[2], "00f067aa0ba902b7");
+    }
+}
@@ -13,7 +13,7 @@ use shuttle_common::{
 };
 use shuttle_proto::{
     logger::{logger_client::LoggerClient, Batcher, LogItem, LogLine},
-    runtime::{self, runtime_client::RuntimeClient, StopRequest},
+    runtime::{self, runtime_client::RuntimeClient, MetricsRequest, StopRequest, TraceExport},
 };
 use shuttle_service::Environment;
 use tokio::{io::AsyncBufReadExt, io::BufReader, process, sync::Mutex};
@@ -49,6 +49,7 @@ pub struct RuntimeManager {
         >,
     >,
     auth_uri: Option<String>,
+    trace_export: Option<TraceExport>,
 }
 
 impl RuntimeManager {
@@ -62,12 +63,14 @@ impl RuntimeManager {
             >,
         >,
         auth_uri: Option<String>,
+        trace_export: Option<TraceExport>,
     ) -> Arc<Mutex<Self>> {
         Arc::new(Mutex::new(Self {
             runtimes: Default::default(),
             provisioner_address,
             logger_client,
             auth_uri,
+            trace_export,
         }))
     }
 
@@ -125,11 +128,21 @@ impl RuntimeManager {
                 .join("bin/shuttle-next")
         };
 
+        // Tell the traces of every deployment apart on the collector
+        let trace_export = self.trace_export.clone().map(|mut trace_export| {
+            trace_export.resource_attributes.extend([
+                ("service.name".to_string(), service_name.clone()),
+                ("shuttle.deployment.id".to_string(), id.to_string()),
+            ]);
+            trace_export
+        });
+
         let (mut process, runtime_client) = runtime::start(
             is_next,
             Environment::Deployment,
             &self.provisioner_address,
             self.auth_uri.as_ref(),
+            trace_export.as_ref(),
             port,
             runtime_executable,
             project_path,
@@ -203,6 +216,39 @@ impl RuntimeManager {
 
         response.into_inner().success
     }
+
+    /// Get the client of the runtime running a deployment
+    pub fn runtime_client(
+        &self,
+        id: &Uuid,
+    ) -> Option<RuntimeClient<ClaimService<InjectPropagation<Channel>>>> {
+        self.runtimes
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|(_, runtime_client)| runtime_client.clone())
+    }
+
+    /// Scrape the metrics of a deployment from the client of its running runtime. Returns `None`
+    /// when the service did not enable metrics. This does not need the manager, so that it can
+    /// be called without holding on to it while the runtime answers.
+    pub async fn scrape_metrics(
+        mut runtime_client: RuntimeClient<ClaimService<InjectPropagation<Channel>>>,
+        id: &Uuid,
+    ) -> anyhow::Result<Option<String>> {
+        trace!(%id, "getting metrics for deployment");
+        let response = match runtime_client
+            .metrics(tonic::Request::new(MetricsRequest {}))
+            .await
+        {
+            Ok(response) => response.into_inner(),
+            // Runtimes from before metrics existed do not know the request
+            Err(status) if status.code() == tonic::Code::Unimplemented => return Ok(None),
+            Err(status) => return Err(status).context("metrics request failed"),
+        };
+
+        Ok(response.enabled.then_some(response.text))
+    }
 }
 
 impl Drop for RuntimeManager {
@@ -79,7 +79,12 @@ async fn get_runtime_manager() -> Arc<Mutex<RuntimeManager>> {
 
     let logger_client = Batcher::wrap(mocked_logger_client(MockedLogger).await);
 
-    RuntimeManager::new(format!("http://{}", provisioner_addr), logger_client, None)
+    RuntimeManager::new(
+        format!("http://{}", provisioner_addr),
+        logger_client,
+        None,
+        None,
+    )
 }
 
 #[derive(Clone)]
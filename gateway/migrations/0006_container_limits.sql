@@ -0,0 +1,26 @@
+-- The tier of the account owning the project, used to look up its container limits.
+ALTER TABLE projects ADD COLUMN account_tier TEXT NOT NULL DEFAULT 'basic';
+
+-- Container limits applied to all projects of an account tier.
+CREATE TABLE IF NOT EXISTS tier_limits (
+  account_tier TEXT PRIMARY KEY,
+  memory_bytes INTEGER NOT NULL,
+  cpu_shares INTEGER NOT NULL,
+  pids_limit INTEGER NOT NULL
+);
+
+INSERT INTO tier_limits (account_tier, memory_bytes, cpu_shares, pids_limit) VALUES
+  ('basic', 6442450000, 1024, 4096),
+  ('pendingpaymentpro', 6442450000, 1024, 4096),
+  ('pro', 8589934592, 2048, 8192),
+  ('team', 8589934592, 2048, 8192),
+  ('admin', 8589934592, 2048, 8192),
+  ('deployer', 6442450000, 1024, 4096);
+
+-- Per project overrides of the limits of its tier. A NULL column falls back to the tier limit.
+CREATE TABLE IF NOT EXISTS project_limits (
+  project_id ULID PRIMARY KEY REFERENCES projects (project_id),
+  memory_bytes INTEGER,
+  cpu_shares INTEGER,
+  pids_limit INTEGER
+);
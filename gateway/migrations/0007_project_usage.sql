@@ -0,0 +1,18 @@
+-- Usage counters of projects, aggregated per hour. Timestamps are in seconds since the UNIX epoch.
+CREATE TABLE IF NOT EXISTS project_usage (
+  project_id ULID NOT NULL REFERENCES projects (project_id),
+  period_start INTEGER NOT NULL,
+  requests INTEGER NOT NULL DEFAULT 0,
+  response_bytes INTEGER NOT NULL DEFAULT 0,
+  build_seconds INTEGER NOT NULL DEFAULT 0,
+  PRIMARY KEY (project_id, period_start)
+);
+
+-- Periods during which the container of a project was running. An open period has no stopped_at.
+CREATE TABLE IF NOT EXISTS project_uptime (
+  project_id ULID NOT NULL REFERENCES projects (project_id),
+  started_at INTEGER NOT NULL,
+  stopped_at INTEGER
+);
+
+CREATE INDEX IF NOT EXISTS project_uptime_project_id ON project_uptime (project_id);
@@ -0,0 +1,19 @@
+-- Teams of accounts that share ownership of projects.
+CREATE TABLE IF NOT EXISTS teams (
+  team_name TEXT PRIMARY KEY,
+  created_at INTEGER NOT NULL
+);
+
+-- The role of every member of a team: 'owner', 'deployer' or 'viewer'.
+CREATE TABLE IF NOT EXISTS team_members (
+  team_name TEXT NOT NULL REFERENCES teams (team_name),
+  account_name TEXT NOT NULL,
+  role TEXT NOT NULL,
+  PRIMARY KEY (team_name, account_name)
+);
+
+CREATE INDEX IF NOT EXISTS team_members_account_name ON team_members (account_name);
+
+-- The team a project has been transferred to, if any. Members of the team get access to
+-- the project based on their role, instead of access being limited to the account_name.
+ALTER TABLE projects ADD COLUMN team_name TEXT REFERENCES teams (team_name);
@@ -0,0 +1,5 @@
+-- CPU time a container can use in every period of 100ms, in microseconds. Defaults to 4 CPUs.
+ALTER TABLE tier_limits ADD COLUMN cpu_quota INTEGER NOT NULL DEFAULT 400000;
+
+-- A NULL quota falls back to the tier limit.
+ALTER TABLE project_limits ADD COLUMN cpu_quota INTEGER;
@@ -29,7 +29,7 @@ static PROXY_CLIENT: Lazy<ReverseProxy<HttpConnector<GaiResolver>>> =
 const CACHE_MINUTES: u64 = 5;
 
 /// The idea of this layer is to do two things:
-/// 1. Forward all user related routes (`/login`, `/logout`, `/users/*`, etc) to our auth service
+/// 1. Forward all user related routes (`/login`, `/logout`, `/users/*`, `/auth/refresh`, etc) to our auth service
 /// 2. Upgrade all Authorization Bearer keys and session cookies to JWT tokens for internal
 /// communication inside and below gateway, fetching the JWT token from a ttl-cache if it isn't expired,
 /// and inserting it in the cache if it isn't there.
@@ -111,8 +111,10 @@ where
         }
 
         let forward_to_auth = match req.uri().path() {
-            "/login" | "/logout" => true,
-            other => other.starts_with("/users"),
+            "/login" | "/logout" | "/auth/refresh" | "/auth/revoke" => true,
+            // Stripe signs its webhooks, so they do not need a user to be authenticated
+            "/billing/stripe/webhook" => true,
+            other => other.starts_with("/users") || other.starts_with("/auth/device/"),
         };
 
         // If /users/reset-api-key is called, invalidate the cached JWT.
@@ -1,35 +1,41 @@
 use std::io::Cursor;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::ops::Sub;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use axum::body::Body;
 use axum::extract::{Extension, Path, Query, State};
 use axum::handler::Handler;
 use axum::http::Request;
-use axum::middleware::from_extractor;
-use axum::response::Response;
-use axum::routing::{any, delete, get, post};
+use axum::middleware::{from_extractor, from_fn, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{any, delete, get, post, put};
 use axum::{Json as AxumJson, Router};
+use chrono::{DateTime, Utc};
 use fqdn::FQDN;
 use futures::Future;
-use http::{StatusCode, Uri};
+use http::{HeaderMap, Method, StatusCode, Uri};
 use instant_acme::{AccountCredentials, ChallengeType};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use shuttle_common::backends::auth::{AuthPublicKey, JwtAuthenticationLayer, ScopedLayer};
+use shuttle_common::backends::audit::AuditLayer;
+use shuttle_common::backends::auth::{
+    AuthPublicKey, JwtAuthenticationLayer, RequiredScopes, ScopedLayer,
+};
 use shuttle_common::backends::cache::CacheManager;
 use shuttle_common::backends::metrics::{Metrics, TraceLayer};
-use shuttle_common::claims::{Scope, EXP_MINUTES};
+use shuttle_common::claims::{AccountTier, Claim, Scope, EXP_MINUTES};
 use shuttle_common::models::error::ErrorKind;
-use shuttle_common::models::{project, stats};
+use shuttle_common::models::team::{self, TeamRole};
+use shuttle_common::models::{account, audit, deployment, project, secret, stats};
 use shuttle_common::{request_span, VersionInfo};
 use shuttle_proto::provisioner::provisioner_client::ProvisionerClient;
 use shuttle_proto::provisioner::Ping;
 use tokio::sync::mpsc::Sender;
 use tokio::sync::{Mutex, MutexGuard};
 use tower::ServiceBuilder;
-use tracing::{field, instrument, trace};
+use tracing::{field, instrument, trace, warn};
 use ttl_cache::TtlCache;
 use utoipa::IntoParams;
 
@@ -43,13 +49,13 @@ use x509_parser::pem::parse_x509_pem;
 use x509_parser::time::ASN1Time;
 
 use crate::acme::{AcmeClient, CustomDomain};
-use crate::auth::{ScopedUser, User};
+use crate::auth::{deployer_route_scope, ScopedUser, User};
 use crate::project::{ContainerInspectResponseExt, Project, ProjectCreating};
 use crate::service::GatewayService;
 use crate::task::{self, BoxedTask, TaskResult};
 use crate::tls::{GatewayCertResolver, RENEWAL_VALIDITY_THRESHOLD_IN_DAYS};
 use crate::worker::WORKER_QUEUE_SIZE;
-use crate::{Error, ProjectName, AUTH_CLIENT};
+use crate::{AccountName, Error, ProjectName, TeamName, AUTH_CLIENT};
 
 use super::auth_layer::ShuttleAuthLayer;
 
@@ -115,12 +121,18 @@ async fn get_project(
 ) -> Result<AxumJson<project::Response>, Error> {
     let project = service.find_project(&scope).await?;
     let idle_minutes = project.state.idle_minutes();
+    let container_limits = project.state.container_limits();
+    let stop_reason = project.state.stop_reason();
+    let team = service.project_team(&scope).await?;
 
     let response = project::Response {
         id: project.project_id.to_uppercase(),
         name: scope.to_string(),
         state: project.state.into(),
         idle_minutes,
+        container_limits,
+        team: team.map(|team| team.to_string()),
+        stop_reason,
     };
 
     Ok(AxumJson(response))
@@ -175,7 +187,10 @@ async fn get_projects_list(
             id: project.0.to_uppercase(),
             name: project.1.to_string(),
             idle_minutes: project.2.idle_minutes(),
+            container_limits: project.2.container_limits(),
+            stop_reason: project.2.stop_reason(),
             state: project.2.into(),
+            team: project.3.map(|team| team.to_string()),
         })
         .collect();
 
@@ -209,10 +224,14 @@ async fn create_project(
             project_name.clone(),
             name.clone(),
             is_admin,
+            claim.tier,
             config.idle_minutes,
         )
         .await?;
     let idle_minutes = project.state.idle_minutes();
+    let container_limits = project.state.container_limits();
+    let stop_reason = project.state.stop_reason();
+    let team = service.project_team(&project_name).await?;
 
     service
         .new_task()
@@ -227,6 +246,9 @@ async fn create_project(
         name: project_name.to_string(),
         state: project.state.into(),
         idle_minutes,
+        container_limits,
+        team: team.map(|team| team.to_string()),
+        stop_reason,
     };
 
     Ok(AxumJson(response))
@@ -255,12 +277,18 @@ async fn destroy_project(
 ) -> Result<AxumJson<project::Response>, Error> {
     let project = service.find_project(&project_name).await?;
     let idle_minutes = project.state.idle_minutes();
+    let container_limits = project.state.container_limits();
+    let stop_reason = project.state.stop_reason();
+    let team = service.project_team(&project_name).await?;
 
     let mut response = project::Response {
         id: project.project_id.to_uppercase(),
         name: project_name.to_string(),
         state: project.state.into(),
         idle_minutes,
+        container_limits,
+        team: team.map(|team| team.to_string()),
+        stop_reason,
     };
 
     if response.state == shuttle_common::models::project::State::Destroyed {
@@ -280,6 +308,330 @@ async fn destroy_project(
     Ok(AxumJson(response))
 }
 
+#[derive(Deserialize, IntoParams)]
+struct UsageParams {
+    /// Start of the period to get the usage for. Defaults to 30 days ago.
+    from: Option<DateTime<Utc>>,
+    /// End of the period to get the usage for. Defaults to now.
+    to: Option<DateTime<Utc>>,
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    get,
+    path = "/projects/{project_name}/usage",
+    responses(
+        (status = 200, description = "Successfully got the usage of a project.", body = shuttle_common::models::stats::ProjectUsage),
+        (status = 400, description = "Invalid period."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+        UsageParams,
+    )
+)]
+async fn get_project_usage(
+    State(RouterState { service, .. }): State<RouterState>,
+    ScopedUser {
+        scope: project_name,
+        ..
+    }: ScopedUser,
+    Query(UsageParams { from, to }): Query<UsageParams>,
+) -> Result<AxumJson<stats::ProjectUsage>, Error> {
+    let to = to.unwrap_or_else(Utc::now);
+    let from = from.unwrap_or_else(|| to - chrono::Duration::days(30));
+
+    if from >= to {
+        return Err(Error::custom(
+            ErrorKind::InvalidOperation,
+            "the start of the usage period should be before its end",
+        ));
+    }
+
+    let usage = service.project_usage(&project_name, from, to).await?;
+
+    Ok(AxumJson(usage))
+}
+
+#[instrument(skip_all, fields(project_name = %scoped_user.scope, %team_name))]
+#[utoipa::path(
+    post,
+    path = "/projects/{project_name}/transfer",
+    request_body = shuttle_common::models::team::TransferRequest,
+    responses(
+        (status = 200, description = "Successfully transferred a project to a team, or back to its account.", body = shuttle_common::models::project::Response),
+        (status = 403, description = "Only owners of a project and of the team can transfer it, when the new owner has room for it in its project quota."),
+        (status = 404, description = "Team not found."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn transfer_project(
+    State(RouterState { service, .. }): State<RouterState>,
+    scoped_user: ScopedUser,
+    AxumJson(team::TransferRequest { team_name }): AxumJson<team::TransferRequest>,
+) -> Result<AxumJson<project::Response>, Error> {
+    let team_name = team_name.map(|name| name.parse::<TeamName>()).transpose()?;
+    let project_name = scoped_user.scope;
+
+    match &team_name {
+        Some(team_name) => {
+            let role = team_role(&service, team_name, &scoped_user.user.name).await?;
+            if role != TeamRole::Owner {
+                return Err(Error::custom(
+                    ErrorKind::Forbidden,
+                    "only owners of a team can transfer projects to it",
+                ));
+            }
+        }
+        None => {
+            let Some(current_team) = service.project_team(&project_name).await? else {
+                return Err(Error::custom(
+                    ErrorKind::InvalidOperation,
+                    "the project does not belong to a team",
+                ));
+            };
+
+            let role = team_role(&service, &current_team, &scoped_user.user.name).await?;
+            if role != TeamRole::Owner {
+                return Err(Error::custom(
+                    ErrorKind::Forbidden,
+                    "only owners of a team can transfer projects out of it",
+                ));
+            }
+        }
+    }
+
+    service
+        .transfer_project(&project_name, team_name.as_ref())
+        .await?;
+
+    let project = service.find_project(&project_name).await?;
+    let idle_minutes = project.state.idle_minutes();
+    let container_limits = project.state.container_limits();
+    let stop_reason = project.state.stop_reason();
+
+    let response = project::Response {
+        id: project.project_id.to_uppercase(),
+        name: project_name.to_string(),
+        state: project.state.into(),
+        idle_minutes,
+        container_limits,
+        team: team_name.map(|team| team.to_string()),
+        stop_reason,
+    };
+
+    Ok(AxumJson(response))
+}
+
+/// Get the role of an account in a team. Teams the account is not a member of are not found.
+async fn team_role(
+    service: &GatewayService,
+    team_name: &TeamName,
+    account_name: &AccountName,
+) -> Result<TeamRole, Error> {
+    service
+        .team_role(team_name, account_name)
+        .await?
+        .ok_or_else(|| Error::from_kind(ErrorKind::TeamNotFound))
+}
+
+async fn team_response(
+    service: &GatewayService,
+    team_name: &TeamName,
+    role: TeamRole,
+) -> Result<team::Response, Error> {
+    let members = service
+        .team_members(team_name)
+        .await?
+        .into_iter()
+        .map(|(account_name, role)| team::Member {
+            account_name: account_name.to_string(),
+            role,
+        })
+        .collect();
+
+    Ok(team::Response {
+        name: team_name.to_string(),
+        role,
+        members,
+    })
+}
+
+#[instrument(skip_all)]
+#[utoipa::path(
+    get,
+    path = "/teams",
+    responses(
+        (status = 200, description = "Successfully got the teams of the account.", body = [shuttle_common::models::team::Response]),
+        (status = 500, description = "Server internal error.")
+    )
+)]
+async fn get_teams_list(
+    State(RouterState { service, .. }): State<RouterState>,
+    User { name, .. }: User,
+) -> Result<AxumJson<Vec<team::Response>>, Error> {
+    let teams = service
+        .iter_user_teams(&name)
+        .await?
+        .map(|(team_name, role)| team::Response {
+            name: team_name.to_string(),
+            role,
+            members: Vec::new(),
+        })
+        .collect();
+
+    Ok(AxumJson(teams))
+}
+
+#[instrument(skip_all, fields(%team_name))]
+#[utoipa::path(
+    get,
+    path = "/teams/{team_name}",
+    responses(
+        (status = 200, description = "Successfully got a team and its members.", body = shuttle_common::models::team::Response),
+        (status = 404, description = "Team not found."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("team_name" = String, Path, description = "The name of the team."),
+    )
+)]
+async fn get_team(
+    State(RouterState { service, .. }): State<RouterState>,
+    User { name, .. }: User,
+    Path(team_name): Path<TeamName>,
+) -> Result<AxumJson<team::Response>, Error> {
+    let role = team_role(&service, &team_name, &name).await?;
+
+    Ok(AxumJson(team_response(&service, &team_name, role).await?))
+}
+
+#[instrument(skip_all, fields(%team_name))]
+#[utoipa::path(
+    post,
+    path = "/teams/{team_name}",
+    responses(
+        (status = 200, description = "Successfully created a team.", body = shuttle_common::models::team::Response),
+        (status = 400, description = "A team with the same name already exists."),
+        (status = 403, description = "Teams are only available on the team tier."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("team_name" = String, Path, description = "The name of the team."),
+    )
+)]
+async fn create_team(
+    State(RouterState { service, .. }): State<RouterState>,
+    User { name, claim, .. }: User,
+    Path(team_name): Path<TeamName>,
+) -> Result<AxumJson<team::Response>, Error> {
+    if claim.tier != AccountTier::Team && !claim.scopes.contains(&Scope::Admin) {
+        return Err(Error::custom(
+            ErrorKind::Forbidden,
+            "teams are only available to accounts on the team tier",
+        ));
+    }
+
+    service.create_team(&team_name, &name).await?;
+
+    Ok(AxumJson(
+        team_response(&service, &team_name, TeamRole::Owner).await?,
+    ))
+}
+
+#[instrument(skip_all, fields(%team_name, %account_name))]
+#[utoipa::path(
+    put,
+    path = "/teams/{team_name}/members/{account_name}",
+    request_body = shuttle_common::models::team::MemberRequest,
+    responses(
+        (status = 200, description = "Successfully added or updated a member of a team.", body = shuttle_common::models::team::Response),
+        (status = 400, description = "The team would be left without an owner."),
+        (status = 403, description = "Only owners of a team can manage its members."),
+        (status = 404, description = "Team or account not found."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("team_name" = String, Path, description = "The name of the team."),
+        ("account_name" = String, Path, description = "The name of the account to add or update."),
+    )
+)]
+async fn set_team_member(
+    State(RouterState { service, .. }): State<RouterState>,
+    User { name, .. }: User,
+    Path((team_name, account_name)): Path<(TeamName, AccountName)>,
+    AxumJson(team::MemberRequest { role }): AxumJson<team::MemberRequest>,
+) -> Result<AxumJson<team::Response>, Error> {
+    let caller_role = team_role(&service, &team_name, &name).await?;
+    if caller_role != TeamRole::Owner {
+        return Err(Error::custom(
+            ErrorKind::Forbidden,
+            "only owners of a team can manage its members",
+        ));
+    }
+
+    if !service.account_exists(&account_name).await? {
+        return Err(Error::from_kind(ErrorKind::UserNotFound));
+    }
+
+    service
+        .set_team_member(&team_name, &account_name, role)
+        .await?;
+
+    let caller_role = if account_name == name {
+        role
+    } else {
+        caller_role
+    };
+
+    Ok(AxumJson(
+        team_response(&service, &team_name, caller_role).await?,
+    ))
+}
+
+#[instrument(skip_all, fields(%team_name, %account_name))]
+#[utoipa::path(
+    delete,
+    path = "/teams/{team_name}/members/{account_name}",
+    responses(
+        (status = 200, description = "Successfully removed a member from a team."),
+        (status = 400, description = "The team would be left without an owner."),
+        (status = 403, description = "Only owners of a team can remove other members."),
+        (status = 404, description = "Team not found."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("team_name" = String, Path, description = "The name of the team."),
+        ("account_name" = String, Path, description = "The name of the account to remove."),
+    )
+)]
+async fn remove_team_member(
+    State(RouterState { service, .. }): State<RouterState>,
+    User { name, .. }: User,
+    Path((team_name, account_name)): Path<(TeamName, AccountName)>,
+) -> Result<AxumJson<String>, Error> {
+    let caller_role = team_role(&service, &team_name, &name).await?;
+
+    // Members can always leave a team
+    if caller_role != TeamRole::Owner && account_name != name {
+        return Err(Error::custom(
+            ErrorKind::Forbidden,
+            "only owners of a team can remove other members",
+        ));
+    }
+
+    service
+        .remove_team_member(&team_name, &account_name)
+        .await?;
+
+    Ok(AxumJson(format!(
+        "{account_name} was removed from team {team_name}"
+    )))
+}
+
 #[derive(Deserialize, IntoParams)]
 struct DeleteProjectParams {
     dry_run: Option<bool>,
@@ -411,6 +763,32 @@ async fn route_project(
         .await
 }
 
+/// Does for the routes proxied to a deployer what a [ScopedLayer] does for the other routes, taking
+/// the required scope from the deployer route the request is for
+async fn require_deployer_scope(mut req: Request<Body>, next: Next<Body>) -> Response {
+    // The path after `/projects/:project_name`
+    let route = match req.uri().path().splitn(4, '/').nth(3) {
+        Some(route) => format!("/{route}"),
+        None => String::new(),
+    };
+
+    let Some(scope) = deployer_route_scope(req.method(), &route) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let Some(claim) = req.extensions().get::<Claim>() else {
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
+
+    if !claim.scopes.contains(&scope) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+
+    req.extensions_mut().insert(RequiredScopes(vec![scope]));
+
+    next.run(req).await
+}
+
 #[utoipa::path(
     get,
     path = "/",
@@ -484,9 +862,19 @@ async fn post_load(
     trace!(id = %build.id, "checking build queue");
     let mut load = calculate_capacity(&mut running_builds);
 
+    // Keep the original start of a build which is already in the queue
+    let started_at = running_builds
+        .get(&build.id)
+        .copied()
+        .unwrap_or_else(Instant::now);
+
     if load.has_capacity
         && running_builds
-            .insert(build.id, (), Duration::from_secs(60 * EXP_MINUTES as u64))
+            .insert(
+                build.id,
+                started_at,
+                Duration::from_secs(60 * EXP_MINUTES as u64),
+            )
             .is_none()
     {
         // Only increase when an item was not already in the queue
@@ -506,11 +894,25 @@ async fn post_load(
     )
 )]
 async fn delete_load(
-    State(RouterState { running_builds, .. }): State<RouterState>,
+    State(RouterState {
+        service,
+        running_builds,
+        ..
+    }): State<RouterState>,
     AxumJson(build): AxumJson<stats::LoadRequest>,
 ) -> Result<AxumJson<stats::LoadResponse>, Error> {
     let mut running_builds = running_builds.lock().await;
-    running_builds.remove(&build.id);
+
+    if let Some(started_at) = running_builds.remove(&build.id) {
+        if let Some(project_name) = build
+            .project_name
+            .and_then(|name| name.parse::<ProjectName>().ok())
+        {
+            service
+                .usage()
+                .record_build(project_name, started_at.elapsed());
+        }
+    }
 
     trace!(id = %build.id, "removing from build queue");
     let load = calculate_capacity(&mut running_builds);
@@ -557,7 +959,9 @@ async fn delete_load_admin(
     Ok(AxumJson(load))
 }
 
-fn calculate_capacity(running_builds: &mut MutexGuard<TtlCache<Uuid, ()>>) -> stats::LoadResponse {
+fn calculate_capacity(
+    running_builds: &mut MutexGuard<TtlCache<Uuid, Instant>>,
+) -> stats::LoadResponse {
     let active = running_builds.iter().count();
     let capacity = running_builds.capacity();
     let has_capacity = active < capacity;
@@ -669,6 +1073,7 @@ async fn request_custom_domain_acme_certificate(
 
     let container = project.state.container().unwrap();
     let idle_minutes = container.idle_minutes();
+    let limits = container.container_limits();
 
     // Destroy and recreate the project with the new domain.
     service
@@ -686,7 +1091,8 @@ async fn request_custom_domain_acme_certificate(
                         project_id,
                         idle_minutes,
                     )
-                    .with_fqdn(fqdn);
+                    .with_fqdn(fqdn)
+                    .with_limits(limits);
                     TaskResult::Done(Project::Creating(creating))
                 }
             }
@@ -842,66 +1248,595 @@ async fn get_projects(
     Ok(AxumJson(projects))
 }
 
-struct SecurityAddon;
+#[utoipa::path(
+    get,
+    path = "/audit",
+    responses(
+        (status = 200, description = "Successfully got the actions taken by the account.", body = [shuttle_common::models::audit::Response]),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        PaginationDetails
+    )
+)]
+async fn get_audit_log(
+    State(RouterState { service, .. }): State<RouterState>,
+    User { name, .. }: User,
+    Query(PaginationDetails { page, limit }): Query<PaginationDetails>,
+) -> Result<AxumJson<Vec<audit::Response>>, Error> {
+    let limit = limit.unwrap_or(u32::MAX);
+    let page = page.unwrap_or(0);
+    let events = service
+        .iter_audit_events(Some(&name), limit.saturating_mul(page), limit)
+        .await?
+        .map(Into::into)
+        .collect();
 
-impl Modify for SecurityAddon {
-    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
-        if let Some(components) = openapi.components.as_mut() {
-            components.add_security_scheme(
-                "Gateway API Key",
-                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("Bearer"))),
-            )
-        }
-    }
+    Ok(AxumJson(events))
 }
 
-#[derive(OpenApi)]
-#[openapi(
-    paths(
-        create_acme_account,
-        request_custom_domain_acme_certificate,
-        renew_custom_domain_acme_certificate,
-        renew_gateway_acme_certificate,
-        get_status,
-        get_projects_list,
-        get_project,
-        destroy_project,
-        create_project,
-        post_load,
-        delete_load,
-        get_projects,
-        revive_projects,
-        destroy_projects,
-        get_load_admin,
-        delete_load_admin
+#[utoipa::path(
+    get,
+    path = "/admin/audit",
+    responses(
+        (status = 200, description = "Successfully got the actions taken by all accounts.", body = [shuttle_common::models::audit::Response]),
+        (status = 500, description = "Server internal error.")
     ),
-    modifiers(&SecurityAddon),
-    components(schemas(
-        shuttle_common::models::project::Response,
-        shuttle_common::models::stats::LoadResponse,
-        shuttle_common::models::project::AdminResponse,
-        shuttle_common::models::stats::LoadResponse,
-        shuttle_common::models::project::State
-    ))
+    params(
+        PaginationDetails
+    )
 )]
-pub struct ApiDoc;
+async fn get_admin_audit_log(
+    State(RouterState { service, .. }): State<RouterState>,
+    Query(PaginationDetails { page, limit }): Query<PaginationDetails>,
+) -> Result<AxumJson<Vec<audit::Response>>, Error> {
+    let limit = limit.unwrap_or(u32::MAX);
+    let page = page.unwrap_or(0);
+    let events = service
+        .iter_audit_events(None, limit.saturating_mul(page), limit)
+        .await?
+        .map(Into::into)
+        .collect();
 
-#[derive(Clone)]
-pub(crate) struct RouterState {
-    pub service: Arc<GatewayService>,
-    pub sender: Sender<BoxedTask>,
-    pub running_builds: Arc<Mutex<TtlCache<Uuid, ()>>>,
+    Ok(AxumJson(events))
 }
 
-pub struct ApiBuilder {
-    router: Router<RouterState>,
-    service: Option<Arc<GatewayService>>,
-    sender: Option<Sender<BoxedTask>>,
-    bind: Option<SocketAddr>,
-}
+/// Send a request to the deployer of a project on behalf of the caller. Returns `None` when the
+/// deployer has no service for the project.
+async fn call_deployer(
+    state: &RouterState,
+    user: &User,
+    project_name: &ProjectName,
+    method: Method,
+    path: &str,
+    headers: &HeaderMap,
+) -> Result<Option<hyper::body::Bytes>, Error> {
+    let mut rb = Request::builder();
+    rb.headers_mut().unwrap().clone_from(headers);
+    let req = rb
+        .uri(path.parse::<Uri>().unwrap())
+        .method(method)
+        .body(hyper::Body::empty())
+        .unwrap();
+    let scoped_user = ScopedUser {
+        user: user.clone(),
+        scope: project_name.clone(),
+    };
 
-impl Default for ApiBuilder {
-    fn default() -> Self {
+    let res = route_project(State(state.clone()), scoped_user, req).await?;
+    // 404 == no service
+    if res.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if res.status() != StatusCode::OK {
+        return Err(Error::custom(
+            ErrorKind::Internal,
+            format!("deployer responded to {path} with {}", res.status()),
+        ));
+    }
+
+    let body_bytes = hyper::body::to_bytes(res.into_body())
+        .await
+        .map_err(|e| Error::source(ErrorKind::Internal, e))?;
+
+    Ok(Some(body_bytes))
+}
+
+fn from_deployer<T: DeserializeOwned + Default>(
+    body: Option<hyper::body::Bytes>,
+) -> Result<T, Error> {
+    match body {
+        Some(body_bytes) => {
+            serde_json::from_slice(&body_bytes).map_err(|e| Error::source(ErrorKind::Internal, e))
+        }
+        None => Ok(T::default()),
+    }
+}
+
+/// Bring back the container of a destroyed or errored project, without starting its last
+/// deployment, so its deployer can be called
+async fn revive_container(state: &RouterState, project_name: &ProjectName) -> Result<(), Error> {
+    let project = state.service.find_project(project_name).await?;
+
+    if !project.state.is_destroyed() && !matches!(project.state, Project::Errored(_)) {
+        return Ok(());
+    }
+
+    state
+        .service
+        .new_task()
+        .project(project_name.clone())
+        .and_then(task::start())
+        .and_then(task::run_until_done())
+        .send(&state.sender)
+        .await?
+        .await;
+
+    Ok(())
+}
+
+/// Gather what is kept for a project, leaving out the values of its secrets. The deployments and
+/// secrets are kept by the deployer of the project, so they are only exported while the project
+/// is running. Exporting never starts a project.
+async fn export_project(
+    state: &RouterState,
+    user: &User,
+    project_name: &ProjectName,
+    headers: &HeaderMap,
+) -> Result<account::ProjectExport, Error> {
+    let project = state.service.find_project(project_name).await?;
+    let is_ready = project.state.is_ready();
+    let project = project::Response {
+        id: project.project_id.to_uppercase(),
+        name: project_name.to_string(),
+        idle_minutes: project.state.idle_minutes(),
+        container_limits: project.state.container_limits(),
+        stop_reason: project.state.stop_reason(),
+        state: project.state.into(),
+        team: None,
+    };
+
+    if !is_ready {
+        return Ok(account::ProjectExport {
+            project,
+            deployments: None,
+            secrets: None,
+        });
+    }
+
+    let deployments: Vec<deployment::Response> = from_deployer(
+        call_deployer(
+            state,
+            user,
+            project_name,
+            Method::GET,
+            &format!("/projects/{project_name}/deployments"),
+            headers,
+        )
+        .await?,
+    )?;
+    let secrets: Vec<secret::Response> = from_deployer(
+        call_deployer(
+            state,
+            user,
+            project_name,
+            Method::GET,
+            &format!("/projects/{project_name}/secrets/{project_name}"),
+            headers,
+        )
+        .await?,
+    )?;
+
+    Ok(account::ProjectExport {
+        project,
+        deployments: Some(deployments),
+        secrets: Some(secrets.into_iter().map(Into::into).collect()),
+    })
+}
+
+async fn export_account(
+    state: &RouterState,
+    user: &User,
+    account_name: &AccountName,
+    projects: Vec<ProjectName>,
+    headers: &HeaderMap,
+) -> Result<account::ExportBundle, Error> {
+    let mut exports = Vec::with_capacity(projects.len());
+    for project_name in &projects {
+        exports.push(export_project(state, user, project_name, headers).await?);
+    }
+
+    Ok(account::ExportBundle {
+        account_name: account_name.to_string(),
+        exported_at: Utc::now(),
+        projects: exports,
+    })
+}
+
+#[instrument(skip_all, fields(account.name = %user.name))]
+#[utoipa::path(
+    get,
+    path = "/account/export",
+    responses(
+        (status = 200, description = "Successfully exported the data of the account.", body = shuttle_common::models::account::ExportBundle),
+        (status = 500, description = "Server internal error.")
+    )
+)]
+async fn get_account_export(
+    State(state): State<RouterState>,
+    user: User,
+    headers: HeaderMap,
+) -> Result<AxumJson<account::ExportBundle>, Error> {
+    // Tokens restricted to a single project only export that project
+    let projects = state
+        .service
+        .iter_owned_projects(&user.name)
+        .await?
+        .filter(|project_name| user.projects.contains(project_name))
+        .collect();
+    let bundle = export_account(&state, &user, &user.name, projects, &headers).await?;
+
+    Ok(AxumJson(bundle))
+}
+
+#[instrument(skip_all, fields(%account_name))]
+#[utoipa::path(
+    get,
+    path = "/admin/accounts/{account_name}/export",
+    responses(
+        (status = 200, description = "Successfully exported the data of an account.", body = shuttle_common::models::account::ExportBundle),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("account_name" = String, Path, description = "The name of the account."),
+    )
+)]
+async fn get_admin_account_export(
+    State(state): State<RouterState>,
+    user: User,
+    Path(account_name): Path<AccountName>,
+    headers: HeaderMap,
+) -> Result<AxumJson<account::ExportBundle>, Error> {
+    let projects = state
+        .service
+        .iter_owned_projects(&account_name)
+        .await?
+        .collect();
+    let bundle = export_account(&state, &user, &account_name, projects, &headers).await?;
+
+    Ok(AxumJson(bundle))
+}
+
+#[instrument(skip_all, fields(%account_name))]
+#[utoipa::path(
+    post,
+    path = "/admin/accounts/{account_name}/suspend",
+    responses(
+        (status = 200, description = "Successfully started destroying the projects of a suspended account.", body = [String]),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("account_name" = String, Path, description = "The name of the account."),
+    )
+)]
+async fn suspend_account(
+    State(RouterState {
+        service, sender, ..
+    }): State<RouterState>,
+    Path(account_name): Path<AccountName>,
+) -> Result<AxumJson<Vec<String>>, Error> {
+    let mut destroying = Vec::new();
+
+    // Destroyed projects are not started by traffic, so they stay down until unsuspended
+    for project_name in service.iter_owned_projects(&account_name).await? {
+        let project = service.find_project(&project_name).await?;
+        if project.state.is_destroyed() {
+            continue;
+        }
+
+        service
+            .new_task()
+            .project(project_name.clone())
+            .and_then(task::destroy())
+            .send(&sender)
+            .await?;
+
+        destroying.push(project_name.to_string());
+    }
+
+    Ok(AxumJson(destroying))
+}
+
+/// Remove everything the deployer of a project keeps, and then the project itself. Every step can
+/// be repeated, so a project that failed part way can be deleted again.
+async fn delete_project_data(
+    state: &RouterState,
+    user: &User,
+    project_name: &ProjectName,
+    headers: &HeaderMap,
+) -> Result<(), Error> {
+    revive_container(state, project_name).await?;
+
+    let service_path = format!("/projects/{project_name}/services/{project_name}");
+
+    // Stop the running deployment, if any
+    call_deployer(
+        state,
+        user,
+        project_name,
+        Method::DELETE,
+        &service_path,
+        headers,
+    )
+    .await?;
+
+    // Databases and secrets are resources too
+    let resources: Vec<shuttle_common::resource::Response> = from_deployer(
+        call_deployer(
+            state,
+            user,
+            project_name,
+            Method::GET,
+            &format!("{service_path}/resources"),
+            headers,
+        )
+        .await?,
+    )?;
+    for resource in resources {
+        call_deployer(
+            state,
+            user,
+            project_name,
+            Method::DELETE,
+            &format!("{service_path}/resources/{}", resource.r#type),
+            headers,
+        )
+        .await?;
+    }
+
+    call_deployer(
+        state,
+        user,
+        project_name,
+        Method::DELETE,
+        &format!("/projects/{project_name}/logs"),
+        headers,
+    )
+    .await?;
+
+    let task = state
+        .service
+        .new_task()
+        .project(project_name.clone())
+        .and_then(task::delete_project())
+        .send(&state.sender)
+        .await?;
+    task.await;
+
+    state.service.delete_project(project_name).await
+}
+
+/// Delete an account from the gateway, in this order:
+/// 1. Remove the account from its teams, so it loses access to team projects straight away.
+/// 2. Delete each project of the account with [delete_project_data], carrying on past projects
+///    that fail.
+/// 3. Respond with the deleted and the failed projects. Any failure makes the response a server
+///    error, and deleting the account again retries the failed projects.
+#[instrument(skip_all, fields(%account_name))]
+#[utoipa::path(
+    delete,
+    path = "/admin/accounts/{account_name}",
+    responses(
+        (status = 200, description = "Deleted all the projects of an account.", body = shuttle_common::models::account::DeleteResponse),
+        (status = 500, description = "Some projects of the account could not be deleted.", body = shuttle_common::models::account::DeleteResponse)
+    ),
+    params(
+        ("account_name" = String, Path, description = "The name of the account."),
+    )
+)]
+async fn delete_account(
+    State(state): State<RouterState>,
+    user: User,
+    Path(account_name): Path<AccountName>,
+    headers: HeaderMap,
+) -> Result<(StatusCode, AxumJson<account::DeleteResponse>), Error> {
+    state
+        .service
+        .remove_account_memberships(&account_name)
+        .await?;
+
+    let projects: Vec<_> = state
+        .service
+        .iter_owned_projects(&account_name)
+        .await?
+        .collect();
+
+    let mut deleted = Vec::new();
+    let mut failed = Vec::new();
+
+    for project_name in projects {
+        match delete_project_data(&state, &user, &project_name, &headers).await {
+            Ok(()) => deleted.push(project_name.to_string()),
+            Err(error) => {
+                warn!(%project_name, %error, "failed to delete project of account");
+                failed.push(account::ProjectFailure {
+                    project_name: project_name.to_string(),
+                    error: error.to_string(),
+                });
+            }
+        }
+    }
+
+    let status = if failed.is_empty() {
+        StatusCode::OK
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    Ok((
+        status,
+        AxumJson(account::DeleteResponse { deleted, failed }),
+    ))
+}
+
+#[instrument(skip_all, fields(%project_name))]
+#[utoipa::path(
+    put,
+    path = "/admin/projects/{project_name}/limits",
+    responses(
+        (status = 200, description = "Successfully set the container limit overrides of a project.", body = shuttle_common::models::project::ContainerLimits),
+        (status = 404, description = "Project not found."),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("project_name" = String, Path, description = "The name of the project."),
+    )
+)]
+async fn set_project_limits(
+    State(RouterState { service, .. }): State<RouterState>,
+    Path(project_name): Path<ProjectName>,
+    AxumJson(overrides): AxumJson<project::ContainerLimitsOverride>,
+) -> Result<AxumJson<project::ContainerLimits>, Error> {
+    let limits = service
+        .set_project_limits(&project_name, &overrides)
+        .await?;
+
+    Ok(AxumJson(limits))
+}
+
+#[instrument(skip_all, fields(%account_tier))]
+#[utoipa::path(
+    get,
+    path = "/admin/tiers/{account_tier}/limits",
+    responses(
+        (status = 200, description = "Successfully got the container limits of an account tier.", body = shuttle_common::models::project::ContainerLimits),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("account_tier" = String, Path, description = "The account tier."),
+    )
+)]
+async fn get_tier_limits(
+    State(RouterState { service, .. }): State<RouterState>,
+    Path(account_tier): Path<AccountTier>,
+) -> Result<AxumJson<project::ContainerLimits>, Error> {
+    let limits = service.tier_limits(account_tier).await?;
+
+    Ok(AxumJson(limits))
+}
+
+#[instrument(skip_all, fields(%account_tier))]
+#[utoipa::path(
+    put,
+    path = "/admin/tiers/{account_tier}/limits",
+    responses(
+        (status = 200, description = "Successfully set the container limits of an account tier.", body = shuttle_common::models::project::ContainerLimits),
+        (status = 500, description = "Server internal error.")
+    ),
+    params(
+        ("account_tier" = String, Path, description = "The account tier."),
+    )
+)]
+async fn set_tier_limits(
+    State(RouterState { service, .. }): State<RouterState>,
+    Path(account_tier): Path<AccountTier>,
+    AxumJson(limits): AxumJson<project::ContainerLimits>,
+) -> Result<AxumJson<project::ContainerLimits>, Error> {
+    service.set_tier_limits(account_tier, &limits).await?;
+
+    Ok(AxumJson(limits))
+}
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "Gateway API Key",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("Bearer"))),
+            )
+        }
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        create_acme_account,
+        request_custom_domain_acme_certificate,
+        renew_custom_domain_acme_certificate,
+        renew_gateway_acme_certificate,
+        get_status,
+        get_projects_list,
+        get_project,
+        get_project_usage,
+        destroy_project,
+        create_project,
+        post_load,
+        delete_load,
+        get_projects,
+        revive_projects,
+        destroy_projects,
+        get_load_admin,
+        delete_load_admin,
+        set_project_limits,
+        get_tier_limits,
+        set_tier_limits,
+        transfer_project,
+        get_teams_list,
+        get_team,
+        create_team,
+        set_team_member,
+        remove_team_member,
+        get_audit_log,
+        get_admin_audit_log,
+        get_account_export,
+        get_admin_account_export,
+        suspend_account,
+        delete_account
+    ),
+    modifiers(&SecurityAddon),
+    components(schemas(
+        shuttle_common::models::project::Response,
+        shuttle_common::models::stats::LoadResponse,
+        shuttle_common::models::project::AdminResponse,
+        shuttle_common::models::stats::LoadResponse,
+        shuttle_common::models::project::State,
+        shuttle_common::models::project::ContainerLimits,
+        shuttle_common::models::project::ContainerLimitsOverride,
+        shuttle_common::models::stats::ProjectUsage,
+        shuttle_common::models::team::Response,
+        shuttle_common::models::team::Member,
+        shuttle_common::models::team::MemberRequest,
+        shuttle_common::models::team::TransferRequest,
+        shuttle_common::models::team::TeamRole,
+        shuttle_common::models::audit::Response,
+        shuttle_common::models::account::ExportBundle,
+        shuttle_common::models::account::ProjectExport,
+        shuttle_common::models::account::SecretMetadata,
+        shuttle_common::models::account::DeleteResponse,
+        shuttle_common::models::account::ProjectFailure
+    ))
+)]
+pub struct ApiDoc;
+
+#[derive(Clone)]
+pub(crate) struct RouterState {
+    pub service: Arc<GatewayService>,
+    pub sender: Sender<BoxedTask>,
+    pub running_builds: Arc<Mutex<TtlCache<Uuid, Instant>>>,
+}
+
+pub struct ApiBuilder {
+    router: Router<RouterState>,
+    service: Option<Arc<GatewayService>>,
+    sender: Option<Sender<BoxedTask>>,
+    bind: Option<SocketAddr>,
+}
+
+impl Default for ApiBuilder {
+    fn default() -> Self {
         Self::new()
     }
 }
@@ -983,9 +1918,21 @@ impl ApiBuilder {
     pub fn with_default_routes(mut self) -> Self {
         let admin_routes = Router::new()
             .route("/projects", get(get_projects))
+            .route("/projects/:project_name/limits", put(set_project_limits))
+            .route(
+                "/tiers/:account_tier/limits",
+                get(get_tier_limits).put(set_tier_limits),
+            )
             .route("/revive", post(revive_projects))
             .route("/destroy", post(destroy_projects))
             .route("/stats/load", get(get_load_admin).delete(delete_load_admin))
+            .route("/audit", get(get_admin_audit_log))
+            .route("/accounts/:account_name", delete(delete_account))
+            .route("/accounts/:account_name/suspend", post(suspend_account))
+            .route(
+                "/accounts/:account_name/export",
+                get(get_admin_account_export),
+            )
             // TODO: The `/swagger-ui` responds with a 303 See Other response which is followed in
             // browsers but leads to 404 Not Found. This must be investigated.
             .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
@@ -1027,14 +1974,61 @@ impl ApiBuilder {
                 "/projects/:project_name/delete",
                 delete(delete_project.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
             )
+            .route(
+                "/projects/:project_name/usage",
+                get(get_project_usage.layer(ScopedLayer::new(vec![Scope::Project]))),
+            )
+            .route(
+                "/projects/:project_name/transfer",
+                post(transfer_project.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
+            )
             .route("/projects/name/:project_name", get(check_project_name))
-            .route("/projects/:project_name/*any", any(route_project))
+            .route(
+                "/teams",
+                get(get_teams_list.layer(ScopedLayer::new(vec![Scope::Project]))),
+            )
+            .route(
+                "/teams/:team_name",
+                get(get_team.layer(ScopedLayer::new(vec![Scope::Project])))
+                    .post(create_team.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
+            )
+            .route(
+                "/teams/:team_name/members/:account_name",
+                put(set_team_member.layer(ScopedLayer::new(vec![Scope::ProjectWrite])))
+                    .delete(remove_team_member.layer(ScopedLayer::new(vec![Scope::ProjectWrite]))),
+            )
+            .route(
+                "/audit",
+                get(get_audit_log.layer(ScopedLayer::new(vec![Scope::Project]))),
+            )
+            .route(
+                "/account/export",
+                get(get_account_export.layer(ScopedLayer::new(vec![Scope::Project]))),
+            )
+            // Everything else on a project goes to its deployer
+            .route(
+                "/projects/:project_name/*any",
+                any(route_project.layer(from_fn(require_deployer_scope))),
+            )
             .route("/stats/load", post(post_load).delete(delete_load))
             .nest("/admin", admin_routes);
 
         self
     }
 
+    /// Record the actions taken on all the routes added so far to the audit log
+    pub fn with_audit_log(mut self, trusted_proxies: Vec<IpAddr>) -> Self {
+        let service = self
+            .service
+            .clone()
+            .expect("a GatewayService is required for the audit log");
+
+        self.router = self
+            .router
+            .route_layer(AuditLayer::new(service).with_trusted_proxies(trusted_proxies));
+        self
+    }
+
     pub fn with_auth_service(mut self, auth_uri: Uri) -> Self {
         let auth_public_key = AuthPublicKey::new(auth_uri.clone());
 
@@ -1073,7 +2067,8 @@ impl ApiBuilder {
     pub fn serve(self) -> impl Future<Output = Result<(), hyper::Error>> {
         let bind = self.bind.expect("a socket address to bind to is required");
         let router = self.into_router();
-        axum::Server::bind(&bind).serve(router.into_make_service())
+        // The address of the client is needed for the audit log
+        axum::Server::bind(&bind).serve(router.into_make_service_with_connect_info::<SocketAddr>())
     }
 }
 
@@ -1278,6 +2273,464 @@ pub mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn api_team_roles() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let service = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let (sender, mut receiver) = channel::<BoxedTask>(256);
+        tokio::spawn(async move {
+            while receiver.recv().await.is_some() {
+                // do not do any work with inbound requests
+            }
+        });
+
+        let mut router = ApiBuilder::new()
+            .with_service(Arc::clone(&service))
+            .with_sender(sender)
+            .with_default_routes()
+            .with_auth_service(world.context().auth_uri)
+            .into_router();
+
+        // Only accounts on the team tier can create teams, which admins bypass
+        let neo = Authorization::bearer(&world.create_user("neo")).unwrap();
+        world.set_super_user("neo");
+        let trinity = Authorization::bearer(&world.create_user("trinity")).unwrap();
+
+        let request = |method: &str, uri: &str, body: &str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("Content-Type", "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        for (req, status) in [
+            (
+                request("POST", "/projects/matrix", "{\"idle_minutes\": 3}"),
+                StatusCode::OK,
+            ),
+            (request("POST", "/teams/zion", ""), StatusCode::OK),
+            (
+                request(
+                    "POST",
+                    "/projects/matrix/transfer",
+                    "{\"team_name\": \"zion\"}",
+                ),
+                StatusCode::OK,
+            ),
+            // Accounts which do not exist cannot be added
+            (
+                request("PUT", "/teams/zion/members/smith", "{\"role\": \"viewer\"}"),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                request(
+                    "PUT",
+                    "/teams/zion/members/trinity",
+                    "{\"role\": \"viewer\"}",
+                ),
+                StatusCode::OK,
+            ),
+        ] {
+            let resp = router.call(req.with_header(&neo)).await.unwrap();
+            assert_eq!(resp.status(), status);
+        }
+
+        // A viewer can see the project, but cannot destroy it
+        let resp = router
+            .call(request("GET", "/projects/matrix", "").with_header(&trinity))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = router
+            .call(request("DELETE", "/projects/matrix", "").with_header(&trinity))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn api_account_export() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let service = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let (sender, mut receiver) = channel::<BoxedTask>(256);
+        tokio::spawn(async move {
+            while receiver.recv().await.is_some() {
+                // do not do any work with inbound requests
+            }
+        });
+
+        let mut router = ApiBuilder::new()
+            .with_service(Arc::clone(&service))
+            .with_sender(sender)
+            .with_default_routes()
+            .with_auth_service(world.context().auth_uri)
+            .into_router();
+
+        let neo = Authorization::bearer(&world.create_user("neo")).unwrap();
+        let trinity = Authorization::bearer(&world.create_user("trinity")).unwrap();
+        world.set_super_user("trinity");
+
+        let get_export = |uri: &str| {
+            Request::builder()
+                .method("GET")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let resp = router
+            .call(get_export("/account/export").with_header(&neo))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let bundle: account::ExportBundle =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        assert_eq!(bundle.account_name, "neo");
+        assert!(bundle.projects.is_empty());
+
+        // Only admins can export other accounts
+        let resp = router
+            .call(get_export("/admin/accounts/trinity/export").with_header(&neo))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = router
+            .call(get_export("/admin/accounts/neo/export").with_header(&trinity))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let bundle: account::ExportBundle =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        assert_eq!(bundle.account_name, "neo");
+
+        // Exporting a destroyed project leaves it destroyed, without what its deployer keeps
+        let resp = router
+            .call(
+                Request::builder()
+                    .method("POST")
+                    .uri("/projects/matrix")
+                    .header("Content-Type", "application/json")
+                    .body("{\"idle_minutes\": 3}".into())
+                    .unwrap()
+                    .with_header(&neo),
+            )
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let destroyed = service.find_project(&matrix).await?.state.destroy()?;
+        service.update_project(&matrix, &destroyed).await?;
+
+        let resp = router
+            .call(get_export("/account/export").with_header(&neo))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let bundle: account::ExportBundle =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        let [export] = bundle.projects.as_slice() else {
+            panic!("expected only the matrix project to be exported");
+        };
+        assert_eq!(export.project.name, "matrix");
+        assert!(export.deployments.is_none());
+        assert!(export.secrets.is_none());
+        assert!(service.find_project(&matrix).await?.state.is_destroyed());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn api_account_suspend() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let service = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let (sender, mut receiver) = channel::<BoxedTask>(256);
+        tokio::spawn(async move {
+            while receiver.recv().await.is_some() {
+                // do not do any work with inbound requests
+            }
+        });
+
+        let mut router = ApiBuilder::new()
+            .with_service(Arc::clone(&service))
+            .with_sender(sender)
+            .with_default_routes()
+            .with_auth_service(world.context().auth_uri)
+            .into_router();
+
+        let neo = Authorization::bearer(&world.create_user("neo")).unwrap();
+        let trinity = Authorization::bearer(&world.create_user("trinity")).unwrap();
+        world.set_super_user("trinity");
+
+        for project in ["matrix", "reloaded"] {
+            let resp = router
+                .call(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/projects/{project}"))
+                        .header("Content-Type", "application/json")
+                        .body("{\"idle_minutes\": 3}".into())
+                        .unwrap()
+                        .with_header(&neo),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let reloaded: ProjectName = "reloaded".parse().unwrap();
+        let destroyed = service.find_project(&reloaded).await?.state.destroy()?;
+        service.update_project(&reloaded, &destroyed).await?;
+
+        let suspend = || {
+            Request::builder()
+                .method("POST")
+                .uri("/admin/accounts/neo/suspend")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let resp = router.call(suspend().with_header(&neo)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = router.call(suspend().with_header(&trinity)).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // Projects which are already destroyed are left alone
+        let destroying: Vec<String> =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        assert_eq!(destroying, vec!["matrix".to_string()]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn api_account_delete() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let service = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let (sender, mut receiver) = channel::<BoxedTask>(256);
+        tokio::spawn(async move {
+            while receiver.recv().await.is_some() {
+                // do not do any work with inbound requests
+            }
+        });
+
+        let mut router = ApiBuilder::new()
+            .with_service(Arc::clone(&service))
+            .with_sender(sender)
+            .with_default_routes()
+            .with_auth_service(world.context().auth_uri)
+            .into_router();
+
+        let neo = Authorization::bearer(&world.create_user("neo")).unwrap();
+        let trinity = Authorization::bearer(&world.create_user("trinity")).unwrap();
+        world.set_super_user("trinity");
+
+        for project in ["matrix", "reloaded"] {
+            let resp = router
+                .call(
+                    Request::builder()
+                        .method("POST")
+                        .uri(format!("/projects/{project}"))
+                        .header("Content-Type", "application/json")
+                        .body("{\"idle_minutes\": 3}".into())
+                        .unwrap()
+                        .with_header(&neo),
+                )
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+        }
+
+        let delete_account = |account: &str| {
+            Request::builder()
+                .method("DELETE")
+                .uri(format!("/admin/accounts/{account}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let zion: TeamName = "zion".parse()?;
+        service.create_team(&zion, &"neo".parse()?).await?;
+
+        let resp = router
+            .call(delete_account("trinity").with_header(&neo))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        // The deployers of projects that are still being created can't be reached. Every project
+        // is tried, and the ones that failed are kept so the delete can be tried again.
+        let resp = router
+            .call(delete_account("neo").with_header(&trinity))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let account::DeleteResponse { deleted, failed } =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        assert!(deleted.is_empty());
+        let mut failed: Vec<_> = failed
+            .into_iter()
+            .map(|failure| failure.project_name)
+            .collect();
+        failed.sort();
+        assert_eq!(failed, vec!["matrix", "reloaded"]);
+        assert!(service.find_project(&"matrix".parse()?).await.is_ok());
+        assert!(service.find_project(&"reloaded".parse()?).await.is_ok());
+
+        // The account is out of its teams even though some projects are left
+        assert_eq!(service.iter_user_teams(&"neo".parse()?).await?.count(), 0);
+
+        // Accounts without projects have nothing left to clean up
+        let resp = router
+            .call(delete_account("trinity").with_header(&trinity))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let account::DeleteResponse { deleted, failed } =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+        assert!(deleted.is_empty());
+        assert!(failed.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn api_audit_log() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let service = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let (sender, mut receiver) = channel::<BoxedTask>(256);
+        tokio::spawn(async move {
+            while receiver.recv().await.is_some() {
+                // do not do any work with inbound requests
+            }
+        });
+
+        let mut router = ApiBuilder::new()
+            .with_service(Arc::clone(&service))
+            .with_sender(sender)
+            .with_default_routes()
+            .with_audit_log(Vec::new())
+            .with_auth_service(world.context().auth_uri)
+            .into_router();
+
+        let neo = Authorization::bearer(&world.create_user("neo")).unwrap();
+        let trinity = Authorization::bearer(&world.create_user("trinity")).unwrap();
+
+        let create_project = |project: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(format!("/projects/{project}"))
+                .header("Content-Type", "application/json")
+                .body("{\"idle_minutes\": 3}".into())
+                .unwrap()
+        };
+
+        let get_audit_log = |query: &str| {
+            Request::builder()
+                .method("GET")
+                .uri(format!("/audit{query}"))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        router
+            .call(create_project("matrix").with_header(&neo))
+            .map_ok(|resp| assert_eq!(resp.status(), StatusCode::OK))
+            .await
+            .unwrap();
+
+        // Failed actions are recorded too
+        router
+            .call(create_project("matrix").with_header(&trinity))
+            .map_ok(|resp| assert!(resp.status().is_client_error()))
+            .await
+            .unwrap();
+
+        // Actions sent on to the deployer are recorded by their own route
+        router
+            .call(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/projects/matrix/services/hello")
+                    .body(Body::empty())
+                    .unwrap()
+                    .with_header(&neo),
+            )
+            .await
+            .unwrap();
+
+        let resp = router
+            .call(get_audit_log("").with_header(&neo))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let events: Vec<audit::Response> =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+
+        // Reading the audit log is not an action itself
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].actor.as_deref(), Some("neo"));
+        assert_eq!(
+            events[0].action,
+            "DELETE /projects/:project_name/services/:service_name"
+        );
+        assert_eq!(events[0].target, "/projects/matrix/services/hello");
+        assert_eq!(events[1].action, "POST /projects/:project_name");
+        assert_eq!(events[1].target, "/projects/matrix");
+        assert_eq!(events[1].outcome, 200);
+
+        let resp = router
+            .call(get_audit_log("?page=1&limit=1").with_header(&neo))
+            .await
+            .unwrap();
+        let events: Vec<audit::Response> =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].action, "POST /projects/:project_name");
+
+        // Pages past the end are empty, even without a limit
+        let resp = router
+            .call(get_audit_log("?page=2").with_header(&neo))
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        let events: Vec<audit::Response> =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+
+        assert!(events.is_empty());
+
+        let resp = router
+            .call(get_audit_log("").with_header(&trinity))
+            .await
+            .unwrap();
+        let events: Vec<audit::Response> =
+            serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+
+        assert_eq!(events.len(), 1);
+        assert!(events[0].outcome >= 400);
+
+        Ok(())
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn status() {
         let world = World::new().await;
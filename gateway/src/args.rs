@@ -1,4 +1,7 @@
-use std::{net::SocketAddr, path::PathBuf};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
 
 use clap::{Parser, Subcommand, ValueEnum};
 use fqdn::FQDN;
@@ -39,6 +42,10 @@ pub struct StartArgs {
     /// Allows to disable the use of TLS in the user proxy service (DANGEROUS)
     #[arg(long, default_value = "enable")]
     pub use_tls: UseTls,
+    /// Addresses of the proxies in front of the control plane. Only their `x-forwarded-for`
+    /// header is trusted when recording the address of the client in the audit log
+    #[arg(long, value_delimiter = ',')]
+    pub trusted_proxies: Vec<IpAddr>,
     #[command(flatten)]
     pub context: ContextArgs,
 }
@@ -74,4 +81,7 @@ pub struct ContextArgs {
     /// Api key for the user that has rights to start deploys
     #[arg(long, default_value = "gateway4deployes")]
     pub deploys_api_key: String,
+    /// Address of the OTLP collector the deployers send the traces of user services to
+    #[arg(long)]
+    pub user_otlp_endpoint: Option<Uri>,
 }
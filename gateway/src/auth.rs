@@ -1,11 +1,15 @@
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::str::FromStr;
 
 use axum::extract::{FromRef, FromRequestParts, Path};
 use axum::http::request::Parts;
+use axum::http::Method;
 use serde::{Deserialize, Serialize};
-use shuttle_common::claims::{Claim, Scope};
-use tracing::{trace, Span};
+use shuttle_common::backends::auth::RequiredScopes;
+use shuttle_common::claims::{Claim, Scope, ScopeBuilder};
+use shuttle_common::models::team::TeamRole;
+use tracing::{error, trace, Span};
 
 use crate::api::latest::RouterState;
 use crate::{AccountName, Error, ErrorKind, ProjectName};
@@ -31,18 +35,20 @@ where
     type Rejection = Error;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let claim = parts.extensions.get::<Claim>().ok_or(ErrorKind::Internal)?;
-        let name = AccountName::from_str(&claim.sub)
-            .map_err(|err| Error::source(ErrorKind::Internal, err))?;
-
-        // Record current account name for tracing purposes
-        Span::current().record("account.name", &name.to_string());
+        let (claim, name) = claim_and_name(parts)?;
 
         let RouterState { service, .. } = RouterState::from_ref(state);
 
+        let mut projects: Vec<ProjectName> = service.iter_user_projects(&name).await?.collect();
+
+        // Tokens restricted to a single project should only see that project
+        if let Some(project) = &claim.project {
+            projects.retain(|p| p.as_str() == project);
+        }
+
         let user = User {
-            claim: claim.clone(),
-            projects: service.iter_user_projects(&name).await?.collect(),
+            claim,
+            projects,
             name,
         };
 
@@ -52,11 +58,23 @@ where
     }
 }
 
+fn claim_and_name(parts: &Parts) -> Result<(Claim, AccountName), Error> {
+    let claim = parts.extensions.get::<Claim>().ok_or(ErrorKind::Internal)?;
+    let name =
+        AccountName::from_str(&claim.sub).map_err(|err| Error::source(ErrorKind::Internal, err))?;
+
+    // Record current account name for tracing purposes
+    Span::current().record("account.name", &name.to_string());
+
+    Ok((claim.clone(), name))
+}
+
 /// A wrapper for a guard that validates a user's API token *and*
 /// scopes the request to a project they own.
 ///
 /// It is guaranteed that [`ScopedUser::scope`] exists and is owned
-/// by [`ScopedUser::name`].
+/// by [`ScopedUser::name`], either directly or through a team in which
+/// their role allows the request.
 #[derive(Clone)]
 pub struct ScopedUser {
     pub user: User,
@@ -72,20 +90,240 @@ where
     type Rejection = Error;
 
     async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
-        let user = User::from_request_parts(parts, state).await?;
-
-        let scope = match Path::<ProjectName>::from_request_parts(parts, state).await {
-            Ok(Path(p)) => p,
-            Err(_) => Path::<(ProjectName, String)>::from_request_parts(parts, state)
-                .await
-                .map(|Path((p, _))| p)
-                .unwrap(),
+        let (claim, name) = claim_and_name(parts)?;
+
+        let Path(params) = Path::<HashMap<String, String>>::from_request_parts(parts, state)
+            .await
+            .map_err(|err| Error::source(ErrorKind::Internal, err))?;
+        let scope: ProjectName = params
+            .get("project_name")
+            .ok_or(ErrorKind::Internal)?
+            .parse()?;
+
+        if claim
+            .project
+            .as_ref()
+            .is_some_and(|project| project != scope.as_str())
+        {
+            trace!(project = ?claim.project, "token is restricted to another project");
+            return Err(Error::from(ErrorKind::Forbidden));
+        }
+
+        // The user is only scoped to this project, so only this project is looked up
+        let user = User {
+            claim,
+            projects: vec![scope.clone()],
+            name,
         };
 
-        if user.projects.contains(&scope) || user.claim.scopes.contains(&Scope::Admin) {
-            Ok(Self { user, scope })
-        } else {
-            Err(Error::from(ErrorKind::ProjectNotFound))
+        if user.claim.scopes.contains(&Scope::Admin) {
+            return Ok(Self { user, scope });
         }
+
+        // Every route to a project declares the scopes it needs with a `ScopedLayer`, or takes them
+        // from the deployer route it goes to
+        let RequiredScopes(required) = parts
+            .extensions
+            .get::<RequiredScopes>()
+            .cloned()
+            .ok_or_else(|| {
+                error!("route to a project has no required scopes");
+                Error::from(ErrorKind::Internal)
+            })?;
+
+        let RouterState { service, .. } = RouterState::from_ref(state);
+
+        match service.project_role(&user.name, &scope).await? {
+            Some(role) => {
+                let allowed = role_scopes(role);
+
+                if required.iter().all(|scope| allowed.contains(scope)) {
+                    Ok(Self { user, scope })
+                } else {
+                    trace!(?role, ?required, "role does not allow request");
+                    Err(Error::from(ErrorKind::Forbidden))
+                }
+            }
+            None => Err(Error::from(ErrorKind::ProjectNotFound)),
+        }
+    }
+}
+
+/// The scopes a role gives on the projects of a team
+pub fn role_scopes(role: TeamRole) -> Vec<Scope> {
+    match role {
+        TeamRole::Owner => ScopeBuilder::new().with_basic().build(),
+        TeamRole::Deployer => ScopeBuilder::new()
+            .with_basic()
+            .build()
+            .into_iter()
+            .filter(|scope| *scope != Scope::ProjectWrite)
+            .collect(),
+        TeamRole::Viewer => vec![Scope::Logs, Scope::Project, Scope::Deployment],
+    }
+}
+
+/// The scope needed by each route of the deployer, by method and by the path after
+/// `/projects/:project_name`. A `:name` segment matches any one segment and a trailing `*` matches
+/// the rest of the path. Requests to a project are proxied to its deployer as they are, so a new
+/// deployer route only needs a line here.
+static DEPLOYER_ROUTES: &[(Method, &str, Scope)] = &[
+    (Method::GET, "/status", Scope::Project),
+    (Method::GET, "/swagger-ui", Scope::Project),
+    (Method::GET, "/swagger-ui/*", Scope::Project),
+    (Method::GET, "/api-docs/openapi.json", Scope::Project),
+    (Method::GET, "/services", Scope::Service),
+    (Method::GET, "/services/:service_name", Scope::Service),
+    (
+        Method::POST,
+        "/services/:service_name",
+        Scope::ServiceCreate,
+    ),
+    (
+        Method::DELETE,
+        "/services/:service_name",
+        Scope::ServiceCreate,
+    ),
+    (
+        Method::GET,
+        "/services/:service_name/metrics",
+        Scope::Service,
+    ),
+    (
+        Method::GET,
+        "/services/:service_name/resources",
+        Scope::Resources,
+    ),
+    (
+        Method::DELETE,
+        "/services/:service_name/resources/:resource_type",
+        Scope::ResourcesWrite,
+    ),
+    (Method::GET, "/deployments", Scope::Service),
+    (
+        Method::GET,
+        "/deployments/:deployment_id",
+        Scope::Deployment,
+    ),
+    (
+        Method::DELETE,
+        "/deployments/:deployment_id",
+        Scope::DeploymentPush,
+    ),
+    (
+        Method::PUT,
+        "/deployments/:deployment_id",
+        Scope::DeploymentPush,
+    ),
+    (Method::GET, "/deployments/:deployment_id/logs", Scope::Logs),
+    (
+        Method::GET,
+        "/ws/deployments/:deployment_id/logs",
+        Scope::Logs,
+    ),
+    (Method::GET, "/secrets/:service_name", Scope::Secret),
+    (Method::POST, "/clean", Scope::DeploymentPush),
+    (Method::DELETE, "/logs", Scope::Admin),
+];
+
+/// Get the scope needed to call a deployer route, or `None` if the deployer has no such route
+pub fn deployer_route_scope(method: &Method, path: &str) -> Option<Scope> {
+    DEPLOYER_ROUTES
+        .iter()
+        .find(|(route_method, route, _)| route_method == method && route_matches(route, path))
+        .map(|(_, _, scope)| scope.clone())
+}
+
+fn route_matches(route: &str, path: &str) -> bool {
+    let mut parts = path.split('/');
+
+    for segment in route.split('/') {
+        match (segment, parts.next()) {
+            ("*", Some(_)) => return true,
+            (_, None) => return false,
+            (segment, Some(part)) if segment.starts_with(':') => {
+                if part.is_empty() {
+                    return false;
+                }
+            }
+            (segment, Some(part)) => {
+                if segment != part {
+                    return false;
+                }
+            }
+        }
+    }
+
+    parts.next().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewer_can_only_read() {
+        let scopes = role_scopes(TeamRole::Viewer);
+
+        assert!(scopes.contains(&Scope::Project));
+        assert!(scopes.contains(&Scope::Deployment));
+        assert!(scopes.contains(&Scope::Logs));
+        assert!(!scopes.contains(&Scope::ServiceCreate));
+        assert!(!scopes.contains(&Scope::Secret));
+        assert!(!scopes.contains(&Scope::Service));
+        assert!(!scopes.contains(&Scope::ProjectWrite));
+    }
+
+    #[test]
+    fn deployer_cannot_delete() {
+        let scopes = role_scopes(TeamRole::Deployer);
+
+        assert!(scopes.contains(&Scope::ServiceCreate));
+        assert!(scopes.contains(&Scope::DeploymentPush));
+        assert!(!scopes.contains(&Scope::ProjectWrite));
+    }
+
+    #[test]
+    fn deployer_routes() {
+        assert_eq!(
+            deployer_route_scope(&Method::GET, "/status"),
+            Some(Scope::Project)
+        );
+        assert_eq!(
+            deployer_route_scope(&Method::GET, "/swagger-ui/index.html"),
+            Some(Scope::Project)
+        );
+        assert_eq!(
+            deployer_route_scope(&Method::GET, "/api-docs/openapi.json"),
+            Some(Scope::Project)
+        );
+        assert_eq!(
+            deployer_route_scope(&Method::GET, "/services/hello"),
+            Some(Scope::Service)
+        );
+        assert_eq!(
+            deployer_route_scope(&Method::POST, "/services/hello"),
+            Some(Scope::ServiceCreate)
+        );
+        assert_eq!(
+            deployer_route_scope(&Method::DELETE, "/services/hello/resources/secrets"),
+            Some(Scope::ResourcesWrite)
+        );
+        assert_eq!(
+            deployer_route_scope(&Method::GET, "/ws/deployments/some-id/logs"),
+            Some(Scope::Logs)
+        );
+        assert_eq!(
+            deployer_route_scope(&Method::DELETE, "/logs"),
+            Some(Scope::Admin)
+        );
+
+        assert_eq!(deployer_route_scope(&Method::PUT, "/services/hello"), None);
+        assert_eq!(deployer_route_scope(&Method::GET, "/services/"), None);
+        assert_eq!(
+            deployer_route_scope(&Method::GET, "/swagger-ui/"),
+            Some(Scope::Project)
+        );
+        assert_eq!(deployer_route_scope(&Method::GET, "/unknown"), None);
     }
 }
@@ -19,6 +19,7 @@ use once_cell::sync::Lazy;
 use serde::{Deserialize, Deserializer, Serialize};
 use service::ContainerSettings;
 use shuttle_common::models::error::{ApiError, ErrorKind};
+use shuttle_common::quota::QuotaExceeded;
 use tokio::sync::mpsc::error::SendError;
 use tracing::error;
 
@@ -31,6 +32,7 @@ pub mod proxy;
 pub mod service;
 pub mod task;
 pub mod tls;
+pub mod usage;
 pub mod worker;
 
 static AUTH_CLIENT: Lazy<Client<HttpConnector>> = Lazy::new(Client::new);
@@ -94,6 +96,12 @@ impl From<io::Error> for Error {
     }
 }
 
+impl From<QuotaExceeded> for Error {
+    fn from(quota: QuotaExceeded) -> Self {
+        Self::from_kind(ErrorKind::QuotaExceeded(quota))
+    }
+}
+
 impl From<AcmeClientError> for Error {
     fn from(error: AcmeClientError) -> Self {
         Self::source(ErrorKind::Internal, error)
@@ -206,6 +214,55 @@ impl<'de> Deserialize<'de> for AccountName {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::Type, Serialize)]
+#[sqlx(transparent)]
+pub struct TeamName(String);
+
+impl TeamName {
+    pub fn is_valid(&self) -> bool {
+        fn is_valid_char(byte: u8) -> bool {
+            matches!(byte, b'a'..=b'z' | b'0'..=b'9' | b'-')
+        }
+
+        !(self.0.bytes().any(|byte| !is_valid_char(byte))
+            || self.0.ends_with('-')
+            || self.0.starts_with('-')
+            || self.0.is_empty()
+            || self.0.len() > 63)
+    }
+}
+
+impl FromStr for TeamName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let team_name = Self(s.to_string());
+
+        if team_name.is_valid() {
+            Ok(team_name)
+        } else {
+            Err(Error::from_kind(ErrorKind::InvalidTeamName))
+        }
+    }
+}
+
+impl std::fmt::Display for TeamName {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl<'de> Deserialize<'de> for TeamName {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(<D::Error as serde::de::Error>::custom)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ProjectDetails {
     pub project_name: ProjectName,
@@ -314,7 +371,7 @@ pub mod tests {
     use anyhow::{anyhow, Context as AnyhowContext};
     use axum::headers::authorization::Bearer;
     use axum::headers::Authorization;
-    use axum::routing::get;
+    use axum::routing::{get, head};
     use axum::{extract, Router, TypedHeader};
     use bollard::Docker;
     use fqdn::FQDN;
@@ -593,6 +650,7 @@ pub mod tests {
                 user,
                 bouncer,
                 use_tls: UseTls::Disable,
+                trusted_proxies: Vec::new(),
                 context: ContextArgs {
                     docker_host,
                     image,
@@ -603,6 +661,7 @@ pub mod tests {
                     network_name,
                     proxy_fqdn: FQDN::from_str("test.shuttleapp.rs").unwrap(),
                     deploys_api_key: "gateway".to_string(),
+                    user_otlp_endpoint: None,
                 },
             };
 
@@ -729,12 +788,22 @@ pub mod tests {
                         if let Some(scopes) = state.users.get(bearer.token()) {
                             let claim = Claim::new(bearer.token().to_string(), scopes.clone());
                             let token = claim.into_token(&state.encoding_key)?;
-                            Ok(serde_json::to_vec(&ConvertResponse { token }).unwrap())
+                            Ok(serde_json::to_vec(&ConvertResponse { token, refresh_token: None }).unwrap())
                         } else {
                             Err(StatusCode::NOT_FOUND)
                         }
                     }),
                 )
+                .route(
+                    "/users/:account_name",
+                    head(|extract::State(state): extract::State<Arc<Mutex<Self>>>, extract::Path(account_name): extract::Path<String>| async move {
+                        if state.lock().unwrap().users.contains_key(&account_name) {
+                            StatusCode::OK
+                        } else {
+                            StatusCode::NOT_FOUND
+                        }
+                    }),
+                )
                 .with_state(this.clone());
 
             tokio::spawn(async move {
@@ -179,6 +179,7 @@ async fn start(db: SqlitePool, fs: PathBuf, args: StartArgs) -> io::Result<()> {
 
     let api_handle = api_builder
         .with_default_routes()
+        .with_audit_log(args.trusted_proxies)
         .with_auth_service(args.context.auth_uri)
         .with_default_traces()
         .serve();
@@ -24,7 +24,9 @@ use once_cell::sync::Lazy;
 use rand::distributions::{Alphanumeric, DistString};
 use serde::{Deserialize, Serialize};
 use shuttle_common::backends::headers::{X_SHUTTLE_ACCOUNT_NAME, X_SHUTTLE_ADMIN_SECRET};
-use shuttle_common::models::project::{default_idle_minutes, DEFAULT_IDLE_MINUTES};
+use shuttle_common::models::project::{
+    default_idle_minutes, ContainerLimits, DEFAULT_IDLE_MINUTES,
+};
 use shuttle_common::models::service;
 use tokio::time::{sleep, timeout};
 use tracing::{debug, error, info, instrument, trace, warn};
@@ -127,6 +129,33 @@ pub trait ContainerInspectResponseExt {
         DEFAULT_IDLE_MINUTES
     }
 
+    /// Resource limits from the host config of the container, falling back to the defaults for unset limits
+    fn container_limits(&self) -> ContainerLimits {
+        let container = self.container();
+        let default = ContainerLimits::default();
+
+        match &container.host_config {
+            Some(host_config) => ContainerLimits {
+                memory_bytes: host_config.memory.unwrap_or(default.memory_bytes),
+                cpu_shares: host_config.cpu_shares.unwrap_or(default.cpu_shares),
+                cpu_quota: host_config.cpu_quota.unwrap_or(default.cpu_quota),
+                pids_limit: host_config.pids_limit.unwrap_or(default.pids_limit),
+            },
+            None => default,
+        }
+    }
+
+    /// Whether the container was killed by the kernel for exceeding its memory limit
+    fn oom_killed(&self) -> bool {
+        let container = self.container();
+
+        container
+            .state
+            .as_ref()
+            .and_then(|state| state.oom_killed)
+            .unwrap_or_default()
+    }
+
     fn find_arg_and_then<'s, F, O>(&'s self, find: &str, and_then: F) -> Result<O, ProjectError>
     where
         F: FnOnce(&'s str) -> O,
@@ -303,6 +332,11 @@ impl Project {
         matches!(self, Self::Destroyed(_))
     }
 
+    /// Whether the container of the project is up and running
+    pub fn is_running(&self) -> bool {
+        matches!(self, Self::Started(_) | Self::Ready(_))
+    }
+
     pub fn is_stopped(&self) -> bool {
         matches!(self, Self::Stopped(_))
     }
@@ -393,6 +427,21 @@ impl Project {
     pub fn idle_minutes(&self) -> Option<u64> {
         self.container().map(|container| container.idle_minutes())
     }
+
+    pub fn container_limits(&self) -> Option<ContainerLimits> {
+        self.container()
+            .map(|container| container.container_limits())
+    }
+
+    /// Why the project stopped when it was not stopped on request
+    pub fn stop_reason(&self) -> Option<String> {
+        match self {
+            Self::Stopped(ProjectStopped { container }) if container.oom_killed() => {
+                Some(out_of_memory_message(container))
+            }
+            _ => None,
+        }
+    }
 }
 
 impl From<Project> for shuttle_common::models::project::State {
@@ -575,6 +624,11 @@ where
                     ContainerStateStatusEnum::RUNNING => {
                         Self::Started(ProjectStarted::new(container, start_count, stats))
                     }
+                    // Report containers killed for exceeding their memory limit. The error
+                    // recovers to the stopped state, which keeps the reason in the project status.
+                    ContainerStateStatusEnum::EXITED if container.oom_killed() => {
+                        Self::Errored(ProjectError::out_of_memory(container))
+                    }
                     // Restart the container if it went down
                     ContainerStateStatusEnum::EXITED => Self::Restarting(ProjectRestarting {
                         container,
@@ -607,6 +661,11 @@ where
                     ContainerStateStatusEnum::RUNNING => {
                         Self::Ready(ProjectReady::new(container, service, stats))
                     }
+                    // Report containers killed for exceeding their memory limit. The error
+                    // recovers to the stopped state, which keeps the reason in the project status.
+                    ContainerStateStatusEnum::EXITED if container.oom_killed() => {
+                        Self::Errored(ProjectError::out_of_memory(container))
+                    }
                     // Restart the container if it went down
                     ContainerStateStatusEnum::EXITED => Self::Restarting(ProjectRestarting {
                         container,
@@ -687,6 +746,9 @@ pub struct ProjectCreating {
     /// Label set on container as to how many minutes to wait before a project is considered idle
     #[serde(default = "default_idle_minutes")]
     idle_minutes: u64,
+    /// Resource limits to apply to the container
+    #[serde(default)]
+    limits: ContainerLimits,
 }
 
 impl ProjectCreating {
@@ -705,9 +767,12 @@ impl ProjectCreating {
             from: None,
             recreate_count: 0,
             idle_minutes,
+            limits: Default::default(),
         }
     }
 
+    /// Recreate the container of a project. The limits of the old container are only kept until
+    /// the state is stored, which applies the limits stored for the project.
     pub fn from_container(
         container: ContainerInspectResponse,
         recreate_count: usize,
@@ -716,6 +781,7 @@ impl ProjectCreating {
         let project_id = container.project_id()?;
         let idle_minutes = container.idle_minutes();
         let initial_key = container.initial_key()?;
+        let limits = container.container_limits();
 
         Ok(Self {
             project_name,
@@ -726,6 +792,7 @@ impl ProjectCreating {
             from: Some(container),
             recreate_count,
             idle_minutes,
+            limits,
         })
     }
 
@@ -753,6 +820,11 @@ impl ProjectCreating {
         self
     }
 
+    pub fn with_limits(mut self, limits: ContainerLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     pub fn project_name(&self) -> &ProjectName {
         &self.project_name
     }
@@ -765,6 +837,10 @@ impl ProjectCreating {
         &self.fqdn
     }
 
+    pub fn limits(&self) -> &ContainerLimits {
+        &self.limits
+    }
+
     fn container_name<C: DockerContext>(&self, ctx: &C) -> String {
         let prefix = &ctx.container_settings().prefix;
 
@@ -792,6 +868,7 @@ impl ProjectCreating {
             builder_host,
             auth_uri,
             fqdn: public,
+            user_otlp_endpoint,
             ..
         } = ctx.container_settings();
 
@@ -801,6 +878,7 @@ impl ProjectCreating {
             fqdn,
             image,
             idle_minutes,
+            limits,
             ..
         } = &self;
 
@@ -814,6 +892,39 @@ impl ProjectCreating {
             .as_ref()
             .and_then(|container| container.config.clone())
             .unwrap_or_else(|| {
+                let mut cmd = serde_json::json!([
+                    "--admin-secret",
+                    initial_key,
+                    "--project",
+                    project_name,
+                    "--api-address",
+                    format!("0.0.0.0:{RUNTIME_API_PORT}"),
+                    "--provisioner-address",
+                    format!("http://{provisioner_host}:8000"),
+                    "--proxy-address",
+                    "0.0.0.0:8000",
+                    "--proxy-fqdn",
+                    fqdn.clone().unwrap_or(format!("{project_name}.{public}")),
+                    "--artifacts-path",
+                    "/opt/shuttle",
+                    "--state",
+                    "/opt/shuttle/deployer.sqlite",
+                    "--auth-uri",
+                    auth_uri,
+                    "--builder-uri",
+                    format!("http://{builder_host}:8000"),
+                    "--project-id",
+                    self.project_id.to_string()
+                ]);
+                if let Some(user_otlp_endpoint) = user_otlp_endpoint {
+                    cmd.as_array_mut()
+                        .expect("the command should be an array")
+                        .extend([
+                            "--user-otlp-endpoint".into(),
+                            user_otlp_endpoint.clone().into(),
+                        ]);
+                }
+
                 deserialize_json!({
                     "Image": image.as_ref().unwrap_or(default_image),
                     "Hostname": format!("{prefix}{project_name}"),
@@ -823,30 +934,7 @@ impl ProjectCreating {
                         "shuttle.project_id": self.project_id.to_string(),
                         "shuttle.idle_minutes": format!("{idle_minutes}"),
                     },
-                    "Cmd": [
-                        "--admin-secret",
-                        initial_key,
-                        "--project",
-                        project_name,
-                        "--api-address",
-                        format!("0.0.0.0:{RUNTIME_API_PORT}"),
-                        "--provisioner-address",
-                        format!("http://{provisioner_host}:8000"),
-                        "--proxy-address",
-                        "0.0.0.0:8000",
-                        "--proxy-fqdn",
-                        fqdn.clone().unwrap_or(format!("{project_name}.{public}")),
-                        "--artifacts-path",
-                        "/opt/shuttle",
-                        "--state",
-                        "/opt/shuttle/deployer.sqlite",
-                        "--auth-uri",
-                        auth_uri,
-                        "--builder-uri",
-                        format!("http://{builder_host}:8000"),
-                        "--project-id",
-                        self.project_id.to_string()
-                    ],
+                    "Cmd": cmd,
                 })
             });
 
@@ -859,11 +947,13 @@ impl ProjectCreating {
                 "Type": "volume"
             }],
             // https://docs.docker.com/config/containers/resource_constraints/#memory
-            "Memory": 6442450000i64, // 6 GiB hard limit
-            "MemoryReservation": 4295000000i64, // 4 GiB soft limit, applied if host is low on memory
+            "Memory": limits.memory_bytes, // Hard limit
+            "MemoryReservation": limits.memory_bytes / 3 * 2, // Soft limit, applied if host is low on memory
             // https://docs.docker.com/config/containers/resource_constraints/#cpu
+            "CpuShares": limits.cpu_shares,
             "CpuPeriod": 100000i64,
-            "CpuQuota": 400000i64
+            "CpuQuota": limits.cpu_quota,
+            "PidsLimit": limits.pids_limit
         });
 
         debug!(
@@ -1663,6 +1753,7 @@ where
 pub enum ProjectErrorKind {
     Internal,
     NoNetwork,
+    OutOfMemory,
 }
 
 /// A runtime error coming from inside a project
@@ -1689,6 +1780,23 @@ impl ProjectError {
             ctx: None,
         }
     }
+
+    /// The project container was killed for going over its memory limit
+    pub fn out_of_memory(container: ContainerInspectResponse) -> Self {
+        Self {
+            kind: ProjectErrorKind::OutOfMemory,
+            message: out_of_memory_message(&container),
+            ctx: Some(Box::new(Project::Stopped(ProjectStopped { container }))),
+        }
+    }
+}
+
+fn out_of_memory_message(container: &ContainerInspectResponse) -> String {
+    format!(
+        "project was killed after running out of memory (limit: {} MiB). \
+        Use `cargo shuttle project restart` to start it again",
+        container.container_limits().memory_bytes / (1024 * 1024)
+    )
 }
 
 impl std::fmt::Display for ProjectError {
@@ -1885,7 +1993,7 @@ pub mod exec {
 #[cfg(test)]
 pub mod tests {
 
-    use bollard::models::ContainerState;
+    use bollard::models::{ContainerState, HostConfig};
     use bollard::service::NetworkSettings;
     use futures::prelude::*;
     use hyper::{Body, Request, StatusCode};
@@ -1894,6 +2002,98 @@ pub mod tests {
     use crate::tests::{assert_matches, assert_stream_matches, World};
     use crate::StateExt;
 
+    #[tokio::test]
+    async fn deployer_gets_the_user_otlp_endpoint() {
+        let world = World::new().await;
+        let creating = ProjectCreating::new_with_random_initial_key(
+            "my-project-test".parse().unwrap(),
+            Ulid::new(),
+            0,
+        );
+
+        let (_, config) = creating.generate_container_config(&world.context());
+        assert!(!config
+            .cmd
+            .unwrap()
+            .contains(&"--user-otlp-endpoint".to_string()));
+
+        let mut ctx = world.context();
+        ctx.container_settings.user_otlp_endpoint = Some("http://otel-collector:4317".to_string());
+
+        let (_, config) = creating.generate_container_config(&ctx);
+        let cmd = config.cmd.unwrap();
+        assert!(
+            cmd.windows(2)
+                .any(|args| args == ["--user-otlp-endpoint", "http://otel-collector:4317"]),
+            "unexpected command: {cmd:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn container_gets_the_project_limits() {
+        let world = World::new().await;
+        let limits = ContainerLimits {
+            memory_bytes: 512 * 1024 * 1024,
+            cpu_shares: 512,
+            cpu_quota: 200000,
+            pids_limit: 128,
+        };
+        let creating = ProjectCreating::new_with_random_initial_key(
+            "my-project-test".parse().unwrap(),
+            Ulid::new(),
+            0,
+        )
+        .with_limits(limits);
+
+        let (_, config) = creating.generate_container_config(&world.context());
+        let host_config = config.host_config.unwrap();
+        assert_eq!(host_config.memory, Some(limits.memory_bytes));
+        assert_eq!(host_config.cpu_shares, Some(limits.cpu_shares));
+        assert_eq!(host_config.cpu_quota, Some(limits.cpu_quota));
+        assert_eq!(host_config.pids_limit, Some(limits.pids_limit));
+    }
+
+    #[tokio::test]
+    async fn out_of_memory_project_is_stopped() {
+        let world = World::new().await;
+        let container = ContainerInspectResponse {
+            state: Some(ContainerState {
+                status: Some(ContainerStateStatusEnum::EXITED),
+                oom_killed: Some(true),
+                ..Default::default()
+            }),
+            host_config: Some(HostConfig {
+                memory: Some(256 * 1024 * 1024),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let error = ProjectError::out_of_memory(container.clone());
+        assert_eq!(error.kind, ProjectErrorKind::OutOfMemory);
+        assert!(
+            error.message.contains("limit: 256 MiB"),
+            "unexpected message: {}",
+            error.message
+        );
+
+        // The error recovers instead of keeping the project errored, and the reason stays around
+        let project = Project::Errored(error)
+            .refresh(&world.context())
+            .await
+            .unwrap();
+        assert!(matches!(project, Project::Stopped(_)));
+        assert_eq!(
+            project.stop_reason(),
+            Some(out_of_memory_message(&container))
+        );
+
+        let stopped = Project::Stopped(ProjectStopped {
+            container: ContainerInspectResponse::default(),
+        });
+        assert_eq!(stopped.stop_reason(), None);
+    }
+
     #[tokio::test]
     async fn create_start_stop_destroy_project() -> anyhow::Result<()> {
         let world = World::new().await;
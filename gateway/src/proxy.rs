@@ -158,6 +158,11 @@ impl UserProxy {
             .map_err(|_| Error::from_kind(ErrorKind::ProjectUnavailable))?;
 
         let (parts, body) = proxy.into_parts();
+
+        // Record the request and the size of its response once the body has been sent
+        let mut meter = self.gateway.usage().meter_request(project_name);
+        let body =
+            Body::wrap_stream(body.inspect_ok(move |chunk| meter.add_response_bytes(chunk.len())));
         let body = <Body as HttpBody>::map_err(body, axum::Error::new).boxed_unsync();
 
         span.record("http.status_code", parts.status.as_u16());
@@ -4,11 +4,13 @@ use std::ops::Sub;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+use async_trait::async_trait;
 use axum::body::Body;
 use axum::headers::HeaderMapExt;
-use axum::http::Request;
+use axum::http::{Method, Request, StatusCode};
 use axum::response::Response;
 use bollard::{Docker, API_DEFAULT_VERSION};
+use chrono::{DateTime, TimeZone, Utc};
 use fqdn::{Fqdn, FQDN};
 use http::header::AUTHORIZATION;
 use http::Uri;
@@ -20,8 +22,13 @@ use instant_acme::{AccountCredentials, ChallengeType};
 use once_cell::sync::Lazy;
 use opentelemetry::global;
 use opentelemetry_http::HeaderInjector;
+use shuttle_common::backends::audit::{AuditEvent, AuditSink};
 use shuttle_common::backends::headers::{XShuttleAccountName, XShuttleAdminSecret};
-use shuttle_common::models::project::State;
+use shuttle_common::claims::AccountTier;
+use shuttle_common::models::project::{ContainerLimits, ContainerLimitsOverride, State};
+use shuttle_common::models::stats::ProjectUsage;
+use shuttle_common::models::team::TeamRole;
+use shuttle_common::quota::{QuotaExceeded, QuotaKind};
 use sqlx::error::DatabaseError;
 use sqlx::migrate::Migrator;
 use sqlx::sqlite::SqlitePool;
@@ -43,15 +50,76 @@ use crate::args::ContextArgs;
 use crate::project::{Project, ProjectCreating, ProjectError, IS_HEALTHY_TIMEOUT};
 use crate::task::{self, BoxedTask, TaskBuilder};
 use crate::tls::{ChainAndPrivateKey, GatewayCertResolver, RENEWAL_VALIDITY_THRESHOLD_IN_DAYS};
+use crate::usage::{self, UsageRecorder};
 use crate::worker::TaskRouter;
 use crate::{
-    AccountName, DockerContext, Error, ErrorKind, ProjectDetails, ProjectName, AUTH_CLIENT,
+    AccountName, DockerContext, Error, ErrorKind, ProjectDetails, ProjectName, TeamName,
+    AUTH_CLIENT,
 };
 
 pub static MIGRATIONS: Migrator = sqlx::migrate!("./migrations");
 static PROXY_CLIENT: Lazy<ReverseProxy<HttpConnector<GaiResolver>>> =
     Lazy::new(|| ReverseProxy::new(Client::new()));
 
+/// Condition for a statement creating a project of the account bound to `?5`, which holds while
+/// the account owns less than `?6` projects that are not destroyed. A `NULL` limit always holds.
+const PROJECT_QUOTA_CHECK: &str = r#"(?6 IS NULL OR (
+            SELECT COUNT(*) FROM projects
+            WHERE account_name = ?5 AND team_name IS NULL
+            AND json_type(project_state, '$.destroyed') IS NULL
+        ) < ?6)"#;
+
+/// Condition for a statement moving the project bound to `?1` into the team bound to `?2`, which
+/// holds while the team has less than `?3` other projects that are not destroyed. Team projects
+/// count towards the quota of their team instead of the one of the account that created them.
+const TEAM_PROJECT_QUOTA_CHECK: &str = r#"(?3 IS NULL OR (
+            SELECT COUNT(*) FROM projects
+            WHERE team_name = ?2 AND project_name != ?1
+            AND json_type(project_state, '$.destroyed') IS NULL
+        ) < ?3)"#;
+
+/// Check that a project being created by an account stays within the quota of its tier. The
+/// number of projects is checked when the project is stored, and the size of its container comes
+/// from the limits stored for the tier.
+fn check_project_quota(account_tier: AccountTier, idle_minutes: u64) -> Result<(), Error> {
+    account_tier.quota().check_idle_minutes(idle_minutes)?;
+
+    Ok(())
+}
+
+/// The most projects an account can own, or `None` when it is unlimited
+fn max_projects(account_tier: AccountTier, is_admin: bool) -> Option<i64> {
+    if is_admin {
+        return None;
+    }
+
+    account_tier
+        .quota()
+        .max_projects
+        .map(|max| i64::try_from(max).unwrap_or(i64::MAX))
+}
+
+fn projects_quota_exceeded(account_tier: AccountTier) -> Error {
+    QuotaExceeded {
+        kind: QuotaKind::Projects,
+        limit: account_tier.quota().max_projects.unwrap_or_default(),
+    }
+    .into()
+}
+
+/// The most projects a team can have, which is the project quota of the team tier
+fn max_team_projects(is_admin: bool) -> Option<i64> {
+    max_projects(AccountTier::Team, is_admin)
+}
+
+fn team_projects_quota_exceeded() -> Error {
+    QuotaExceeded {
+        kind: QuotaKind::TeamProjects,
+        limit: AccountTier::Team.quota().max_projects.unwrap_or_default(),
+    }
+    .into()
+}
+
 impl From<SqlxError> for Error {
     fn from(err: SqlxError) -> Self {
         debug!("internal SQLx error: {err}");
@@ -67,6 +135,7 @@ pub struct ContainerSettingsBuilder {
     auth_uri: Option<String>,
     network_name: Option<String>,
     fqdn: Option<String>,
+    user_otlp_endpoint: Option<String>,
 }
 
 impl Default for ContainerSettingsBuilder {
@@ -85,6 +154,7 @@ impl ContainerSettingsBuilder {
             auth_uri: None,
             network_name: None,
             fqdn: None,
+            user_otlp_endpoint: None,
         }
     }
 
@@ -97,17 +167,24 @@ impl ContainerSettingsBuilder {
             auth_uri,
             image,
             proxy_fqdn,
+            user_otlp_endpoint,
             ..
         } = args;
-        self.prefix(prefix)
+        let builder = self
+            .prefix(prefix)
             .image(image)
             .provisioner_host(provisioner_host)
             .builder_host(builder_host)
             .auth_uri(auth_uri)
             .network_name(network_name)
-            .fqdn(proxy_fqdn)
-            .build()
-            .await
+            .fqdn(proxy_fqdn);
+
+        match user_otlp_endpoint {
+            Some(endpoint) => builder.user_otlp_endpoint(endpoint),
+            None => builder,
+        }
+        .build()
+        .await
     }
 
     pub fn prefix<S: ToString>(mut self, prefix: S) -> Self {
@@ -145,6 +222,11 @@ impl ContainerSettingsBuilder {
         self
     }
 
+    pub fn user_otlp_endpoint<S: ToString>(mut self, endpoint: S) -> Self {
+        self.user_otlp_endpoint = Some(endpoint.to_string());
+        self
+    }
+
     pub async fn build(mut self) -> ContainerSettings {
         let prefix = self.prefix.take().unwrap();
         let image = self.image.take().unwrap();
@@ -163,6 +245,7 @@ impl ContainerSettingsBuilder {
             auth_uri,
             network_name,
             fqdn,
+            user_otlp_endpoint: self.user_otlp_endpoint,
         }
     }
 }
@@ -176,6 +259,8 @@ pub struct ContainerSettings {
     pub auth_uri: String,
     pub network_name: String,
     pub fqdn: String,
+    /// Address of the OTLP collector the deployers send the traces of user services to
+    pub user_otlp_endpoint: Option<String>,
 }
 
 impl ContainerSettings {
@@ -221,6 +306,7 @@ pub struct GatewayService {
     db: SqlitePool,
     task_router: TaskRouter<BoxedTask>,
     state_location: PathBuf,
+    usage: UsageRecorder,
 
     // We store these because we'll need them for the health checks
     provisioner_host: Endpoint,
@@ -245,11 +331,13 @@ impl GatewayService {
         );
 
         let task_router = TaskRouter::new();
+        let usage = UsageRecorder::new(db.clone());
         Self {
             provider,
             db,
             task_router,
             state_location,
+            usage,
             provisioner_host: Endpoint::new(format!("http://{}:8000", args.provisioner_host))
                 .expect("to have a valid provisioner endpoint"),
             auth_host: args.auth_uri,
@@ -339,14 +427,16 @@ impl GatewayService {
         account_name: &AccountName,
         offset: u32,
         limit: u32,
-    ) -> Result<impl Iterator<Item = (String, ProjectName, Project)>, Error> {
+    ) -> Result<impl Iterator<Item = (String, ProjectName, Project, Option<TeamName>)>, Error> {
         let mut query = QueryBuilder::new(
-            "SELECT project_id, project_name, project_state FROM projects WHERE account_name = ",
+            "SELECT project_id, project_name, project_state, team_name FROM projects WHERE (account_name = ",
         );
 
         query
             .push_bind(account_name)
-            .push(" ORDER BY project_id DESC, project_name LIMIT ")
+            .push(" AND team_name IS NULL) OR team_name IN (SELECT team_name FROM team_members WHERE account_name = ")
+            .push_bind(account_name)
+            .push(") ORDER BY project_id DESC, project_name LIMIT ")
             .push_bind(limit);
 
         if offset > 0 {
@@ -371,6 +461,7 @@ impl GatewayService {
                                 "Error when trying to deserialize state of project.",
                             ))
                         }),
+                    row.get("team_name"),
                 )
             });
         Ok(iter)
@@ -381,6 +472,20 @@ impl GatewayService {
         project_name: &ProjectName,
         project: &Project,
     ) -> Result<(), Error> {
+        // A container being (re)created always gets the limits stored for its project, instead of
+        // the limits of the container it replaces
+        let creating;
+        let project = match project {
+            Project::Creating(state) => {
+                let (project_id, account_tier) = self.project_id_and_tier(project_name).await?;
+                let limits = self.container_limits(&project_id, account_tier).await?;
+
+                creating = Project::Creating(state.clone().with_limits(limits));
+                &creating
+            }
+            project => project,
+        };
+
         let query = match project {
             Project::Creating(state) => query(
                 "UPDATE projects SET initial_key = ?1, project_state = ?2 WHERE project_name = ?3",
@@ -425,25 +530,63 @@ impl GatewayService {
         &self,
         AccountName(account_name): &AccountName,
     ) -> Result<impl Iterator<Item = ProjectName>, Error> {
-        let iter = query("SELECT project_name FROM projects WHERE account_name = ?1")
-            .bind(account_name)
-            .fetch_all(&self.db)
-            .await?
-            .into_iter()
-            .map(|row| row.try_get::<ProjectName, _>("project_name").unwrap());
+        let iter = query(
+            r#"
+        SELECT project_name
+        FROM projects
+        WHERE (account_name = ?1 AND team_name IS NULL)
+        OR team_name IN (SELECT team_name FROM team_members WHERE account_name = ?1)
+        "#,
+        )
+        .bind(account_name)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|row| row.try_get::<ProjectName, _>("project_name").unwrap());
+        Ok(iter)
+    }
+
+    /// Projects owned by an account itself, leaving out those of its teams
+    pub async fn iter_owned_projects(
+        &self,
+        account_name: &AccountName,
+    ) -> Result<impl Iterator<Item = ProjectName>, Error> {
+        let iter = query(
+            "SELECT project_name FROM projects WHERE account_name = ?1 AND team_name IS NULL",
+        )
+        .bind(account_name)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|row| row.try_get::<ProjectName, _>("project_name").unwrap());
+
         Ok(iter)
     }
 
+    /// Remove an account from all the teams it is a member of
+    pub async fn remove_account_memberships(
+        &self,
+        account_name: &AccountName,
+    ) -> Result<(), Error> {
+        query("DELETE FROM team_members WHERE account_name = ?1")
+            .bind(account_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn create_project(
         &self,
         project_name: ProjectName,
         account_name: AccountName,
         is_admin: bool,
+        account_tier: AccountTier,
         idle_minutes: u64,
     ) -> Result<FindProjectPayload, Error> {
         if let Some(row) = query(
             r#"
-        SELECT project_name, project_id, account_name, initial_key, project_state
+        SELECT project_name, project_id, account_name, initial_key, project_state, team_name
         FROM projects
         WHERE (project_name = ?1)
         AND (account_name = ?2 OR ?3)
@@ -468,6 +611,11 @@ impl GatewayService {
             let project_id = row.get::<String, _>("project_id");
             if project.is_destroyed() {
                 // But is in `::Destroyed` state, recreate it
+                let limits = self.container_limits(&project_id, account_tier).await?;
+                if !is_admin {
+                    check_project_quota(account_tier, idle_minutes)?;
+                }
+
                 let mut creating = ProjectCreating::new_with_random_initial_key(
                     project_name.clone(),
                     Ulid::from_string(project_id.as_str()).map_err(|err| {
@@ -477,7 +625,8 @@ impl GatewayService {
                         )
                     })?,
                     idle_minutes,
-                );
+                )
+                .with_limits(limits);
                 // Restore previous custom domain, if any
                 match self.find_custom_domain_for_project(&project_id).await {
                     Ok(custom_domain) => {
@@ -488,8 +637,50 @@ impl GatewayService {
                     }
                     Err(error) => return Err(error),
                 }
+                let initial_key = creating.initial_key().to_string();
                 let project = Project::Creating(creating);
-                self.update_project(&project_name, &project).await?;
+
+                // The destroyed project is not counted, so reviving it takes one more project of
+                // the quota of its owner, which is its team if it has one. Checking in the same
+                // statement keeps concurrent requests in the quota.
+                let team_name: Option<TeamName> = row.get("team_name");
+                let updated = match &team_name {
+                    None => query(&format!(
+                        r#"
+        UPDATE projects SET initial_key = ?1, project_state = ?2, account_tier = ?3
+        WHERE project_name = ?4 AND {PROJECT_QUOTA_CHECK}
+        "#
+                    ))
+                    .bind(initial_key)
+                    .bind(SqlxJson(&project))
+                    .bind(account_tier.to_string())
+                    .bind(&project_name)
+                    .bind(&account_name)
+                    .bind(max_projects(account_tier, is_admin)),
+                    Some(team_name) => query(&format!(
+                        r#"
+        UPDATE projects SET initial_key = ?4, project_state = ?5, account_tier = ?6
+        WHERE project_name = ?1 AND {TEAM_PROJECT_QUOTA_CHECK}
+        "#
+                    ))
+                    .bind(&project_name)
+                    .bind(team_name)
+                    .bind(max_team_projects(is_admin))
+                    .bind(initial_key)
+                    .bind(SqlxJson(&project))
+                    .bind(account_tier.to_string()),
+                }
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+
+                if updated == 0 {
+                    return Err(match team_name {
+                        None => projects_quota_exceeded(account_tier),
+                        Some(_) => team_projects_quota_exceeded(),
+                    });
+                }
+
                 Ok(FindProjectPayload {
                     project_id,
                     state: project,
@@ -538,11 +729,22 @@ impl GatewayService {
             // TODO: remove this check when we update the project name rules
             // in shuttle-common
             if project_name.is_valid() {
+                if !is_admin {
+                    check_project_quota(account_tier, idle_minutes)?;
+                }
+
                 // Otherwise attempt to create a new one. This will fail
                 // outright if the project already exists (this happens if
                 // it belongs to another account).
-                self.insert_project(project_name, Ulid::new(), account_name, idle_minutes)
-                    .await
+                self.insert_project(
+                    project_name,
+                    Ulid::new(),
+                    account_name,
+                    account_tier,
+                    idle_minutes,
+                    max_projects(account_tier, is_admin),
+                )
+                .await
             } else {
                 Err(Error::from_kind(ErrorKind::InvalidProjectName))
             }
@@ -554,21 +756,34 @@ impl GatewayService {
         project_name: ProjectName,
         project_id: Ulid,
         account_name: AccountName,
+        account_tier: AccountTier,
         idle_minutes: u64,
+        max_projects: Option<i64>,
     ) -> Result<FindProjectPayload, Error> {
         let project = SqlxJson(Project::Creating(
             ProjectCreating::new_with_random_initial_key(
                 project_name.clone(),
                 project_id,
                 idle_minutes,
-            ),
+            )
+            .with_limits(self.tier_limits(account_tier).await?),
         ));
 
-        query("INSERT INTO projects (project_id, project_name, account_name, initial_key, project_state) VALUES (ulid(), ?1, ?2, ?3, ?4)")
-            .bind(&project_name)
-            .bind(&account_name)
+        // Only insert when the account has room left in its quota, in the same statement to keep
+        // concurrent requests from going over it
+        let inserted = query(&format!(
+            r#"
+        INSERT INTO projects (project_id, project_name, account_name, initial_key, project_state, account_tier)
+        SELECT ulid(), ?4, ?5, ?1, ?2, ?3
+        WHERE {PROJECT_QUOTA_CHECK}
+        "#
+        ))
             .bind(project.initial_key().unwrap())
             .bind(&project)
+            .bind(account_tier.to_string())
+            .bind(&project_name)
+            .bind(&account_name)
+            .bind(max_projects)
             .execute(&self.db)
             .await
             .map_err(|err| {
@@ -581,14 +796,540 @@ impl GatewayService {
                 }
                 // Otherwise this is internal
                 err.into()
+            })?
+            .rows_affected();
+
+        if inserted == 0 {
+            return Err(projects_quota_exceeded(account_tier));
+        }
+
+        let project = project.0;
+
+        Ok(FindProjectPayload {
+            project_id: project_id.to_string(),
+            state: project,
+        })
+    }
+
+    /// Get the container limits for all projects of an account tier
+    pub async fn tier_limits(&self, account_tier: AccountTier) -> Result<ContainerLimits, Error> {
+        let limits = query(
+            "SELECT memory_bytes, cpu_shares, cpu_quota, pids_limit FROM tier_limits WHERE account_tier = ?1",
+        )
+        .bind(account_tier.to_string())
+        .fetch_optional(&self.db)
+        .await?
+        .map(|row| ContainerLimits {
+            memory_bytes: row.get("memory_bytes"),
+            cpu_shares: row.get("cpu_shares"),
+            cpu_quota: row.get("cpu_quota"),
+            pids_limit: row.get("pids_limit"),
+        })
+        .unwrap_or_default();
+
+        Ok(limits)
+    }
+
+    pub async fn set_tier_limits(
+        &self,
+        account_tier: AccountTier,
+        limits: &ContainerLimits,
+    ) -> Result<(), Error> {
+        query("INSERT OR REPLACE INTO tier_limits (account_tier, memory_bytes, cpu_shares, cpu_quota, pids_limit) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .bind(account_tier.to_string())
+            .bind(limits.memory_bytes)
+            .bind(limits.cpu_shares)
+            .bind(limits.cpu_quota)
+            .bind(limits.pids_limit)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the container limits of a project by applying its overrides on top of the limits of its tier
+    pub async fn container_limits(
+        &self,
+        project_id: &str,
+        account_tier: AccountTier,
+    ) -> Result<ContainerLimits, Error> {
+        let overrides = query(
+            "SELECT memory_bytes, cpu_shares, cpu_quota, pids_limit FROM project_limits WHERE project_id = ?1",
+        )
+        .bind(project_id)
+        .fetch_optional(&self.db)
+        .await?
+        .map(|row| ContainerLimitsOverride {
+            memory_bytes: row.get("memory_bytes"),
+            cpu_shares: row.get("cpu_shares"),
+            cpu_quota: row.get("cpu_quota"),
+            pids_limit: row.get("pids_limit"),
+        })
+        .unwrap_or_default();
+
+        Ok(self
+            .tier_limits(account_tier)
+            .await?
+            .with_overrides(&overrides))
+    }
+
+    /// Set the limit overrides of a project. These are applied the next time its container is created.
+    pub async fn set_project_limits(
+        &self,
+        project_name: &ProjectName,
+        overrides: &ContainerLimitsOverride,
+    ) -> Result<ContainerLimits, Error> {
+        let (project_id, account_tier) = self.project_id_and_tier(project_name).await?;
+
+        query("INSERT OR REPLACE INTO project_limits (project_id, memory_bytes, cpu_shares, cpu_quota, pids_limit) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .bind(&project_id)
+            .bind(overrides.memory_bytes)
+            .bind(overrides.cpu_shares)
+            .bind(overrides.cpu_quota)
+            .bind(overrides.pids_limit)
+            .execute(&self.db)
+            .await?;
+
+        self.container_limits(&project_id, account_tier).await
+    }
+
+    async fn project_id_and_tier(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<(String, AccountTier), Error> {
+        let row = query("SELECT project_id, account_tier FROM projects WHERE project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or_else(|| Error::from_kind(ErrorKind::ProjectNotFound))?;
+        let account_tier = row
+            .get::<String, _>("account_tier")
+            .parse::<AccountTier>()
+            .unwrap_or_default();
+
+        Ok((row.get("project_id"), account_tier))
+    }
+
+    /// Get the role an account has on a project. Accounts are the owner of the projects they
+    /// created, until the project is transferred to a team. From then on the role of the
+    /// account in the team is used.
+    pub async fn project_role(
+        &self,
+        account_name: &AccountName,
+        project_name: &ProjectName,
+    ) -> Result<Option<TeamRole>, Error> {
+        let Some(row) = query(
+            r#"
+        SELECT p.account_name, p.team_name, m.role
+        FROM projects AS p
+        LEFT JOIN team_members AS m ON m.team_name = p.team_name AND m.account_name = ?1
+        WHERE p.project_name = ?2
+        "#,
+        )
+        .bind(account_name)
+        .bind(project_name)
+        .fetch_optional(&self.db)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let team_name: Option<TeamName> = row.get("team_name");
+        if team_name.is_none() {
+            let owner: AccountName = row.get("account_name");
+
+            return Ok((&owner == account_name).then_some(TeamRole::Owner));
+        }
+
+        Ok(row
+            .get::<Option<String>, _>("role")
+            .and_then(|role| role.parse().ok()))
+    }
+
+    /// Get the team a project has been transferred to, if any
+    pub async fn project_team(
+        &self,
+        project_name: &ProjectName,
+    ) -> Result<Option<TeamName>, Error> {
+        let team_name = query("SELECT team_name FROM projects WHERE project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .and_then(|row| row.get("team_name"));
+
+        Ok(team_name)
+    }
+
+    pub async fn create_team(
+        &self,
+        team_name: &TeamName,
+        owner: &AccountName,
+    ) -> Result<(), Error> {
+        let mut transaction = self.db.begin().await?;
+
+        query("INSERT INTO teams (team_name, created_at) VALUES (?1, ?2)")
+            .bind(team_name)
+            .bind(Utc::now().timestamp())
+            .execute(&mut *transaction)
+            .await
+            .map_err(|err| {
+                if let Some(db_err_code) = err.as_database_error().and_then(DatabaseError::code) {
+                    if db_err_code == "1555" {
+                        // SQLITE_CONSTRAINT_PRIMARYKEY
+                        return Error::from_kind(ErrorKind::TeamAlreadyExists);
+                    }
+                }
+                err.into()
             })?;
 
-        let project = project.0;
+        query("INSERT INTO team_members (team_name, account_name, role) VALUES (?1, ?2, ?3)")
+            .bind(team_name)
+            .bind(owner)
+            .bind(TeamRole::Owner.to_string())
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Get the teams an account is a member of, along with its role in each
+    pub async fn iter_user_teams(
+        &self,
+        account_name: &AccountName,
+    ) -> Result<impl Iterator<Item = (TeamName, TeamRole)>, Error> {
+        let iter = query(
+            "SELECT team_name, role FROM team_members WHERE account_name = ?1 ORDER BY team_name",
+        )
+        .bind(account_name)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .filter_map(|row| {
+            let role = row.get::<String, _>("role").parse().ok()?;
+
+            Some((row.get("team_name"), role))
+        });
+
+        Ok(iter)
+    }
+
+    pub async fn team_role(
+        &self,
+        team_name: &TeamName,
+        account_name: &AccountName,
+    ) -> Result<Option<TeamRole>, Error> {
+        let role =
+            query("SELECT role FROM team_members WHERE team_name = ?1 AND account_name = ?2")
+                .bind(team_name)
+                .bind(account_name)
+                .fetch_optional(&self.db)
+                .await?
+                .and_then(|row| row.get::<String, _>("role").parse().ok());
+
+        Ok(role)
+    }
+
+    pub async fn team_members(
+        &self,
+        team_name: &TeamName,
+    ) -> Result<Vec<(AccountName, TeamRole)>, Error> {
+        let members = query(
+            "SELECT account_name, role FROM team_members WHERE team_name = ?1 ORDER BY account_name",
+        )
+        .bind(team_name)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .filter_map(|row| {
+            let role = row.get::<String, _>("role").parse().ok()?;
+
+            Some((row.get("account_name"), role))
+        })
+        .collect();
+
+        Ok(members)
+    }
+
+    /// Check with the auth service that an account exists, using the key of the gateway
+    pub async fn account_exists(&self, account_name: &AccountName) -> Result<bool, Error> {
+        let req = Request::builder()
+            .method(Method::HEAD)
+            .uri(format!("{}users/{account_name}", self.auth_host))
+            .header(AUTHORIZATION, format!("Bearer {}", self.provider.api_key))
+            .body(Body::empty())
+            .map_err(|err| Error::source(ErrorKind::Internal, err))?;
+
+        let resp = timeout(IS_HEALTHY_TIMEOUT, AUTH_CLIENT.request(req))
+            .await
+            .map_err(|_| Error::from_kind(ErrorKind::ServiceUnavailable))?
+            .map_err(|err| Error::source(ErrorKind::ServiceUnavailable, err))?;
+
+        match resp.status() {
+            StatusCode::OK => Ok(true),
+            StatusCode::NOT_FOUND => Ok(false),
+            status => Err(Error::custom(
+                ErrorKind::Internal,
+                format!("unexpected response from auth service: {status}"),
+            )),
+        }
+    }
+
+    /// Add an account to a team, or change its role if it is already a member
+    pub async fn set_team_member(
+        &self,
+        team_name: &TeamName,
+        account_name: &AccountName,
+        role: TeamRole,
+    ) -> Result<(), Error> {
+        if role != TeamRole::Owner {
+            self.ensure_other_owner(team_name, account_name).await?;
+        }
+
+        query("INSERT OR REPLACE INTO team_members (team_name, account_name, role) VALUES (?1, ?2, ?3)")
+            .bind(team_name)
+            .bind(account_name)
+            .bind(role.to_string())
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn remove_team_member(
+        &self,
+        team_name: &TeamName,
+        account_name: &AccountName,
+    ) -> Result<(), Error> {
+        self.ensure_other_owner(team_name, account_name).await?;
+
+        query("DELETE FROM team_members WHERE team_name = ?1 AND account_name = ?2")
+            .bind(team_name)
+            .bind(account_name)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Make sure a team keeps an owner when an account stops being one of its owners
+    async fn ensure_other_owner(
+        &self,
+        team_name: &TeamName,
+        account_name: &AccountName,
+    ) -> Result<(), Error> {
+        let owners: i64 = query(
+            "SELECT COUNT(*) FROM team_members WHERE team_name = ?1 AND role = ?2 AND account_name != ?3",
+        )
+        .bind(team_name)
+        .bind(TeamRole::Owner.to_string())
+        .bind(account_name)
+        .fetch_one(&self.db)
+        .await?
+        .get(0);
+
+        if owners == 0 {
+            return Err(Error::custom(
+                ErrorKind::InvalidOperation,
+                "a team needs to keep at least one owner",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Transfer the ownership of a project to a team, or back to the account which created it when
+    /// no team is given. The project has to fit in the project quota of its new owner.
+    pub async fn transfer_project(
+        &self,
+        project_name: &ProjectName,
+        team_name: Option<&TeamName>,
+    ) -> Result<(), Error> {
+        match team_name {
+            Some(team_name) => {
+                let transferred = query(&format!(
+                    r#"
+        UPDATE projects SET team_name = ?2
+        WHERE project_name = ?1 AND {TEAM_PROJECT_QUOTA_CHECK}
+        "#
+                ))
+                .bind(project_name)
+                .bind(team_name)
+                .bind(max_team_projects(false))
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+
+                if transferred == 0 {
+                    return Err(team_projects_quota_exceeded());
+                }
+            }
+            None => {
+                let (_, account_tier) = self.project_id_and_tier(project_name).await?;
+
+                let transferred = query(
+                    r#"
+        UPDATE projects SET team_name = NULL
+        WHERE project_name = ?1 AND (?2 IS NULL OR (
+            SELECT COUNT(*) FROM projects AS owned
+            WHERE owned.account_name = projects.account_name AND owned.team_name IS NULL
+            AND json_type(owned.project_state, '$.destroyed') IS NULL
+        ) < ?2)
+        "#,
+                )
+                .bind(project_name)
+                .bind(max_projects(account_tier, false))
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+
+                if transferred == 0 {
+                    return Err(projects_quota_exceeded(account_tier));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Open or close an uptime period of a project when its container starts or stops running
+    pub async fn update_project_uptime(
+        &self,
+        project_name: &ProjectName,
+        was_running: bool,
+        is_running: bool,
+    ) -> Result<(), Error> {
+        let now = Utc::now().timestamp();
+
+        if !was_running && is_running {
+            query("INSERT INTO project_uptime (project_id, started_at) SELECT project_id, ?2 FROM projects WHERE project_name = ?1")
+                .bind(project_name)
+                .bind(now)
+                .execute(&self.db)
+                .await?;
+        } else if was_running && !is_running {
+            query("UPDATE project_uptime SET stopped_at = ?2 WHERE stopped_at IS NULL AND project_id = (SELECT project_id FROM projects WHERE project_name = ?1)")
+                .bind(project_name)
+                .bind(now)
+                .execute(&self.db)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the usage of a project between two points in time
+    pub async fn project_usage(
+        &self,
+        project_name: &ProjectName,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<ProjectUsage, Error> {
+        let project_id = query("SELECT project_id FROM projects WHERE project_name = ?1")
+            .bind(project_name)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or_else(|| Error::from_kind(ErrorKind::ProjectNotFound))?
+            .get::<String, _>("project_id");
+
+        let counters = query(
+            r#"
+        SELECT
+          COALESCE(SUM(requests), 0) AS requests,
+          COALESCE(SUM(response_bytes), 0) AS response_bytes,
+          COALESCE(SUM(build_seconds), 0) AS build_seconds
+        FROM project_usage
+        WHERE project_id = ?1 AND period_start >= ?2 AND period_start < ?3
+        "#,
+        )
+        .bind(&project_id)
+        .bind(usage::period_start(from))
+        .bind(to.timestamp())
+        .fetch_one(&self.db)
+        .await?;
+
+        // Only count the part of each uptime period which overlaps with the requested range.
+        // Periods which are still open are running up to now.
+        let uptime_seconds = query(
+            r#"
+        SELECT COALESCE(SUM(MIN(COALESCE(stopped_at, ?4), ?3) - MAX(started_at, ?2)), 0) AS uptime_seconds
+        FROM project_uptime
+        WHERE project_id = ?1 AND started_at < ?3 AND COALESCE(stopped_at, ?4) > ?2
+        "#,
+        )
+        .bind(&project_id)
+        .bind(from.timestamp())
+        .bind(to.timestamp())
+        .bind(Utc::now().timestamp())
+        .fetch_one(&self.db)
+        .await?
+        .get::<i64, _>("uptime_seconds");
+
+        Ok(ProjectUsage {
+            from,
+            to,
+            requests: counters.get::<i64, _>("requests") as u64,
+            response_bytes: counters.get::<i64, _>("response_bytes") as u64,
+            uptime_seconds: uptime_seconds.max(0) as u64,
+            build_seconds: counters.get::<i64, _>("build_seconds") as u64,
+        })
+    }
+
+    /// Append an action to the audit log
+    pub async fn record_audit_event(&self, event: &AuditEvent) -> Result<(), Error> {
+        query("INSERT INTO audit_log (timestamp, actor, action, target, source_ip, outcome) VALUES (?1, ?2, ?3, ?4, ?5, ?6)")
+            .bind(event.timestamp.timestamp())
+            .bind(&event.actor)
+            .bind(&event.action)
+            .bind(&event.target)
+            .bind(&event.source_ip)
+            .bind(event.outcome)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Get the most recent actions in the audit log, optionally only those taken by one account
+    pub async fn iter_audit_events(
+        &self,
+        actor: Option<&AccountName>,
+        offset: u32,
+        limit: u32,
+    ) -> Result<impl Iterator<Item = AuditEvent>, Error> {
+        let mut query = QueryBuilder::new(
+            "SELECT timestamp, actor, action, target, source_ip, outcome FROM audit_log",
+        );
+
+        if let Some(actor) = actor {
+            query.push(" WHERE actor = ").push_bind(actor);
+        }
+
+        query.push(" ORDER BY id DESC LIMIT ").push_bind(limit);
+
+        if offset > 0 {
+            query.push(" OFFSET ").push_bind(offset);
+        }
 
-        Ok(FindProjectPayload {
-            project_id: project_id.to_string(),
-            state: project,
-        })
+        let iter = query
+            .build()
+            .fetch_all(&self.db)
+            .await?
+            .into_iter()
+            .map(|row| AuditEvent {
+                actor: row.get("actor"),
+                action: row.get("action"),
+                target: row.get("target"),
+                source_ip: row.get("source_ip"),
+                outcome: row.get("outcome"),
+                timestamp: Utc
+                    .timestamp_opt(row.get("timestamp"), 0)
+                    .single()
+                    .unwrap_or_default(),
+            });
+
+        Ok(iter)
     }
 
     pub async fn delete_project(&self, project_name: &ProjectName) -> Result<(), Error> {
@@ -601,7 +1342,22 @@ impl GatewayService {
         let mut transaction = self.db.begin().await?;
 
         query("DELETE FROM custom_domains WHERE project_id = ?1")
-            .bind(project_id)
+            .bind(&project_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        query("DELETE FROM project_limits WHERE project_id = ?1")
+            .bind(&project_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        query("DELETE FROM project_usage WHERE project_id = ?1")
+            .bind(&project_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        query("DELETE FROM project_uptime WHERE project_id = ?1")
+            .bind(&project_id)
             .execute(&mut *transaction)
             .await?;
 
@@ -827,6 +1583,10 @@ impl GatewayService {
         self.provider.context()
     }
 
+    pub fn usage(&self) -> &UsageRecorder {
+        &self.usage
+    }
+
     /// Create a builder for a new [ProjectTask]
     pub fn new_task(self: &Arc<Self>) -> TaskBuilder {
         TaskBuilder::new(self.clone())
@@ -943,6 +1703,15 @@ impl GatewayContext {
     }
 }
 
+#[async_trait]
+impl AuditSink for GatewayService {
+    async fn record(&self, event: AuditEvent) -> anyhow::Result<()> {
+        self.record_audit_event(&event).await?;
+
+        Ok(())
+    }
+}
+
 pub struct FindProjectPayload {
     pub project_id: String,
     pub state: Project,
@@ -957,6 +1726,7 @@ pub mod tests {
     use crate::task::{self, TaskResult};
     use crate::tests::{assert_err_kind, World};
     use crate::{Error, ErrorKind};
+    use shuttle_common::quota::{QuotaExceeded, QuotaKind};
 
     #[tokio::test]
     async fn service_create_find_stop_delete_project() -> anyhow::Result<()> {
@@ -975,7 +1745,7 @@ pub mod tests {
         };
 
         let project = svc
-            .create_project(matrix.clone(), neo.clone(), false, 0)
+            .create_project(matrix.clone(), neo.clone(), false, AccountTier::Basic, 30)
             .await
             .unwrap();
 
@@ -1007,9 +1777,15 @@ pub mod tests {
 
         // Test project pagination, first create 20 test projects (including the one from above).
         for p in (1..20).map(|p| format!("matrix-{p}")) {
-            svc.create_project(ProjectName(p.clone()), neo.clone(), false, 0)
-                .await
-                .unwrap();
+            svc.create_project(
+                ProjectName(p.clone()),
+                neo.clone(),
+                false,
+                AccountTier::Team,
+                0,
+            )
+            .await
+            .unwrap();
         }
 
         // We need to fetch all of them from the DB since they are ordered by created_at (in the id) and project_name,
@@ -1071,8 +1847,14 @@ pub mod tests {
 
         // If recreated by a different user
         assert!(matches!(
-            svc.create_project(matrix.clone(), trinity.clone(), false, 0)
-                .await,
+            svc.create_project(
+                matrix.clone(),
+                trinity.clone(),
+                false,
+                AccountTier::Basic,
+                30
+            )
+            .await,
             Err(Error {
                 kind: ErrorKind::ProjectAlreadyExists,
                 ..
@@ -1081,7 +1863,7 @@ pub mod tests {
 
         // If recreated by the same user
         assert!(matches!(
-            svc.create_project(matrix.clone(), neo.clone(), false, 0)
+            svc.create_project(matrix.clone(), neo.clone(), false, AccountTier::Team, 30)
                 .await,
             Ok(FindProjectPayload {
                 project_id: _,
@@ -1091,7 +1873,8 @@ pub mod tests {
 
         // If recreated by the same user again while it's running
         assert!(matches!(
-            svc.create_project(matrix.clone(), neo, false, 0).await,
+            svc.create_project(matrix.clone(), neo, false, AccountTier::Basic, 30)
+                .await,
             Err(Error {
                 kind: ErrorKind::OwnProjectAlreadyExists(_),
                 ..
@@ -1118,8 +1901,14 @@ pub mod tests {
 
         // If recreated by an admin
         assert!(matches!(
-            svc.create_project(matrix.clone(), trinity.clone(), true, 0)
-                .await,
+            svc.create_project(
+                matrix.clone(),
+                trinity.clone(),
+                true,
+                AccountTier::Basic,
+                30
+            )
+            .await,
             Ok(FindProjectPayload {
                 project_id: _,
                 state: Project::Creating(_),
@@ -1128,8 +1917,14 @@ pub mod tests {
 
         // If recreated by an admin again while it's running
         assert!(matches!(
-            svc.create_project(matrix.clone(), trinity.clone(), true, 0)
-                .await,
+            svc.create_project(
+                matrix.clone(),
+                trinity.clone(),
+                true,
+                AccountTier::Basic,
+                30
+            )
+            .await,
             Err(Error {
                 kind: ErrorKind::OwnProjectAlreadyExists(_),
                 ..
@@ -1150,7 +1945,8 @@ pub mod tests {
 
         // It can be re-created by anyone, with the same project name
         assert!(matches!(
-            svc.create_project(matrix, trinity, false, 0).await,
+            svc.create_project(matrix, trinity, false, AccountTier::Basic, 30)
+                .await,
             Ok(FindProjectPayload {
                 project_id: _,
                 state: Project::Creating(_),
@@ -1167,7 +1963,7 @@ pub mod tests {
         let neo: AccountName = "neo".parse().unwrap();
         let matrix: ProjectName = "matrix".parse().unwrap();
 
-        svc.create_project(matrix.clone(), neo.clone(), false, 0)
+        svc.create_project(matrix.clone(), neo.clone(), false, AccountTier::Basic, 30)
             .await
             .unwrap();
 
@@ -1228,7 +2024,13 @@ pub mod tests {
         );
 
         let _ = svc
-            .create_project(project_name.clone(), account.clone(), false, 0)
+            .create_project(
+                project_name.clone(),
+                account.clone(),
+                false,
+                AccountTier::Basic,
+                30,
+            )
             .await
             .unwrap();
 
@@ -1282,7 +2084,13 @@ pub mod tests {
         );
 
         let _ = svc
-            .create_project(project_name.clone(), account.clone(), false, 0)
+            .create_project(
+                project_name.clone(),
+                account.clone(),
+                false,
+                AccountTier::Basic,
+                30,
+            )
             .await
             .unwrap();
 
@@ -1300,7 +2108,13 @@ pub mod tests {
         assert!(matches!(work.poll(()).await, TaskResult::Done(())));
 
         let recreated_project = svc
-            .create_project(project_name.clone(), account.clone(), false, 0)
+            .create_project(
+                project_name.clone(),
+                account.clone(),
+                false,
+                AccountTier::Basic,
+                30,
+            )
             .await
             .unwrap();
 
@@ -1311,4 +2125,458 @@ pub mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn service_project_container_limits() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let neo: AccountName = "neo".parse().unwrap();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+
+        let pro_limits = svc.tier_limits(AccountTier::Pro).await?;
+        assert_ne!(pro_limits, svc.tier_limits(AccountTier::Basic).await?);
+
+        let project = svc
+            .create_project(matrix.clone(), neo, false, AccountTier::Pro, 0)
+            .await?;
+        let Project::Creating(creating) = project.state else {
+            panic!("Project should be Creating");
+        };
+        assert_eq!(creating.limits(), &pro_limits);
+
+        let limits = svc
+            .set_project_limits(
+                &matrix,
+                &ContainerLimitsOverride {
+                    memory_bytes: Some(1024),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        assert_eq!(
+            limits,
+            ContainerLimits {
+                memory_bytes: 1024,
+                ..pro_limits
+            }
+        );
+
+        let tier_limits = ContainerLimits {
+            memory_bytes: 2048,
+            cpu_shares: 512,
+            cpu_quota: 200000,
+            pids_limit: 128,
+        };
+        svc.set_tier_limits(AccountTier::Pro, &tier_limits).await?;
+        assert_eq!(
+            svc.container_limits(&project.project_id, AccountTier::Pro)
+                .await?,
+            ContainerLimits {
+                memory_bytes: 1024,
+                ..tier_limits
+            }
+        );
+
+        // A recreated container gets the stored limits rather than the limits it was created with
+        svc.update_project(&matrix, &Project::Creating(creating))
+            .await?;
+        let Project::Creating(recreating) = svc.find_project(&matrix).await?.state else {
+            panic!("Project should be Creating");
+        };
+        assert_eq!(
+            recreating.limits(),
+            &ContainerLimits {
+                memory_bytes: 1024,
+                ..tier_limits
+            }
+        );
+
+        assert_err_kind!(
+            svc.set_project_limits(&"no-matrix".parse().unwrap(), &Default::default())
+                .await,
+            ErrorKind::ProjectNotFound
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_project_quota() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let neo: AccountName = "neo".parse().unwrap();
+        let quota = AccountTier::Basic.quota();
+
+        assert_eq!(
+            svc.create_project(
+                "matrix".parse().unwrap(),
+                neo.clone(),
+                false,
+                AccountTier::Basic,
+                0
+            )
+            .await
+            .map(|_| ())
+            .map_err(|err| err.kind()),
+            Err(ErrorKind::QuotaExceeded(QuotaExceeded {
+                kind: QuotaKind::IdleMinutes,
+                limit: quota.max_idle_minutes.unwrap(),
+            }))
+        );
+
+        let max_projects = quota.max_projects.unwrap();
+        for p in 0..max_projects {
+            svc.create_project(
+                ProjectName(format!("matrix-{p}")),
+                neo.clone(),
+                false,
+                AccountTier::Basic,
+                30,
+            )
+            .await?;
+        }
+
+        assert_eq!(
+            svc.create_project(
+                "reloaded".parse().unwrap(),
+                neo.clone(),
+                false,
+                AccountTier::Basic,
+                30
+            )
+            .await
+            .map(|_| ())
+            .map_err(|err| err.kind()),
+            Err(ErrorKind::QuotaExceeded(QuotaExceeded {
+                kind: QuotaKind::Projects,
+                limit: max_projects,
+            }))
+        );
+
+        // Destroyed projects do not count towards the quota
+        let mut work = svc
+            .new_task()
+            .project("matrix-0".parse().unwrap())
+            .and_then(task::destroy())
+            .build();
+
+        while let TaskResult::Pending(_) = work.poll(()).await {}
+        assert!(matches!(work.poll(()).await, TaskResult::Done(())));
+
+        svc.create_project(
+            "reloaded".parse().unwrap(),
+            neo.clone(),
+            false,
+            AccountTier::Basic,
+            30,
+        )
+        .await?;
+
+        // But reviving one takes up room in the quota again
+        assert_eq!(
+            svc.create_project(
+                "matrix-0".parse().unwrap(),
+                neo.clone(),
+                false,
+                AccountTier::Basic,
+                30
+            )
+            .await
+            .map(|_| ())
+            .map_err(|err| err.kind()),
+            Err(ErrorKind::QuotaExceeded(QuotaExceeded {
+                kind: QuotaKind::Projects,
+                limit: max_projects,
+            }))
+        );
+
+        // Admins are not bound by the quota of their tier
+        svc.create_project(
+            "revolutions".parse().unwrap(),
+            neo,
+            true,
+            AccountTier::Basic,
+            0,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_team_projects() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let neo: AccountName = "neo".parse().unwrap();
+        let trinity: AccountName = "trinity".parse().unwrap();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let zion: TeamName = "zion".parse().unwrap();
+
+        svc.create_project(matrix.clone(), neo.clone(), false, AccountTier::Team, 30)
+            .await?;
+        assert_eq!(
+            svc.project_role(&neo, &matrix).await?,
+            Some(TeamRole::Owner)
+        );
+        assert_eq!(svc.project_role(&trinity, &matrix).await?, None);
+
+        svc.create_team(&zion, &neo).await?;
+        assert_err_kind!(
+            svc.create_team(&zion, &trinity).await,
+            ErrorKind::TeamAlreadyExists
+        );
+
+        svc.set_team_member(&zion, &trinity, TeamRole::Viewer)
+            .await?;
+        svc.transfer_project(&matrix, Some(&zion)).await?;
+
+        assert_eq!(svc.project_team(&matrix).await?, Some(zion.clone()));
+        assert_eq!(
+            svc.project_role(&trinity, &matrix).await?,
+            Some(TeamRole::Viewer)
+        );
+        assert_eq!(
+            svc.iter_user_projects(&trinity).await?.collect::<Vec<_>>(),
+            vec![matrix.clone()]
+        );
+        assert_eq!(
+            svc.iter_user_projects_detailed(&trinity, 0, u32::MAX)
+                .await?
+                .map(|item| item.3)
+                .collect::<Vec<_>>(),
+            vec![Some(zion.clone())]
+        );
+
+        // The last owner cannot leave or be demoted
+        assert_err_kind!(
+            svc.set_team_member(&zion, &neo, TeamRole::Deployer).await,
+            ErrorKind::InvalidOperation
+        );
+        assert_err_kind!(
+            svc.remove_team_member(&zion, &neo).await,
+            ErrorKind::InvalidOperation
+        );
+
+        svc.remove_team_member(&zion, &trinity).await?;
+        assert_eq!(svc.project_role(&trinity, &matrix).await?, None);
+        assert_eq!(svc.iter_user_teams(&trinity).await?.count(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_team_project_quota() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let neo: AccountName = "neo".parse().unwrap();
+        let zion: TeamName = "zion".parse().unwrap();
+        let max_projects = AccountTier::Basic.quota().max_projects.unwrap();
+        let max_team_projects = AccountTier::Team.quota().max_projects.unwrap();
+
+        svc.create_team(&zion, &neo).await?;
+
+        // Fill up the team, without being held back by the quota of the account
+        for p in 0..max_team_projects {
+            let project_name = ProjectName(format!("matrix-{p}"));
+            svc.create_project(
+                project_name.clone(),
+                neo.clone(),
+                true,
+                AccountTier::Basic,
+                30,
+            )
+            .await?;
+            svc.transfer_project(&project_name, Some(&zion)).await?;
+        }
+
+        // Projects moved to a team still count towards the quota of the team
+        let reloaded: ProjectName = "reloaded".parse().unwrap();
+        svc.create_project(reloaded.clone(), neo.clone(), false, AccountTier::Basic, 30)
+            .await?;
+        assert_eq!(
+            svc.transfer_project(&reloaded, Some(&zion))
+                .await
+                .map_err(|err| err.kind()),
+            Err(ErrorKind::QuotaExceeded(QuotaExceeded {
+                kind: QuotaKind::TeamProjects,
+                limit: max_team_projects,
+            }))
+        );
+
+        // And projects moved back to their account count towards the quota of the account
+        let matrix: ProjectName = "matrix-0".parse().unwrap();
+        svc.transfer_project(&matrix, None).await?;
+        assert_eq!(svc.project_team(&matrix).await?, None);
+        svc.transfer_project(&reloaded, Some(&zion)).await?;
+
+        for project_name in ["revolutions", "resurrections"] {
+            svc.create_project(
+                project_name.parse().unwrap(),
+                neo.clone(),
+                false,
+                AccountTier::Basic,
+                30,
+            )
+            .await?;
+        }
+        assert_eq!(
+            svc.transfer_project(&"matrix-1".parse().unwrap(), None)
+                .await
+                .map_err(|err| err.kind()),
+            Err(ErrorKind::QuotaExceeded(QuotaExceeded {
+                kind: QuotaKind::Projects,
+                limit: max_projects,
+            }))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_owned_projects() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let neo: AccountName = "neo".parse().unwrap();
+        let trinity: AccountName = "trinity".parse().unwrap();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+        let nebuchadnezzar: ProjectName = "nebuchadnezzar".parse().unwrap();
+        let zion: TeamName = "zion".parse().unwrap();
+
+        svc.create_project(matrix.clone(), neo.clone(), false, AccountTier::Team, 30)
+            .await?;
+        svc.create_project(
+            nebuchadnezzar.clone(),
+            neo.clone(),
+            false,
+            AccountTier::Team,
+            30,
+        )
+        .await?;
+
+        svc.create_team(&zion, &neo).await?;
+        svc.set_team_member(&zion, &trinity, TeamRole::Owner)
+            .await?;
+        svc.transfer_project(&nebuchadnezzar, Some(&zion)).await?;
+
+        // Projects of teams are not owned by the account itself
+        assert_eq!(
+            svc.iter_owned_projects(&neo).await?.collect::<Vec<_>>(),
+            vec![matrix.clone()]
+        );
+        assert_eq!(svc.iter_owned_projects(&trinity).await?.count(), 0);
+
+        svc.remove_account_memberships(&neo).await?;
+        assert_eq!(svc.iter_user_teams(&neo).await?.count(), 0);
+        assert_eq!(
+            svc.iter_user_projects(&neo).await?.collect::<Vec<_>>(),
+            vec![matrix]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_project_usage() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = Arc::new(GatewayService::init(world.args(), world.pool(), "".into()).await);
+
+        let neo: AccountName = "neo".parse().unwrap();
+        let matrix: ProjectName = "matrix".parse().unwrap();
+
+        svc.create_project(matrix.clone(), neo, false, AccountTier::Basic, 30)
+            .await?;
+
+        let now = Utc::now();
+        let delta = usage::UsageDelta {
+            requests: 2,
+            response_bytes: 1024,
+            build_seconds: 60,
+        };
+        usage::write_usage(&svc.db, &matrix, usage::period_start(now), &delta).await?;
+        usage::write_usage(&svc.db, &matrix, usage::period_start(now), &delta).await?;
+
+        svc.update_project_uptime(&matrix, false, true).await?;
+
+        let project_usage = svc
+            .project_usage(
+                &matrix,
+                now - chrono::Duration::hours(1),
+                now + chrono::Duration::hours(1),
+            )
+            .await?;
+        assert_eq!(project_usage.requests, 4);
+        assert_eq!(project_usage.response_bytes, 2048);
+        assert_eq!(project_usage.build_seconds, 120);
+
+        // Usage outside of the period is not counted
+        let project_usage = svc
+            .project_usage(
+                &matrix,
+                now - chrono::Duration::days(2),
+                now - chrono::Duration::days(1),
+            )
+            .await?;
+        assert_eq!(project_usage.requests, 0);
+        assert_eq!(project_usage.uptime_seconds, 0);
+
+        assert_err_kind!(
+            svc.project_usage(&"no-matrix".parse().unwrap(), now, now)
+                .await,
+            ErrorKind::ProjectNotFound
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn service_audit_log() -> anyhow::Result<()> {
+        let world = World::new().await;
+        let svc = GatewayService::init(world.args(), world.pool(), "".into()).await;
+
+        let neo: AccountName = "neo".parse().unwrap();
+
+        for (actor, target) in [
+            ("neo", "/projects/matrix"),
+            ("trinity", "/projects/nebuchadnezzar"),
+            ("neo", "/projects/zion"),
+        ] {
+            svc.record_audit_event(&AuditEvent {
+                actor: Some(actor.to_string()),
+                action: "POST /projects/:project_name".to_string(),
+                target: target.to_string(),
+                source_ip: Some("10.0.0.1".to_string()),
+                outcome: 200,
+                timestamp: Utc::now(),
+            })
+            .await?;
+        }
+
+        // The most recent events come first
+        let targets: Vec<_> = svc
+            .iter_audit_events(Some(&neo), 0, u32::MAX)
+            .await?
+            .map(|event| event.target)
+            .collect();
+        assert_eq!(targets, vec!["/projects/zion", "/projects/matrix"]);
+
+        assert_eq!(svc.iter_audit_events(None, 0, u32::MAX).await?.count(), 3);
+        assert_eq!(svc.iter_audit_events(None, 2, 10).await?.count(), 1);
+
+        // Events cannot be changed or removed
+        assert!(query("UPDATE audit_log SET actor = 'smith'")
+            .execute(&svc.db)
+            .await
+            .is_err());
+        assert!(query("DELETE FROM audit_log")
+            .execute(&svc.db)
+            .await
+            .is_err());
+
+        Ok(())
+    }
 }
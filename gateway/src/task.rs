@@ -507,6 +507,8 @@ where
             Err(err) => return TaskResult::Err(err),
         };
 
+        let was_running = project.state.is_running();
+
         let project_ctx = ProjectContext {
             project_name: self.project_name.clone(),
             account_name: account_name.clone(),
@@ -550,6 +552,14 @@ where
             {
                 Ok(_) => {
                     trace!(new_state = ?update.state(), "successfully updated project state");
+
+                    if let Err(err) = self
+                        .service
+                        .update_project_uptime(&self.project_name, was_running, update.is_running())
+                        .await
+                    {
+                        error!(err = %err, "could not update project uptime");
+                    }
                 }
                 Err(err) => {
                     error!(err = %err, "could not update project state");
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use sqlx::{query, SqlitePool};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::interval;
+use tracing::{error, trace};
+
+use crate::{Error, ProjectName};
+
+/// How often the usage buffered in memory is written to the database
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Length of the periods usage is aggregated over in the database
+pub const USAGE_PERIOD_SECONDS: i64 = 60 * 60;
+
+/// Usage counters of a project that are aggregated per period
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct UsageDelta {
+    pub requests: i64,
+    pub response_bytes: i64,
+    pub build_seconds: i64,
+}
+
+impl UsageDelta {
+    fn add(&mut self, other: &UsageDelta) {
+        self.requests += other.requests;
+        self.response_bytes += other.response_bytes;
+        self.build_seconds += other.build_seconds;
+    }
+}
+
+struct UsageEvent {
+    project_name: ProjectName,
+    at: DateTime<Utc>,
+    delta: UsageDelta,
+}
+
+/// Records the usage of projects. Usage is buffered in memory and written to
+/// the database in batches to keep writes off the proxy's hot path.
+#[derive(Clone)]
+pub struct UsageRecorder {
+    sender: UnboundedSender<UsageEvent>,
+}
+
+impl UsageRecorder {
+    pub fn new(db: SqlitePool) -> Self {
+        let (sender, receiver) = unbounded_channel();
+
+        tokio::spawn(flush_task(db, receiver));
+
+        Self { sender }
+    }
+
+    /// Start metering a request to a project. The request is recorded,
+    /// along with the bytes of its response, once the meter is dropped.
+    pub fn meter_request(&self, project_name: ProjectName) -> RequestMeter {
+        RequestMeter {
+            recorder: self.clone(),
+            project_name,
+            response_bytes: 0,
+        }
+    }
+
+    pub fn record_build(&self, project_name: ProjectName, duration: Duration) {
+        self.record(
+            project_name,
+            UsageDelta {
+                build_seconds: duration.as_secs() as i64,
+                ..Default::default()
+            },
+        );
+    }
+
+    fn record(&self, project_name: ProjectName, delta: UsageDelta) {
+        let event = UsageEvent {
+            project_name,
+            at: Utc::now(),
+            delta,
+        };
+
+        if self.sender.send(event).is_err() {
+            error!("usage flush task has stopped, usage will not be recorded");
+        }
+    }
+}
+
+/// Meter for a single proxied request
+pub struct RequestMeter {
+    recorder: UsageRecorder,
+    project_name: ProjectName,
+    response_bytes: u64,
+}
+
+impl RequestMeter {
+    pub fn add_response_bytes(&mut self, bytes: usize) {
+        self.response_bytes += bytes as u64;
+    }
+}
+
+impl Drop for RequestMeter {
+    fn drop(&mut self) {
+        self.recorder.record(
+            self.project_name.clone(),
+            UsageDelta {
+                requests: 1,
+                response_bytes: self.response_bytes as i64,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Start of the period a point in time falls in, as a UTC timestamp
+pub fn period_start(at: DateTime<Utc>) -> i64 {
+    let timestamp = at.timestamp();
+
+    timestamp - timestamp.rem_euclid(USAGE_PERIOD_SECONDS)
+}
+
+async fn flush_task(db: SqlitePool, mut receiver: UnboundedReceiver<UsageEvent>) {
+    let mut pending: HashMap<(ProjectName, i64), UsageDelta> = HashMap::new();
+    let mut ticker = interval(FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Some(UsageEvent { project_name, at, delta }) => {
+                    pending
+                        .entry((project_name, period_start(at)))
+                        .or_default()
+                        .add(&delta);
+                }
+                None => {
+                    flush(&db, &mut pending).await;
+                    break;
+                }
+            },
+            _ = ticker.tick() => flush(&db, &mut pending).await,
+        }
+    }
+}
+
+async fn flush(db: &SqlitePool, pending: &mut HashMap<(ProjectName, i64), UsageDelta>) {
+    if pending.is_empty() {
+        return;
+    }
+
+    trace!(count = pending.len(), "flushing project usage");
+
+    for ((project_name, period_start), delta) in pending.drain() {
+        if let Err(error) = write_usage(db, &project_name, period_start, &delta).await {
+            error!(
+                error = &error as &dyn std::error::Error,
+                %project_name,
+                "failed to write project usage"
+            );
+        }
+    }
+}
+
+/// Add usage to the totals of a project for a period
+pub async fn write_usage(
+    db: &SqlitePool,
+    project_name: &ProjectName,
+    period_start: i64,
+    delta: &UsageDelta,
+) -> Result<(), Error> {
+    query(
+        r#"
+        INSERT INTO project_usage (project_id, period_start, requests, response_bytes, build_seconds)
+        SELECT project_id, ?2, ?3, ?4, ?5 FROM projects WHERE project_name = ?1
+        ON CONFLICT (project_id, period_start) DO UPDATE SET
+          requests = requests + excluded.requests,
+          response_bytes = response_bytes + excluded.response_bytes,
+          build_seconds = build_seconds + excluded.build_seconds
+        "#,
+    )
+    .bind(project_name)
+    .bind(period_start)
+    .bind(delta.requests)
+    .bind(delta.response_bytes)
+    .bind(delta.build_seconds)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
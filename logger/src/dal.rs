@@ -28,6 +28,9 @@ pub enum DalError {
 pub trait Dal {
     /// Get logs for a deployment
     async fn get_logs(&self, deployment_id: String) -> Result<Vec<Log>, DalError>;
+
+    /// Delete all logs for the given deployments, returning how many were removed
+    async fn delete_logs(&self, deployment_ids: Vec<String>) -> Result<u64, DalError>;
 }
 
 #[derive(Clone)]
@@ -132,6 +135,15 @@ impl Dal for Postgres {
 
         Ok(result)
     }
+
+    async fn delete_logs(&self, deployment_ids: Vec<String>) -> Result<u64, DalError> {
+        let result = sqlx::query("DELETE FROM logs WHERE deployment_id = ANY($1)")
+            .bind(deployment_ids)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
 }
 
 #[derive(Clone, Debug, FromRow)]
@@ -4,7 +4,8 @@ use dal::{Dal, DalError};
 use shuttle_common::{backends::auth::VerifyClaim, claims::Scope};
 use shuttle_proto::logger::LogLine;
 use shuttle_proto::logger::{
-    logger_server::Logger, LogsRequest, LogsResponse, StoreLogsRequest, StoreLogsResponse,
+    logger_server::Logger, DeleteLogsRequest, DeleteLogsResponse, LogsRequest, LogsResponse,
+    StoreLogsRequest, StoreLogsResponse,
 };
 use thiserror::Error;
 use tokio::sync::broadcast::Sender;
@@ -99,6 +100,23 @@ where
         Ok(Response::new(result))
     }
 
+    #[tracing::instrument(skip(self, request))]
+    async fn delete_logs(
+        &self,
+        request: Request<DeleteLogsRequest>,
+    ) -> Result<Response<DeleteLogsResponse>, Status> {
+        request.verify(Scope::Admin)?;
+
+        let request = request.into_inner();
+        let deleted = self
+            .dal
+            .delete_logs(request.deployment_ids)
+            .await
+            .map_err(Error::from)?;
+
+        Ok(Response::new(DeleteLogsResponse { deleted }))
+    }
+
     type GetLogsStreamStream = ReceiverStream<Result<LogLine, Status>>;
 
     #[tracing::instrument(skip(self))]
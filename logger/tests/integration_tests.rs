@@ -10,8 +10,8 @@ use shuttle_common::claims::Scope;
 use shuttle_common_tests::JwtScopesLayer;
 use shuttle_logger::{Postgres, Service};
 use shuttle_proto::logger::{
-    logger_client::LoggerClient, logger_server::LoggerServer, LogItem, LogLine, LogsRequest,
-    StoreLogsRequest,
+    logger_client::LoggerClient, logger_server::LoggerServer, DeleteLogsRequest, LogItem, LogLine,
+    LogsRequest, StoreLogsRequest,
 };
 use sqlx::__rt::timeout;
 use tokio::task::JoinHandle;
@@ -46,7 +46,7 @@ mod needs_docker {
         // Create a unique database name so we have a new database for each test.
         let db_name = Uuid::new_v4().to_string();
 
-        let server = spawn_server(logger_port, db_name);
+        let server = spawn_server(logger_port, db_name, vec![Scope::Logs]);
 
         let test_future = tokio::spawn(async move {
             // Ensure the DB has been created and server has started.
@@ -120,7 +120,7 @@ mod needs_docker {
         // Create a unique database name so we have a new database for each test.
         let db_name = Uuid::new_v4().to_string();
 
-        let server = spawn_server(logger_port, db_name);
+        let server = spawn_server(logger_port, db_name, vec![Scope::Logs]);
 
         let test_future = tokio::spawn(async move {
             tokio::time::sleep(Duration::from_millis(800)).await;
@@ -191,7 +191,100 @@ mod needs_docker {
         }
     }
 
-    fn spawn_server(port: u16, db_name: String) -> JoinHandle<()> {
+    #[tokio::test]
+    async fn delete_logs() {
+        let logger_port = pick_unused_port().unwrap();
+        let deleted_id = "runtime-delete-logs-deployment-id";
+        let kept_id = "runtime-keep-logs-deployment-id";
+
+        // Create a unique database name so we have a new database for each test.
+        let db_name = Uuid::new_v4().to_string();
+
+        let server = spawn_server(logger_port, db_name, vec![Scope::Logs, Scope::Admin]);
+
+        let test_future = tokio::spawn(async move {
+            // Ensure the DB has been created and server has started.
+            tokio::time::sleep(Duration::from_millis(300)).await;
+
+            let dst = format!("http://localhost:{logger_port}");
+            let mut client = LoggerClient::connect(dst).await.unwrap();
+
+            let log_item = |deployment_id: &str, data: &str| LogItem {
+                deployment_id: deployment_id.to_string(),
+                log_line: Some(LogLine {
+                    service_name: SHUTTLE_SERVICE.to_string(),
+                    tx_timestamp: Some(Timestamp::from(SystemTime::UNIX_EPOCH)),
+                    data: data.as_bytes().to_vec(),
+                }),
+            };
+
+            let response = client
+                .store_logs(Request::new(StoreLogsRequest {
+                    logs: vec![
+                        log_item(deleted_id, "log 1 example"),
+                        log_item(deleted_id, "log 2 example"),
+                        log_item(kept_id, "log 3 example"),
+                    ],
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(response.success);
+
+            let delete_logs = |deployment_id: &str| {
+                Request::new(DeleteLogsRequest {
+                    deployment_ids: vec![deployment_id.to_string()],
+                })
+            };
+
+            let deleted = client
+                .delete_logs(delete_logs(deleted_id))
+                .await
+                .unwrap()
+                .into_inner()
+                .deleted;
+            assert_eq!(deleted, 2);
+
+            // Deleting again is not an error, there is just nothing left to delete
+            let deleted = client
+                .delete_logs(delete_logs(deleted_id))
+                .await
+                .unwrap()
+                .into_inner()
+                .deleted;
+            assert_eq!(deleted, 0);
+
+            let get_logs = |deployment_id: &str| {
+                Request::new(LogsRequest {
+                    deployment_id: deployment_id.to_string(),
+                })
+            };
+
+            let logs = client
+                .get_logs(get_logs(deleted_id))
+                .await
+                .unwrap()
+                .into_inner()
+                .log_items;
+            assert!(logs.is_empty());
+
+            // The logs of other deployments are left alone
+            let logs = client
+                .get_logs(get_logs(kept_id))
+                .await
+                .unwrap()
+                .into_inner()
+                .log_items;
+            assert_eq!(logs.len(), 1);
+        });
+
+        tokio::select! {
+            _ = server => panic!("server stopped first"),
+            result = test_future => result.expect("test should succeed")
+        }
+    }
+
+    fn spawn_server(port: u16, db_name: String, scopes: Vec<Scope>) -> JoinHandle<()> {
         let addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port);
 
         // Get the PG uri first so the static PG is initialized.
@@ -202,7 +295,7 @@ mod needs_docker {
         tokio::task::spawn(async move {
             let pg = Postgres::new(&pg_uri).await;
             Server::builder()
-                .layer(JwtScopesLayer::new(vec![Scope::Logs]))
+                .layer(JwtScopesLayer::new(scopes))
                 .add_service(LoggerServer::new(Service::new(pg.get_sender(), pg)))
                 .serve(addr)
                 .await
@@ -18,6 +18,18 @@ pub struct LogsRequest {
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteLogsRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub deployment_ids: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeleteLogsResponse {
+    #[prost(uint64, tag = "1")]
+    pub deleted: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct LogsResponse {
     #[prost(message, repeated, tag = "1")]
     pub log_items: ::prost::alloc::vec::Vec<LogLine>,
@@ -168,6 +180,26 @@ pub mod logger_client {
             );
             self.inner.server_streaming(request.into_request(), path, codec).await
         }
+        /// Delete the stored logs of deployments
+        pub async fn delete_logs(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteLogsRequest>,
+        ) -> Result<tonic::Response<super::DeleteLogsResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/logger.Logger/DeleteLogs",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -198,6 +230,11 @@ pub mod logger_server {
             &self,
             request: tonic::Request<super::LogsRequest>,
         ) -> Result<tonic::Response<Self::GetLogsStreamStream>, tonic::Status>;
+        /// Delete the stored logs of deployments
+        async fn delete_logs(
+            &self,
+            request: tonic::Request<super::DeleteLogsRequest>,
+        ) -> Result<tonic::Response<super::DeleteLogsResponse>, tonic::Status>;
     }
     #[derive(Debug)]
     pub struct LoggerServer<T: Logger> {
@@ -371,6 +408,44 @@ pub mod logger_server {
                     };
                     Box::pin(fut)
                 }
+                "/logger.Logger/DeleteLogs" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteLogsSvc<T: Logger>(pub Arc<T>);
+                    impl<
+                        T: Logger,
+                    > tonic::server::UnaryService<super::DeleteLogsRequest>
+                    for DeleteLogsSvc<T> {
+                        type Response = super::DeleteLogsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteLogsRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move { (*inner).delete_logs(request).await };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = DeleteLogsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -113,11 +113,41 @@ pub mod runtime {
 
     include!("generated/runtime.rs");
 
+    /// Where an alpha runtime should export the traces of its service to
+    #[derive(Clone, Debug)]
+    pub struct TraceExport {
+        /// Address of the OTLP collector to send the traces to
+        pub endpoint: String,
+
+        /// Attributes to describe the service in every trace, like its name
+        pub resource_attributes: Vec<(String, String)>,
+    }
+
+    impl TraceExport {
+        /// The environment variables which the runtime reads its exporter config from. These are
+        /// the ones defined by OpenTelemetry, so that a collector can also be set for local runs.
+        fn envs(&self) -> [(&'static str, String); 2] {
+            let resource_attributes = self
+                .resource_attributes
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join(",");
+
+            [
+                ("OTEL_EXPORTER_OTLP_ENDPOINT", self.endpoint.clone()),
+                ("OTEL_RESOURCE_ATTRIBUTES", resource_attributes),
+            ]
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn start(
         wasm: bool,
         environment: Environment,
         provisioner_address: &str,
         auth_uri: Option<&String>,
+        trace_export: Option<&TraceExport>,
         port: u16,
         runtime_executable: PathBuf,
         project_path: &Path,
@@ -128,39 +158,43 @@ pub mod runtime {
         let port = &port.to_string();
         let environment = &environment.to_string();
 
-        let args = if wasm {
-            vec!["--port", port]
-        } else {
-            let mut args = vec![
-                "--port",
-                port,
-                "--provisioner-address",
-                provisioner_address,
-                "--env",
-                environment,
-            ];
+        let mut args = vec![
+            "--port",
+            port,
+            "--provisioner-address",
+            provisioner_address,
+            "--env",
+            environment,
+        ];
 
+        if !wasm {
             if let Some(auth_uri) = auth_uri {
                 args.append(&mut vec!["--auth-uri", auth_uri]);
             }
-
-            args
-        };
+        }
 
         info!(
             "Spawning runtime process: {} {}",
             runtime_executable.display(),
             args.join(" ")
         );
-        let runtime = process::Command::new(
+        let mut command = process::Command::new(
             dunce::canonicalize(runtime_executable).context("canonicalize path of executable")?,
-        )
-        .current_dir(project_path)
-        .args(&args)
-        .stdout(Stdio::piped())
-        .kill_on_drop(true)
-        .spawn()
-        .context("spawning runtime process")?;
+        );
+
+        if !wasm {
+            if let Some(trace_export) = trace_export {
+                command.envs(trace_export.envs());
+            }
+        }
+
+        let runtime = command
+            .current_dir(project_path)
+            .args(&args)
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .context("spawning runtime process")?;
 
         info!("connecting runtime client");
         let conn = Endpoint::new(format!("http://127.0.0.1:{port}"))
@@ -192,6 +226,36 @@ pub mod runtime {
 
         Ok((runtime, runtime_client))
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::TraceExport;
+
+        #[test]
+        fn trace_export_envs() {
+            let trace_export = TraceExport {
+                endpoint: "http://otel-collector:4317".to_string(),
+                resource_attributes: vec![
+                    ("service.name".to_string(), "hello-world".to_string()),
+                    ("shuttle.project.name".to_string(), "hello".to_string()),
+                ],
+            };
+
+            assert_eq!(
+                trace_export.envs(),
+                [
+                    (
+                        "OTEL_EXPORTER_OTLP_ENDPOINT",
+                        "http://otel-collector:4317".to_string()
+                    ),
+                    (
+                        "OTEL_RESOURCE_ATTRIBUTES",
+                        "service.name=hello-world,shuttle.project.name=hello".to_string()
+                    ),
+                ]
+            );
+        }
+    }
 }
 
 pub mod resource_recorder {
@@ -5,6 +5,7 @@ use aws_sdk_rds::{
         describe_db_instances::DescribeDBInstancesError,
     },
 };
+use shuttle_common::quota::QuotaExceeded;
 use thiserror::Error;
 use tonic::Status;
 use tracing::error;
@@ -33,12 +34,18 @@ pub enum Error {
     DeleteRDSInstance(#[from] SdkError<DeleteDBInstanceError>),
     #[error["plain error: {0}"]]
     Plain(String),
+    #[error("quota exceeded: {0}")]
+    QuotaExceeded(#[from] QuotaExceeded),
 }
 
 unsafe impl Send for Error {}
 
 impl From<Error> for Status {
     fn from(err: Error) -> Self {
+        if let Error::QuotaExceeded(quota) = err {
+            return Status::resource_exhausted(format!("quota exceeded: {quota}"));
+        }
+
         error!(error = &err as &dyn std::error::Error, "provision failed");
 
         let message = match err {
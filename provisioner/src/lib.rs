@@ -4,17 +4,23 @@ use std::time::Duration;
 pub use args::Args;
 use aws_config::timeout;
 use aws_sdk_rds::{
-    error::SdkError, operation::modify_db_instance::ModifyDBInstanceError, types::DbInstance,
+    error::SdkError,
+    operation::{
+        describe_db_instances::DescribeDBInstancesError, modify_db_instance::ModifyDBInstanceError,
+    },
+    types::DbInstance,
     Client,
 };
 pub use error::Error;
 use mongodb::{bson::doc, options::ClientOptions};
 use rand::Rng;
 use shuttle_common::backends::auth::VerifyClaim;
-use shuttle_common::claims::Scope;
+use shuttle_common::claims::{Claim, Scope};
+use shuttle_common::quota::Quota;
 pub use shuttle_proto::provisioner::provisioner_server::ProvisionerServer;
 use shuttle_proto::provisioner::{
-    aws_rds, database_request::DbType, shared, AwsRds, DatabaseRequest, DatabaseResponse, Shared,
+    aws_rds, database_request::DbType, shared, AwsRds, DatabaseRequest, DatabaseResponse,
+    RdsConfig, Shared,
 };
 use shuttle_proto::provisioner::{provisioner_server::Provisioner, DatabaseDeletionResponse};
 use shuttle_proto::provisioner::{Ping, Pong};
@@ -337,6 +343,125 @@ impl MyProvisioner {
         })
     }
 
+    /// Make sure provisioning a database for a project does not take it over the database
+    /// quota of its account tier. Databases that already exist can always be provisioned again,
+    /// which is what happens on every deploy, so those are not counted at all.
+    async fn check_database_quota(
+        &self,
+        project_name: &str,
+        db_type: &DbType,
+        quota: Quota,
+    ) -> Result<(), Error> {
+        if quota.max_databases.is_none() {
+            return Ok(());
+        }
+
+        let exists = match db_type {
+            DbType::Shared(Shared {
+                engine: Some(shared::Engine::Postgres(_)),
+            }) => self.shared_pg_exists(project_name).await?,
+            DbType::Shared(Shared {
+                engine: Some(shared::Engine::Mongodb(_)),
+            }) => self.shared_mongodb_exists(project_name).await?,
+            DbType::AwsRds(AwsRds {
+                engine: Some(engine),
+            }) => self.aws_rds_exists(project_name, engine).await?,
+            DbType::Shared(Shared { engine: None }) | DbType::AwsRds(AwsRds { engine: None }) => {
+                false
+            }
+        };
+        if exists {
+            return Ok(());
+        }
+
+        let provisioned = self.provisioned_databases(project_name, db_type).await?;
+        quota.check_databases(provisioned)?;
+
+        Ok(())
+    }
+
+    /// Count the databases that have been provisioned for a project. A shared database is still
+    /// counted when AWS cannot be reached, by skipping the RDS instances that cannot be checked.
+    async fn provisioned_databases(
+        &self,
+        project_name: &str,
+        requested: &DbType,
+    ) -> Result<u64, Error> {
+        let mut provisioned = 0;
+
+        if self.shared_pg_exists(project_name).await? {
+            provisioned += 1;
+        }
+
+        if self.shared_mongodb_exists(project_name).await? {
+            provisioned += 1;
+        }
+
+        for engine in [
+            aws_rds::Engine::Postgres(RdsConfig {}),
+            aws_rds::Engine::Mysql(RdsConfig {}),
+            aws_rds::Engine::Mariadb(RdsConfig {}),
+        ] {
+            match self.aws_rds_exists(project_name, &engine).await {
+                Ok(true) => provisioned += 1,
+                Ok(false) => {}
+                Err(error) if matches!(requested, DbType::Shared(_)) => {
+                    warn!(
+                        error = %error,
+                        "could not check for AWS RDS {engine} instance, not counting it"
+                    );
+                }
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(provisioned)
+    }
+
+    async fn shared_pg_exists(&self, project_name: &str) -> Result<bool, Error> {
+        let database = sqlx::query("SELECT 1 FROM pg_database WHERE datname = $1")
+            .bind(format!("db-{project_name}"))
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(database.is_some())
+    }
+
+    async fn shared_mongodb_exists(&self, project_name: &str) -> Result<bool, Error> {
+        let databases = self
+            .mongodb_client
+            .list_database_names(doc! { "name": format!("mongodb-{project_name}") }, None)
+            .await?;
+
+        Ok(!databases.is_empty())
+    }
+
+    async fn aws_rds_exists(
+        &self,
+        project_name: &str,
+        engine: &aws_rds::Engine,
+    ) -> Result<bool, Error> {
+        let instance = self
+            .rds_client
+            .describe_db_instances()
+            .db_instance_identifier(format!("{project_name}-{engine}"))
+            .send()
+            .await;
+
+        match instance {
+            Ok(_) => Ok(true),
+            Err(SdkError::ServiceError(err))
+                if matches!(
+                    err.err(),
+                    DescribeDBInstancesError::DbInstanceNotFoundFault(_)
+                ) =>
+            {
+                Ok(false)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
     async fn delete_shared_db(
         &self,
         project_name: &str,
@@ -455,12 +580,21 @@ impl Provisioner for MyProvisioner {
     ) -> Result<Response<DatabaseResponse>, Status> {
         request.verify(Scope::ResourcesWrite)?;
 
+        let tier = request
+            .extensions()
+            .get::<Claim>()
+            .map(|claim| claim.tier)
+            .ok_or_else(|| Status::internal("could not get claim"))?;
+
         let request = request.into_inner();
         if !shuttle_common::project::ProjectName::is_valid(&request.project_name) {
             return Err(Status::invalid_argument("invalid project name"));
         }
         let db_type = request.db_type.unwrap();
 
+        self.check_database_quota(&request.project_name, &db_type, tier.quota())
+            .await?;
+
         let reply = match db_type {
             DbType::Shared(Shared { engine }) => {
                 self.request_shared_db(&request.project_name, engine.expect("engine to be set"))
@@ -0,0 +1,267 @@
+#![doc = include_str!("../README.md")]
+
+use std::fs;
+use std::io::ErrorKind;
+use std::marker::PhantomData;
+
+use async_trait::async_trait;
+use secrecy::ExposeSecret;
+use serde::de::DeserializeOwned;
+use shuttle_service::{CustomError, Error, Factory, ResourceBuilder, Type};
+use toml::{Table, Value};
+
+/// The file the config is read from, at the root of the project
+const SHUTTLE_TOML: &str = "Shuttle.toml";
+
+/// Secrets with keys starting with this are merged into the config
+const SECRETS_PREFIX: &str = "config.";
+
+/// A struct that represents the config of a service, deserialized into `T`
+pub struct Config<T> {
+    config: PhantomData<fn() -> T>,
+}
+
+/// Get the config of a deployment, merged from Shuttle.toml and its secrets
+#[async_trait]
+impl<T: DeserializeOwned> ResourceBuilder<T> for Config<T> {
+    const TYPE: Type = Type::Custom;
+
+    // Like secrets, the config has to be read again for every deployment instead of being cached
+    type Config = ();
+
+    type Output = serde_json::Value;
+
+    fn new() -> Self {
+        Self {
+            config: PhantomData,
+        }
+    }
+
+    fn config(&self) -> &Self::Config {
+        &()
+    }
+
+    async fn output(self, factory: &mut dyn Factory) -> Result<Self::Output, crate::Error> {
+        let shuttle_toml = match fs::read_to_string(SHUTTLE_TOML) {
+            Ok(shuttle_toml) => toml::from_str(&shuttle_toml).map_err(|error| {
+                CustomError::msg(format!("failed to parse {SHUTTLE_TOML}: {error}"))
+            })?,
+            Err(error) if error.kind() == ErrorKind::NotFound => Table::new(),
+            Err(error) => return Err(error.into()),
+        };
+
+        let secrets = factory.get_secrets().await?;
+        let env = factory.get_metadata().env.to_string();
+
+        let config = merge(
+            shuttle_toml,
+            &env,
+            secrets
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.expose_secret().as_str())),
+        )?;
+
+        serde_json::to_value(config).map_err(|error| {
+            CustomError::msg(format!("failed to serialize config: {error}")).into()
+        })
+    }
+
+    async fn build(build_data: &Self::Output) -> Result<T, crate::Error> {
+        parse(build_data)
+    }
+}
+
+/// Merge the `[config]` table of Shuttle.toml with the `[env.<env>.config]` table and the secrets
+/// starting with `config.`. Later sources replace the values of earlier ones.
+fn merge<'a>(
+    mut shuttle_toml: Table,
+    env: &str,
+    secrets: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Result<Table, Error> {
+    let mut config = match shuttle_toml.remove("config") {
+        Some(Value::Table(config)) => config,
+        Some(_) => return Err(invalid("`config` in Shuttle.toml should be a table")),
+        None => Table::new(),
+    };
+
+    let overrides = shuttle_toml
+        .get_mut("env")
+        .and_then(|envs| envs.get_mut(env))
+        .and_then(|env| env.as_table_mut())
+        .and_then(|env| env.remove("config"));
+
+    match overrides {
+        Some(Value::Table(overrides)) => merge_tables(&mut config, overrides),
+        Some(_) => {
+            return Err(invalid(&format!(
+                "`env.{env}.config` in Shuttle.toml should be a table"
+            )))
+        }
+        None => {}
+    }
+
+    for (key, value) in secrets {
+        if let Some(path) = key.strip_prefix(SECRETS_PREFIX) {
+            set_secret(&mut config, path, value)?;
+        }
+    }
+
+    Ok(config)
+}
+
+fn merge_tables(base: &mut Table, overrides: Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base)), Value::Table(overrides)) => merge_tables(base, overrides),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Set the value at a dotted path to a secret, converted to the type of the value it replaces
+fn set_secret(config: &mut Table, path: &str, secret: &str) -> Result<(), Error> {
+    let (tables, key) = match path.rsplit_once('.') {
+        Some((tables, key)) => (Some(tables), key),
+        None => (None, path),
+    };
+
+    let mut table = config;
+    for name in tables.into_iter().flat_map(|tables| tables.split('.')) {
+        table = match table.entry(name).or_insert(Value::Table(Table::new())) {
+            Value::Table(table) => table,
+            _ => {
+                return Err(invalid(&format!(
+                    "secret `{SECRETS_PREFIX}{path}` is inside `{name}`, which is not a table"
+                )))
+            }
+        };
+    }
+
+    let value = match table.get(key) {
+        Some(Value::Integer(_)) => secret.parse().map(Value::Integer).ok(),
+        Some(Value::Float(_)) => secret.parse().map(Value::Float).ok(),
+        Some(Value::Boolean(_)) => secret.parse().map(Value::Boolean).ok(),
+        _ => None,
+    };
+    // A secret which does not match the type is kept as a string, for deserializing to report
+    table.insert(
+        key.to_string(),
+        value.unwrap_or_else(|| Value::String(secret.to_string())),
+    );
+
+    Ok(())
+}
+
+fn parse<T: DeserializeOwned>(config: &serde_json::Value) -> Result<T, Error> {
+    serde_path_to_error::deserialize(config).map_err(|error| {
+        invalid(&format!(
+            "invalid value at `{}`: {}",
+            error.path(),
+            error.inner()
+        ))
+    })
+}
+
+fn invalid(message: &str) -> Error {
+    CustomError::msg(format!("invalid config: {message}")).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct MyConfig {
+        greeting: String,
+        database: DatabaseConfig,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct DatabaseConfig {
+        host: String,
+        port: u16,
+        password: String,
+    }
+
+    const PROJECT_TOML: &str = r#"
+        name = "my-project"
+
+        [config]
+        greeting = "Hello"
+        database = { host = "localhost", port = 5432, password = "local" }
+
+        [env.production.config]
+        database = { host = "db.example.com" }
+    "#;
+
+    fn config(env: &str, secrets: &[(&str, &str)]) -> Result<MyConfig, Error> {
+        let table = merge(
+            toml::from_str(PROJECT_TOML).unwrap(),
+            env,
+            secrets.iter().copied(),
+        )?;
+
+        parse(&serde_json::to_value(table).unwrap())
+    }
+
+    #[test]
+    fn merge_sources_in_order() {
+        assert_eq!(
+            config("local", &[]).unwrap(),
+            MyConfig {
+                greeting: "Hello".to_string(),
+                database: DatabaseConfig {
+                    host: "localhost".to_string(),
+                    port: 5432,
+                    password: "local".to_string(),
+                },
+            }
+        );
+
+        assert_eq!(
+            config(
+                "production",
+                &[
+                    ("config.database.password", "hunter2"),
+                    ("config.database.port", "6543"),
+                    ("API_KEY", "not config"),
+                ]
+            )
+            .unwrap(),
+            MyConfig {
+                greeting: "Hello".to_string(),
+                database: DatabaseConfig {
+                    host: "db.example.com".to_string(),
+                    port: 6543,
+                    password: "hunter2".to_string(),
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn report_invalid_values() {
+        let error = config("local", &[("config.database.port", "not a port")]).unwrap_err();
+
+        assert!(
+            error.to_string().contains("`database.port`"),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn reject_secrets_inside_values() {
+        let error = config("local", &[("config.greeting.formal", "Good day")]).unwrap_err();
+
+        assert!(
+            error
+                .to_string()
+                .contains("`greeting`, which is not a table"),
+            "unexpected error: {error}"
+        );
+    }
+}
@@ -24,8 +24,8 @@ use shuttle_proto::{
     provisioner::provisioner_client::ProvisionerClient,
     runtime::{
         runtime_server::{Runtime, RuntimeServer},
-        LoadRequest, LoadResponse, StartRequest, StartResponse, StopReason, StopRequest,
-        StopResponse, SubscribeStopRequest, SubscribeStopResponse,
+        LoadRequest, LoadResponse, MetricsRequest, MetricsResponse, StartRequest, StartResponse,
+        StopReason, StopRequest, StopResponse, SubscribeStopRequest, SubscribeStopResponse,
     },
 };
 use shuttle_service::{Environment, Factory, ProjectName, Service};
@@ -41,6 +41,7 @@ use tonic::{
 use tower::ServiceBuilder;
 
 use crate::__internals::{print_version, ProvisionerFactory, ResourceTracker};
+use crate::composite::join_error_message;
 
 use self::args::Args;
 
@@ -77,7 +78,7 @@ pub async fn start(loader: impl Loader<ProvisionerFactory> + Send + 'static) {
 
         colored::control::set_override(true); // always apply color
 
-        tracing_subscriber::registry()
+        let registry = tracing_subscriber::registry()
             .with(tracing_subscriber::fmt::layer().without_time())
             .with(
                 // let user override RUST_LOG in local run if they want to
@@ -85,8 +86,15 @@ pub async fn start(loader: impl Loader<ProvisionerFactory> + Send + 'static) {
                     // otherwise use our default
                     .or_else(|_| tracing_subscriber::EnvFilter::try_new("info,shuttle=trace"))
                     .unwrap(),
-            )
-            .init();
+            );
+
+        // only export traces when a collector is configured for this runtime
+        #[cfg(feature = "otlp")]
+        let registry = registry.with(
+            crate::otlp::tracer().map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)),
+        );
+
+        registry.init();
 
         println!(
             "{}\n\
@@ -187,6 +195,12 @@ where
     }
 }
 
+/// The return types a `shuttle_runtime::main` function can have. The macro asserts this on the
+/// return type, so that a function which returns something else is reported right there.
+pub trait ServiceResult {}
+
+impl<S: Service> ServiceResult for Result<S, shuttle_service::Error> {}
+
 #[async_trait]
 impl<L, S> Runtime for Alpha<L, S>
 where
@@ -243,9 +257,10 @@ where
                 Err(error) => {
                     println!("loading service failed: {error:#}");
 
+                    // Keep the causes, since they explain why a resource could not be loaded
                     let message = LoadResponse {
                         success: false,
-                        message: error.to_string(),
+                        message: format!("{error:#}"),
                         resources: new_resources
                             .lock()
                             .expect("to get lock no new resources")
@@ -337,22 +352,22 @@ where
             tokio::select! {
                 res = &mut background => {
                     match res {
-                        Ok(_) => {
+                        Ok(Ok(())) => {
                             println!("service stopped all on its own");
                             let _ = stopped_tx
                                 .send((StopReason::End, String::new()))
                                 .map_err(|e| println!("{e}"));
                         },
+                        // Such as a background task of a composite service giving up
+                        Ok(Err(error)) => {
+                            println!("service returned an error: {error}");
+                            let _ = stopped_tx
+                                .send((StopReason::Crash, error.to_string()))
+                                .map_err(|e| println!("{e}"));
+                        },
                         Err(error) => {
                             if error.is_panic() {
-                                let panic = error.into_panic();
-                                let msg = match panic.downcast_ref::<String>() {
-                                    Some(msg) => msg.to_string(),
-                                    None => match panic.downcast_ref::<&str>() {
-                                        Some(msg) => msg.to_string(),
-                                        None => "<no panic message>".to_string(),
-                                    },
-                                };
+                                let msg = join_error_message(error);
 
                                 println!("service panicked: {msg}");
 
@@ -430,4 +445,23 @@ where
 
         Ok(Response::new(ReceiverStream::new(rx)))
     }
+
+    async fn metrics(
+        &self,
+        _request: Request<MetricsRequest>,
+    ) -> Result<Response<MetricsResponse>, Status> {
+        #[cfg(feature = "metrics")]
+        let message = MetricsResponse {
+            enabled: true,
+            text: crate::metrics::gather(),
+        };
+
+        #[cfg(not(feature = "metrics"))]
+        let message = MetricsResponse {
+            enabled: false,
+            text: String::new(),
+        };
+
+        Ok(Response::new(message))
+    }
 }
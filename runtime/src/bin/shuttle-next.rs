@@ -5,7 +5,7 @@ use std::{
 
 use shuttle_common::backends::tracing::ExtractPropagationLayer;
 use shuttle_proto::runtime::runtime_server::RuntimeServer;
-use shuttle_runtime::__internals::{print_version, AxumWasm, NextArgs};
+use shuttle_runtime::__internals::{print_version, AxumWasm, Limits, NextArgs};
 use tonic::transport::Server;
 
 #[tokio::main(flavor = "multi_thread")]
@@ -26,7 +26,12 @@ async fn main() {
         .http2_keepalive_interval(Some(Duration::from_secs(60)))
         .layer(ExtractPropagationLayer);
 
-    let axum = AxumWasm::default();
+    let limits = Limits {
+        request_timeout: Duration::from_secs(args.request_timeout),
+        max_memory: args.max_memory,
+        max_instances: args.max_instances,
+    };
+    let axum = AxumWasm::new(args.provisioner_address, args.env, limits);
     let svc = RuntimeServer::new(axum);
     let router = server_builder.add_service(svc);
 
@@ -0,0 +1,349 @@
+//! A service made of a web service and background tasks, which all run in the same deployment.
+//!
+//! ```rust,no_run
+//! use axum::{routing::get, Router};
+//! use shuttle_runtime::{Composite, RestartPolicy, ShuttleComposite};
+//!
+//! async fn consume_queue() -> Result<(), shuttle_runtime::Error> {
+//!     // Take jobs off a queue until it fails
+//!     Ok(())
+//! }
+//!
+//! #[shuttle_runtime::main]
+//! async fn main() -> ShuttleComposite<shuttle_axum::AxumService> {
+//!     let router = Router::new().route("/", get(|| async { "Hello, world!" }));
+//!
+//!     Ok(Composite::new(router.into())
+//!         .task("queue-consumer", RestartPolicy::Always, consume_queue))
+//! }
+//! ```
+//!
+//! The tasks are started along with the web service and stopped with it. A task which crashes, by
+//! returning an error or panicking, is restarted as its [RestartPolicy] allows. When it is not
+//! restarted anymore, the whole service stops with an error naming the task.
+use std::{any::Any, future::Future, net::SocketAddr, pin::Pin, time::Duration};
+
+use async_trait::async_trait;
+use shuttle_service::{CustomError, Error, Service};
+use tokio::{
+    task::{JoinError, JoinSet},
+    time::Instant,
+};
+
+/// How long to wait before the first restart of a task
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+/// The longest to wait between restarts of a task. A task which ran for longer than this before
+/// crashing is restarted after the initial backoff again, and its restarts are counted afresh.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+type TaskFn =
+    Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> + Send + Sync>;
+
+/// Return type from the [main][crate::main] macro for a [Composite] service.
+pub type ShuttleComposite<S> = Result<Composite<S>, Error>;
+
+/// When to restart a background task which stopped
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RestartPolicy {
+    /// Never restart the task. A crash stops the whole service.
+    Never,
+
+    /// Restart the task when it crashes, up to a number of times in a row. A task which finishes
+    /// successfully is not restarted.
+    OnFailure { max_restarts: u32 },
+
+    /// Always restart the task, whether it crashed or finished
+    Always,
+}
+
+/// A web service with named background tasks that are supervised while it runs
+pub struct Composite<S> {
+    service: S,
+    tasks: Vec<Task>,
+}
+
+struct Task {
+    name: String,
+    policy: RestartPolicy,
+    run: TaskFn,
+}
+
+impl<S: Service> Composite<S> {
+    /// Wrap the web service of a deployment
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Add a background task. The task is started by calling `run`, which is called again for
+    /// every restart.
+    pub fn task<F, Fut>(mut self, name: impl Into<String>, policy: RestartPolicy, run: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), Error>> + Send + 'static,
+    {
+        self.tasks.push(Task {
+            name: name.into(),
+            policy,
+            run: Box::new(move || Box::pin(run())),
+        });
+
+        self
+    }
+}
+
+#[async_trait]
+impl<S: Service> Service for Composite<S> {
+    async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+        // Dropping the set when the web service stops aborts the tasks still running
+        let mut tasks = JoinSet::new();
+        for task in self.tasks {
+            tasks.spawn(task.supervise());
+        }
+
+        let service = self.service.bind(addr);
+        tokio::pin!(service);
+
+        loop {
+            tokio::select! {
+                result = &mut service => return result,
+                Some(result) = tasks.join_next() => match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(error)) => return Err(error),
+                    Err(error) => return Err(CustomError::new(error).into()),
+                },
+            }
+        }
+    }
+}
+
+impl Task {
+    /// Run the task until its policy no longer restarts it. Returns an error when it stopped
+    /// because of a crash.
+    async fn supervise(self) -> Result<(), Error> {
+        let mut restarts = 0;
+        let mut backoff = INITIAL_BACKOFF;
+
+        loop {
+            println!("starting background task `{}`", self.name);
+
+            let started = Instant::now();
+
+            // Run on its own task to catch panics, in a set to abort it when this is aborted
+            let mut run = JoinSet::new();
+            run.spawn((self.run)());
+
+            let crash = match run.join_next().await.expect("task to be in the set") {
+                Ok(Ok(())) => None,
+                Ok(Err(error)) => Some(error.to_string()),
+                Err(error) => Some(join_error_message(error)),
+            };
+
+            // A crash after a long healthy run does not count towards the ones before it
+            if started.elapsed() > MAX_BACKOFF {
+                restarts = 0;
+                backoff = INITIAL_BACKOFF;
+            }
+
+            let restart = match (self.policy, &crash) {
+                (RestartPolicy::Never, _) | (RestartPolicy::OnFailure { .. }, None) => false,
+                (RestartPolicy::OnFailure { max_restarts }, Some(_)) => restarts < max_restarts,
+                (RestartPolicy::Always, _) => true,
+            };
+
+            match (restart, crash) {
+                (false, None) => {
+                    println!("background task `{}` finished", self.name);
+                    return Ok(());
+                }
+                (false, Some(crash)) => {
+                    return Err(CustomError::msg(format!(
+                        "background task `{}` crashed: {crash}",
+                        self.name
+                    ))
+                    .into());
+                }
+                (true, Some(crash)) => {
+                    println!("background task `{}` crashed: {crash}", self.name)
+                }
+                (true, None) => println!("background task `{}` finished", self.name),
+            }
+
+            println!(
+                "restarting background task `{}` in {}ms",
+                self.name,
+                backoff.as_millis()
+            );
+            tokio::time::sleep(backoff).await;
+
+            restarts += 1;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+}
+
+/// Get the message of a task which panicked or was cancelled
+pub(crate) fn join_error_message(error: JoinError) -> String {
+    if error.is_panic() {
+        panic_message(error.into_panic())
+    } else {
+        error.to_string()
+    }
+}
+
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    match panic.downcast_ref::<String>() {
+        Some(msg) => msg.to_string(),
+        None => match panic.downcast_ref::<&str>() {
+            Some(msg) => msg.to_string(),
+            None => "<no panic message>".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use super::*;
+
+    /// A web service which runs for some time before ending
+    struct Sleep(Duration);
+
+    #[async_trait]
+    impl Service for Sleep {
+        async fn bind(mut self, _addr: SocketAddr) -> Result<(), Error> {
+            tokio::time::sleep(self.0).await;
+
+            Ok(())
+        }
+    }
+
+    fn counting_task(
+        runs: &Arc<AtomicU32>,
+        result: fn() -> Result<(), Error>,
+    ) -> impl Fn() -> Pin<Box<dyn Future<Output = Result<(), Error>> + Send>> {
+        let runs = runs.clone();
+
+        move || {
+            runs.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move { result() })
+        }
+    }
+
+    fn addr() -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], 8000))
+    }
+
+    #[tokio::test]
+    async fn crash_after_max_restarts() {
+        let runs = Arc::new(AtomicU32::new(0));
+
+        let error = Composite::new(Sleep(Duration::from_secs(60)))
+            .task(
+                "flaky",
+                RestartPolicy::OnFailure { max_restarts: 2 },
+                counting_task(&runs, || Err(CustomError::msg("queue went away").into())),
+            )
+            .bind(addr())
+            .await
+            .unwrap_err();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            error.to_string(),
+            "background task `flaky` crashed: queue went away"
+        );
+    }
+
+    async fn panicky() -> Result<(), Error> {
+        panic!("out of cheese")
+    }
+
+    #[tokio::test]
+    async fn report_panics() {
+        let error = Composite::new(Sleep(Duration::from_secs(60)))
+            .task("panicky", RestartPolicy::Never, panicky)
+            .bind(addr())
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "background task `panicky` crashed: out of cheese"
+        );
+    }
+
+    #[tokio::test]
+    async fn keep_serving_after_task_finishes() {
+        let runs = Arc::new(AtomicU32::new(0));
+
+        Composite::new(Sleep(Duration::from_millis(300)))
+            .task(
+                "once",
+                RestartPolicy::OnFailure { max_restarts: 5 },
+                counting_task(&runs, || Ok(())),
+            )
+            .bind(addr())
+            .await
+            .unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn always_restart() {
+        let finishes = Arc::new(AtomicU32::new(0));
+        let crashes = Arc::new(AtomicU32::new(0));
+
+        // Restarted after 100ms and 300ms, before the web service ends at 500ms
+        Composite::new(Sleep(Duration::from_millis(500)))
+            .task(
+                "finishing",
+                RestartPolicy::Always,
+                counting_task(&finishes, || Ok(())),
+            )
+            .task(
+                "crashing",
+                RestartPolicy::Always,
+                counting_task(&crashes, || Err(CustomError::msg("queue went away").into())),
+            )
+            .bind(addr())
+            .await
+            .unwrap();
+
+        assert_eq!(finishes.load(Ordering::SeqCst), 3);
+        assert_eq!(crashes.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restarts_reset_after_healthy_run() {
+        let runs = Arc::new(AtomicU32::new(0));
+        let task_runs = runs.clone();
+
+        // Every run is healthy for longer than the max backoff before crashing
+        Composite::new(Sleep(MAX_BACKOFF * 5))
+            .task(
+                "long-lived",
+                RestartPolicy::OnFailure { max_restarts: 1 },
+                move || {
+                    task_runs.fetch_add(1, Ordering::SeqCst);
+                    async {
+                        tokio::time::sleep(MAX_BACKOFF * 2).await;
+                        Err(CustomError::msg("queue went away").into())
+                    }
+                },
+            )
+            .bind(addr())
+            .await
+            .unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+    }
+}
@@ -204,17 +204,26 @@
 //! You can also [open an issue or a discussion on GitHub](https://github.com/shuttle-hq/shuttle).
 
 // Public API
+pub use composite::{Composite, RestartPolicy, ShuttleComposite};
 pub use shuttle_codegen::main;
 pub use shuttle_service::{CustomError, Error, Factory, ResourceBuilder, Service};
 
 // Useful re-exports
 pub use async_trait::async_trait;
+#[cfg(feature = "otlp")]
+pub use shuttle_common::backends::tracing::ExtractPropagationLayer;
 pub use tokio;
 
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
 mod alpha;
 mod args;
+mod composite;
 #[cfg(feature = "next")]
 mod next;
+#[cfg(feature = "otlp")]
+mod otlp;
 mod provisioner_factory;
 mod resource_tracker;
 
@@ -225,9 +234,9 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 #[doc(hidden)]
 pub mod __internals {
     // Internals used by the codegen
-    pub use crate::alpha::{start, Alpha};
+    pub use crate::alpha::{start, Alpha, ServiceResult};
     #[cfg(feature = "next")]
-    pub use crate::next::{AxumWasm, NextArgs};
+    pub use crate::next::{AxumWasm, Limits, NextArgs};
     pub use crate::provisioner_factory::ProvisionerFactory;
     pub use crate::resource_tracker::{get_resource, ResourceTracker};
 
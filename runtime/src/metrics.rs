@@ -0,0 +1,324 @@
+//! Metrics for a service, which can be scraped through the deployer.
+//!
+//! Enabling the `metrics` feature of `shuttle-runtime` turns on metrics for a service. The process
+//! metrics of the service are then collected on every scrape, along with any metric registered on
+//! the [global registry](registry):
+//!
+//! ```rust,no_run
+//! use shuttle_runtime::metrics::registry;
+//!
+//! async fn hello_world() -> &'static str {
+//!     registry()
+//!         .counter("hello_requests_total", "Number of times hello was requested")
+//!         .inc();
+//!
+//!     "Hello, world!"
+//! }
+//! ```
+//!
+//! The tokio task metrics are only collected when building with `RUSTFLAGS="--cfg tokio_unstable"`,
+//! since tokio does not make them available otherwise.
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+static REGISTRY: Registry = Registry {
+    metrics: Mutex::new(BTreeMap::new()),
+};
+
+/// The clock ticks per second used by `/proc/self/stat`, which is 100 on every Linux we run on
+const CLOCK_TICKS: f64 = 100.0;
+
+/// Get the global registry of this service
+pub fn registry() -> &'static Registry {
+    &REGISTRY
+}
+
+/// A set of named metrics
+pub struct Registry {
+    metrics: Mutex<BTreeMap<String, Metric>>,
+}
+
+struct Metric {
+    help: String,
+    value: Value,
+}
+
+#[derive(Clone)]
+enum Value {
+    Counter(Counter),
+    Gauge(Gauge),
+}
+
+/// A value which only goes up
+#[derive(Clone, Debug, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+/// A value which can go up and down
+#[derive(Clone, Debug, Default)]
+pub struct Gauge(Arc<AtomicU64>);
+
+impl Registry {
+    /// Get the counter with this name, registering it if it does not exist yet
+    ///
+    /// # Panics
+    ///
+    /// If the name is not a valid Prometheus metric name, or is already used by a gauge
+    pub fn counter(&self, name: &str, help: &str) -> Counter {
+        match self.get_or_register(name, help, || Value::Counter(Counter::default())) {
+            Value::Counter(counter) => counter,
+            Value::Gauge(_) => panic!("metric `{name}` is already registered as a gauge"),
+        }
+    }
+
+    /// Get the gauge with this name, registering it if it does not exist yet
+    ///
+    /// # Panics
+    ///
+    /// If the name is not a valid Prometheus metric name, or is already used by a counter
+    pub fn gauge(&self, name: &str, help: &str) -> Gauge {
+        match self.get_or_register(name, help, || Value::Gauge(Gauge::default())) {
+            Value::Gauge(gauge) => gauge,
+            Value::Counter(_) => panic!("metric `{name}` is already registered as a counter"),
+        }
+    }
+
+    fn get_or_register(&self, name: &str, help: &str, new: impl FnOnce() -> Value) -> Value {
+        assert!(is_valid_name(name), "`{name}` is not a valid metric name");
+
+        self.metrics
+            .lock()
+            .expect("to get lock on metrics")
+            .entry(name.to_string())
+            .or_insert_with(|| Metric {
+                help: help.to_string(),
+                value: new(),
+            })
+            .value
+            .clone()
+    }
+
+    /// Write all the metrics in the Prometheus text format
+    fn encode(&self, out: &mut String) {
+        for (name, metric) in self.metrics.lock().expect("to get lock on metrics").iter() {
+            match &metric.value {
+                Value::Counter(counter) => {
+                    write_metric(out, name, &metric.help, "counter", counter.get() as f64)
+                }
+                Value::Gauge(gauge) => write_metric(out, name, &metric.help, "gauge", gauge.get()),
+            }
+        }
+    }
+}
+
+impl Counter {
+    /// Add one to the counter
+    pub fn inc(&self) {
+        self.inc_by(1);
+    }
+
+    /// Add a number to the counter
+    pub fn inc_by(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Get the current value of the counter
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Gauge {
+    /// Set the gauge to a value
+    pub fn set(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Add a value to the gauge, which can be negative
+    pub fn add(&self, value: f64) {
+        // The closure always returns `Some`, so this cannot fail
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + value).to_bits())
+            });
+    }
+
+    /// Get the current value of the gauge
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Collect the process metrics and the metrics of the global registry, for a scrape
+// `tokio_unstable` is set by users who want the tokio task metrics, and is unknown to newer
+// compilers. Older ones do not know the lint either.
+#[allow(unknown_lints, unexpected_cfgs)]
+pub(crate) fn gather() -> String {
+    let mut out = String::new();
+
+    if let Some(cpu_seconds) = fs::read_to_string("/proc/self/stat")
+        .ok()
+        .and_then(|stat| cpu_seconds(&stat))
+    {
+        write_metric(
+            &mut out,
+            "process_cpu_seconds_total",
+            "Total user and system CPU time spent in seconds",
+            "counter",
+            cpu_seconds,
+        );
+    }
+
+    if let Some(rss_bytes) = fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| resident_memory_bytes(&status))
+    {
+        write_metric(
+            &mut out,
+            "process_resident_memory_bytes",
+            "Resident memory size in bytes",
+            "gauge",
+            rss_bytes as f64,
+        );
+    }
+
+    if let Ok(fds) = fs::read_dir("/proc/self/fd") {
+        write_metric(
+            &mut out,
+            "process_open_fds",
+            "Number of open file descriptors",
+            "gauge",
+            fds.count() as f64,
+        );
+    }
+
+    #[cfg(tokio_unstable)]
+    if let Ok(handle) = tokio::runtime::Handle::try_current() {
+        let metrics = handle.metrics();
+
+        write_metric(
+            &mut out,
+            "tokio_alive_tasks",
+            "Number of tasks alive on the tokio runtime",
+            "gauge",
+            metrics.active_tasks_count() as f64,
+        );
+        write_metric(
+            &mut out,
+            "tokio_workers",
+            "Number of worker threads of the tokio runtime",
+            "gauge",
+            metrics.num_workers() as f64,
+        );
+    }
+
+    registry().encode(&mut out);
+
+    out
+}
+
+fn write_metric(out: &mut String, name: &str, help: &str, kind: &str, value: f64) {
+    let help = help.replace('\\', "\\\\").replace('\n', "\\n");
+
+    // Writing to a string cannot fail
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Get the user and system time from the contents of `/proc/self/stat`
+fn cpu_seconds(stat: &str) -> Option<f64> {
+    // The name of the executable can contain spaces, so skip past it before splitting
+    let (_, fields) = stat.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(11);
+
+    let utime: u64 = fields.next()?.parse().ok()?;
+    let stime: u64 = fields.next()?.parse().ok()?;
+
+    Some((utime + stime) as f64 / CLOCK_TICKS)
+}
+
+/// Get the resident set size from the contents of `/proc/self/status`
+fn resident_memory_bytes(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode_registry() {
+        let registry = Registry {
+            metrics: Mutex::new(BTreeMap::new()),
+        };
+
+        registry
+            .counter("requests_total", "Requests served")
+            .inc_by(3);
+        registry.counter("requests_total", "Requests served").inc();
+        registry
+            .gauge("queue_length", "Jobs waiting\nto run")
+            .set(2.5);
+
+        let mut out = String::new();
+        registry.encode(&mut out);
+
+        assert_eq!(
+            out,
+            "# HELP queue_length Jobs waiting\\nto run\n\
+             # TYPE queue_length gauge\n\
+             queue_length 2.5\n\
+             # HELP requests_total Requests served\n\
+             # TYPE requests_total counter\n\
+             requests_total 4\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "already registered as a counter")]
+    fn reject_different_kind() {
+        let registry = registry();
+
+        registry.counter("jobs_total", "Jobs");
+        registry.gauge("jobs_total", "Jobs");
+    }
+
+    #[test]
+    fn parse_proc_files() {
+        let stat = "1234 (my (weird) app) S 1 1234 1234 0 -1 4194560 1052 0 0 0 250 50 0 0 20 0 9";
+        assert_eq!(cpu_seconds(stat), Some(3.0));
+
+        let status = "Name:\tapp\nVmPeak:\t  20000 kB\nVmRSS:\t   10240 kB\nThreads:\t4\n";
+        assert_eq!(resident_memory_bytes(status), Some(10 * 1024 * 1024));
+
+        assert!(is_valid_name("http_requests_total"));
+        assert!(!is_valid_name("2xx_responses"));
+        assert!(!is_valid_name("requests-total"));
+    }
+}
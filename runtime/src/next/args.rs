@@ -1,7 +1,15 @@
+use shuttle_service::Environment;
+use tonic::transport::Endpoint;
+
 use crate::args::args;
 
 args! {
     pub struct NextArgs {
         "--port" => pub port: u16,
+        "--provisioner-address" => #[arg(default_value = "http://localhost:3000")] pub provisioner_address: Endpoint,
+        "--env" => #[arg(default_value = "local")] pub env: Environment,
+        "--request-timeout" => #[arg(default_value = "30")] pub request_timeout: u64,
+        "--max-memory" => #[arg(default_value = "134217728")] pub max_memory: u64,
+        "--max-instances" => #[arg(default_value = "100")] pub max_instances: u32,
     }
 }
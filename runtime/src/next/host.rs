@@ -0,0 +1,335 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use serde_json::Value;
+use shuttle_common::database;
+use shuttle_common::wasm::{HostRequest, HostResponse, MetadataWrapper};
+use sqlx::postgres::PgArguments;
+use sqlx::{Arguments, PgPool};
+use wasmtime::{Caller, Linker, Memory, StoreLimits};
+use wasmtime_wasi::WasiCtx;
+
+/// Name of the module the host functions are imported from by shuttle-next modules
+const HOST_MODULE: &str = "shuttle";
+
+/// The resources of a shuttle-next service, shared by all the requests it handles
+#[derive(Default)]
+pub struct Resources {
+    pub secrets: BTreeMap<String, String>,
+    pub metadata: Option<MetadataWrapper>,
+    pub databases: Vec<(database::Type, PgPool)>,
+    /// Directory on the host which is mounted for the persist resource
+    pub persist_dir: Option<PathBuf>,
+}
+
+/// The data of the store a module is instantiated in
+pub struct HostState {
+    pub wasi: WasiCtx,
+    pub resources: Arc<Resources>,
+    /// Limits on the memory and instances the module can use
+    pub limits: StoreLimits,
+    /// The response to the last host call, until the module copies it into its memory
+    response: Vec<u8>,
+}
+
+impl HostState {
+    pub fn new(wasi: WasiCtx, resources: Arc<Resources>, limits: StoreLimits) -> Self {
+        Self {
+            wasi,
+            resources,
+            limits,
+            response: Vec::new(),
+        }
+    }
+}
+
+/// Add the host functions to a linker. A module makes a call by passing a [HostRequest] to
+/// `call`, which returns the length of the [HostResponse]. The module then passes a buffer of that
+/// length to `response` to get the response copied into it.
+pub fn add_to_linker(linker: &mut Linker<HostState>) -> anyhow::Result<()> {
+    linker.func_wrap(
+        HOST_MODULE,
+        "call",
+        |mut caller: Caller<'_, HostState>, ptr: u32, len: u32| -> anyhow::Result<u32> {
+            let memory = memory(&mut caller)?;
+
+            // Check the bounds before allocating, so a module cannot make the host allocate more
+            // than the memory it has
+            let end = (ptr as usize).checked_add(len as usize);
+            if end.map_or(true, |end| end > memory.data_size(&caller)) {
+                anyhow::bail!("host request is out of the bounds of the module's memory");
+            }
+
+            let mut request = vec![0; len as usize];
+            memory
+                .read(&caller, ptr as usize, &mut request)
+                .context("failed to read host request")?;
+
+            let response = match rmp_serde::from_slice(&request) {
+                Ok(request) => caller.data().resources.handle(request),
+                Err(error) => HostResponse::Error(format!("invalid host request: {error}")),
+            };
+            let response =
+                rmp_serde::to_vec(&response).context("failed to serialize host response")?;
+            let len = response.len() as u32;
+
+            caller.data_mut().response = response;
+
+            Ok(len)
+        },
+    )?;
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "response",
+        |mut caller: Caller<'_, HostState>, ptr: u32| -> anyhow::Result<()> {
+            let memory = memory(&mut caller)?;
+            let response = std::mem::take(&mut caller.data_mut().response);
+
+            memory
+                .write(&mut caller, ptr as usize, &response)
+                .context("failed to write host response")?;
+
+            Ok(())
+        },
+    )?;
+
+    Ok(())
+}
+
+fn memory(caller: &mut Caller<'_, HostState>) -> anyhow::Result<Memory> {
+    caller
+        .get_export("memory")
+        .and_then(|export| export.into_memory())
+        .context("module should export its memory")
+}
+
+impl Resources {
+    fn handle(&self, request: HostRequest) -> HostResponse {
+        match request {
+            HostRequest::Secrets => HostResponse::Secrets(self.secrets.clone()),
+            HostRequest::Metadata => match &self.metadata {
+                Some(metadata) => HostResponse::Metadata(metadata.clone()),
+                None => {
+                    HostResponse::Error("the metadata of the service is not loaded".to_string())
+                }
+            },
+            HostRequest::Query {
+                database,
+                sql,
+                params,
+            } => match self.database(database) {
+                Ok(pool) => match block_on(query(pool, &sql, params)) {
+                    Ok(rows) => HostResponse::Rows(rows),
+                    Err(error) => HostResponse::Error(format!("{error:#}")),
+                },
+                Err(error) => error,
+            },
+            HostRequest::Execute {
+                database,
+                sql,
+                params,
+            } => match self.database(database) {
+                Ok(pool) => match block_on(execute(pool, &sql, params)) {
+                    Ok(rows_affected) => HostResponse::RowsAffected(rows_affected),
+                    Err(error) => HostResponse::Error(format!("{error:#}")),
+                },
+                Err(error) => error,
+            },
+        }
+    }
+
+    fn database(&self, r#type: database::Type) -> Result<&PgPool, HostResponse> {
+        self.databases
+            .iter()
+            .find(|(database, _)| *database == r#type)
+            .map(|(_, pool)| pool)
+            .ok_or_else(|| {
+                HostResponse::Error(format!(
+                    "no {} database was provisioned for this service",
+                    resource_name(r#type)
+                ))
+            })
+    }
+}
+
+fn resource_name(r#type: database::Type) -> String {
+    match r#type {
+        database::Type::AwsRds(engine) => format!("AWS RDS {engine}"),
+        database::Type::Shared(engine) => format!("shared {engine}"),
+    }
+}
+
+/// Host functions are called synchronously from a module, while the database is used
+/// asynchronously
+fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::task::block_in_place(|| tokio::runtime::Handle::current().block_on(future))
+}
+
+async fn query(pool: &PgPool, sql: &str, params: Vec<Value>) -> anyhow::Result<Vec<Value>> {
+    let rows: String = sqlx::query_scalar_with(&rows_as_json(sql), arguments(params))
+        .fetch_one(pool)
+        .await
+        .context("failed to run query")?;
+
+    serde_json::from_str(&rows).context("failed to parse the rows of the query")
+}
+
+/// Let postgres turn the rows of a statement into JSON, so that columns of any type can be sent
+/// to the module. The statement goes in a `WITH` query rather than a subquery, so that statements
+/// like `INSERT ... RETURNING` can be used too.
+fn rows_as_json(sql: &str) -> String {
+    let sql = sql.trim_end().trim_end_matches(';');
+
+    format!("WITH rows AS ({sql}) SELECT coalesce(json_agg(rows), '[]')::text FROM rows")
+}
+
+async fn execute(pool: &PgPool, sql: &str, params: Vec<Value>) -> anyhow::Result<u64> {
+    let result = sqlx::query_with(sql, arguments(params))
+        .execute(pool)
+        .await
+        .context("failed to run statement")?;
+
+    Ok(result.rows_affected())
+}
+
+/// Bind JSON values to the parameters of a statement. Arrays and objects are bound as text, so
+/// they need to be cast in the statement, like `$1::jsonb`.
+fn arguments(params: Vec<Value>) -> PgArguments {
+    let mut arguments = PgArguments::default();
+
+    for param in params {
+        match param {
+            Value::Null => arguments.add(None::<String>),
+            Value::Bool(boolean) => arguments.add(boolean),
+            Value::Number(number) => match number.as_i64() {
+                Some(integer) => arguments.add(integer),
+                None => arguments.add(number.as_f64()),
+            },
+            Value::String(string) => arguments.add(string),
+            other => arguments.add(other.to_string()),
+        }
+    }
+
+    arguments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::{Engine, Instance, Module, Store};
+    use wasmtime_wasi::WasiCtxBuilder;
+
+    /// A module which passes its calls straight to the host functions
+    const MODULE: &str = r#"
+        (module
+            (import "shuttle" "call" (func $call (param i32 i32) (result i32)))
+            (import "shuttle" "response" (func $response (param i32)))
+            (memory (export "memory") 1)
+            (func (export "call") (param i32 i32) (result i32)
+                local.get 0
+                local.get 1
+                call $call)
+            (func (export "response") (param i32)
+                local.get 0
+                call $response))
+    "#;
+
+    fn instantiate(resources: Resources) -> (Store<HostState>, Instance) {
+        let engine = Engine::default();
+        let mut linker = Linker::new(&engine);
+        add_to_linker(&mut linker).unwrap();
+
+        let state = HostState::new(
+            WasiCtxBuilder::new().build(),
+            Arc::new(resources),
+            StoreLimits::default(),
+        );
+        let mut store = Store::new(&engine, state);
+        let module = Module::new(&engine, MODULE).unwrap();
+        let instance = linker.instantiate(&mut store, &module).unwrap();
+
+        (store, instance)
+    }
+
+    #[test]
+    fn call_and_response() {
+        let resources = Resources {
+            secrets: BTreeMap::from([("API_KEY".to_string(), "secret".to_string())]),
+            ..Default::default()
+        };
+        let (mut store, instance) = instantiate(resources);
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        let call = instance
+            .get_typed_func::<(u32, u32), u32>(&mut store, "call")
+            .unwrap();
+        let response = instance
+            .get_typed_func::<u32, ()>(&mut store, "response")
+            .unwrap();
+
+        let request = rmp_serde::to_vec(&HostRequest::Secrets).unwrap();
+        memory.write(&mut store, 0, &request).unwrap();
+
+        let len = call.call(&mut store, (0, request.len() as u32)).unwrap();
+        response.call(&mut store, 1024).unwrap();
+
+        let mut buf = vec![0; len as usize];
+        memory.read(&store, 1024, &mut buf).unwrap();
+        let HostResponse::Secrets(secrets) = rmp_serde::from_slice(&buf).unwrap() else {
+            panic!("expected the secrets");
+        };
+        assert_eq!(secrets["API_KEY"], "secret");
+
+        // Errors of a request are sent back to the module
+        let request = rmp_serde::to_vec(&HostRequest::Metadata).unwrap();
+        memory.write(&mut store, 0, &request).unwrap();
+
+        let len = call.call(&mut store, (0, request.len() as u32)).unwrap();
+        response.call(&mut store, 1024).unwrap();
+
+        let mut buf = vec![0; len as usize];
+        memory.read(&store, 1024, &mut buf).unwrap();
+        assert!(matches!(
+            rmp_serde::from_slice(&buf).unwrap(),
+            HostResponse::Error(_)
+        ));
+    }
+
+    #[test]
+    fn call_out_of_bounds() {
+        let (mut store, instance) = instantiate(Resources::default());
+        let call = instance
+            .get_typed_func::<(u32, u32), u32>(&mut store, "call")
+            .unwrap();
+
+        // Larger than the single page of memory the module has
+        let error = call.call(&mut store, (0, u32::MAX)).unwrap_err();
+        assert!(format!("{error:?}").contains("out of the bounds"));
+
+        // Starts inside the memory, but ends outside of it
+        let error = call.call(&mut store, (u32::MAX, 2)).unwrap_err();
+        assert!(format!("{error:?}").contains("out of the bounds"));
+
+        // Responses are checked by wasmtime itself
+        let response = instance
+            .get_typed_func::<u32, ()>(&mut store, "response")
+            .unwrap();
+        let request = rmp_serde::to_vec(&HostRequest::Secrets).unwrap();
+        let memory = instance.get_memory(&mut store, "memory").unwrap();
+        memory.write(&mut store, 0, &request).unwrap();
+        call.call(&mut store, (0, request.len() as u32)).unwrap();
+        assert!(response.call(&mut store, u32::MAX).is_err());
+    }
+
+    #[test]
+    fn query_rows_as_json() {
+        assert_eq!(
+            rows_as_json("INSERT INTO todos (note) VALUES ($1) RETURNING id; \n"),
+            "WITH rows AS (INSERT INTO todos (note) VALUES ($1) RETURNING id) \
+             SELECT coalesce(json_agg(rows), '[]')::text FROM rows"
+        );
+    }
+}
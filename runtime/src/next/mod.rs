@@ -1,64 +1,251 @@
+use std::collections::HashMap;
 use std::convert::Infallible;
-use std::io::{BufReader, Read, Write};
+use std::io::{self, BufReader, Read, Write};
 use std::net::{Shutdown, SocketAddr};
 use std::ops::DerefMut;
 use std::os::unix::prelude::RawFd;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use anyhow::Context;
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 use cap_std::os::unix::net::UnixStream;
 use futures::TryStreamExt;
 use hyper::body::HttpBody;
 use hyper::service::{make_service_fn, service_fn};
 use hyper::{Body, Request, Response};
-use shuttle_common::wasm::{RequestWrapper, ResponseWrapper};
+use serde_json::json;
+use shuttle_common::claims::{
+    Claim, ClaimLayer, ClaimService, InjectPropagation, InjectPropagationLayer,
+};
+use shuttle_common::constants::STORAGE_DIRNAME;
+use shuttle_common::wasm::{MetadataWrapper, RequestWrapper, ResponseWrapper, PERSIST_DIR};
+use shuttle_common::{database, resource, DatabaseReadyInfo, DbOutput};
+use shuttle_proto::provisioner::{provisioner_client::ProvisionerClient, DatabaseRequest};
 use shuttle_proto::runtime::runtime_server::Runtime;
 use shuttle_proto::runtime::{
-    LoadRequest, LoadResponse, StartRequest, StartResponse, StopReason, StopRequest, StopResponse,
-    SubscribeStopRequest, SubscribeStopResponse,
+    LoadRequest, LoadResponse, MetricsRequest, MetricsResponse, StartRequest, StartResponse,
+    StopReason, StopRequest, StopResponse, SubscribeStopRequest, SubscribeStopResponse,
 };
+use shuttle_service::Environment;
+use sqlx::postgres::PgPoolOptions;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::unix::OwnedWriteHalf;
 use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio_stream::wrappers::ReceiverStream;
+use tonic::transport::{Channel, Endpoint};
 use tonic::Status;
+use tower::ServiceBuilder;
 use wasi_common::file::FileAccessMode;
-use wasmtime::{Engine, Linker, Module, Store};
+use wasmtime::{
+    Config, Engine, InstanceAllocationStrategy, Linker, Module, PoolingAllocationConfig, Store,
+    StoreLimitsBuilder, Trap, TypedFunc, UpdateDeadline,
+};
 use wasmtime_wasi::sync::net::UnixStream as WasiUnixStream;
+use wasmtime_wasi::sync::{ambient_authority, Dir};
 use wasmtime_wasi::{WasiCtx, WasiCtxBuilder};
 
 mod args;
+mod host;
 
 pub use self::args::NextArgs;
+use self::host::{HostState, Resources};
 
 extern crate rmp_serde as rmps;
 
 const PARTS_FD: u32 = 3;
 const BODY_FD: u32 = 4;
 
+/// How often the epoch of the engine is incremented. Request timeouts are counted in these ticks.
+const EPOCH_TICK: Duration = Duration::from_millis(100);
+
+/// Size of a page of WASM linear memory
+const WASM_PAGE_SIZE: u64 = 64 * 1024;
+
+/// Limits on the requests handled by a shuttle-next module
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    /// How long a request can run in the module before it is interrupted. For modules which
+    /// stream their response, this is how long the module can go without sending the head of the
+    /// response or the next chunk of its body.
+    pub request_timeout: Duration,
+    /// Most linear memory, in bytes, the module can use while handling a request
+    pub max_memory: u64,
+    /// Most requests which can be handled at the same time
+    pub max_instances: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(30),
+            max_memory: 128 * 1024 * 1024,
+            max_instances: 100,
+        }
+    }
+}
+
 pub struct AxumWasm {
     router: Mutex<Option<Router>>,
     kill_tx: Mutex<Option<oneshot::Sender<String>>>,
     stopped_tx: broadcast::Sender<(StopReason, String)>,
+    provisioner_address: Endpoint,
+    env: Environment,
+    limits: Limits,
 }
 
 impl AxumWasm {
-    pub fn new() -> Self {
+    pub fn new(provisioner_address: Endpoint, env: Environment, limits: Limits) -> Self {
         let (stopped_tx, _stopped_rx) = broadcast::channel(10);
 
         Self {
             router: Mutex::new(None),
             kill_tx: Mutex::new(None),
             stopped_tx,
+            provisioner_address,
+            env,
+            limits,
+        }
+    }
+
+    /// Provision the resources a module asked for. Each resource is added to `new_resources` as
+    /// soon as it is ready, so that it is recorded even when provisioning another one fails.
+    async fn provision(
+        &self,
+        resource_types: Vec<resource::Type>,
+        past_resources: &[resource::Response],
+        secrets: HashMap<String, String>,
+        service_name: &str,
+        claim: Option<Claim>,
+        new_resources: &mut Vec<resource::Response>,
+    ) -> anyhow::Result<Resources> {
+        // Secrets and metadata are always available, even when they are not asked for
+        let mut resources = Resources {
+            secrets: secrets.into_iter().collect(),
+            metadata: Some(MetadataWrapper {
+                env: self.env.to_string(),
+                project_name: service_name.to_string(),
+                service_name: service_name.to_string(),
+            }),
+            ..Default::default()
+        };
+        let mut provisioner_client = None;
+
+        for resource_type in resource_types {
+            println!("[Resource][{resource_type:?}] Getting resource");
+
+            let data = match resource_type {
+                resource::Type::Database(
+                    db_type @ (database::Type::Shared(database::SharedEngine::Postgres)
+                    | database::Type::AwsRds(database::AwsRdsEngine::Postgres)),
+                ) => {
+                    let past_output = past_resources
+                        .iter()
+                        .find(|resource| resource.r#type == resource_type)
+                        .and_then(|resource| serde_json::from_value(resource.data.clone()).ok());
+
+                    let output = match past_output {
+                        Some(output) => output,
+                        None => {
+                            println!("[Resource][{resource_type:?}] Provisioning. This can take a while...");
+
+                            if provisioner_client.is_none() {
+                                provisioner_client = Some(self.provisioner_client().await?);
+                            }
+                            let info = provision_database(
+                                provisioner_client.as_mut().expect("client to be connected"),
+                                db_type,
+                                service_name,
+                                claim.clone(),
+                            )
+                            .await?;
+
+                            DbOutput::Info(info)
+                        }
+                    };
+
+                    let connection_string = match &output {
+                        DbOutput::Info(info) => info.connection_string_private(),
+                        DbOutput::Local(connection_string) => connection_string.clone(),
+                    };
+                    let pool = PgPoolOptions::new()
+                        .max_connections(5)
+                        .connect_lazy(&connection_string)
+                        .context("failed to set up database pool")?;
+                    resources.databases.push((db_type, pool));
+
+                    serde_json::to_value(&output).context("failed to serialize database output")?
+                }
+                resource::Type::Secrets => json!({ "secrets": resources.secrets }),
+                resource::Type::Metadata => json!(resources.metadata),
+                resource::Type::Persist => {
+                    let persist_dir = PathBuf::from(STORAGE_DIRNAME)
+                        .join("shuttle-persist")
+                        .join(service_name);
+                    std::fs::create_dir_all(&persist_dir)
+                        .context("failed to create persist directory")?;
+                    resources.persist_dir = Some(persist_dir);
+
+                    json!({})
+                }
+                other => bail!("{other:?} resources are not supported by shuttle-next"),
+            };
+
+            new_resources.push(resource::Response {
+                r#type: resource_type,
+                config: json!({}),
+                data,
+            });
+
+            println!("[Resource][{resource_type:?}] Resource ready");
         }
+
+        Ok(resources)
+    }
+
+    async fn provisioner_client(
+        &self,
+    ) -> anyhow::Result<ProvisionerClient<ClaimService<InjectPropagation<Channel>>>> {
+        let channel = self
+            .provisioner_address
+            .clone()
+            .connect()
+            .await
+            .context("failed to connect to provisioner")?;
+        let channel = ServiceBuilder::new()
+            .layer(ClaimLayer)
+            .layer(InjectPropagationLayer)
+            .service(channel);
+
+        Ok(ProvisionerClient::new(channel))
     }
 }
 
-impl Default for AxumWasm {
-    fn default() -> Self {
-        Self::new()
+async fn provision_database(
+    provisioner_client: &mut ProvisionerClient<ClaimService<InjectPropagation<Channel>>>,
+    db_type: database::Type,
+    service_name: &str,
+    claim: Option<Claim>,
+) -> anyhow::Result<DatabaseReadyInfo> {
+    let mut request = tonic::Request::new(DatabaseRequest {
+        project_name: service_name.to_string(),
+        db_type: Some(db_type.into()),
+    });
+
+    if let Some(claim) = claim {
+        request.extensions_mut().insert(claim);
     }
+
+    let response = provisioner_client
+        .provision_database(request)
+        .await
+        .context("failed to provision database")?
+        .into_inner();
+
+    Ok(response.into())
 }
 
 #[async_trait]
@@ -67,21 +254,66 @@ impl Runtime for AxumWasm {
         &self,
         request: tonic::Request<LoadRequest>,
     ) -> Result<tonic::Response<LoadResponse>, Status> {
-        let wasm_path = request.into_inner().path;
-        println!("loading shuttle-next project: {wasm_path}");
+        let claim = request.extensions().get::<Claim>().map(Clone::clone);
+
+        let LoadRequest {
+            path,
+            resources,
+            secrets,
+            service_name,
+        } = request.into_inner();
+        println!("loading shuttle-next project: {path}");
 
-        let router = RouterBuilder::new()
+        let mut router = RouterBuilder::new(self.limits)
             .map_err(|err| Status::from_error(err.into()))?
-            .src(wasm_path)
+            .src(path)
             .build()
             .map_err(|err| Status::from_error(err.into()))?;
 
+        let resource_types = router
+            .resource_types()
+            .map_err(|err| Status::from_error(err.into()))?;
+        let past_resources: Vec<_> = resources
+            .into_iter()
+            .map(resource::Response::from_bytes)
+            .collect();
+        let mut new_resources = Vec::new();
+
+        let provisioned = self
+            .provision(
+                resource_types,
+                &past_resources,
+                secrets,
+                &service_name,
+                claim,
+                &mut new_resources,
+            )
+            .await;
+        let resources = new_resources
+            .iter()
+            .map(resource::Response::to_bytes)
+            .collect();
+
+        match provisioned {
+            Ok(provisioned) => router.resources = Arc::new(provisioned),
+            Err(error) => {
+                println!("loading service failed: {error:#}");
+
+                let message = LoadResponse {
+                    success: false,
+                    message: error.to_string(),
+                    resources,
+                };
+                return Ok(tonic::Response::new(message));
+            }
+        }
+
         *self.router.lock().unwrap() = Some(router);
 
         let message = LoadResponse {
             success: true,
             message: String::new(),
-            resources: Vec::new(),
+            resources,
         };
 
         Ok(tonic::Response::new(message))
@@ -164,24 +396,51 @@ impl Runtime for AxumWasm {
 
         Ok(tonic::Response::new(ReceiverStream::new(rx)))
     }
+
+    async fn metrics(
+        &self,
+        _request: tonic::Request<MetricsRequest>,
+    ) -> Result<tonic::Response<MetricsResponse>, Status> {
+        // Modules have no way to register metrics, so there is nothing to report
+        Ok(tonic::Response::new(MetricsResponse {
+            enabled: false,
+            text: String::new(),
+        }))
+    }
 }
 struct RouterBuilder {
     engine: Engine,
-    linker: Linker<WasiCtx>,
+    linker: Linker<HostState>,
     src: Option<PathBuf>,
+    limits: Limits,
 }
 
 impl RouterBuilder {
-    fn new() -> anyhow::Result<Self> {
-        let engine = Engine::default();
-
-        let mut linker: Linker<WasiCtx> = Linker::new(&engine);
-        wasmtime_wasi::add_to_linker(&mut linker, |s| s)?;
+    fn new(limits: Limits) -> anyhow::Result<Self> {
+        // Every request gets a fresh instance, so keep a pool of them ready to be reused
+        let mut pooling = PoolingAllocationConfig::default();
+        pooling
+            .total_core_instances(limits.max_instances)
+            .total_memories(limits.max_instances)
+            .total_tables(limits.max_instances)
+            .memory_pages(limits.max_memory / WASM_PAGE_SIZE);
+
+        let mut config = Config::new();
+        config
+            .epoch_interruption(true)
+            .allocation_strategy(InstanceAllocationStrategy::Pooling(pooling));
+
+        let engine = Engine::new(&config)?;
+
+        let mut linker: Linker<HostState> = Linker::new(&engine);
+        wasmtime_wasi::add_to_linker(&mut linker, |s| &mut s.wasi)?;
+        host::add_to_linker(&mut linker)?;
 
         Ok(Self {
             engine,
             linker,
             src: None,
+            limits,
         })
     }
 
@@ -202,33 +461,128 @@ impl RouterBuilder {
             linker: self.linker,
             engine: self.engine,
             module,
+            resources: Default::default(),
+            limits: self.limits,
         })
     }
 }
 
 #[derive(Clone)]
 struct Router {
-    linker: Linker<WasiCtx>,
+    linker: Linker<HostState>,
     engine: Engine,
     module: Module,
+    resources: Arc<Resources>,
+    limits: Limits,
 }
 
 impl Router {
+    /// Make a store to instantiate the module in, which is held to the limits of the router
+    fn store(&self, wasi: WasiCtx) -> Store<HostState> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(self.limits.max_memory as usize)
+            .build();
+
+        let mut store = Store::new(
+            &self.engine,
+            HostState::new(wasi, self.resources.clone(), limits),
+        );
+        store.limiter(|state| &mut state.limits);
+
+        store.set_epoch_deadline(epoch_ticks(self.limits.request_timeout));
+        store.epoch_deadline_trap();
+
+        store
+    }
+
+    /// Ask the module which resources it needs. Modules built before shuttle-next supported
+    /// resources do not export a function for this, and need none.
+    fn resource_types(&self) -> anyhow::Result<Vec<resource::Type>> {
+        let wasi = WasiCtxBuilder::new().inherit_stdio().build();
+        let mut store = self.store(wasi);
+
+        // The router's own linker should only get the module when handling a request
+        let mut linker = self.linker.clone();
+        linker.module(&mut store, "axum", &self.module)?;
+
+        let Some(resources_fn) = linker.get(&mut store, "axum", "__SHUTTLE_Resources") else {
+            return Ok(Vec::new());
+        };
+
+        let (mut resources_stream, resources_client) =
+            UnixStream::pair().context("failed to open resources unixstream")?;
+        store.data_mut().wasi.insert_file(
+            PARTS_FD,
+            Box::new(WasiUnixStream::from_cap_std(resources_client)),
+            FileAccessMode::all(),
+        );
+
+        resources_fn
+            .into_func()
+            .context("resources function should be a function")?
+            .typed::<RawFd, ()>(&store)?
+            .call(&mut store, PARTS_FD as i32)?;
+
+        let reader = BufReader::new(&mut resources_stream);
+        let requested: Vec<resource::Type> =
+            rmps::from_read(reader).context("failed to deserialize resource types")?;
+
+        // Many endpoints can use the same resource
+        let mut resource_types = Vec::with_capacity(requested.len());
+        for resource_type in requested {
+            if !resource_types.contains(&resource_type) {
+                resource_types.push(resource_type);
+            }
+        }
+
+        Ok(resource_types)
+    }
+
     /// Send a HTTP request with body to given endpoint on the axum-wasm router and return the response
     async fn handle_request(
         &mut self,
         req: hyper::Request<Body>,
     ) -> anyhow::Result<Response<Body>> {
-        let wasi = WasiCtxBuilder::new()
-            .inherit_stdio()
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.inherit_stdio()
             .inherit_stdout()
             .inherit_args()
-            .context("failed to read args")?
-            .build();
+            .context("failed to read args")?;
+
+        if let Some(persist_dir) = &self.resources.persist_dir {
+            let dir = Dir::open_ambient_dir(persist_dir, ambient_authority())
+                .context("failed to open persist directory")?;
+            wasi.preopened_dir(dir, PERSIST_DIR)
+                .context("failed to mount persist directory")?;
+        }
 
-        let mut store = Store::new(&self.engine, wasi);
+        let mut store = self.store(wasi.build());
         self.linker.module(&mut store, "axum", &self.module)?;
 
+        // Modules built before bodies were streamed only export the buffered router function
+        match self
+            .linker
+            .get(&mut store, "axum", "__SHUTTLE_Axum_stream_call")
+        {
+            Some(call) => {
+                let call = call
+                    .into_func()
+                    .context("router function should be a function")?
+                    .typed::<(RawFd, RawFd), ()>(&store)?;
+
+                call_streaming(store, call, req, self.limits.request_timeout).await
+            }
+            None => self.call_buffered(store, req).await,
+        }
+    }
+
+    /// Send a request to a module which takes the whole body at once, and gives back the whole
+    /// body of its response
+    async fn call_buffered(
+        &self,
+        mut store: Store<HostState>,
+        req: hyper::Request<Body>,
+    ) -> anyhow::Result<Response<Body>> {
         let (mut parts_stream, parts_client) =
             UnixStream::pair().context("failed to open parts unixstream")?;
         let (mut body_stream, body_client) =
@@ -239,9 +593,11 @@ impl Router {
 
         store
             .data_mut()
+            .wasi
             .insert_file(PARTS_FD, Box::new(parts_client), FileAccessMode::all());
         store
             .data_mut()
+            .wasi
             .insert_file(BODY_FD, Box::new(body_client), FileAccessMode::all());
 
         let (parts, body) = req.into_parts();
@@ -316,6 +672,157 @@ impl Router {
     }
 }
 
+/// Send a request to a module which streams bodies. The module is called on a blocking thread, so
+/// that the request body can be written to it and the response body read from it as it runs.
+async fn call_streaming(
+    mut store: Store<HostState>,
+    call: TypedFunc<(RawFd, RawFd), ()>,
+    req: hyper::Request<Body>,
+    timeout: Duration,
+) -> anyhow::Result<Response<Body>> {
+    // A long stream, like server-sent events, can run for much longer than a request is allowed
+    // to. So the deadline counts from the last frame the module sent rather than from the start,
+    // which also leaves out the time the module waits for a slow client to take its frames.
+    let last_frame = Arc::new(Mutex::new(Instant::now()));
+    let deadline_last_frame = last_frame.clone();
+    store.epoch_deadline_callback(move |_| {
+        let idle = deadline_last_frame
+            .lock()
+            .expect("last frame lock should not be poisoned")
+            .elapsed();
+
+        match timeout.checked_sub(idle) {
+            Some(remaining) if !remaining.is_zero() => {
+                Ok(UpdateDeadline::Continue(epoch_ticks(remaining)))
+            }
+            _ => Err(Trap::Interrupt.into()),
+        }
+    });
+
+    let (mut parts_read, mut parts_write) = stream_pair(&mut store, PARTS_FD)?.into_split();
+    let (body_read, body_write) = stream_pair(&mut store, BODY_FD)?.into_split();
+
+    let (parts, body) = req.into_parts();
+
+    let request_rmp = RequestWrapper::from(parts)
+        .into_rmp()
+        .context("failed to make request wrapper")?;
+    write_frame(&mut parts_write, &request_rmp)
+        .await
+        .context("failed to write http parts to wasm")?;
+
+    // The module can respond without reading the whole body, which closes the stream
+    tokio::spawn(async move {
+        let _ = send_body(body, body_write).await;
+    });
+
+    let call = tokio::task::spawn_blocking(move || {
+        call.call(&mut store, (PARTS_FD as i32, BODY_FD as i32))
+    });
+
+    let response_rmp = match read_frame(&mut parts_read).await {
+        Ok(response_rmp) => response_rmp,
+        Err(error) => {
+            // The module stopped before responding, and the reason it did is more useful
+            call.await??;
+
+            return Err(error).context("failed to read response parts");
+        }
+    };
+    let wrapper: ResponseWrapper =
+        rmps::from_slice(&response_rmp).context("failed to deserialize response parts")?;
+
+    // Errors after this point can only cut the body short, so they are logged
+    tokio::spawn(async move {
+        match call.await {
+            Ok(Ok(())) => {}
+            Ok(Err(error)) => println!("request failed while streaming its response: {error:?}"),
+            Err(error) => println!("request panicked while streaming its response: {error}"),
+        }
+    });
+
+    *last_frame
+        .lock()
+        .expect("last frame lock should not be poisoned") = Instant::now();
+
+    let body = futures::stream::unfold(Some(body_read), move |body_read| {
+        let last_frame = last_frame.clone();
+
+        async move {
+            let mut body_read = body_read?;
+
+            match read_frame(&mut body_read).await {
+                Ok(frame) if frame.is_empty() => None,
+                Ok(frame) => {
+                    *last_frame
+                        .lock()
+                        .expect("last frame lock should not be poisoned") = Instant::now();
+                    Some((Ok(frame), Some(body_read)))
+                }
+                Err(error) => Some((Err(error), None)),
+            }
+        }
+    });
+
+    wrapper
+        .into_response_builder()
+        .body(Body::wrap_stream(body))
+        .context("failed to construct http response")
+}
+
+/// Open a stream to a module, giving it the other end as the file descriptor `fd`
+fn stream_pair(store: &mut Store<HostState>, fd: u32) -> anyhow::Result<tokio::net::UnixStream> {
+    let (stream, client) =
+        std::os::unix::net::UnixStream::pair().context("failed to open unixstream")?;
+
+    let client = WasiUnixStream::from_cap_std(UnixStream::from_std(client));
+    store
+        .data_mut()
+        .wasi
+        .insert_file(fd, Box::new(client), FileAccessMode::all());
+
+    stream
+        .set_nonblocking(true)
+        .context("failed to make unixstream non-blocking")?;
+
+    tokio::net::UnixStream::from_std(stream).context("failed to register unixstream")
+}
+
+/// Stream the body of a request to a module. A chunk is only written once the module has read
+/// enough of the ones before it.
+async fn send_body(mut body: Body, mut writer: OwnedWriteHalf) -> anyhow::Result<()> {
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.context("failed to read request body")?;
+
+        if !chunk.is_empty() {
+            write_frame(&mut writer, &chunk).await?;
+        }
+    }
+
+    write_frame(&mut writer, &[]).await?;
+
+    Ok(())
+}
+
+/// Async version of [shuttle_common::wasm::write_frame]
+async fn write_frame(writer: &mut (impl AsyncWrite + Unpin), frame: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(frame.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large"))?;
+
+    writer.write_u32(len).await?;
+    writer.write_all(frame).await
+}
+
+/// Async version of [shuttle_common::wasm::read_frame]
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<Vec<u8>> {
+    let len = reader.read_u32().await?;
+
+    let mut frame = vec![0; len as usize];
+    reader.read_exact(&mut frame).await?;
+
+    Ok(frame)
+}
+
 /// Start a hyper server with a service that calls an axum router in WASM,
 /// and a kill receiver for stopping the server.
 async fn run_until_stopped(
@@ -324,6 +831,7 @@ async fn run_until_stopped(
     kill_rx: tokio::sync::oneshot::Receiver<String>,
     stopped_tx: broadcast::Sender<(StopReason, String)>,
 ) {
+    let engine = router.engine.clone();
     let make_service = make_service_fn(move |_conn| {
         let router = router.clone();
         async move {
@@ -332,13 +840,7 @@ async fn run_until_stopped(
                 async move {
                     Ok::<_, Infallible>(match router.handle_request(req).await {
                         Ok(res) => res,
-                        Err(err) => {
-                            println!("error sending request: {}", err);
-                            Response::builder()
-                                .status(hyper::http::StatusCode::INTERNAL_SERVER_ERROR)
-                                .body(Body::empty())
-                                .expect("building request with empty body should not fail")
-                        }
+                        Err(err) => error_response(err),
                     })
                 }
             }))
@@ -346,6 +848,7 @@ async fn run_until_stopped(
     });
 
     let server = hyper::Server::bind(&address).serve(make_service);
+    let ticker = tick_epochs(engine);
 
     println!("starting hyper server on: {}", &address);
     tokio::select! {
@@ -368,6 +871,72 @@ async fn run_until_stopped(
             }
         }
     };
+
+    drop(ticker);
+}
+
+/// The number of epoch ticks a store can run for in the given time
+fn epoch_ticks(duration: Duration) -> u64 {
+    let ticks = duration.as_millis() / EPOCH_TICK.as_millis();
+
+    ticks.max(1) as u64
+}
+
+/// Keep incrementing the epoch of an engine, so that the stores in it reach their deadlines. This
+/// runs on its own thread, since a module that never yields keeps its tokio worker busy and could
+/// starve a ticking task of the time it needs to interrupt that same module.
+fn tick_epochs(engine: Engine) -> EpochTicker {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let ticker = EpochTicker {
+        stopped: stopped.clone(),
+    };
+
+    std::thread::Builder::new()
+        .name("epoch-ticker".to_string())
+        .spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                std::thread::sleep(EPOCH_TICK);
+                engine.increment_epoch();
+            }
+        })
+        .expect("spawning the epoch ticker thread should not fail");
+
+    ticker
+}
+
+/// Stops the thread of [tick_epochs] when dropped
+struct EpochTicker {
+    stopped: Arc<AtomicBool>,
+}
+
+impl Drop for EpochTicker {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Turn a request which failed, possibly because the module trapped or ran out of time, into a
+/// response. The reason is only logged, so that it ends up in the deployment logs.
+fn error_response(error: anyhow::Error) -> Response<Body> {
+    let status = match error.downcast_ref::<Trap>() {
+        Some(Trap::Interrupt) => {
+            println!("request timed out: {error:?}");
+            hyper::http::StatusCode::GATEWAY_TIMEOUT
+        }
+        Some(trap) => {
+            println!("request trapped with {trap}: {error:?}");
+            hyper::http::StatusCode::INTERNAL_SERVER_ERROR
+        }
+        None => {
+            println!("error sending request: {error:?}");
+            hyper::http::StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("building request with empty body should not fail")
 }
 
 #[cfg(test)]
@@ -394,7 +963,7 @@ pub mod tests {
     async fn axum() {
         compile_module();
 
-        let router = RouterBuilder::new()
+        let router = RouterBuilder::new(Limits::default())
             .unwrap()
             .src("tests/resources/axum-wasm-expanded/target/wasm32-wasi/debug/shuttle_axum_expanded.wasm")
             .build()
@@ -480,5 +1049,92 @@ pub mod tests {
                 .as_ref(),
             b"THIS SHOULD BE UPPERCASED"
         );
+
+        // POST /uppercase with a body too large to be buffered
+        let body = "streamed ".repeat(100_000);
+        let request: Request<Body> = Request::builder()
+            .method(Method::POST)
+            .version(Version::HTTP_11)
+            .uri("https://axum-wasm.example/uppercase")
+            .body(body.clone().into())
+            .unwrap();
+
+        let res = router.clone().handle_request(request).await.unwrap();
+
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            hyper::body::to_bytes(res.into_body()).await.unwrap(),
+            body.to_uppercase()
+        );
+    }
+
+    // A single worker thread is kept busy by the module, so only a ticker on its own thread can
+    // interrupt it
+    #[tokio::test(flavor = "current_thread")]
+    async fn request_timeout() {
+        let limits = Limits {
+            request_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let router = RouterBuilder::new(limits)
+            .unwrap()
+            .src("tests/resources/next-infinite-loop/module.wat")
+            .build()
+            .unwrap();
+        let ticker = tick_epochs(router.engine.clone());
+
+        let request: Request<Body> = Request::builder()
+            .method(Method::GET)
+            .version(Version::HTTP_11)
+            .uri("https://axum-wasm.example/hello")
+            .body(Body::empty())
+            .unwrap();
+
+        let error = router.clone().handle_request(request).await.unwrap_err();
+        drop(ticker);
+
+        assert!(matches!(
+            error.downcast_ref::<Trap>(),
+            Some(Trap::Interrupt)
+        ));
+        assert_eq!(error_response(error).status(), StatusCode::GATEWAY_TIMEOUT);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn streaming_request_timeout() {
+        let limits = Limits {
+            request_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let router = RouterBuilder::new(limits)
+            .unwrap()
+            .src("tests/resources/next-infinite-stream/module.wat")
+            .build()
+            .unwrap();
+        let ticker = tick_epochs(router.engine.clone());
+
+        let request: Request<Body> = Request::builder()
+            .method(Method::GET)
+            .version(Version::HTTP_11)
+            .uri("https://axum-wasm.example/events")
+            .body(Body::empty())
+            .unwrap();
+
+        // Without a single frame, the deadline counts from the start of the request
+        let error = router.clone().handle_request(request).await.unwrap_err();
+        drop(ticker);
+
+        assert!(matches!(
+            error.downcast_ref::<Trap>(),
+            Some(Trap::Interrupt)
+        ));
+    }
+
+    #[test]
+    fn epoch_ticks_of_timeouts() {
+        assert_eq!(epoch_ticks(Duration::from_secs(30)), 300);
+        assert_eq!(epoch_ticks(Duration::from_millis(150)), 1);
+        // A store with no ticks left would be interrupted right away
+        assert_eq!(epoch_ticks(Duration::from_millis(10)), 1);
     }
 }
@@ -0,0 +1,49 @@
+//! Export of the traces of a service to an OpenTelemetry collector.
+//!
+//! The exporter is configured with the standard `OTEL_EXPORTER_OTLP_ENDPOINT` and
+//! `OTEL_RESOURCE_ATTRIBUTES` variables, which the deployer sets for every runtime it starts. They
+//! can also be set for `cargo shuttle run` to send the traces to a local collector.
+use std::env;
+
+use opentelemetry::{
+    global,
+    runtime::Tokio,
+    sdk::{
+        propagation::TraceContextPropagator,
+        trace::{self, Tracer},
+        Resource,
+    },
+};
+use opentelemetry_otlp::WithExportConfig;
+
+const ENDPOINT_VAR: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+
+/// Get a tracer which exports to the configured collector, if there is one
+pub(crate) fn tracer() -> Option<Tracer> {
+    let endpoint = env::var(ENDPOINT_VAR).ok()?;
+
+    // Needed to continue the traces of the requests coming through the proxy
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(&endpoint),
+        )
+        // The default resource is read from `OTEL_RESOURCE_ATTRIBUTES`
+        .with_trace_config(trace::config().with_resource(Resource::default()))
+        .install_batch(Tokio);
+
+    match tracer {
+        Ok(tracer) => {
+            println!("exporting traces to {endpoint}");
+            Some(tracer)
+        }
+        Err(error) => {
+            println!("failed to set up the export of traces to {endpoint}: {error}");
+            None
+        }
+    }
+}
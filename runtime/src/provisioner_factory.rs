@@ -13,6 +13,7 @@ use shuttle_service::{DeploymentMetadata, Environment, Factory, ProjectName};
 use tonic::{transport::Channel, Request};
 
 /// A factory (service locator) which goes through the provisioner crate
+#[derive(Clone)]
 pub struct ProvisionerFactory {
     service_name: ProjectName,
     provisioner_client: ProvisionerClient<ClaimService<InjectPropagation<Channel>>>,
@@ -1,10 +1,17 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
-use anyhow::Context;
+use anyhow::{anyhow, Context};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
-use shuttle_common::resource::{self, Type};
+use shuttle_common::{
+    database,
+    resource::{self, Type},
+};
 use shuttle_service::ResourceBuilder;
+use tokio::time::Instant;
 
 use crate::__internals::ProvisionerFactory;
 
@@ -42,7 +49,7 @@ impl ResourceTracker {
     }
 
     /// Record a resource that has been requested
-    pub fn record_resource(&mut self, r#type: Type, config: Value, output: Value) {
+    pub fn record_resource(&self, r#type: Type, config: Value, output: Value) {
         self.new_resources
             .lock()
             .expect("to get lock on new resources")
@@ -60,13 +67,69 @@ macro_rules! log {
     };
 }
 
+/// How often to log that a resource is still being provisioned
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long getting a resource of a type may take before giving up on it
+fn resource_timeout(r#type: Type) -> Duration {
+    match r#type {
+        // Creating an RDS instance alone can take minutes
+        Type::Database(database::Type::AwsRds(_)) => Duration::from_secs(20 * 60),
+        _ => Duration::from_secs(5 * 60),
+    }
+}
+
 /// Helper function to get a resource from a builder.
 ///
-/// This function is called by the codegen to create each type of needed resource.
+/// This function is called by the codegen to create each type of needed resource. The codegen
+/// gets all the resources concurrently, so each call gets its own copy of the factory. A resource
+/// is recorded as soon as it is ready, so that it is kept even when getting another one fails.
 pub async fn get_resource<B, T, O>(
+    builder: B,
+    factory: &ProvisionerFactory,
+    resource_tracker: &ResourceTracker,
+) -> Result<T, shuttle_service::Error>
+where
+    B: ResourceBuilder<T, Output = O>,
+    O: Serialize + DeserializeOwned,
+{
+    let mut factory = factory.clone();
+    let timeout = resource_timeout(B::TYPE);
+    let started = Instant::now();
+
+    let resource = load_resource(builder, &mut factory, resource_tracker);
+    tokio::pin!(resource);
+
+    let mut progress = tokio::time::interval_at(started + PROGRESS_INTERVAL, PROGRESS_INTERVAL);
+    let deadline = tokio::time::sleep_until(started + timeout);
+    tokio::pin!(deadline);
+
+    loop {
+        tokio::select! {
+            resource = &mut resource => return resource,
+            _ = progress.tick() => {
+                log!(format!(
+                    "Still getting resource after {}s",
+                    started.elapsed().as_secs()
+                ));
+            }
+            _ = &mut deadline => {
+                log!("Timed out");
+
+                return Err(anyhow!(
+                    "timed out getting resource after {}s",
+                    timeout.as_secs()
+                )
+                .into());
+            }
+        }
+    }
+}
+
+async fn load_resource<B, T, O>(
     builder: B,
     factory: &mut ProvisionerFactory,
-    resource_tracker: &mut ResourceTracker,
+    resource_tracker: &ResourceTracker,
 ) -> Result<T, shuttle_service::Error>
 where
     B: ResourceBuilder<T, Output = O>,
@@ -127,3 +190,189 @@ where
 
     Ok(resource)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::BTreeMap,
+        str::FromStr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use serde_json::json;
+    use shuttle_common::{
+        claims::{ClaimLayer, InjectPropagationLayer},
+        resource::{self, Type},
+    };
+    use shuttle_proto::provisioner::provisioner_client::ProvisionerClient;
+    use shuttle_service::{CustomError, Environment, Factory, ProjectName, ResourceBuilder};
+    use tokio::time::Instant;
+    use tonic::transport::Endpoint;
+    use tower::ServiceBuilder;
+
+    use super::{get_resource, resource_timeout, ResourceTracker};
+    use crate::__internals::ProvisionerFactory;
+
+    /// How long each test resource takes to provision
+    const PROVISION_TIME: Duration = Duration::from_secs(10);
+
+    enum Outcome {
+        Ready,
+        Fails,
+        Hangs,
+    }
+
+    struct TestResource {
+        config: String,
+        outcome: Outcome,
+    }
+
+    impl TestResource {
+        fn with(config: &str, outcome: Outcome) -> Self {
+            Self {
+                config: config.to_string(),
+                outcome,
+            }
+        }
+    }
+
+    #[async_trait]
+    impl ResourceBuilder<String> for TestResource {
+        const TYPE: Type = Type::Custom;
+
+        type Config = String;
+
+        type Output = String;
+
+        fn new() -> Self {
+            Self::with("ready", Outcome::Ready)
+        }
+
+        fn config(&self) -> &Self::Config {
+            &self.config
+        }
+
+        async fn output(self, _: &mut dyn Factory) -> Result<Self::Output, shuttle_service::Error> {
+            tokio::time::sleep(PROVISION_TIME).await;
+
+            match self.outcome {
+                Outcome::Ready => Ok(format!("{} output", self.config)),
+                Outcome::Fails => Err(CustomError::msg("broken on purpose").into()),
+                Outcome::Hangs => std::future::pending().await,
+            }
+        }
+
+        async fn build(build_data: &Self::Output) -> Result<String, shuttle_service::Error> {
+            Ok(build_data.clone())
+        }
+    }
+
+    fn factory() -> ProvisionerFactory {
+        // The test resources don't use the provisioner, so it is never connected to
+        let channel = Endpoint::from_static("http://localhost:5000").connect_lazy();
+        let channel = ServiceBuilder::new()
+            .layer(ClaimLayer)
+            .layer(InjectPropagationLayer)
+            .service(channel);
+
+        ProvisionerFactory::new(
+            ProvisionerClient::new(channel),
+            ProjectName::from_str("resource-test").unwrap(),
+            BTreeMap::new(),
+            Environment::Local,
+            None,
+        )
+    }
+
+    fn resource_tracker() -> (ResourceTracker, Arc<Mutex<Vec<resource::Response>>>) {
+        let new_resources = Arc::new(Mutex::new(Vec::new()));
+
+        (
+            ResourceTracker::new(Vec::new(), new_resources.clone()),
+            new_resources,
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resources_are_provisioned_concurrently() {
+        let factory = factory();
+        let (resource_tracker, new_resources) = resource_tracker();
+        let started = Instant::now();
+
+        let (first, second) = tokio::join!(
+            get_resource(
+                TestResource::with("first", Outcome::Ready),
+                &factory,
+                &resource_tracker
+            ),
+            get_resource(
+                TestResource::with("second", Outcome::Ready),
+                &factory,
+                &resource_tracker
+            ),
+        );
+
+        assert_eq!(first.unwrap(), "first output");
+        assert_eq!(second.unwrap(), "second output");
+        // One after the other would have taken twice as long
+        assert!(started.elapsed() < 2 * PROVISION_TIME);
+        assert_eq!(new_resources.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_resource_keeps_the_others() {
+        let factory = factory();
+        let (resource_tracker, new_resources) = resource_tracker();
+
+        let (works, breaks) = tokio::join!(
+            get_resource(
+                TestResource::with("works", Outcome::Ready),
+                &factory,
+                &resource_tracker
+            ),
+            get_resource(
+                TestResource::with("breaks", Outcome::Fails),
+                &factory,
+                &resource_tracker
+            ),
+        );
+
+        assert_eq!(works.unwrap(), "works output");
+        assert!(format!("{:#}", breaks.unwrap_err()).contains("broken on purpose"));
+
+        let new_resources = new_resources.lock().unwrap();
+        let [resource] = new_resources.as_slice() else {
+            panic!("expected only the resource that worked to be recorded");
+        };
+        assert_eq!(resource.r#type, Type::Custom);
+        assert_eq!(resource.config, json!("works"));
+        assert_eq!(resource.data, json!("works output"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn hung_resource_times_out() {
+        let factory = factory();
+        let (resource_tracker, new_resources) = resource_tracker();
+        let started = Instant::now();
+
+        let error = get_resource(
+            TestResource::with("hangs", Outcome::Hangs),
+            &factory,
+            &resource_tracker,
+        )
+        .await
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "timed out getting resource after {}s",
+                resource_timeout(Type::Custom).as_secs()
+            )
+        );
+        assert!(started.elapsed() >= resource_timeout(Type::Custom));
+        assert!(new_resources.lock().unwrap().is_empty());
+    }
+}
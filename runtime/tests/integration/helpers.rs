@@ -54,6 +54,7 @@ pub async fn spawn_runtime(project_path: String, service_name: &str) -> Result<T
         Environment::Local,
         &format!("http://{}", provisioner_address),
         None,
+        None,
         runtime_port,
         runtime_executable,
         Path::new(&project_path),
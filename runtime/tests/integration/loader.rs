@@ -1,3 +1,5 @@
+use serde_json::json;
+use shuttle_common::resource;
 use shuttle_proto::runtime::{LoadRequest, StartRequest, StopReason, SubscribeStopRequest};
 
 use crate::helpers::{spawn_runtime, TestRuntime};
@@ -95,6 +97,101 @@ async fn bind_panic_owned() {
     assert_eq!(reason.message, "panic in bind");
 }
 
+#[tokio::test]
+async fn task_crash() {
+    let project_path = format!("{}/tests/resources/task-crash", env!("CARGO_MANIFEST_DIR"));
+
+    let TestRuntime {
+        bin_path,
+        service_name,
+        secrets,
+        mut runtime_client,
+        runtime_address,
+        runtime: _runtime, // Keep it to not be dropped and have the process killed.
+    } = spawn_runtime(project_path, "task-crash").await.unwrap();
+
+    let load_request = tonic::Request::new(LoadRequest {
+        path: bin_path,
+        service_name,
+        resources: Default::default(),
+        secrets,
+    });
+
+    runtime_client.load(load_request).await.unwrap();
+
+    let mut stream = runtime_client
+        .subscribe_stop(tonic::Request::new(SubscribeStopRequest {}))
+        .await
+        .unwrap()
+        .into_inner();
+
+    let start_request = StartRequest {
+        ip: runtime_address.to_string(),
+    };
+
+    runtime_client
+        .start(tonic::Request::new(start_request))
+        .await
+        .unwrap();
+
+    let reason = stream.message().await.unwrap().unwrap();
+
+    assert_eq!(reason.reason, StopReason::Crash as i32);
+    assert_eq!(
+        reason.message,
+        "background task `queue-consumer` crashed: queue went away"
+    );
+}
+
+#[tokio::test]
+async fn resource_failure() {
+    let project_path = format!(
+        "{}/tests/resources/resource-failure",
+        env!("CARGO_MANIFEST_DIR")
+    );
+
+    let TestRuntime {
+        bin_path,
+        service_name,
+        secrets,
+        mut runtime_client,
+        runtime_address: _,
+        runtime: _runtime, // Keep it to not be dropped and have the process killed.
+    } = spawn_runtime(project_path, "resource-failure")
+        .await
+        .unwrap();
+
+    let load_request = tonic::Request::new(LoadRequest {
+        path: bin_path,
+        service_name,
+        resources: Default::default(),
+        secrets,
+    });
+
+    let load_response = runtime_client
+        .load(load_request)
+        .await
+        .unwrap()
+        .into_inner();
+    assert!(!load_response.success);
+    assert!(load_response
+        .message
+        .starts_with("failed to provision Breaks"));
+    assert!(load_response.message.ends_with("broken on purpose"));
+
+    // The resource that was provisioned is kept for the next deployment
+    let resources: Vec<_> = load_response
+        .resources
+        .into_iter()
+        .map(resource::Response::from_bytes)
+        .collect();
+    let [resource] = resources.as_slice() else {
+        panic!("expected only the resource that worked to be returned");
+    };
+    assert_eq!(resource.config, json!("works"));
+    assert_eq!(resource.data, json!("works output"));
+}
+
 #[tokio::test]
 async fn loader_panic() {
     let project_path = format!(
@@ -100,3 +100,18 @@ pub extern "C" fn __SHUTTLE_Axum_call(
         body_stream.write_all(body.unwrap().as_ref()).unwrap();
     }
 }
+
+#[no_mangle]
+#[allow(non_snake_case)]
+pub extern "C" fn __SHUTTLE_Axum_stream_call(
+    parts_fd: std::os::wasi::prelude::RawFd,
+    body_fd: std::os::wasi::prelude::RawFd,
+) {
+    use shuttle_next::tracing_prelude::*;
+
+    shuttle_next::tracing_registry()
+        .with(shuttle_next::tracing_fmt::layer().without_time())
+        .init();
+
+    shuttle_next::stream::serve(parts_fd, body_fd, app);
+}
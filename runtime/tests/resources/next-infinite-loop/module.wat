@@ -0,0 +1,6 @@
+;; A module whose router never returns, for testing request timeouts
+(module
+  (memory (export "memory") 1)
+  (func (export "__SHUTTLE_Axum_call") (param i32 i32)
+    (loop $forever
+      (br $forever))))
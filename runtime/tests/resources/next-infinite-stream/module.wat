@@ -0,0 +1,6 @@
+;; A module whose streaming router never sends a response, for testing timeouts of streams
+(module
+  (memory (export "memory") 1)
+  (func (export "__SHUTTLE_Axum_stream_call") (param i32 i32)
+    (loop $forever
+      (br $forever))))
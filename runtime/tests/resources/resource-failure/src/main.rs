@@ -0,0 +1,73 @@
+use shuttle_runtime::{CustomError, Error, Factory, ResourceBuilder};
+use shuttle_service::Type;
+
+struct MyService;
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for MyService {
+    async fn bind(mut self, _: std::net::SocketAddr) -> Result<(), Error> {
+        std::future::pending().await
+    }
+}
+
+struct Works;
+
+#[shuttle_runtime::async_trait]
+impl ResourceBuilder<String> for Works {
+    const TYPE: Type = Type::Custom;
+
+    type Config = &'static str;
+
+    type Output = String;
+
+    fn new() -> Self {
+        Self
+    }
+
+    fn config(&self) -> &Self::Config {
+        &"works"
+    }
+
+    async fn output(self, _: &mut dyn Factory) -> Result<Self::Output, Error> {
+        Ok("works output".to_string())
+    }
+
+    async fn build(output: &Self::Output) -> Result<String, Error> {
+        Ok(output.clone())
+    }
+}
+
+struct Breaks;
+
+#[shuttle_runtime::async_trait]
+impl ResourceBuilder<String> for Breaks {
+    const TYPE: Type = Type::Custom;
+
+    type Config = &'static str;
+
+    type Output = String;
+
+    fn new() -> Self {
+        Self
+    }
+
+    fn config(&self) -> &Self::Config {
+        &"breaks"
+    }
+
+    async fn output(self, _: &mut dyn Factory) -> Result<Self::Output, Error> {
+        Err(CustomError::msg("broken on purpose").into())
+    }
+
+    async fn build(output: &Self::Output) -> Result<String, Error> {
+        Ok(output.clone())
+    }
+}
+
+#[shuttle_runtime::main]
+async fn resource_failure(
+    #[Works] _works: String,
+    #[Breaks] _breaks: String,
+) -> Result<MyService, Error> {
+    Ok(MyService)
+}
@@ -0,0 +1,19 @@
+use shuttle_runtime::{Composite, CustomError, RestartPolicy, ShuttleComposite};
+
+struct MyService;
+
+#[shuttle_runtime::async_trait]
+impl shuttle_runtime::Service for MyService {
+    async fn bind(mut self, _: std::net::SocketAddr) -> Result<(), shuttle_runtime::Error> {
+        std::future::pending().await
+    }
+}
+
+async fn consume_queue() -> Result<(), shuttle_runtime::Error> {
+    Err(CustomError::msg("queue went away").into())
+}
+
+#[shuttle_runtime::main]
+async fn task_crash() -> ShuttleComposite<MyService> {
+    Ok(Composite::new(MyService).task("queue-consumer", RestartPolicy::Never, consume_queue))
+}
@@ -16,6 +16,11 @@
 //!     Ok(router.into())
 //! }
 //! ```
+//!
+//! ## Tracing
+//!
+//! With the `otlp` feature, requests continue the traces started by the shuttle proxy, and their
+//! spans are exported to the OpenTelemetry collector configured for the project.
 use shuttle_runtime::{CustomError, Error};
 use std::net::SocketAddr;
 
@@ -27,8 +32,14 @@ impl shuttle_runtime::Service for AxumService {
     /// Takes the router that is returned by the user in their [shuttle_runtime::main] function
     /// and binds to an address passed in by shuttle.
     async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+        // Continue the traces of the requests coming through the shuttle proxy
+        #[cfg(feature = "otlp")]
+        let router = self.0.layer(shuttle_runtime::ExtractPropagationLayer);
+        #[cfg(not(feature = "otlp"))]
+        let router = self.0;
+
         axum::Server::bind(&addr)
-            .serve(self.0.into_make_service())
+            .serve(router.into_make_service())
             .await
             .map_err(CustomError::new)?;
 
@@ -1,5 +1,10 @@
 //! [shuttle_next](https://docs.shuttle.rs/examples/shuttle-next)
 //! A batteries-included, WASM-based backend web-framework.
+pub mod resources;
+#[cfg(target_os = "wasi")]
+#[doc(hidden)]
+pub mod stream;
+
 pub use axum::*;
 pub use futures_executor::block_on;
 pub use http::Request;
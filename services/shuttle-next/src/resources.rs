@@ -0,0 +1,282 @@
+//! Resources of a shuttle-next app, which are provided by the runtime hosting it.
+//!
+//! An endpoint asks for a resource by marking the parameter it is extracted into:
+//!
+//! ```rust,ignore
+//! shuttle_next::app! {
+//!     use shuttle_next::resources::Postgres;
+//!
+//!     #[shuttle_next::endpoint(method = get, route = "/todos")]
+//!     async fn todos(#[shuttle_next::resource] db: Postgres) -> String {
+//!         db.query("SELECT note FROM todos", &[]).unwrap().len().to_string()
+//!     }
+//! }
+//! ```
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+
+use axum::async_trait;
+use axum::extract::FromRequestParts;
+use axum::response::{IntoResponse, Response};
+use http::request::Parts;
+use http::StatusCode;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use shuttle_common::database;
+use shuttle_common::resource;
+use shuttle_common::wasm::{HostRequest, HostResponse, PERSIST_DIR};
+
+/// A resource which the runtime provisions before the app is started
+pub trait Resource {
+    const TYPE: resource::Type;
+}
+
+/// Failure to use a resource
+#[derive(Debug)]
+pub struct Error(String);
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> Response {
+        (StatusCode::INTERNAL_SERVER_ERROR, self.0).into_response()
+    }
+}
+
+#[cfg(target_family = "wasm")]
+#[link(wasm_import_module = "shuttle")]
+extern "C" {
+    fn call(request: *const u8, len: usize) -> usize;
+    fn response(buf: *mut u8);
+}
+
+#[cfg(target_family = "wasm")]
+fn host_call(request: HostRequest) -> Result<HostResponse, Error> {
+    let request = rmp_serde::to_vec(&request)
+        .map_err(|error| Error(format!("failed to serialize host request: {error}")))?;
+
+    // Safe since the host only reads the request and writes as many bytes as it said it would
+    let response = unsafe {
+        let len = call(request.as_ptr(), request.len());
+        let mut buf = vec![0; len];
+        response(buf.as_mut_ptr());
+
+        buf
+    };
+
+    match rmp_serde::from_slice(&response) {
+        Ok(HostResponse::Error(error)) => Err(Error(error)),
+        Ok(response) => Ok(response),
+        Err(error) => Err(Error(format!(
+            "failed to deserialize host response: {error}"
+        ))),
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+fn host_call(_request: HostRequest) -> Result<HostResponse, Error> {
+    Err(Error(
+        "resources are only available to apps running in a shuttle-next runtime".to_string(),
+    ))
+}
+
+fn unexpected(response: HostResponse) -> Error {
+    Error(format!("unexpected host response: {response:?}"))
+}
+
+/// The secrets of the service, from `Secrets.toml`
+pub struct Secrets(BTreeMap<String, String>);
+
+impl Secrets {
+    pub fn get(&self, key: &str) -> Option<String> {
+        self.0.get(key).cloned()
+    }
+}
+
+impl Resource for Secrets {
+    const TYPE: resource::Type = resource::Type::Secrets;
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Secrets {
+    type Rejection = Error;
+
+    async fn from_request_parts(_parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match host_call(HostRequest::Secrets)? {
+            HostResponse::Secrets(secrets) => Ok(Self(secrets)),
+            other => Err(unexpected(other)),
+        }
+    }
+}
+
+/// The metadata of the deployment the app is running in
+pub struct Metadata {
+    pub env: String,
+    pub project_name: String,
+    pub service_name: String,
+}
+
+impl Resource for Metadata {
+    const TYPE: resource::Type = resource::Type::Metadata;
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Metadata {
+    type Rejection = Error;
+
+    async fn from_request_parts(_parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match host_call(HostRequest::Metadata)? {
+            HostResponse::Metadata(metadata) => Ok(Self {
+                env: metadata.env,
+                project_name: metadata.project_name,
+                service_name: metadata.service_name,
+            }),
+            other => Err(unexpected(other)),
+        }
+    }
+}
+
+macro_rules! postgres {
+    ($(#[$doc:meta])* $name:ident, $type:expr) => {
+        $(#[$doc])*
+        ///
+        /// Queries run on the runtime's connection pool, so parameters are given as JSON values.
+        /// Arrays and objects are sent as text and need a cast in the query, like `$1::jsonb`.
+        pub struct $name;
+
+        impl $name {
+            /// Run a query and get the rows it returns as JSON objects
+            pub fn query(&self, sql: &str, params: &[Value]) -> Result<Vec<Value>, Error> {
+                let request = HostRequest::Query {
+                    database: $type,
+                    sql: sql.to_string(),
+                    params: params.to_vec(),
+                };
+
+                match host_call(request)? {
+                    HostResponse::Rows(rows) => Ok(rows),
+                    other => Err(unexpected(other)),
+                }
+            }
+
+            /// Run a query and deserialize the rows it returns
+            pub fn query_as<T: DeserializeOwned>(
+                &self,
+                sql: &str,
+                params: &[Value],
+            ) -> Result<Vec<T>, Error> {
+                self.query(sql, params)?
+                    .into_iter()
+                    .map(|row| {
+                        serde_json::from_value(row)
+                            .map_err(|error| Error(format!("failed to deserialize row: {error}")))
+                    })
+                    .collect()
+            }
+
+            /// Run a statement and get the number of rows it affected
+            pub fn execute(&self, sql: &str, params: &[Value]) -> Result<u64, Error> {
+                let request = HostRequest::Execute {
+                    database: $type,
+                    sql: sql.to_string(),
+                    params: params.to_vec(),
+                };
+
+                match host_call(request)? {
+                    HostResponse::RowsAffected(rows_affected) => Ok(rows_affected),
+                    other => Err(unexpected(other)),
+                }
+            }
+        }
+
+        impl Resource for $name {
+            const TYPE: resource::Type = resource::Type::Database($type);
+        }
+
+        #[async_trait]
+        impl<S: Send + Sync> FromRequestParts<S> for $name {
+            type Rejection = Error;
+
+            async fn from_request_parts(
+                _parts: &mut Parts,
+                _state: &S,
+            ) -> Result<Self, Self::Rejection> {
+                Ok(Self)
+            }
+        }
+    };
+}
+
+postgres!(
+    /// A Postgres database on the shared cluster
+    Postgres,
+    database::Type::Shared(database::SharedEngine::Postgres)
+);
+
+postgres!(
+    /// A dedicated Postgres instance on AWS RDS
+    RdsPostgres,
+    database::Type::AwsRds(database::AwsRdsEngine::Postgres)
+);
+
+/// Storage for values which should outlive a deployment
+pub struct Persist;
+
+impl Persist {
+    /// Save a value under a key, replacing the value saved before
+    pub fn save<T: Serialize>(&self, key: &str, value: T) -> Result<(), Error> {
+        let bytes = rmp_serde::to_vec(&value)
+            .map_err(|error| Error(format!("failed to serialize value: {error}")))?;
+
+        fs::write(Self::path(key), bytes)
+            .map_err(|error| Error(format!("failed to save value: {error}")))
+    }
+
+    /// Load the value saved under a key
+    pub fn load<T: DeserializeOwned>(&self, key: &str) -> Result<T, Error> {
+        let bytes = fs::read(Self::path(key))
+            .map_err(|error| Error(format!("failed to load value: {error}")))?;
+
+        rmp_serde::from_slice(&bytes)
+            .map_err(|error| Error(format!("failed to deserialize value: {error}")))
+    }
+
+    fn path(key: &str) -> PathBuf {
+        PathBuf::from(PERSIST_DIR).join(format!("{key}.rmp"))
+    }
+}
+
+impl Resource for Persist {
+    const TYPE: resource::Type = resource::Type::Persist;
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Persist {
+    type Rejection = Error;
+
+    async fn from_request_parts(_parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self)
+    }
+}
+
+/// Used by the codegen to tell the runtime which resources the app needs
+#[doc(hidden)]
+pub fn write_resources(
+    writer: &mut impl std::io::Write,
+    resources: &[resource::Type],
+) -> Result<(), Error> {
+    let bytes = rmp_serde::to_vec(resources)
+        .map_err(|error| Error(format!("failed to serialize resources: {error}")))?;
+
+    writer
+        .write_all(&bytes)
+        .map_err(|error| Error(format!("failed to write resources: {error}")))
+}
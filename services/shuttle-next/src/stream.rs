@@ -0,0 +1,67 @@
+//! Streaming of requests and responses between a shuttle-next app and the runtime hosting it.
+//!
+//! The parts of a request and response are sent as single frames on the parts stream, while their
+//! bodies are sent as many frames on the body stream, ending with an empty frame. A body is only
+//! read from its stream as the app polls it, so a slow reader pushes back on the writer.
+use std::fs::File;
+use std::future::Future;
+use std::io;
+use std::os::wasi::io::{FromRawFd, RawFd};
+use std::sync::Arc;
+
+use axum::body::{boxed, BoxBody, Bytes, HttpBody, StreamBody};
+use axum::response::Response;
+use futures_executor::block_on;
+use http::Request;
+use shuttle_common::wasm::{read_frame, write_frame, RequestWrapper, ResponseWrapper};
+
+/// Used by the codegen to handle a request with the app, streaming its body in and the body of
+/// the response out
+pub fn serve<F, Fut>(parts_fd: RawFd, body_fd: RawFd, app: F)
+where
+    F: FnOnce(Request<BoxBody>) -> Fut,
+    Fut: Future<Output = Response>,
+{
+    // Safe since the runtime gives these descriptors to the module for this request only
+    let mut parts_stream = unsafe { File::from_raw_fd(parts_fd) };
+    let body_stream = Arc::new(unsafe { File::from_raw_fd(body_fd) });
+
+    let parts = read_frame(&mut parts_stream).expect("failed to read request parts");
+    let wrapper: RequestWrapper =
+        rmp_serde::from_slice(&parts).expect("failed to deserialize request parts");
+
+    let request = wrapper
+        .into_request_builder()
+        .body(boxed(StreamBody::new(request_body(body_stream.clone()))))
+        .expect("failed to build request");
+
+    let (parts, mut body) = block_on(app(request)).into_parts();
+
+    let parts = ResponseWrapper::from(parts)
+        .into_rmp()
+        .expect("failed to serialize response parts");
+    write_frame(&mut parts_stream, &parts).expect("failed to write response parts");
+
+    while let Some(chunk) = block_on(body.data()) {
+        let chunk = chunk.expect("failed to get chunk of response body");
+
+        if !chunk.is_empty() {
+            write_frame(&mut &*body_stream, &chunk).expect("failed to write response body");
+        }
+    }
+
+    write_frame(&mut &*body_stream, &[]).expect("failed to end response body");
+}
+
+/// The body of a request, which is read a frame at a time as it is polled
+fn request_body(body_stream: Arc<File>) -> impl futures_util::Stream<Item = io::Result<Bytes>> {
+    futures_util::stream::unfold(Some(body_stream), |body_stream| async move {
+        let body_stream = body_stream?;
+
+        match read_frame(&mut &*body_stream) {
+            Ok(frame) if frame.is_empty() => None,
+            Ok(frame) => Some((Ok(Bytes::from(frame)), Some(body_stream))),
+            Err(error) => Some((Err(error), None)),
+        }
+    })
+}